@@ -0,0 +1,436 @@
+use {
+    crate::{
+        Reply,
+        Result,
+        Service,
+        ServiceArc,
+        core::Recover,
+    },
+    std::{
+        collections::{
+            HashMap,
+            VecDeque,
+        },
+        fmt,
+        sync::{
+            Arc,
+            Mutex,
+            MutexGuard,
+            Weak,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+};
+
+/// State of the circuit breaker of a [`Service`] instance.
+///
+/// - [`Closed`](CircuitState::Closed): the instance is healthy and
+///   receives its share of the traffic.
+/// - [`Open`](CircuitState::Open): the instance failed too often and is
+///   skipped until its cool-down is over.
+/// - [`HalfOpen`](CircuitState::HalfOpen): the cool-down is over and a
+///   single probe request was let through; its outcome closes or opens the
+///   circuit again.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CircuitState {
+    #[default]
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl AsRef<str> for CircuitState {
+    /// Returns the string representation of the state.
+    ///
+    /// Values are "closed", "open" and "half_open".
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// When the circuit breaker of a [`Service`] instance opens, and for how
+/// long.
+///
+/// A circuit opens after a number of consecutive failures (5 by default)
+/// or, if set, once the failure rate over a window of recent calls
+/// reaches a threshold. It stays open for the cool-down (30s by default),
+/// then lets a single probe through.
+///
+/// Only [retryable](crate::Error::is_retryable) errors count as failures:
+/// other errors tell about the request, not about the health of the
+/// instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use {
+///     bakbon::*,
+///     std::time::Duration,
+/// };
+///
+/// let policy = BreakerPolicy::default()
+///     .consecutive_failures(3)
+///     .failure_rate(0.5, 20)
+///     .cool_down(Duration::from_secs(10));
+///
+/// let router = Router::builder()
+///     .circuit_breaker(policy)
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakerPolicy {
+    consecutive_failures: u32,
+    failure_rate:         Option<(f64, usize)>,
+    cool_down:            Duration,
+}
+
+impl BreakerPolicy {
+    /// Opens the circuit after `failures` consecutive failures.
+    pub fn consecutive_failures(mut self, failures: u32) -> Self {
+        self.consecutive_failures = failures.max(1);
+        self
+    }
+
+    /// Opens the circuit once the failures make up `rate` (between `0`
+    /// and `1`) of the last `window` calls.
+    ///
+    /// The rate is only checked once `window` calls were made, and only
+    /// when at least one of them failed: a `rate` of `0` opens the circuit
+    /// on any failure in the window.
+    pub fn failure_rate(mut self, rate: f64, window: usize) -> Self {
+        self.failure_rate = Some((rate.clamp(0.0, 1.0), window.max(1)));
+        self
+    }
+
+    /// Sets how long an open circuit skips its instance before letting a
+    /// probe through.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            failure_rate:         None,
+            cool_down:            Duration::from_secs(30),
+        }
+    }
+}
+
+/// Circuit breaker of a single instance, or of a peer
+/// [`Router`](crate::Router).
+#[derive(Debug)]
+pub(crate) struct Circuit {
+    state:       CircuitState,
+    // When the circuit opened, or when the probe was let through.
+    since:       Instant,
+    consecutive: u32,
+    // Recent outcomes, `true` for a failure.
+    outcomes:    VecDeque<bool>,
+}
+
+impl Circuit {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            state:       CircuitState::Closed,
+            since:       now,
+            consecutive: 0,
+            outcomes:    VecDeque::new(),
+        }
+    }
+
+    /// Returns whether the instance can be selected.
+    ///
+    /// A probe which never reported back is given up on after a
+    /// cool-down, so the instance is not skipped forever.
+    pub(crate) fn is_available(&self, policy: &BreakerPolicy, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            _ => now.duration_since(self.since) >= policy.cool_down,
+        }
+    }
+
+    /// Lets a probe through an open circuit.
+    pub(crate) fn select(&mut self, now: Instant) {
+        if self.state != CircuitState::Closed {
+            self.state = CircuitState::HalfOpen;
+            self.since = now;
+        }
+    }
+
+    pub(crate) fn record(&mut self, policy: &BreakerPolicy, failed: bool, now: Instant) {
+        if self.state == CircuitState::HalfOpen {
+            match failed {
+                true => self.open(now),
+                false => self.close(),
+            }
+            return;
+        }
+
+        self.consecutive = match failed {
+            true => self.consecutive + 1,
+            false => 0,
+        };
+        if self.consecutive >= policy.consecutive_failures {
+            return self.open(now);
+        }
+
+        if let Some((rate, window)) = policy.failure_rate {
+            self.outcomes
+                .push_back(failed);
+            if self.outcomes.len() > window {
+                self.outcomes.pop_front();
+            }
+            let failures = self
+                .outcomes
+                .iter()
+                .filter(|failed| **failed)
+                .count();
+            if self.outcomes.len() == window
+                && failures > 0
+                && failures as f64 >= rate * window as f64
+            {
+                self.open(now);
+            }
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState { self.state }
+
+    fn open(&mut self, now: Instant) {
+        self.close();
+        self.state = CircuitState::Open;
+        self.since = now;
+    }
+
+    fn close(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive = 0;
+        self.outcomes.clear();
+    }
+}
+
+/// An instance tracked by its circuit breaker.
+///
+/// The weak reference keeps the allocation of the instance, hence its
+/// identity, from being reused while it is tracked.
+struct Tracked {
+    instance: Weak<dyn Service>,
+    circuit:  Circuit,
+}
+
+/// Circuit breakers of the [`Service`] instances selected by a
+/// [`Balancer`](super::Balancer), keyed by instance identity.
+pub(super) struct Breakers {
+    policy:   BreakerPolicy,
+    circuits: Mutex<HashMap<usize, Tracked>>,
+}
+
+impl Breakers {
+    pub(super) fn new(policy: BreakerPolicy) -> Self {
+        Self {
+            policy,
+            circuits: Mutex::default(),
+        }
+    }
+
+    /// Returns the identity of an instance: the address of its data.
+    fn key(instance: &dyn Service) -> usize { instance as *const dyn Service as *const () as usize }
+
+    /// Acquires the circuits.
+    fn circuits(&self) -> MutexGuard<'_, HashMap<usize, Tracked>> { self.circuits.lock().recover() }
+
+    /// Lets `pick` select one of the instances whose circuit is not open,
+    /// which it is told by the predicate it is given, and lets a probe
+    /// through the circuit of the selected instance.
+    pub(super) fn select<'a, F>(
+        &self,
+        instances: &'a [ServiceArc],
+        pick: F,
+    ) -> Result<&'a ServiceArc>
+    where
+        F: FnOnce(&dyn Fn(&ServiceArc) -> bool) -> Result<&'a ServiceArc>,
+    {
+        let now = Instant::now();
+        let mut circuits = self.circuits();
+        if instances
+            .iter()
+            .any(|instance| !circuits.contains_key(&Self::key(instance.as_ref())))
+        {
+            // Forget the instances dropped since the last newcomer.
+            circuits.retain(|_, tracked| {
+                tracked
+                    .instance
+                    .strong_count()
+                    > 0
+            });
+            for instance in instances {
+                circuits
+                    .entry(Self::key(instance.as_ref()))
+                    .or_insert_with(|| Tracked {
+                        instance: Arc::downgrade(instance),
+                        circuit:  Circuit::new(now),
+                    });
+            }
+        }
+
+        let closed = |instance: &ServiceArc| {
+            circuits[&Self::key(instance.as_ref())]
+                .circuit
+                .is_available(&self.policy, now)
+        };
+        let selected = pick(&closed)?;
+        if let Some(tracked) = circuits.get_mut(&Self::key(selected.as_ref())) {
+            tracked.circuit.select(now);
+        }
+        Ok(selected)
+    }
+
+    /// Records the outcome of a call to an instance.
+    ///
+    /// Instances which were never selected, such as a fallback
+    /// [`Service`], are ignored.
+    pub(super) fn record(&self, instance: &dyn Service, outcome: &Result<Reply>) {
+        let failed = match outcome {
+            Ok(_) => false,
+            Err(error) => error.is_retryable(),
+        };
+        if let Some(tracked) = self
+            .circuits()
+            .get_mut(&Self::key(instance))
+        {
+            tracked
+                .circuit
+                .record(&self.policy, failed, Instant::now());
+        }
+    }
+
+    /// Returns whether an instance can be selected, without letting a
+    /// probe through its circuit.
+    pub(super) fn is_available(&self, instance: &dyn Service) -> bool {
+        self.circuits()
+            .get(&Self::key(instance))
+            .is_none_or(|tracked| {
+                tracked
+                    .circuit
+                    .is_available(&self.policy, Instant::now())
+            })
+    }
+
+    /// Returns the state of the circuit of an instance.
+    pub(super) fn state(&self, instance: &dyn Service) -> CircuitState {
+        self.circuits()
+            .get(&Self::key(instance))
+            .map(|tracked| tracked.circuit.state)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Breakers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Breakers")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> BreakerPolicy {
+        BreakerPolicy::default()
+            .consecutive_failures(2)
+            .cool_down(Duration::from_secs(10))
+    }
+
+    #[test]
+    fn circuit_state_names() {
+        assert_eq!(CircuitState::default(), CircuitState::Closed);
+        assert_eq!(CircuitState::Open.as_ref(), "open");
+        assert_eq!(CircuitState::HalfOpen.as_ref(), "half_open");
+    }
+
+    #[test]
+    fn open_after_consecutive_failures() {
+        let policy = policy();
+        let now = Instant::now();
+        let mut circuit = Circuit::new(now);
+
+        circuit.record(&policy, true, now);
+        circuit.record(&policy, false, now);
+        circuit.record(&policy, true, now);
+        assert_eq!(circuit.state, CircuitState::Closed);
+
+        circuit.record(&policy, true, now);
+        assert_eq!(circuit.state, CircuitState::Open);
+        assert!(!circuit.is_available(&policy, now));
+    }
+
+    #[test]
+    fn open_on_failure_rate() {
+        let policy = BreakerPolicy::default().failure_rate(0.5, 4);
+        let now = Instant::now();
+        let mut circuit = Circuit::new(now);
+
+        for failed in [true, false, true] {
+            circuit.record(&policy, failed, now);
+        }
+        assert_eq!(circuit.state, CircuitState::Closed);
+
+        circuit.record(&policy, false, now);
+        assert_eq!(circuit.state, CircuitState::Open);
+    }
+
+    #[test]
+    fn zero_failure_rate_needs_a_failure() {
+        let policy = BreakerPolicy::default().failure_rate(0.0, 2);
+        let now = Instant::now();
+        let mut circuit = Circuit::new(now);
+
+        for _ in 0..4 {
+            circuit.record(&policy, false, now);
+        }
+        assert_eq!(circuit.state, CircuitState::Closed);
+
+        circuit.record(&policy, true, now);
+        assert_eq!(circuit.state, CircuitState::Open);
+    }
+
+    #[test]
+    fn half_open_after_cool_down() {
+        let policy = policy();
+        let now = Instant::now();
+        let later = now + Duration::from_secs(10);
+        let mut circuit = Circuit::new(now);
+        circuit.open(now);
+
+        // A failed probe opens the circuit again.
+        assert!(circuit.is_available(&policy, later));
+        circuit.select(later);
+        assert_eq!(circuit.state, CircuitState::HalfOpen);
+        assert!(!circuit.is_available(&policy, later));
+        circuit.record(&policy, true, later);
+        assert_eq!(circuit.state, CircuitState::Open);
+
+        // A successful one closes it.
+        let much_later = later + Duration::from_secs(10);
+        circuit.select(much_later);
+        circuit.record(&policy, false, much_later);
+        assert_eq!(circuit.state, CircuitState::Closed);
+    }
+}
@@ -1,91 +1,342 @@
 //! Module containing the load-balancing logic.
 //!
 //! This module provides the `Balancer` struct, which is used internally by
-//! [`Router`](super::Router) to select a [`Service`] instance from a pool
-//! of registered instances for a given logical [`Service`]. It wraps a
-//! [`Strategy`] and uses it to perform instance selection.
+//! [`Router`](super::Router) to select a [`Service`](crate::Service)
+//! instance from a pool of registered instances for a given logical
+//! [`Service`](crate::Service). It wraps a [`Strategy`] and uses it to
+//! perform instance selection.
 //!
-//! Note: currently only the [`round_robin`](Strategy::RoundRobin) strategy
-//! has real logic. [`weighted`](Strategy::Weighted),
+//! Note: currently only the [`round_robin`](Strategy::RoundRobin) and
+//! [`consistent_hash`](Strategy::ConsistentHash) strategies have real
+//! logic. [`weighted`](Strategy::Weighted),
 //! [`least_connections`](Strategy::LeastConnections), and
 //! [`random`](Strategy::Random) behave as simple fallbacks and are not
 //! production-ready yet.
 
+mod breaker;
 mod strategy;
 
+pub(crate) use breaker::Circuit;
+pub use breaker::{
+    BreakerPolicy,
+    CircuitState,
+};
 use {
     crate::{
+        Envelope,
         Error,
+        Reply,
         Result,
         Service,
+        ServiceArc,
     },
+    breaker::Breakers,
+    std::sync::atomic::AtomicUsize,
     strategy::Strategy,
 };
 
 /// Load balancer.
 ///
-/// `Balancer` wraps a [`Strategy`] and selects a [`Service`] instance from
-/// a pool of registred instances for a given logical [`Service`]. It is
-/// only responsible for instance selection; the
-/// [`Registry`](crate::Registry) handles [`Service`] lookup by
-/// [`Address`](crate::Address).
+/// `Balancer` wraps a [`Strategy`] and selects a
+/// [`Service`](crate::Service) instance from a pool of registred instances
+/// for a given logical [`Service`](crate::Service). It is only responsible
+/// for instance selection; the [`Registry`](crate::Registry) handles
+/// [`Service`](crate::Service) lookup by [`Address`](crate::Address).
+///
+/// Used internally by [`Router`](super::Router). The [`Strategy`] state
+/// is atomic so that selection only needs a shared reference: a
+/// `Balancer` can be shared between threads, and a
+/// [`Service`](crate::Service) can route new messages through the same
+/// [`Router`](super::Router) while it is being processed.
+///
+/// With a [`BreakerPolicy`], the `Balancer` also keeps a circuit breaker
+/// per shared [`Service`] instance and skips the instances whose circuit
+/// is [`Open`](CircuitState::Open).
+///
+/// A [`Router`](super::Router) has a default `Balancer`, and can be given
+/// another one per [`Service`](crate::Service), e.g. consistent hashing
+/// for a stateful session service next to stateless round-robin ones.
+///
+/// Note: currently only the [`round_robin`](Strategy::RoundRobin) and
+/// `consistent_hash` strategies have real logic. `weighted`,
+/// `least_connections`, and `random` behave as simple fallbacks and are
+/// not production-ready yet.
 ///
-/// Used internally by [`Router`](super::Router)
+/// # Examples
 ///
-/// Note: currently only the [`round_robin`](Strategy::RoundRobin)
-/// `Strategy` has real logic. `weighted`, `least_connections`, and
-/// `random` behave as simple fallbacks and are not production-ready yet.
+/// ```rust
+/// use bakbon::*;
+///
+/// let router = Router::builder()
+///     .balancer("round_robin")
+///     .service_balancer("http://sessions", Balancer::consistent_hash("x-user-id"))
+///     .build();
+///
+/// let sessions = Address::parse("http://sessions")?;
+/// assert_eq!(router.balancing_strategy(), "round_robin");
+/// assert_eq!(router.balancing_strategy_of(&sessions), "consistent_hash");
+/// # Ok::<(), Error>(())
+/// ```
 #[derive(Default)]
-pub struct Balancer(Strategy);
+pub struct Balancer(Strategy, Option<Breakers>);
 
 impl Balancer {
     /// Creates a new balancer from a strategy name.
     ///
-    /// The `strategy` string is converted into a [`Strategy`] struct using
-    /// its `From<&str>` implementation (e.g. "round_robin", "random")
-    pub(super) fn new(strategy: &str) -> Self { Self(strategy.into()) }
+    /// The `strategy` string is converted into a `Strategy` using its
+    /// `From<&str>` implementation (e.g. "round_robin", "random").
+    /// Unknown names fall back to "round_robin".
+    pub fn new(strategy: &str) -> Self { Self(strategy.into(), None) }
+
+    /// Creates a balancer sending the messages carrying the same value in
+    /// a header to the same instance.
+    ///
+    /// Instances are picked by a jump consistent hash of the value, so
+    /// adding an instance only moves the sessions landing on it. Messages
+    /// without the header are balanced round-robin.
+    pub fn consistent_hash(header: &str) -> Self {
+        let strategy = Strategy::ConsistentHash {
+            index:  AtomicUsize::new(0),
+            header: header.to_string(),
+        };
+        Self(strategy, None)
+    }
+
+    /// Enables circuit breakers with the given [`BreakerPolicy`].
+    pub(crate) fn with_breakers(mut self, policy: BreakerPolicy) -> Self {
+        self.1 = Some(Breakers::new(policy));
+        self
+    }
 
     /// Selects a service instance from the provided list.
     ///
     /// The selection logic depends on the configured strategy:
     /// - `round_robin`: cycles through all instances in order.
+    /// - `consistent_hash`: same as `round_robin`, see
+    ///   [`select_for()`](Balancer::select_for) to hash a message.
     /// - `weighted`, `least_connections`, `random`: placeholders for
     ///   future implementations.
     ///
+    /// Instances can be any [`Service`](crate::Service) handle, such as
+    /// the shared instances of a [`Registry`](crate::Registry) or the
+    /// `AsyncService`s of the `async` feature.
+    ///
     /// Returns an [`Error::ServiceNotFound`] is the instances list is
     /// empty.
-    pub fn select<'a>(
-        &'a mut self,
-        instances: &'a [Box<dyn Service>],
-    ) -> Result<&'a Box<dyn Service>> {
-        if instances.is_empty() {
-            return Err(Error::ServiceNotFound);
+    pub fn select<'a, S>(&self, instances: &'a [S]) -> Result<&'a S> {
+        self.take(instances.len(), None, |_| true)
+            .map(|index| &instances[index])
+    }
+
+    /// Selects a service instance for a message.
+    ///
+    /// With the `consistent_hash` strategy, the instance is picked from
+    /// the value of the hashed header, if the message carries it.
+    /// Otherwise, same as [`select()`](Balancer::select).
+    pub fn select_for<'a, S>(&self, instances: &'a [S], msg: &Envelope) -> Result<&'a S> {
+        self.take(
+            instances.len(),
+            self.hashed_value(msg),
+            |_| true,
+        )
+        .map(|index| &instances[index])
+    }
+
+    /// Selects a shared service instance for a message, whose circuit is
+    /// not open and which `skip` does not rule out.
+    ///
+    /// A hashed message goes to the instance its key hashes to among all
+    /// the instances, or to the next one that can be selected: ruling an
+    /// instance out only moves the sessions landing on it.
+    /// Returns [`Error::CircuitOpen`] if every circuit is open, and
+    /// [`Error::ServiceNotFound`] if the instances list is empty.
+    pub(crate) fn select_instance<'a>(
+        &self,
+        instances: &'a [ServiceArc],
+        msg: &Envelope,
+        skip: impl Fn(&ServiceArc) -> bool,
+    ) -> Result<&'a ServiceArc> {
+        let key = self.hashed_value(msg);
+        let select = |selectable: &dyn Fn(&ServiceArc) -> bool| {
+            self.take(instances.len(), key, |index| {
+                !skip(&instances[index]) && selectable(&instances[index])
+            })
+            .map(|index| &instances[index])
+        };
+        match &self.1 {
+            Some(breakers) => breakers.select(instances, select),
+            None => select(&|_| true),
         }
-        match &mut self.0 {
-            Strategy::RoundRobin { index } => {
-                let service = &instances[*index % instances.len()];
-                *index += 1;
-                Ok(service)
-            }
-            Strategy::Weighted { index, .. } => {
-                // todo!("Implement the weighted logic");
-                let service = &instances[*index % instances.len()];
-                *index += 1;
-                Ok(service)
+    }
+
+    /// Takes a turn of the strategy to choose one of `len` instances, see
+    /// [`choose()`].
+    fn take(
+        &self,
+        len: usize,
+        key: Option<&str>,
+        selectable: impl Fn(usize) -> bool,
+    ) -> Result<usize> {
+        loop {
+            let (index, choice) = choose(len, key, self.turn(), &selectable)?;
+            match choice {
+                // Another selection took this turn first: choose again.
+                Choice::Turn(turn, _) if !self.0.advance(turn)? => continue,
+                _ => return Ok(index),
             }
-            Strategy::LeastConnections { .. } => {
-                // todo!("Implement least connection logic");
-                Ok(&instances[0])
+        }
+    }
+
+    /// Returns the value of the header hashed by the `consistent_hash`
+    /// strategy, if the message carries it.
+    fn hashed_value<'m>(&self, msg: &'m Envelope) -> Option<&'m str> {
+        self.hash_header()
+            .and_then(|header| msg.get_header(header))
+    }
+
+    /// Lets a message through to a given instance, unless its circuit is
+    /// open, as [`select_instance()`](Balancer::select_instance) would.
+    ///
+    /// Useful when the instance is not up to the strategy, e.g. to reach
+    /// every instance. Returns [`Error::CircuitOpen`] if its circuit is
+    /// open.
+    pub(crate) fn admit(&self, instance: &ServiceArc) -> Result<()> {
+        match &self.1 {
+            Some(breakers) => breakers
+                .select(std::slice::from_ref(instance), |closed| {
+                    choose(1, None, None, |_| closed(instance)).map(|_| instance)
+                })
+                .map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Tells which instance
+    /// [`select_instance()`](Balancer::select_instance) would pick for
+    /// a message, and why, without advancing the strategy nor letting
+    /// a probe through an open circuit.
+    ///
+    /// Returns the index of the instance along with the reason, or the
+    /// same errors as [`select_instance()`](Balancer::select_instance).
+    pub(crate) fn preview(
+        &self,
+        instances: &[ServiceArc],
+        msg: &Envelope,
+    ) -> Result<(usize, String)> {
+        let (index, choice) = choose(
+            instances.len(),
+            self.hashed_value(msg),
+            self.turn(),
+            |index| self.is_available(instances[index].as_ref()),
+        )?;
+
+        let name = self.strategy();
+        let reason = match choice {
+            Choice::Hashed(value) => {
+                let header = self
+                    .hash_header()
+                    .unwrap_or_default();
+                format!("{name} of {header} \"{value}\"")
             }
-            Strategy::Random => {
-                // todo!("Implement random logic");
-                Ok(&instances[0])
+            Choice::Turn(turn, count) => {
+                format!("{name} turn {turn} over {count} available instances")
             }
+            Choice::First => format!("{name} picks the first available instance"),
+        };
+        Ok((index, reason))
+    }
+
+    /// Returns whether an instance can be selected, i.e. its circuit is
+    /// not open, or its cool-down is over.
+    pub(crate) fn is_available(&self, instance: &dyn Service) -> bool {
+        self.1
+            .as_ref()
+            .is_none_or(|breakers| breakers.is_available(instance))
+    }
+
+    /// Returns the index of the next turn of the strategy, for the
+    /// strategies taking turns.
+    pub(crate) fn turn(&self) -> Option<usize> { self.0.index().ok() }
+
+    /// Returns the header hashed by the `consistent_hash` strategy.
+    pub(crate) fn hash_header(&self) -> Option<&str> { self.0.hash_header().ok() }
+
+    /// Records the outcome of a call to a selected instance in its circuit
+    /// breaker, if any.
+    pub(crate) fn record(&self, instance: &dyn Service, outcome: &Result<Reply>) {
+        if let Some(breakers) = &self.1 {
+            breakers.record(instance, outcome);
         }
     }
 
+    /// Returns the [`CircuitState`] of an instance.
+    ///
+    /// Instances are [`Closed`](CircuitState::Closed) until they fail, and
+    /// always are without circuit breakers.
+    pub fn circuit_state(&self, instance: &dyn Service) -> CircuitState {
+        self.1
+            .as_ref()
+            .map(|breakers| breakers.state(instance))
+            .unwrap_or_default()
+    }
+
     /// Returns the balancing strategy as a string.
-    pub fn strategy(&self) -> &str { self.0.as_ref() }
+    pub fn strategy(&self) -> &'static str { self.0.name() }
+}
+
+/// Why [`choose()`] chose an instance.
+enum Choice<'m> {
+    /// The hashed key of the message.
+    Hashed(&'m str),
+    /// The turn of a strategy taking turns, over a number of selectable
+    /// instances.
+    Turn(usize, usize),
+    /// The strategy takes no turns.
+    First,
+}
+
+/// Chooses one of `len` instances, among the `selectable` ones, at a
+/// given `turn` of the strategy, if it takes turns.
+///
+/// A hashed `key` goes to the instance it hashes to among all `len`
+/// instances, or to the next selectable one after it, wrapping around.
+/// Otherwise, the turn cycles through the selectable instances.
+///
+/// Returns the index of the instance and why it was chosen, or
+/// [`Error::ServiceNotFound`] if there are no instances and
+/// [`Error::CircuitOpen`] if none is selectable.
+fn choose<'m>(
+    len: usize,
+    key: Option<&'m str>,
+    turn: Option<usize>,
+    selectable: impl Fn(usize) -> bool,
+) -> Result<(usize, Choice<'m>)> {
+    if len == 0 {
+        return Err(Error::ServiceNotFound);
+    }
+    let available: Vec<usize> = (0..len)
+        .filter(|index| selectable(*index))
+        .collect();
+    if available.is_empty() {
+        return Err(Error::CircuitOpen);
+    }
+
+    Ok(match (key, turn) {
+        (Some(key), _) => {
+            let hashed = strategy::jump_hash(key, len);
+            let index = available
+                .iter()
+                .copied()
+                .find(|index| *index >= hashed)
+                .unwrap_or(available[0]);
+            (index, Choice::Hashed(key))
+        }
+        (None, Some(turn)) => (
+            available[turn % available.len()],
+            Choice::Turn(turn, available.len()),
+        ),
+        (None, None) => (available[0], Choice::First),
+    })
 }
 
 //  +------------+
@@ -99,8 +350,10 @@ mod tests {
         crate::{
             Address,
             Envelope,
+            Payload,
             Reply,
             Result,
+            Service,
         },
     };
 
@@ -118,8 +371,7 @@ mod tests {
     #[test]
     fn default_balancer() {
         let balancer = Balancer::default();
-        let round_robin = Strategy::RoundRobin { index: 0 };
-        assert_eq!(balancer.0, round_robin);
+        assert_eq!(balancer.0, Strategy::default());
         assert_eq!(balancer.strategy(), "round_robin");
     }
 
@@ -147,7 +399,7 @@ mod tests {
 
         let instances: Vec<Box<dyn Service>> = vec![Box::new(srv1), Box::new(srv2), Box::new(srv3)];
 
-        let mut balancer = Balancer::default();
+        let balancer = Balancer::default();
 
         let selected = balancer.select(&instances)?;
         assert_eq!(selected.address().to_string(), src1);
@@ -164,11 +416,150 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn balancer_select_for_session() -> Result<()> {
+        let instances: Vec<Box<dyn Service>> = (1..=3)
+            .map(|i| {
+                let addr = Address::parse(format!("http://no-service-{i}.com"))?;
+                Ok(Box::new(NoService(addr)) as Box<dyn Service>)
+            })
+            .collect::<Result<_>>()?;
+        let anonymous = Envelope::new(
+            Address::parse("http://client.com")?,
+            Address::parse("http://no-service.com")?,
+            Payload::new(),
+        );
+        let msg = |session: &str| {
+            anonymous
+                .clone()
+                .header("x-user-id", session)
+        };
+
+        let balancer = Balancer::consistent_hash("x-user-id");
+        assert_eq!(balancer.strategy(), "consistent_hash");
+
+        // A session sticks to its instance.
+        let first = balancer
+            .select_for(&instances, &msg("alice"))?
+            .address()
+            .clone();
+        for _ in 0..5 {
+            let selected = balancer.select_for(&instances, &msg("alice"))?;
+            assert_eq!(selected.address(), &first);
+        }
+
+        // Messages without a session are balanced round-robin.
+        let selected = balancer.select_for(&instances, &anonymous)?;
+        assert_eq!(
+            selected.address().to_string(),
+            "http://no-service-1.com"
+        );
+        let selected = balancer.select_for(&instances, &anonymous)?;
+        assert_eq!(
+            selected.address().to_string(),
+            "http://no-service-2.com"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn hashed_sessions_step_over_ruled_out_instances() -> Result<()> {
+        let instances: Vec<ServiceArc> = (1..=4)
+            .map(|i| {
+                let addr = Address::parse(format!("http://no-service-{i}.com"))?;
+                Ok(std::sync::Arc::new(NoService(addr)) as ServiceArc)
+            })
+            .collect::<Result<_>>()?;
+        let msg = Envelope::new(
+            Address::parse("http://client.com")?,
+            Address::parse("http://no-service.com")?,
+            Payload::new(),
+        );
+        let balancer = Balancer::consistent_hash("x-user-id");
+        let index = |selected: &ServiceArc| {
+            instances
+                .iter()
+                .position(|instance| std::sync::Arc::ptr_eq(instance, selected))
+                .unwrap()
+        };
+
+        let ruled_out = |instance: &ServiceArc| std::sync::Arc::ptr_eq(instance, &instances[3]);
+        for user in 0..64 {
+            let session = msg
+                .clone()
+                .header("x-user-id", &format!("user-{user}"));
+            let before = index(balancer.select_instance(&instances, &session, |_| false)?);
+            let after = index(balancer.select_instance(&instances, &session, ruled_out)?);
+            match before {
+                3 => assert_eq!(after, 0),
+                _ => assert_eq!(after, before),
+            }
+            let (preview, _) = balancer.preview(&instances, &session)?;
+            assert_eq!(preview, before);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn balancer_preview() -> Result<()> {
+        let instances: Vec<ServiceArc> = (1..=3)
+            .map(|i| {
+                let addr = Address::parse(format!("http://no-service-{i}.com"))?;
+                Ok(std::sync::Arc::new(NoService(addr)) as ServiceArc)
+            })
+            .collect::<Result<_>>()?;
+        let msg = Envelope::new(
+            Address::parse("http://client.com")?,
+            Address::parse("http://no-service.com")?,
+            Payload::new(),
+        );
+
+        // Previewing does not take a turn.
+        let balancer = Balancer::default();
+        balancer.select(&instances)?;
+        for _ in 0..2 {
+            let (index, reason) = balancer.preview(&instances, &msg)?;
+            assert_eq!(index, 1);
+            assert_eq!(
+                reason,
+                "round_robin turn 1 over 3 available instances"
+            );
+        }
+        assert_eq!(balancer.turn(), Some(1));
+        assert!(std::sync::Arc::ptr_eq(
+            balancer.select_instance(&instances, &msg, |_| false)?,
+            &instances[1]
+        ));
+        assert_eq!(balancer.turn(), Some(2));
+
+        let balancer = Balancer::consistent_hash("x-user-id");
+        let alice = msg.header("x-user-id", "alice");
+        let (index, reason) = balancer.preview(&instances, &alice)?;
+        assert_eq!(
+            balancer
+                .select_instance(&instances, &alice, |_| false)?
+                .address(),
+            instances[index].address()
+        );
+        assert_eq!(
+            reason,
+            "consistent_hash of x-user-id \"alice\""
+        );
+        assert_eq!(balancer.hash_header(), Some("x-user-id"));
+
+        assert!(matches!(
+            Balancer::new("random").preview(&[], &alice),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+
     #[test]
     fn balancer_select_on_empty_list() {
         let instances: Vec<Box<dyn Service>> = vec![];
 
-        let mut balancer = Balancer::default();
+        let balancer = Balancer::default();
 
         let result = balancer.select(&instances);
         assert!(result.is_err());
@@ -3,7 +3,13 @@ use {
         Error,
         Result,
     },
-    std::collections::HashMap,
+    std::{
+        collections::HashMap,
+        sync::atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    },
 };
 
 /// Per-service-instance weights used by the `Weighted` strategy.
@@ -21,6 +27,9 @@ type Weights = HashMap<String, u32>;
 /// number of active connections.
 type Connections = HashMap<String, u32>;
 
+/// Header hashed by the `ConsistentHash` strategy when none is given.
+pub(super) const DEFAULT_HASH_HEADER: &str = "x-session-id";
+
 /// Load balancing strategies supported by the
 /// [`Balancer`](super::Balancer).
 ///
@@ -34,24 +43,43 @@ type Connections = HashMap<String, u32>;
 /// - [`LeastConnections`](Strategy::LeastConnections) prefers instances
 ///   with fewer active connections.
 /// - [`Random`](Strategy::Random) chooses a random instance.
+/// - [`ConsistentHash`](Strategy::ConsistentHash) sends the messages
+///   carrying the same value in a header to the same instance.
 ///
-/// Only [`RoundRobin`](Strategy::RoundRobin) is fully implemented to this
-/// day; other strategies are placeholders for future development and
+/// Only [`RoundRobin`](Strategy::RoundRobin) and
+/// [`ConsistentHash`](Strategy::ConsistentHash) are fully implemented to
+/// this day; other strategies are placeholders for future development and
 /// currently behave like simple round-robin or fixed selection in the
 /// [`Balancer`](super::Balancer).
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Indexes are atomic so that a [`Balancer`](super::Balancer) shared
+/// between threads can advance them through a shared reference.
+#[derive(Debug)]
 pub(super) enum Strategy {
     // Round-robin selection with internal index.
-    RoundRobin { index: usize },
+    RoundRobin {
+        index: AtomicUsize,
+    },
 
     // Weighted selection with internal index and per-instance weight map.
-    Weighted { index: usize, weights: Weights },
+    Weighted {
+        index:   AtomicUsize,
+        weights: Weights,
+    },
 
     // Selection based on the number of active connections.
-    LeastConnections { connections: Connections },
+    LeastConnections {
+        connections: Connections,
+    },
 
     // Random instance selection.
     Random,
+
+    // Selection by hash of a header, round-robin for messages without it.
+    ConsistentHash {
+        index:  AtomicUsize,
+        header: String,
+    },
 }
 
 #[allow(unused)]
@@ -61,14 +89,51 @@ impl Strategy {
     /// Equivalent to `Strategy::from(value)`.
     pub fn new(value: &str) -> Self { value.into() }
 
+    /// Returns the string representation of the strategy.
+    ///
+    /// Same as [`as_ref()`](AsRef::as_ref), with a `'static` lifetime.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RoundRobin { .. } => "round_robin",
+            Self::Weighted { .. } => "weighted",
+            Self::LeastConnections { .. } => "least_connections",
+            Self::Random => "random",
+            Self::ConsistentHash { .. } => "consistent_hash",
+        }
+    }
+
     /// Returns the internal index for strategies that track one.
     ///
-    /// Only valid for [`Strategy::RoundRobin`] and [`Strategy::Weighted`];
-    /// calling it on other strategies will return
-    /// [`Error::WrongStrategy`].
+    /// Only valid for [`Strategy::RoundRobin`], [`Strategy::Weighted`] and
+    /// [`Strategy::ConsistentHash`]; calling it on other strategies will
+    /// return [`Error::WrongStrategy`].
     pub fn index(&self) -> Result<usize> {
         match self {
-            Self::RoundRobin { index } | Self::Weighted { index, .. } => Ok(*index),
+            Self::RoundRobin { index }
+            | Self::Weighted { index, .. }
+            | Self::ConsistentHash { index, .. } => Ok(index.load(Ordering::Relaxed)),
+            _ => Err(Error::WrongStrategy),
+        }
+    }
+
+    /// Increments the internal index if it still is `from`, for strategies
+    /// that track one, and returns whether it did.
+    ///
+    /// The increment is atomic, so only one of concurrent callers
+    /// advancing from the same index does. Calling it on other strategies
+    /// will return [`Error::WrongStrategy`].
+    pub fn advance(&self, from: usize) -> Result<bool> {
+        match self {
+            Self::RoundRobin { index }
+            | Self::Weighted { index, .. }
+            | Self::ConsistentHash { index, .. } => Ok(index
+                .compare_exchange(
+                    from,
+                    from.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()),
             _ => Err(Error::WrongStrategy),
         }
     }
@@ -85,6 +150,16 @@ impl Strategy {
         }
     }
 
+    /// Returns the header hashed by [`Strategy::ConsistentHash`].
+    ///
+    /// Calling it on another variant will return [`Error::WrongStrategy`].
+    pub fn hash_header(&self) -> Result<&str> {
+        match self {
+            Self::ConsistentHash { header, .. } => Ok(header),
+            _ => Err(Error::WrongStrategy),
+        }
+    }
+
     /// Returns the connetion count map for [`Strategy::LeastConnections`].
     ///
     /// Calling it on another variant will return [`Error::WrongStrategy`].
@@ -101,9 +176,57 @@ impl Strategy {
 
 impl Default for Strategy {
     /// Returns the default strategy (`round_robin`).
-    fn default() -> Self { Self::RoundRobin { index: 0 } }
+    fn default() -> Self {
+        Self::RoundRobin {
+            index: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl PartialEq for Strategy {
+    /// Two strategies are equal when they have the same variant and the
+    /// same state at the time of comparison.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::RoundRobin { index: a }, Self::RoundRobin { index: b }) => {
+                a.load(Ordering::Relaxed) == b.load(Ordering::Relaxed)
+            }
+            (
+                Self::Weighted {
+                    index: a,
+                    weights: wa,
+                },
+                Self::Weighted {
+                    index: b,
+                    weights: wb,
+                },
+            ) => a.load(Ordering::Relaxed) == b.load(Ordering::Relaxed) && wa == wb,
+            (
+                Self::LeastConnections {
+                    connections: a,
+                },
+                Self::LeastConnections {
+                    connections: b,
+                },
+            ) => a == b,
+            (Self::Random, Self::Random) => true,
+            (
+                Self::ConsistentHash {
+                    index: a,
+                    header: ha,
+                },
+                Self::ConsistentHash {
+                    index: b,
+                    header: hb,
+                },
+            ) => a.load(Ordering::Relaxed) == b.load(Ordering::Relaxed) && ha == hb,
+            _ => false,
+        }
+    }
 }
 
+impl Eq for Strategy {}
+
 impl From<&str> for Strategy {
     /// Parse a strategy name into a [`Strategy`] value.
     ///
@@ -112,20 +235,25 @@ impl From<&str> for Strategy {
     /// - "weighted"
     /// - "least_connections"
     /// - "random"
+    /// - "consistent_hash", hashing the `x-session-id` header
     ///
     /// Any unrecognized name falls back to the default strategy
     /// (`round_robin`).
     fn from(value: &str) -> Self {
         match value {
-            "round_robin" => Self::RoundRobin { index: 0 },
+            "round_robin" => Self::default(),
             "weighted" => Self::Weighted {
-                index:   0,
+                index:   AtomicUsize::new(0),
                 weights: Weights::new(),
             },
             "least_connections" => Self::LeastConnections {
                 connections: Connections::new(),
             },
             "random" => Self::Random,
+            "consistent_hash" => Self::ConsistentHash {
+                index:  AtomicUsize::new(0),
+                header: DEFAULT_HASH_HEADER.to_string(),
+            },
             _ => Self::default(),
         }
     }
@@ -136,14 +264,43 @@ impl AsRef<str> for Strategy {
     ///
     /// Values match what [`From<&str>`] accept (e.g. "round_robin",
     /// "weighted").
-    fn as_ref(&self) -> &str {
-        match self {
-            Self::RoundRobin { .. } => "round_robin",
-            Self::Weighted { .. } => "weighted",
-            Self::LeastConnections { .. } => "least_connections",
-            Self::Random => "random",
-        }
+    fn as_ref(&self) -> &str { self.name() }
+}
+
+/// Maps a key onto one of `buckets` buckets with the jump consistent
+/// hash of Lamping and Veach.
+///
+/// Adding a bucket only moves the keys landing in the new one, so a
+/// session keeps its instance while instances are added. The key is
+/// hashed with [`fnv1a()`], so it lands in the same bucket across builds
+/// and processes.
+pub(super) fn jump_hash(key: &str, buckets: usize) -> usize {
+    let mut key = fnv1a(key.as_bytes());
+
+    let (mut bucket, mut next) = (0i64, 0i64);
+    while next < buckets as i64 {
+        bucket = next;
+        key = key
+            .wrapping_mul(2_862_933_555_777_941_757)
+            .wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
     }
+    bucket as usize
+}
+
+/// Hashes bytes with 64-bit FNV-1a.
+///
+/// Unlike [`DefaultHasher`](std::hash::DefaultHasher), its output is fixed
+/// and the same on every platform.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes
+        .iter()
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+        })
 }
 
 //  +------------+
@@ -157,7 +314,9 @@ mod tests {
     #[test]
     fn default_strategy() {
         let strategy = Strategy::default();
-        let round_robin = Strategy::RoundRobin { index: 0 };
+        let round_robin = Strategy::RoundRobin {
+            index: AtomicUsize::new(0),
+        };
         assert_eq!(strategy, round_robin);
         assert_eq!(strategy.as_ref(), "round_robin");
 
@@ -189,7 +348,7 @@ mod tests {
         assert_eq!(strategy.as_ref(), strategy_str);
 
         let weighted = Strategy::Weighted {
-            index:   0,
+            index:   AtomicUsize::new(0),
             weights: Weights::new(),
         };
         assert_eq!(strategy, weighted);
@@ -203,6 +362,21 @@ mod tests {
         assert!(weights.unwrap().is_empty());
     }
 
+    #[test]
+    fn advance_strategy_index() {
+        let strategy = Strategy::default();
+        assert!(strategy.advance(0).unwrap());
+        assert!(!strategy.advance(0).unwrap());
+        assert!(strategy.advance(1).unwrap());
+        assert_eq!(strategy.index().unwrap(), 2);
+
+        let strategy = Strategy::Random;
+        assert!(matches!(
+            strategy.advance(0),
+            Err(Error::WrongStrategy)
+        ));
+    }
+
     #[test]
     fn least_connections_strategy() {
         let strategy_str = "least_connections";
@@ -222,4 +396,47 @@ mod tests {
                 .is_empty()
         );
     }
+
+    #[test]
+    fn consistent_hash_strategy() {
+        let strategy = Strategy::from("consistent_hash");
+        assert_eq!(strategy.as_ref(), "consistent_hash");
+        assert_eq!(
+            strategy
+                .hash_header()
+                .unwrap(),
+            DEFAULT_HASH_HEADER
+        );
+        assert!(strategy.advance(0).unwrap());
+        assert!(matches!(
+            Strategy::Random.hash_header(),
+            Err(Error::WrongStrategy)
+        ));
+    }
+
+    #[test]
+    fn jump_consistent_hash() {
+        assert_eq!(jump_hash("session", 1), 0);
+        for buckets in 1..10 {
+            assert!(jump_hash("session", buckets) < buckets);
+        }
+
+        // Growing from 4 to 5 buckets only moves keys to the new bucket.
+        for key in (0..200).map(|i| i.to_string()) {
+            let before = jump_hash(&key, 4);
+            let after = jump_hash(&key, 5);
+            assert!(after == before || after == 4);
+        }
+    }
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        // Pinned: a key must land in the same bucket on every build.
+        assert_eq!(jump_hash("session", 16), 11);
+        assert_eq!(jump_hash("user-42", 100), 79);
+        assert_eq!(jump_hash("user-42", 1000), 364);
+    }
 }
@@ -4,6 +4,7 @@ use {
         Error,
         core::Protocol,
     },
+    std::fmt,
 };
 
 /// Uniform Resource Identifier for BakBon.
@@ -79,6 +80,16 @@ impl Address {
             .as_deref()
             .unwrap_or_default()
     }
+
+    /// Returns a copy of the address moved onto the scheme and authority
+    /// of another one, keeping its path, query and fragment.
+    pub(crate) fn rebase(&self, base: &Address) -> Self {
+        Self {
+            scheme: base.scheme.clone(),
+            authority: base.authority.clone(),
+            ..self.clone()
+        }
+    }
 }
 
 impl TryInto<Address> for &str {
@@ -111,9 +122,10 @@ impl TryInto<Address> for &str {
     }
 }
 
-impl ToString for Address {
-    fn to_string(&self) -> String {
-        format!(
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
             "{}://{}{}{}{}",
             self.scheme,
             self.authority,
@@ -175,6 +187,18 @@ mod tests {
         assert_eq!(address.to_string(), uri);
     }
 
+    #[test]
+    fn rebase_address() {
+        let address = Address::parse("http://orders-v1/orders/42?full=1").unwrap();
+        let base = Address::parse("tcp://orders-v2/ignored").unwrap();
+        assert_eq!(
+            address
+                .rebase(&base)
+                .to_string(),
+            "tcp://orders-v2/orders/42?full=1"
+        );
+    }
+
     #[test]
     fn address_with_custom_protocol() {
         let uri = "mpsc://uri-address.custom";
@@ -4,6 +4,7 @@ use {
         fmt,
         result,
         sync::PoisonError,
+        time::Duration,
     },
 };
 
@@ -27,16 +28,104 @@ pub type Result<T> = result::Result<T, Error>;
 /// - [`ServiceNotFound`](Error::ServiceNotFound): The requested
 ///   [`Service`](crate::Service) was not found in the
 ///   [`Registry`](crate::Registry).
-/// - [`ProcessorNotFound`](Error::ProcessorNotFound): The requested
-///   [`Processor`](crate::Processor) was not found in the .
+/// - [`ProcessorNotFound`](Error::ProcessorNotFound): No
+///   [`Processor`](crate::Processor) is registered for the given path.
+/// - [`ExtractionFailed`](Error::ExtractionFailed): A handler argument
+///   could not be extracted from the [`Envelope`].
+/// - [`SerializationFailed`](Error::SerializationFailed): A handler return
+///   value could not be serialized into a reply.
+/// - [`UnsupportedVersion`](Error::UnsupportedVersion): No
+///   [`Upcaster`](crate::Upcaster) chain leads from this schema version to
+///   the current one.
+/// - [`UpcastFailed`](Error::UpcastFailed): A dequeued [`Envelope`] could
+///   not be upcasted; it is handed back with the error of the
+///   [`Upcasters`](crate::Upcasters).
+/// - [`TaskFailed`](Error::TaskFailed): A task running a service panicked
+///   or was cancelled.
+/// - [`TimedOut`](Error::TimedOut): No reply came back within the given
+///   [`Duration`].
+/// - [`DuplicateCorrelation`](Error::DuplicateCorrelation): A request with
+///   the same correlation id is already waiting for its reply.
+/// - [`Unavailable`](Error::Unavailable): A [`Service`](crate::Service)
+///   cannot process the request right now, but may later.
+/// - [`CircuitOpen`](Error::CircuitOpen): The circuit breaker of every
+///   instance of the [`Service`](crate::Service) is open.
+/// - [`Throttled`](Error::Throttled): A rate or concurrency limit of the
+///   route was reached; the request may be sent again after the given
+///   [`Duration`].
+/// - [`InvalidVersion`](Error::InvalidVersion): A
+///   [`SemVer`](crate::SemVer) or [`VersionRange`](crate::VersionRange)
+///   could not be parsed.
+/// - [`NoMatchingVersion`](Error::NoMatchingVersion): No instance of the
+///   [`Service`](crate::Service) has a version in the requested range; the
+///   registered versions are listed.
+/// - [`HopLimitExceeded`](Error::HopLimitExceeded): A message was
+///   forwarded between [`Router`](crate::Router)s as many times as
+///   allowed.
+/// - [`InvalidAdvertisement`](Error::InvalidAdvertisement): An
+///   [`Advertisement`](crate::Advertisement) could not be decoded.
+/// - [`SagaNotFound`](Error::SagaNotFound): No [`Saga`](crate::Saga) run
+///   with this id is stored.
+/// - [`PersistenceFailed`](Error::PersistenceFailed): The state of a
+///   [`Saga`](crate::Saga) run could not be saved or loaded.
 #[derive(Debug)]
 pub enum Error {
     InvalidAddress,
     WrongStrategy,
-    QueueFull(Envelope),
+    QueueFull(Box<Envelope>),
     LockFailed(String),
     ServiceNotFound,
-    ProcessorNotFound,
+    ProcessorNotFound(String),
+    UnsupportedVersion(String, u32),
+    UpcastFailed(Box<Envelope>, Box<Error>),
+    ExtractionFailed(String),
+    SerializationFailed(String),
+    TaskFailed(String),
+    TimedOut(Duration),
+    DuplicateCorrelation(String),
+    Unavailable(String),
+    CircuitOpen,
+    Throttled(Duration),
+    InvalidVersion(String),
+    NoMatchingVersion(String, Vec<String>),
+    HopLimitExceeded(usize),
+    InvalidAdvertisement(String),
+    SagaNotFound(String),
+    PersistenceFailed(String),
+}
+
+impl Error {
+    /// Returns whether the operation may succeed if attempted again.
+    ///
+    /// Transient failures are retryable:
+    /// [`Unavailable`](Error::Unavailable),
+    /// [`TimedOut`](Error::TimedOut), [`TaskFailed`](Error::TaskFailed),
+    /// [`QueueFull`](Error::QueueFull) and
+    /// [`LockFailed`](Error::LockFailed). Every other error would fail the
+    /// same way again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Unavailable(_)
+                | Self::TimedOut(_)
+                | Self::TaskFailed(_)
+                | Self::QueueFull(_)
+                | Self::LockFailed(_)
+        )
+    }
+
+    /// Returns how long to wait before sending a
+    /// [`Throttled`](Error::Throttled) request again.
+    ///
+    /// Throttling is not [retryable](Error::is_retryable): the request is
+    /// fine and the [`Service`](crate::Service) healthy, but retrying
+    /// before this hint would be throttled again.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Throttled(retry_after) => Some(*retry_after),
+            _ => None,
+        }
+    }
 }
 
 impl<T> From<PoisonError<T>> for Error {
@@ -53,7 +142,42 @@ impl fmt::Display for Error {
             Self::QueueFull(msg) => write!(f, "Queue is full: {:?}", msg),
             Self::LockFailed(e) => write!(f, "Failed to acquire enqueue lock: {}", e),
             Self::ServiceNotFound => f.write_str("Service not found."),
-            Self::ProcessorNotFound => f.write_str("Processor not found."),
+            Self::ProcessorNotFound(path) => write!(f, "Processor not found: {path}"),
+            Self::UnsupportedVersion(schema, version) => {
+                write!(
+                    f,
+                    "Unsupported version {version} of schema '{schema}'."
+                )
+            }
+            Self::UpcastFailed(_, e) => write!(f, "Upcast failed: {e}"),
+            Self::ExtractionFailed(e) => write!(f, "Extraction failed: {e}"),
+            Self::SerializationFailed(e) => write!(f, "Serialization failed: {e}"),
+            Self::TaskFailed(e) => write!(f, "Task failed: {e}"),
+            Self::TimedOut(timeout) => write!(f, "Timed out after {timeout:?}."),
+            Self::DuplicateCorrelation(id) => {
+                write!(f, "Correlation id already pending: {id}")
+            }
+            Self::Unavailable(e) => write!(f, "Service unavailable: {e}"),
+            Self::CircuitOpen => f.write_str("Circuit open on every instance."),
+            Self::Throttled(retry_after) => {
+                write!(f, "Throttled: retry after {retry_after:?}.")
+            }
+            Self::InvalidVersion(version) => write!(f, "Invalid version: {version}"),
+            Self::NoMatchingVersion(range, registered) => match registered.is_empty() {
+                true => write!(
+                    f,
+                    "No version matches {range}: none registered."
+                ),
+                false => write!(
+                    f,
+                    "No version matches {range}: registered {}.",
+                    registered.join(", ")
+                ),
+            },
+            Self::HopLimitExceeded(hops) => write!(f, "Hop limit of {hops} exceeded."),
+            Self::InvalidAdvertisement(e) => write!(f, "Invalid advertisement: {e}"),
+            Self::SagaNotFound(id) => write!(f, "Saga not found: {id}"),
+            Self::PersistenceFailed(e) => write!(f, "Persistence failed: {e}"),
         }
     }
 }
@@ -85,7 +209,36 @@ mod tests {
         let wrong_strategy = Error::WrongStrategy;
         let lock_failed = Error::LockFailed("test".to_string());
         let service_not_found = Error::ServiceNotFound;
-        let processor_not_found = Error::ProcessorNotFound;
+        let processor_not_found = Error::ProcessorNotFound("/users".to_string());
+        let unsupported_version = Error::UnsupportedVersion("order".to_string(), 3);
+        let upcast_failed = Error::UpcastFailed(
+            Box::new(Envelope::new(
+                Address::parse("http://service.com").unwrap(),
+                Address::parse("http://queue.com").unwrap(),
+                Payload::default(),
+            )),
+            Box::new(Error::UnsupportedVersion(
+                "order".to_string(),
+                3,
+            )),
+        );
+        let extraction_failed = Error::ExtractionFailed("missing header".to_string());
+        let serialization_failed = Error::SerializationFailed("bad value".to_string());
+        let task_failed = Error::TaskFailed("cancelled".to_string());
+        let timed_out = Error::TimedOut(Duration::from_millis(250));
+        let duplicate_correlation = Error::DuplicateCorrelation("42".to_string());
+        let unavailable = Error::Unavailable("restarting".to_string());
+        let circuit_open = Error::CircuitOpen;
+        let throttled = Error::Throttled(Duration::from_millis(40));
+        let invalid_version = Error::InvalidVersion("2.x.1".to_string());
+        let no_matching_version = Error::NoMatchingVersion(
+            "^2.1".to_string(),
+            vec!["1.4.0".to_string(), "3.0.0".to_string()],
+        );
+        let hop_limit_exceeded = Error::HopLimitExceeded(8);
+        let invalid_advertisement = Error::InvalidAdvertisement("missing origin".to_string());
+        let saga_not_found = Error::SagaNotFound("42".to_string());
+        let persistence_failed = Error::PersistenceFailed("disk full".to_string());
 
         assert_eq!(invalid_addr.to_string(), "Invalid address.");
         assert_eq!(
@@ -102,8 +255,91 @@ mod tests {
         );
         assert_eq!(
             processor_not_found.to_string(),
-            "Processor not found."
+            "Processor not found: /users"
+        );
+        assert_eq!(
+            unsupported_version.to_string(),
+            "Unsupported version 3 of schema 'order'."
+        );
+        assert_eq!(
+            upcast_failed.to_string(),
+            "Upcast failed: Unsupported version 3 of schema 'order'."
+        );
+        assert_eq!(
+            extraction_failed.to_string(),
+            "Extraction failed: missing header"
+        );
+        assert_eq!(
+            serialization_failed.to_string(),
+            "Serialization failed: bad value"
+        );
+        assert_eq!(
+            task_failed.to_string(),
+            "Task failed: cancelled"
+        );
+        assert_eq!(
+            timed_out.to_string(),
+            "Timed out after 250ms."
+        );
+        assert_eq!(
+            duplicate_correlation.to_string(),
+            "Correlation id already pending: 42"
+        );
+        assert_eq!(
+            unavailable.to_string(),
+            "Service unavailable: restarting"
+        );
+        assert_eq!(
+            circuit_open.to_string(),
+            "Circuit open on every instance."
+        );
+        assert_eq!(
+            throttled.to_string(),
+            "Throttled: retry after 40ms."
+        );
+        assert_eq!(
+            invalid_version.to_string(),
+            "Invalid version: 2.x.1"
+        );
+        assert_eq!(
+            no_matching_version.to_string(),
+            "No version matches ^2.1: registered 1.4.0, 3.0.0."
+        );
+        assert_eq!(
+            Error::NoMatchingVersion("^2.1".to_string(), Vec::new()).to_string(),
+            "No version matches ^2.1: none registered."
+        );
+        assert_eq!(
+            hop_limit_exceeded.to_string(),
+            "Hop limit of 8 exceeded."
+        );
+        assert_eq!(
+            invalid_advertisement.to_string(),
+            "Invalid advertisement: missing origin"
+        );
+        assert_eq!(
+            saga_not_found.to_string(),
+            "Saga not found: 42"
+        );
+        assert_eq!(
+            persistence_failed.to_string(),
+            "Persistence failed: disk full"
+        );
+    }
+
+    #[test]
+    fn retryable_errors() {
+        assert!(Error::Unavailable("restarting".to_string()).is_retryable());
+        assert!(Error::TimedOut(Duration::from_secs(1)).is_retryable());
+        assert!(Error::TaskFailed("panicked".to_string()).is_retryable());
+        assert!(!Error::ServiceNotFound.is_retryable());
+        assert!(!Error::ExtractionFailed("missing header".to_string()).is_retryable());
+        assert!(!Error::Throttled(Duration::from_secs(1)).is_retryable());
+        assert_eq!(
+            Error::Throttled(Duration::from_secs(1)).retry_after(),
+            Some(Duration::from_secs(1))
         );
+        assert_eq!(Error::CircuitOpen.retry_after(), None);
     }
 
     #[test]
@@ -133,7 +369,7 @@ mod tests {
         let payload = Payload::default();
 
         let msg = Envelope::new(src.clone(), dst.clone(), payload.clone());
-        let queue_full = Error::QueueFull(msg);
+        let queue_full = Error::QueueFull(Box::new(msg));
         assert!(matches!(queue_full, Error::QueueFull(_)));
         assert_eq!(
             queue_full.to_string(),
@@ -0,0 +1,33 @@
+use std::sync::{
+    LockResult,
+    PoisonError,
+};
+
+/// Recovers the guard of a poisoned lock of the routing state.
+///
+/// The [`Router`](crate::Router) keeps its state (worker backlog, circuit
+/// breakers, rate limit buckets, peers, mirror stats, live registry)
+/// behind locks taken on every message. A lock is only poisoned by a panic
+/// while it is held, and none of these critical sections can leave their
+/// data half updated:
+///
+/// - they only update counters, flags and maps, and never run user code in
+///   the middle of an update;
+/// - the user code they call, such as
+///   [`Service::address()`](crate::Service::address) or
+///   [`Service::duplicate()`](crate::Service::duplicate) while registering
+///   an instance, runs before anything is changed.
+///
+/// Recovering the guard keeps the [`Router`](crate::Router) serving after
+/// such a panic, where reporting [`LockFailed`](crate::Error::LockFailed)
+/// would fail every message from then on. Locks guarding data a panic can
+/// leave inconsistent, such as a [`Queue`](crate::Queue), report it
+/// instead.
+pub(crate) trait Recover<G> {
+    /// Returns the guard, whether the lock was poisoned or not.
+    fn recover(self) -> G;
+}
+
+impl<G> Recover<G> for LockResult<G> {
+    fn recover(self) -> G { self.unwrap_or_else(PoisonError::into_inner) }
+}
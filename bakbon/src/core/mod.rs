@@ -2,12 +2,17 @@
 //!
 //! - [`Protocol`] models the transport scheme (e.g. "tcp", "http"...).
 //! - [`Address`] is a URI-like endpoint built on top of or [`Protocol`].
+//! - [`SemVer`] and [`VersionRange`] version [`Service`](crate::Service)
+//!   instances and the requests they accept.
 //! - [`Error`] and [`Result`] are used for BakBon error handling.
 
 mod address;
 mod error;
+mod lock;
 mod protocol;
+mod semver;
 
+pub(crate) use lock::Recover;
 pub use {
     address::Address,
     error::{
@@ -15,4 +20,8 @@ pub use {
         Result,
     },
     protocol::Protocol,
+    semver::{
+        SemVer,
+        VersionRange,
+    },
 };
@@ -0,0 +1,684 @@
+use {
+    crate::{
+        Error,
+        Result,
+    },
+    std::{
+        cmp::Ordering,
+        fmt,
+    },
+};
+
+/// Semantic version of a [`Service`](crate::Service) instance, such as
+/// `2.1.3` or `3.0.0-beta.1`.
+///
+/// Versions are ordered by precedence: `major`, `minor` and `patch`
+/// numbers first, then a version with a pre-release comes before the same
+/// version without one. Build metadata (`+build.5`) is kept, but only
+/// breaks ties: [`cmp_precedence()`](SemVer::cmp_precedence) ignores it,
+/// so that builds of the same version rank the same.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let stable = SemVer::parse("2.1.3")?;
+/// let beta = SemVer::parse("3.0.0-beta.1")?;
+/// assert_eq!(stable.major(), 2);
+/// assert!(stable < beta);
+/// assert!(beta < SemVer::parse("3.0.0")?);
+/// assert!(SemVer::parse("2.1").is_err());
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SemVer {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre:   Vec<Identifier>,
+    build: String,
+}
+
+/// Identifier of a pre-release: numeric ones come before alphanumeric
+/// ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl SemVer {
+    /// Creates a version without pre-release nor build metadata.
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+            build: String::new(),
+        }
+    }
+
+    /// Parses a `major.minor.patch` version, optionally followed by a
+    /// `-pre.release` and `+build` metadata.
+    pub fn parse(version: &str) -> Result<Self> {
+        let invalid = || Error::InvalidVersion(version.to_string());
+        let (version_pre, build) = split(version.trim(), '+');
+        let (numbers, pre) = split(version_pre, '-');
+
+        let numbers: Vec<u64> = numbers
+            .split('.')
+            .map(number)
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let [major, minor, patch] = numbers[..]
+        else {
+            return Err(invalid());
+        };
+        let pre = pre_release(pre).ok_or_else(invalid)?;
+        let build = match build {
+            Some(build)
+                if !build
+                    .split('.')
+                    .all(is_identifier) =>
+            {
+                return Err(invalid());
+            }
+            Some(build) => build.to_string(),
+            None => String::new(),
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        })
+    }
+
+    /// Returns the major version number.
+    pub fn major(&self) -> u64 { self.major }
+
+    /// Returns the minor version number.
+    pub fn minor(&self) -> u64 { self.minor }
+
+    /// Returns the patch version number.
+    pub fn patch(&self) -> u64 { self.patch }
+
+    /// Returns whether this is a pre-release.
+    pub fn is_prerelease(&self) -> bool { !self.pre.is_empty() }
+
+    /// Compares the precedence of two versions: `major`, `minor`, `patch`
+    /// and pre-release, ignoring build metadata.
+    pub fn cmp_precedence(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| compare_pre(&self.pre, &other.pre))
+    }
+}
+
+impl Ord for SemVer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_precedence(other)
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for SemVer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}",
+            self.major, self.minor, self.patch
+        )?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", join(&self.pre))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+
+/// Range of [`SemVer`]s accepted by a request, with the syntax of Cargo
+/// requirements.
+///
+/// A range is a comma-separated list of comparators, all of which must
+/// match:
+/// - `^2.1` (or `2.1`): compatible updates, `>=2.1.0, <3.0.0`; below `1.0`
+///   the minor version is the breaking one, so `^0.2` is `>=0.2.0,
+///   <0.3.0`.
+/// - `~2.1`: patch updates, `>=2.1.0, <2.2.0`.
+/// - `=2.1`, `>2.1`, `>=2.1.3`, `<3`, `<=2.1`: plain comparisons.
+/// - `2.*`, `2.1.x`, `*`: wildcards.
+///
+/// Pre-releases only match a range naming a pre-release of the same
+/// `major.minor.patch`, e.g. `>=3.0.0-beta` matches `3.0.0-rc.1` but not
+/// `3.1.0-alpha`.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let range = VersionRange::parse("^2.1")?;
+/// assert!(range.matches(&SemVer::parse("2.4.0")?));
+/// assert!(!range.matches(&SemVer::parse("2.0.9")?));
+/// assert!(!range.matches(&SemVer::parse("3.0.0")?));
+///
+/// let range = VersionRange::parse(">=1.2, <2")?;
+/// assert!(range.matches(&SemVer::parse("1.9.0")?));
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRange {
+    text:        String,
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op:    Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre:   Vec<Identifier>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    Wildcard,
+}
+
+impl VersionRange {
+    /// Parses a range, such as `^2.1` or `>=1.2, <2`.
+    pub fn parse(range: &str) -> Result<Self> {
+        let invalid = || Error::InvalidVersion(range.to_string());
+        let text = range.trim();
+        if text.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut comparators = Vec::new();
+        for comparator in text.split(',') {
+            let comparator = comparator.trim();
+            if comparator == "*" {
+                continue;
+            }
+            comparators.push(Comparator::parse(comparator).ok_or_else(invalid)?);
+        }
+        Ok(Self {
+            text: text.to_string(),
+            comparators,
+        })
+    }
+
+    /// Returns whether a version is in the range.
+    pub fn matches(&self, version: &SemVer) -> bool {
+        let comparators_match = self
+            .comparators
+            .iter()
+            .all(|comparator| comparator.matches(version));
+        let pre_allowed = !version.is_prerelease()
+            || self
+                .comparators
+                .iter()
+                .any(|comparator| comparator.allows_pre(version));
+        comparators_match && pre_allowed
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.text) }
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Option<Self> {
+        let (op, rest) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| {
+            text.strip_prefix(prefix)
+                .map(|rest| (Some(op), rest.trim_start()))
+        })
+        .unwrap_or((None, text));
+
+        let (numbers, pre) = split(rest, '-');
+        let parts: Vec<&str> = numbers.split('.').collect();
+        if parts.is_empty() || parts.len() > 3 {
+            return None;
+        }
+
+        let is_wildcard = |part: &&str| matches!(*part, "*" | "x" | "X");
+        let wildcards = parts
+            .iter()
+            .position(is_wildcard);
+        let numbers: Vec<u64> = match wildcards {
+            Some(first)
+                if parts[first..]
+                    .iter()
+                    .all(is_wildcard) =>
+            {
+                parts[..first]
+                    .iter()
+                    .map(|part| number(part))
+                    .collect::<Option<_>>()?
+            }
+            Some(_) => return None,
+            None => parts
+                .iter()
+                .map(|part| number(part))
+                .collect::<Option<_>>()?,
+        };
+        let op = match (op, wildcards) {
+            (None | Some(Op::Exact), Some(_)) => Op::Wildcard,
+            (Some(_), Some(_)) => return None,
+            (None, None) => Op::Caret,
+            (Some(op), None) => op,
+        };
+        let pre = pre_release(pre)?;
+        if !pre.is_empty() && numbers.len() < 3 {
+            return None;
+        }
+
+        Some(Self {
+            op,
+            major: *numbers.first()?,
+            minor: numbers.get(1).copied(),
+            patch: numbers.get(2).copied(),
+            pre,
+        })
+    }
+
+    fn matches(&self, v: &SemVer) -> bool {
+        match self.op {
+            Op::Exact | Op::Wildcard => self.matches_exact(v),
+            Op::Greater => self.matches_greater(v),
+            Op::GreaterEq => self.matches_exact(v) || self.matches_greater(v),
+            Op::Less => self.matches_less(v),
+            Op::LessEq => self.matches_exact(v) || self.matches_less(v),
+            Op::Tilde => self.matches_tilde(v),
+            Op::Caret => self.matches_caret(v),
+        }
+    }
+
+    /// Returns whether the comparator names a pre-release of the same
+    /// `major.minor.patch` as a version.
+    fn allows_pre(&self, v: &SemVer) -> bool {
+        !self.pre.is_empty()
+            && self.major == v.major
+            && self.minor == Some(v.minor)
+            && self.patch == Some(v.patch)
+    }
+
+    fn matches_exact(&self, v: &SemVer) -> bool {
+        v.major == self.major
+            && self
+                .minor
+                .is_none_or(|minor| v.minor == minor)
+            && self
+                .patch
+                .is_none_or(|patch| v.patch == patch)
+            && (self.patch.is_none() || v.pre == self.pre)
+    }
+
+    fn matches_greater(&self, v: &SemVer) -> bool {
+        if v.major != self.major {
+            return v.major > self.major;
+        }
+        let Some(minor) = self.minor
+        else {
+            return false;
+        };
+        if v.minor != minor {
+            return v.minor > minor;
+        }
+        let Some(patch) = self.patch
+        else {
+            return false;
+        };
+        if v.patch != patch {
+            return v.patch > patch;
+        }
+        compare_pre(&v.pre, &self.pre).is_gt()
+    }
+
+    fn matches_less(&self, v: &SemVer) -> bool {
+        if v.major != self.major {
+            return v.major < self.major;
+        }
+        let Some(minor) = self.minor
+        else {
+            return false;
+        };
+        if v.minor != minor {
+            return v.minor < minor;
+        }
+        let Some(patch) = self.patch
+        else {
+            return false;
+        };
+        if v.patch != patch {
+            return v.patch < patch;
+        }
+        compare_pre(&v.pre, &self.pre).is_lt()
+    }
+
+    fn matches_tilde(&self, v: &SemVer) -> bool {
+        if v.major != self.major
+            || self
+                .minor
+                .is_some_and(|minor| v.minor != minor)
+        {
+            return false;
+        }
+        match self.patch {
+            Some(patch) if v.patch != patch => v.patch > patch,
+            Some(_) => compare_pre(&v.pre, &self.pre).is_ge(),
+            None => true,
+        }
+    }
+
+    fn matches_caret(&self, v: &SemVer) -> bool {
+        if v.major != self.major {
+            return false;
+        }
+        let Some(minor) = self.minor
+        else {
+            return true;
+        };
+        let Some(patch) = self.patch
+        else {
+            return match self.major {
+                0 => v.minor == minor,
+                _ => v.minor >= minor,
+            };
+        };
+
+        if v.minor != minor {
+            // Below 1.0, the minor version is the breaking one.
+            return self.major > 0 && v.minor > minor;
+        }
+        if self.major == 0 && minor == 0 && v.patch != patch {
+            return false;
+        }
+        if v.patch != patch {
+            return v.patch > patch;
+        }
+        compare_pre(&v.pre, &self.pre).is_ge()
+    }
+}
+
+/// Compares pre-releases: a version without one comes after any with one.
+fn compare_pre(a: &[Identifier], b: &[Identifier]) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.cmp(b),
+    }
+}
+
+/// Parses a version number, without leading zeros.
+fn number(part: &str) -> Option<u64> {
+    let valid = !part.is_empty()
+        && part
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        && (part == "0" || !part.starts_with('0'));
+    valid
+        .then(|| part.parse().ok())
+        .flatten()
+}
+
+fn is_identifier(part: &str) -> bool {
+    !part.is_empty()
+        && part
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Splits a text at the first occurrence of a separator, if any.
+fn split(text: &str, separator: char) -> (&str, Option<&str>) {
+    match text.split_once(separator) {
+        Some((head, tail)) => (head, Some(tail)),
+        None => (text, None),
+    }
+}
+
+/// Parses the dot-separated identifiers of a pre-release, if any.
+fn pre_release(pre: Option<&str>) -> Option<Vec<Identifier>> {
+    let Some(pre) = pre
+    else {
+        return Some(Vec::new());
+    };
+    pre.split('.')
+        .map(|part| match number(part) {
+            Some(n) => Some(Identifier::Numeric(n)),
+            None if is_identifier(part) => Some(Identifier::Alphanumeric(part.to_string())),
+            None => None,
+        })
+        .collect()
+}
+
+fn join(pre: &[Identifier]) -> String {
+    pre.iter()
+        .map(|identifier| match identifier {
+            Identifier::Numeric(n) => n.to_string(),
+            Identifier::Alphanumeric(s) => s.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> SemVer { SemVer::parse(version).unwrap() }
+
+    fn matching(range: &str, versions: &[&str]) -> Vec<String> {
+        let range = VersionRange::parse(range).unwrap();
+        versions
+            .iter()
+            .filter(|version| range.matches(&v(version)))
+            .map(|version| version.to_string())
+            .collect()
+    }
+
+    const VERSIONS: &[&str] = &[
+        "0.2.3",
+        "0.2.9",
+        "0.3.0",
+        "1.0.0",
+        "1.2.0",
+        "1.2.7",
+        "1.3.0",
+        "2.0.0-rc.1",
+        "2.0.0",
+    ];
+
+    #[test]
+    fn parse_version() {
+        let version = v("1.2.3-alpha.10+build.5");
+        assert_eq!(
+            (
+                version.major(),
+                version.minor(),
+                version.patch()
+            ),
+            (1, 2, 3)
+        );
+        assert!(version.is_prerelease());
+        assert_eq!(version.to_string(), "1.2.3-alpha.10+build.5");
+
+        for invalid in [
+            "1.2",
+            "1.2.3.4",
+            "01.2.3",
+            "1.2.x",
+            "1.2.3-",
+            "1.2.3-a..b",
+            "v1.2.3",
+        ] {
+            assert!(matches!(
+                SemVer::parse(invalid),
+                Err(Error::InvalidVersion(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn version_precedence() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.10.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                v(pair[0]) < v(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        assert_eq!(
+            VERSIONS
+                .iter()
+                .map(|version| v(version))
+                .max(),
+            Some(v("2.0.0"))
+        );
+
+        let (a, b) = (v("2.4.1+a"), v("2.4.1+b"));
+        assert!(a < b);
+        assert!(a.cmp_precedence(&b).is_eq());
+        assert!(
+            v("2.4.1-rc.1+b")
+                .cmp_precedence(&a)
+                .is_lt()
+        );
+    }
+
+    #[test]
+    fn caret_ranges() {
+        assert_eq!(
+            matching("^1.2", VERSIONS),
+            ["1.2.0", "1.2.7", "1.3.0"]
+        );
+        assert_eq!(
+            matching("1.2.7", VERSIONS),
+            ["1.2.7", "1.3.0"]
+        );
+        assert_eq!(matching("^0.2.4", VERSIONS), ["0.2.9"]);
+        assert_eq!(
+            matching("^0", VERSIONS),
+            ["0.2.3", "0.2.9", "0.3.0"]
+        );
+        assert_eq!(matching("^2", VERSIONS), ["2.0.0"]);
+    }
+
+    #[test]
+    fn tilde_and_wildcard_ranges() {
+        assert_eq!(
+            matching("~1.2", VERSIONS),
+            ["1.2.0", "1.2.7"]
+        );
+        assert_eq!(matching("~1.2.3", VERSIONS), ["1.2.7"]);
+        assert_eq!(
+            matching("1.*", VERSIONS),
+            ["1.0.0", "1.2.0", "1.2.7", "1.3.0"]
+        );
+        assert_eq!(
+            matching("0.2.x", VERSIONS),
+            ["0.2.3", "0.2.9"]
+        );
+        assert_eq!(matching("*", VERSIONS).len(), 8);
+    }
+
+    #[test]
+    fn comparison_ranges() {
+        assert_eq!(
+            matching(">=1.2, <2", VERSIONS),
+            ["1.2.0", "1.2.7", "1.3.0"]
+        );
+        assert_eq!(
+            matching(">1.2", VERSIONS),
+            ["1.3.0", "2.0.0"]
+        );
+        assert_eq!(
+            matching("<=0.2", VERSIONS),
+            ["0.2.3", "0.2.9"]
+        );
+        assert_eq!(matching("=1.2.7", VERSIONS), ["1.2.7"]);
+    }
+
+    #[test]
+    fn prerelease_ranges() {
+        assert_eq!(
+            matching(">=2.0.0-beta", VERSIONS),
+            ["2.0.0-rc.1", "2.0.0"]
+        );
+        assert_eq!(
+            matching("^2.0.0-rc.1", VERSIONS),
+            ["2.0.0-rc.1", "2.0.0"]
+        );
+        assert!(
+            !VersionRange::parse(">=1.0.0-beta")
+                .unwrap()
+                .matches(&v("2.0.0-rc.1"))
+        );
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        for invalid in ["", "^", "^1.2.3.4", ">=1.*", "1.*.3", "^x1", "1.2, nope"] {
+            assert!(
+                matches!(
+                    VersionRange::parse(invalid),
+                    Err(Error::InvalidVersion(_))
+                ),
+                "{invalid}"
+            );
+        }
+        assert_eq!(
+            VersionRange::parse(" ^2.1 ")
+                .unwrap()
+                .to_string(),
+            "^2.1"
+        );
+    }
+}
@@ -90,7 +90,7 @@ mod tests {
         assert_eq!(builder.port, PORT);
         assert_eq!(builder.protocol, Protocol::default());
         assert_eq!(builder.max_payload_size, None);
-        assert_eq!(builder.compression, false);
+        assert!(!builder.compression);
         Ok(())
     }
 
@@ -102,7 +102,7 @@ mod tests {
         assert_eq!(gateway.port(), PORT);
         assert_eq!(gateway.protocol(), &Protocol::InProc);
         assert_eq!(gateway.max_payload_size, None);
-        assert_eq!(gateway.compression, false);
+        assert!(!gateway.compression);
         Ok(())
     }
 
@@ -144,7 +144,7 @@ mod tests {
         let gateway = builder
             .enable_compression()
             .build();
-        assert_eq!(gateway.compression(), true);
+        assert!(gateway.compression());
         Ok(())
     }
 }
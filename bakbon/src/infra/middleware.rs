@@ -1,18 +1,288 @@
-use crate::{
-    Envelope,
-    Result,
+use {
+    crate::{
+        Envelope,
+        Reply,
+        Result,
+    },
+    std::sync::Arc,
 };
 
-/// Example:
-/// ```ignore
-/// struct LogMiddleware;
-/// impl Middleware for LogMiddleware {
+/// Step of a middleware chain run around the processing of an
+/// [`Envelope`], such as logging, authentication or validation.
+///
+/// Middlewares are set on a [`Router`](crate::Router) with
+/// `RouterBuilder::middleware()`, or on a single
+/// [`Service`](crate::Service) with [`Layered`](crate::Layered), and run
+/// in the order they were added.
+///
+/// - [`intercept()`](Middleware::intercept) runs before the message is
+///   processed, and may rewrite it or stop it with an error.
+/// - [`around()`](Middleware::around) wraps the rest of the chain: it can
+///   also answer in its place, or work on the [`Reply`]. By default, it
+///   calls [`intercept()`](Middleware::intercept) then the rest of the
+///   chain.
+///
+/// Middlewares must be `Send + Sync`, like the
+/// [`Service`](crate::Service)s they run in front of.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// struct Auth;
+///
+/// impl Middleware for Auth {
 ///     fn intercept(&self, msg: Envelope) -> Result<Envelope> {
-///         println!("incoming: {:?}", msg);
-///         Ok(msg)
+///         match msg.get_header("authorization") {
+///             Some(_) => Ok(msg),
+///             None => Err(Error::ExtractionFailed("missing credentials".into())),
+///         }
 ///     }
 /// }
+///
+/// struct Ping;
+///
+/// impl Middleware for Ping {
+///     fn around(&self, msg: Envelope, next: Next<'_>) -> Result<Reply> {
+///         match msg.payload().as_ref() {
+///             b"ping" => Ok(Some(msg.into_reply(Payload::from("pong")))),
+///             _ => next.run(msg),
+///         }
+///     }
+/// }
+///
+/// let router = Router::builder()
+///     .middleware(Auth)
+///     .middleware(Ping)
+///     .build();
+///
+/// let ping = Envelope::new(
+///     Address::parse("http://client.com")?,
+///     Address::parse("http://anywhere.com")?,
+///     Payload::from("ping"),
+/// );
+/// assert!(router.route(ping.clone()).is_err());
+///
+/// let reply = router.route(ping.header("authorization", "token"))?;
+/// assert_eq!(reply.unwrap().payload(), &Payload::from("pong"));
+/// # Ok::<(), Error>(())
 /// ```
-pub trait Middleware {
-    fn intercept(&self, message: Envelope) -> Result<Envelope>;
+pub trait Middleware: Send + Sync {
+    /// Returns the name of the middleware, shown by
+    /// [`Router::explain()`](crate::Router::explain). Defaults to the name
+    /// of its type.
+    fn name(&self) -> &str { std::any::type_name::<Self>() }
+
+    /// Inspects or rewrites a message before it is processed.
+    ///
+    /// Returning an error stops the message: the error is returned to the
+    /// caller and the rest of the chain does not run.
+    fn intercept(&self, message: Envelope) -> Result<Envelope> { Ok(message) }
+
+    /// Runs around the rest of the chain.
+    ///
+    /// Call [`next.run()`](Next::run) to hand the message over, or return
+    /// a [`Reply`] or an error without calling it to short-circuit.
+    fn around(&self, message: Envelope, next: Next<'_>) -> Result<Reply> {
+        let message = self.intercept(message)?;
+        next.run(message)
+    }
+}
+
+/// The rest of a middleware chain, ending with the processing of the
+/// message.
+pub struct Next<'a> {
+    chain:    &'a [Arc<dyn Middleware>],
+    endpoint: &'a dyn Fn(Envelope) -> Result<Reply>,
+}
+
+impl Next<'_> {
+    /// Runs the rest of the chain on a message and returns its [`Reply`].
+    pub fn run(self, message: Envelope) -> Result<Reply> {
+        match self.chain.split_first() {
+            Some((middleware, chain)) => middleware.around(
+                message,
+                Next {
+                    chain,
+                    endpoint: self.endpoint,
+                },
+            ),
+            None => (self.endpoint)(message),
+        }
+    }
+}
+
+/// An ordered middleware chain.
+#[derive(Clone, Default)]
+pub(crate) struct Chain(Vec<Arc<dyn Middleware>>);
+
+impl Chain {
+    /// Adds a middleware at the end of the chain.
+    pub(crate) fn push(&mut self, middleware: impl Middleware + 'static) {
+        self.0
+            .push(Arc::new(middleware));
+    }
+
+    /// Returns the number of middlewares in the chain.
+    pub(crate) fn len(&self) -> usize { self.0.len() }
+
+    /// Returns the names of the middlewares, in the order they run.
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|middleware| middleware.name().to_string())
+            .collect()
+    }
+
+    /// Runs the chain on a message, then `endpoint` if no middleware
+    /// short-circuited.
+    pub(crate) fn run<F>(&self, message: Envelope, endpoint: F) -> Result<Reply>
+    where
+        F: Fn(Envelope) -> Result<Reply>,
+    {
+        if self.0.is_empty() {
+            return endpoint(message);
+        }
+
+        Next {
+            chain:    &self.0,
+            endpoint: &endpoint,
+        }
+        .run(message)
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Error,
+            Payload,
+            testing::{
+                SRC,
+                message,
+            },
+        },
+        std::sync::Mutex,
+    };
+
+    /// Records when it runs, under a name.
+    struct Trace(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Trace {
+        fn name(&self) -> &str { self.0 }
+
+        fn around(&self, message: Envelope, next: Next<'_>) -> Result<Reply> {
+            let trail = |step: &str| {
+                self.1
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {step}", self.0))
+            };
+            trail("before");
+            let reply = next.run(message);
+            trail("after");
+            reply
+        }
+    }
+
+    /// Tags messages with a header.
+    struct Tag;
+
+    impl Middleware for Tag {
+        fn intercept(&self, message: Envelope) -> Result<Envelope> {
+            Ok(message.header("x-tag", "tagged"))
+        }
+    }
+
+    /// Rejects every message.
+    struct Reject;
+
+    impl Middleware for Reject {
+        fn intercept(&self, _message: Envelope) -> Result<Envelope> {
+            Err(Error::ExtractionFailed(
+                "rejected".to_string(),
+            ))
+        }
+    }
+
+    fn echo_tag(message: Envelope) -> Result<Reply> {
+        let tag = message
+            .get_header("x-tag")
+            .unwrap_or("untagged")
+            .to_string();
+        Ok(Some(message.into_reply(Payload::from(tag))))
+    }
+
+    #[test]
+    fn empty_chain() -> Result<()> {
+        let chain = Chain::default();
+        let reply = chain.run(
+            message(SRC, "http://service.com", "")?,
+            echo_tag,
+        )?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("untagged")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn chain_order() -> Result<()> {
+        let trail = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = Chain::default();
+        chain.push(Trace("outer", trail.clone()));
+        chain.push(Tag);
+        chain.push(Trace("inner", trail.clone()));
+        assert_eq!(chain.len(), 3);
+        let names = chain.names();
+        assert_eq!(names[0], "outer");
+        assert!(names[1].ends_with("::Tag"));
+        assert_eq!(names[2], "inner");
+
+        let reply = chain.run(
+            message(SRC, "http://service.com", "")?,
+            echo_tag,
+        )?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("tagged")
+        );
+        assert_eq!(
+            *trail.lock().unwrap(),
+            ["outer before", "inner before", "inner after", "outer after"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn short_circuit() -> Result<()> {
+        let trail = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = Chain::default();
+        chain.push(Reject);
+        chain.push(Trace("unreached", trail.clone()));
+
+        let reply = chain.run(
+            message(SRC, "http://service.com", "")?,
+            |_| panic!("endpoint reached"),
+        );
+        assert!(matches!(
+            reply,
+            Err(Error::ExtractionFailed(_))
+        ));
+        assert!(
+            trail
+                .lock()
+                .unwrap()
+                .is_empty()
+        );
+        Ok(())
+    }
 }
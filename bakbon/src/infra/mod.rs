@@ -1,7 +1,11 @@
 mod cache;
 mod middleware;
 
+pub(crate) use middleware::Chain;
 pub use {
     cache::Cache,
-    middleware::Middleware,
+    middleware::{
+        Middleware,
+        Next,
+    },
 };
@@ -6,15 +6,36 @@
 //!
 //! # Modules
 //!
-//! - `Balancer`: [`Balancer`] for load balancing.
-//! - `Core`: [`Address`], [`Protocol`], [`Error`], [`Result`].
-//! - `Discovery`: [`Registry`] for service discovery.
+//! - `Balancer`: [`Balancer`] for load balancing, with per-instance
+//!   circuit breakers set by a [`BreakerPolicy`].
+//! - `Core`: [`Address`], [`Protocol`], [`SemVer`] and [`VersionRange`],
+//!   [`Error`], [`Result`].
+//! - `Discovery`: [`Registry`] for service discovery, keyed by
+//!   [`ServiceKey`], and its [`LiveRegistry`] handle.
 //! - `Gateway`: [`Gateway`] for network communication.
-//! - `Message`: [`Envelope`], [`Headers`], [`Payload`], [`Reply`].
-//! - `Infra`: [`Cache`], [`Middleware`].
+//! - `Message`: [`Envelope`], [`Headers`], [`Payload`], [`Reply`],
+//!   [`Replies`], [`Upcasters`].
+//! - `Infra`: [`Cache`], [`Middleware`] chains run by the [`Router`] or by
+//!   a [`Layered`] service.
 //! - `Queue`: [`Queue`] and delivery semantics.
-//! - `Routing`: [`Router`] for message routing.
-//! - `Service`: [`Service`] and [`Processor`] traits.
+//! - `Routing`: [`Router`] for message routing, [`Gather`] for
+//!   scatter-gather, [`RetryPolicy`] for retries, [`Split`] and mirrors
+//!   for canary releases, [`Rule`]s for content-based routing, [`Saga`]s
+//!   for multi-step flows with compensations, [`PeerLink`]s forwarding to
+//!   peer routers, [`Explanation`]s and [`RouteInfo`]s for introspection,
+//!   [`RateLimit`]s and [`ConcurrencyLimit`]s throttling routes.
+//! - `Service`: [`Service`] and [`Processor`] traits, [`Dispatcher`],
+//!   typed [`handler`]s, [`Versioned`] instances.
+//!
+//! # Features
+//!
+//! - `json`: `Json` extractor and responder for typed handlers, JSON
+//!   export of [`Explanation`]s and [`RouteInfo`]s.
+//! - `async`: `AsyncService` and `AsyncProcessor` traits, the runtime
+//!   agnostic `AsyncRouter`, and the `Blocking` adapter registering sync
+//!   services unchanged.
+//! - `tokio`: `async` plus the `SpawnBlocking` adapter and
+//!   `AsyncRouter::spawn()`.
 //!
 //! # Quick Example
 //!
@@ -53,7 +74,7 @@
 //!         .build();
 //!
 //!     // Create a router.
-//!     let mut router = Router::builder()
+//!     let router = Router::builder()
 //!         .registry(registry)
 //!         .build();
 //!
@@ -72,61 +93,212 @@ mod queue;
 mod registry;
 mod router;
 mod service;
+#[cfg(test)]
+mod testing;
 
+#[cfg(feature = "json")]
+pub use service::Json;
+#[cfg(feature = "tokio")]
+pub use service::SpawnBlocking;
 pub use {
-    balancer::Balancer,
+    balancer::{
+        Balancer,
+        BreakerPolicy,
+        CircuitState,
+    },
     core::{
         Address,
         Error,
         Protocol,
         Result,
+        SemVer,
+        VersionRange,
     },
     gateway::Gateway,
     infra::{
         Cache,
         Middleware,
+        Next,
     },
     message::{
+        ACCEPT_VERSION_HEADER,
+        CORRELATION_HEADER,
+        DEADLINE_HEADER,
         Envelope,
         Headers,
+        Params,
         Payload,
+        REPLY_TO_HEADER,
+        Replies,
         Reply,
+        SCHEMA_HEADER,
+        Upcaster,
+        Upcasters,
+        VERSION_HEADER,
     },
     queue::Queue,
-    registry::Registry,
-    router::Router,
+    registry::{
+        KeyMode,
+        LiveRegistry,
+        Registry,
+        ServiceKey,
+    },
+    router::{
+        ATTEMPT_HEADER,
+        AdvertisedRoute,
+        Advertisement,
+        Aggregator,
+        Candidate,
+        ConcurrencyLimit,
+        Condition,
+        DEAD_LETTER_DESTINATION_HEADER,
+        DEAD_LETTER_ERROR_HEADER,
+        DEAD_LETTER_REASON_HEADER,
+        DEAD_LETTER_SERVICE_HEADER,
+        DeadLetterReason,
+        Decision,
+        Explanation,
+        FORWARDED_BY_HEADER,
+        FileSagaStore,
+        Gather,
+        GatherBuilder,
+        GatherReport,
+        HOPS_HEADER,
+        InstanceInfo,
+        MIRROR_HEADER,
+        MemorySagaStore,
+        MirrorStats,
+        PeerHealth,
+        PeerLink,
+        RateLimit,
+        RetryPolicy,
+        RouteInfo,
+        Router,
+        Rule,
+        RuleCheck,
+        SAGA_ID_HEADER,
+        SAGA_STEP_HEADER,
+        Saga,
+        SagaBuilder,
+        SagaState,
+        SagaStatus,
+        SagaStore,
+        Split,
+        Step,
+        StepState,
+        StepStatus,
+    },
     service::{
+        Dispatcher,
+        DispatcherBuilder,
+        FnProcessor,
+        FnService,
+        FromEnvelope,
+        Handler,
+        HandlerProcessor,
+        Header,
+        HeaderName,
+        Layered,
+        METHOD_HEADER,
+        Method,
+        Param,
+        ParamName,
+        Path,
         ProcMap,
         Processor,
+        Responder,
         Service,
+        ServiceArc,
         ServiceBox,
+        ServiceContext,
         ServiceMap,
         ServiceVec,
+        Source,
+        Versioned,
+        handler,
+        processor_fn,
+        service_fn,
+    },
+};
+#[cfg(feature = "async")]
+pub use {
+    router::{
+        AsyncRouter,
+        AsyncRouterBuilder,
+    },
+    service::{
+        AsyncProcessor,
+        AsyncService,
+        AsyncServiceBox,
+        Blocking,
+        BoxFuture,
     },
 };
 
 pub mod prelude {
     pub use crate::{
         Address,
+        Advertisement,
         Balancer,
+        BreakerPolicy,
         Cache,
+        CircuitState,
+        ConcurrencyLimit,
+        Condition,
+        DeadLetterReason,
+        Decision,
+        Dispatcher,
         Envelope,
         Error,
+        Explanation,
         Gateway,
+        Gather,
+        GatherReport,
         Headers,
+        KeyMode,
+        Layered,
+        LiveRegistry,
+        Method,
         Middleware,
+        MirrorStats,
+        Next,
+        Params,
         Payload,
+        PeerHealth,
+        PeerLink,
         ProcMap,
         Processor,
         Protocol,
         Queue,
+        RateLimit,
         Registry,
+        Replies,
         Reply,
         Result,
+        RetryPolicy,
+        RouteInfo,
         Router,
+        Rule,
+        Saga,
+        SagaState,
+        SagaStatus,
+        SemVer,
         Service,
+        ServiceArc,
         ServiceBox,
+        ServiceContext,
+        ServiceKey,
         ServiceMap,
         ServiceVec,
+        Split,
+        Step,
+        StepStatus,
+        Upcaster,
+        Upcasters,
+        VersionRange,
+        Versioned,
+        handler,
+        processor_fn,
+        service_fn,
     };
 }
@@ -1,14 +1,38 @@
 use {
     super::{
         Headers,
+        Params,
+        SCHEMA_HEADER,
+        VERSION_HEADER,
         route::Route,
     },
     crate::{
         Address,
         Payload,
     },
+    std::time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
 };
 
+/// Header carrying the id matching a reply to its request.
+pub const CORRELATION_HEADER: &str = "x-correlation-id";
+
+/// Header carrying the [`Address`] a reply must be sent to, when it is
+/// not the source of the request.
+pub const REPLY_TO_HEADER: &str = "x-reply-to";
+
+/// Header carrying the deadline of an [`Envelope`], in milliseconds since
+/// the Unix epoch.
+pub const DEADLINE_HEADER: &str = "x-deadline";
+
+/// Header carrying the [`VersionRange`](crate::VersionRange) of the
+/// [`Service`](crate::Service) instances accepted for an [`Envelope`],
+/// such as `^2.1`.
+pub const ACCEPT_VERSION_HEADER: &str = "x-accept-version";
+
 /// Application-level message wrapper with [`Headers`],
 /// [`Route`] and [`Payload`].
 ///
@@ -19,11 +43,18 @@ use {
 /// - [`Headers`] for metadata,
 /// - a [`Route`] with source and destination [`Address`]es,
 /// - a raw bytes [`Payload`]
-#[derive(Debug)]
+/// - [`Params`] captured from the destination path by a
+///   [`Dispatcher`](crate::Dispatcher).
+///
+/// Cloning an `Envelope` is cheap on the [`Payload`], whose bytes are
+/// shared, which lets the [`Router`](crate::Router) deliver copies to
+/// several services.
+#[derive(Debug, Clone)]
 pub struct Envelope {
     headers: Headers,
     route:   Route,
     payload: Payload,
+    params:  Params,
 }
 
 impl Envelope {
@@ -36,6 +67,7 @@ impl Envelope {
             headers: Headers::default(),
             route: Route::new(src, dst),
             payload,
+            params: Params::new(),
         }
     }
 
@@ -68,6 +100,12 @@ impl Envelope {
             .insert(k.to_string(), v.to_string());
     }
 
+    /// Removes a header in-place and returns its value, if it existed.
+    pub fn remove_header(&mut self, key: &str) -> Option<String> { self.headers.remove(key) }
+
+    /// Returns every header.
+    pub fn headers(&self) -> &Headers { &self.headers }
+
     /// Returns the value of a header given the key, if it exists.
     pub fn get_header(&self, key: &str) -> Option<&str> {
         self.headers
@@ -75,32 +113,148 @@ impl Envelope {
             .map(|v| v.as_str())
     }
 
+    /// Appends a path parameter in-place.
+    ///
+    /// Usually called by a [`Dispatcher`](crate::Dispatcher) with the
+    /// parameters captured from the destination path.
+    pub fn add_param(&mut self, name: &str, value: &str) {
+        self.params
+            .push((name.to_string(), value.to_string()));
+    }
+
+    /// Returns the value of a path parameter given its name, if it exists.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns all path parameters in capture order.
+    pub fn params(&self) -> &Params { &self.params }
+
     /// Converts this `Envelope` into a [`Reply`](crate::Reply) message
     /// with a new [`Payload`].
     ///
     /// This swaps the source and the destination so that the
     /// [`Reply`](crate::Reply) is routed back to the original sender,
     /// and replace the [`Payload`] with the provided bytes.
-    /// Existed [`Headers`] are preserved.
+    /// Existed [`Headers`] are preserved, including the correlation id.
+    /// If the `Envelope` has a valid [`reply_to()`](Envelope::reply_to)
+    /// address, the [`Reply`](crate::Reply) is sent there instead.
     /// Typical usage is inside
     /// [`Service::process()`](crate::Service::process) and/or
     /// [`Processor::execute()`](crate::Processor::execute) before
     /// returning.
-
     pub fn into_reply(mut self, payload: Payload) -> Self {
+        let reply_to = self.reply_to();
         self.route.swap_endpoints();
+        if let Some(reply_to) = reply_to {
+            self.route
+                .set_destination(reply_to);
+        }
+        self.payload = payload;
+        self
+    }
+
+    /// Replaces the [`Payload`] and returns the updated `Envelope`.
+    ///
+    /// Unlike [`into_reply()`](Envelope::into_reply), the route is left
+    /// untouched.
+    pub fn with_payload(mut self, payload: Payload) -> Self {
         self.payload = payload;
         self
     }
 
+    /// Replaces the destination [`Address`] and returns the updated
+    /// `Envelope`.
+    ///
+    /// Unlike [`into_reply()`](Envelope::into_reply), the source and the
+    /// [`Payload`] are left untouched.
+    pub fn redirect(mut self, destination: Address) -> Self {
+        self.route
+            .set_destination(destination);
+        self
+    }
+
+    /// Tags the `Envelope` with a [`Payload`] schema name and version and
+    /// returns the updated `Envelope`.
+    ///
+    /// ```ignore
+    /// let msg = Envelope::new(src, dst, payload).versioned("order", 2);
+    /// ```
+    pub fn versioned(self, schema: &str, version: u32) -> Self {
+        self.header(SCHEMA_HEADER, schema)
+            .header(VERSION_HEADER, &version.to_string())
+    }
+
+    /// Overrides the schema version header in-place.
+    pub fn set_version(&mut self, version: u32) {
+        self.add_header(VERSION_HEADER, &version.to_string());
+    }
+
+    /// Returns the [`Payload`] schema name, if any.
+    pub fn schema(&self) -> Option<&str> { self.get_header(SCHEMA_HEADER) }
+
+    /// Returns the [`Payload`] schema version, if any and valid.
+    pub fn version(&self) -> Option<u32> {
+        self.get_header(VERSION_HEADER)
+            .and_then(|v| v.parse().ok())
+    }
+
+    /// Returns the correlation id matching a reply to its request, if any.
+    ///
+    /// Set by [`Router::send_and_wait()`](crate::Router::send_and_wait)
+    /// and kept by [`into_reply()`](Envelope::into_reply).
+    pub fn correlation_id(&self) -> Option<&str> { self.get_header(CORRELATION_HEADER) }
+
+    /// Returns the [`Address`] a reply must be sent to, if any and valid.
+    pub fn reply_to(&self) -> Option<Address> {
+        self.get_header(REPLY_TO_HEADER)
+            .and_then(|v| Address::parse(v).ok())
+    }
+
+    /// Sets the time by which the `Envelope` must be processed and returns
+    /// the updated `Envelope`.
+    ///
+    /// The deadline is absolute, so it still holds after the `Envelope`
+    /// went through a [`Queue`](crate::Queue) or another
+    /// [`Router`](crate::Router).
+    pub fn with_deadline(self, deadline: SystemTime) -> Self {
+        let millis = deadline
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        self.header(DEADLINE_HEADER, &millis.to_string())
+    }
+
+    /// Sets the deadline of the `Envelope` to a given time from now and
+    /// returns the updated `Envelope`.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(SystemTime::now() + timeout)
+    }
+
+    /// Returns the time by which the `Envelope` must be processed, if any
+    /// and valid.
+    pub fn deadline(&self) -> Option<SystemTime> {
+        self.get_header(DEADLINE_HEADER)
+            .and_then(|v| v.parse().ok())
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
+    }
+
+    /// Returns the range of [`Service`](crate::Service) versions accepted
+    /// for the `Envelope`, if any, as written in its
+    /// [`ACCEPT_VERSION_HEADER`].
+    pub fn accepted_version(&self) -> Option<&str> { self.get_header(ACCEPT_VERSION_HEADER) }
+
     /// Returns the reference to the raw [`Payload`] bytes.
     pub fn payload(&self) -> &Payload { &self.payload }
 
     /// Returns the reference to the source [`Address`] of the `Envelope`.
-    pub fn source(&self) -> &Address { &self.route.source() }
+    pub fn source(&self) -> &Address { self.route.source() }
 
     /// Returns the reference to the `Envelope`'s destination [`Address`].
-    pub fn destination(&self) -> &Address { &self.route.destination() }
+    pub fn destination(&self) -> &Address { self.route.destination() }
 }
 
 //  +------------+
@@ -198,4 +352,110 @@ mod tests {
         assert!(encoding.is_some());
         assert_eq!(encoding.unwrap(), "utf-8");
     }
+
+    #[test]
+    fn message_params() {
+        let src = Address::parse(SRC).unwrap();
+        let dst = Address::parse(DST).unwrap();
+
+        let mut msg = Envelope::new(src, dst, Payload::new());
+        assert!(msg.params().is_empty());
+
+        msg.add_param("id", "42");
+        msg.add_param("order", "7");
+        assert_eq!(msg.param("id"), Some("42"));
+        assert_eq!(msg.param("order"), Some("7"));
+        assert!(msg.param("missing").is_none());
+        assert_eq!(msg.params()[1].0, "order");
+    }
+
+    #[test]
+    fn redirected_message() {
+        let src = Address::parse(SRC).unwrap();
+        let dst = Address::parse(DST).unwrap();
+        let other = Address::parse("https://other.com").unwrap();
+        let msg = Envelope::new(src.clone(), dst, Payload::from("data"));
+
+        let redirected = msg
+            .clone()
+            .redirect(other.clone());
+        assert_eq!(redirected.source(), &src);
+        assert_eq!(redirected.destination(), &other);
+        assert_eq!(redirected.payload(), msg.payload());
+    }
+
+    #[test]
+    fn versioned_message() {
+        let src = Address::parse(SRC).unwrap();
+        let dst = Address::parse(DST).unwrap();
+
+        let mut msg = Envelope::new(src, dst, Payload::new());
+        assert!(msg.schema().is_none());
+        assert!(msg.version().is_none());
+
+        msg = msg.versioned("order", 1);
+        assert_eq!(msg.schema(), Some("order"));
+        assert_eq!(msg.version(), Some(1));
+
+        msg.set_version(2);
+        assert_eq!(msg.version(), Some(2));
+        assert_eq!(msg.get_header(VERSION_HEADER), Some("2"));
+    }
+
+    #[test]
+    fn correlated_reply() {
+        let src = Address::parse(SRC).unwrap();
+        let dst = Address::parse(DST).unwrap();
+        let inbox = Address::parse("https://inbox.com").unwrap();
+
+        let msg = Envelope::new(src.clone(), dst.clone(), Payload::new())
+            .header(CORRELATION_HEADER, "42");
+        assert_eq!(msg.correlation_id(), Some("42"));
+        assert!(msg.reply_to().is_none());
+
+        let reply = msg
+            .clone()
+            .into_reply(Payload::from("ok"));
+        assert_eq!(reply.destination(), &src);
+        assert_eq!(reply.correlation_id(), Some("42"));
+
+        let reply = msg
+            .header(REPLY_TO_HEADER, "https://inbox.com")
+            .into_reply(Payload::from("ok"));
+        assert_eq!(reply.source(), &dst);
+        assert_eq!(reply.destination(), &inbox);
+    }
+
+    #[test]
+    fn message_with_deadline() {
+        let src = Address::parse(SRC).unwrap();
+        let dst = Address::parse(DST).unwrap();
+        let msg = Envelope::new(src, dst, Payload::new());
+        assert!(msg.deadline().is_none());
+
+        let deadline = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let msg = msg.with_deadline(deadline);
+        assert_eq!(
+            msg.get_header(DEADLINE_HEADER),
+            Some("1700000000123")
+        );
+        assert_eq!(msg.deadline(), Some(deadline));
+
+        let msg = msg.with_timeout(Duration::from_secs(60));
+        assert!(msg.deadline().unwrap() > SystemTime::now());
+    }
+
+    #[test]
+    fn message_with_accepted_version() {
+        let src = Address::parse(SRC).unwrap();
+        let dst = Address::parse(DST).unwrap();
+        let msg = Envelope::new(src, dst, Payload::new());
+        assert!(
+            msg.accepted_version()
+                .is_none()
+        );
+
+        let msg = msg.header(ACCEPT_VERSION_HEADER, "^2.1");
+        assert_eq!(msg.accepted_version(), Some("^2.1"));
+    }
 }
@@ -6,8 +6,13 @@
 //!   and routing  metadata.
 //! - [Headers] is a map of string key/value pairs attached to an
 //!   [`Envelope`].
+//! - [Params] are the path parameters captured by a
+//!   [`Dispatcher`](crate::Dispatcher).
 //! - [Reply] models a optional reply message returned by
-//!   [`Processor`](crate::Processor)
+//!   [`Processor`](crate::Processor), and [Replies] the outcome of a
+//!   message delivered to several services.
+//! - [`Upcasters`] migrate older [`Envelope`] schema versions to the
+//!   current one.
 //!
 //! High level components such as [`Gateway`](crate::Gateway),
 //! [`Service`](crate::Service), [`Router`](crate::Router) build on top of
@@ -15,12 +20,31 @@
 
 mod envelope;
 mod route;
+mod version;
 
-pub use envelope::Envelope;
 use {
+    crate::{
+        Result,
+        ServiceKey,
+    },
     bytes::Bytes,
     std::collections::HashMap,
 };
+pub use {
+    envelope::{
+        ACCEPT_VERSION_HEADER,
+        CORRELATION_HEADER,
+        DEADLINE_HEADER,
+        Envelope,
+        REPLY_TO_HEADER,
+    },
+    version::{
+        SCHEMA_HEADER,
+        Upcaster,
+        Upcasters,
+        VERSION_HEADER,
+    },
+};
 
 /// Message metadata attached to an [`Envelope`](super::Envelope)
 ///
@@ -30,8 +54,20 @@ use {
 /// - `x-correlation-id`
 pub type Headers = HashMap<String, String>;
 
+/// Path parameters attached to an [`Envelope`](super::Envelope).
+///
+/// Ordered `(name, value)` pairs captured from the destination path by a
+/// [`Dispatcher`](crate::Dispatcher), e.g. `[("id", "42")]` for
+/// `/users/42` matched against `/users/:id`.
+pub type Params = Vec<(String, String)>;
+
 /// Optional reply message returned by a [`Processor`](crate::Processor)
 pub type Reply = Option<Envelope>;
 
+/// Outcome of each delivery of an [`Envelope`] sent to several services,
+/// e.g. by [`Router::broadcast()`](crate::Router::broadcast), along with
+/// the [`ServiceKey`] of the service it was delivered to.
+pub type Replies = Vec<(ServiceKey, Result<Reply>)>;
+
 /// Message payload attached to an [`Envelope`](super::Envelope)
 pub type Payload = Bytes;
@@ -6,7 +6,7 @@ use crate::Address;
 /// of a message inside the system. It is used by
 /// [`Envelope`](super::Envelope) to represent where a message comes from
 /// and where it is going.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(super) struct Route {
     source:      Address,
     destination: Address,
@@ -30,6 +30,11 @@ impl Route {
         std::mem::swap(&mut self.source, &mut self.destination);
     }
 
+    /// Replaces the destination address.
+    pub(super) fn set_destination(&mut self, destination: Address) {
+        self.destination = destination;
+    }
+
     /// Return the source [`Address`](crate::Address) reference of this
     /// route.
     pub(super) fn source(&self) -> &Address { &self.source }
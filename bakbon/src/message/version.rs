@@ -0,0 +1,272 @@
+use {
+    crate::{
+        Envelope,
+        Error,
+        Result,
+    },
+    std::collections::HashMap,
+};
+
+/// Header carrying the name of the [`Payload`](crate::Payload) schema.
+pub const SCHEMA_HEADER: &str = "x-schema";
+
+/// Header carrying the version of the [`Payload`](crate::Payload) schema.
+pub const VERSION_HEADER: &str = "x-schema-version";
+
+/// Transforms an [`Envelope`] from one schema version into the next one.
+///
+/// An `Upcaster` registered for version `n` receives envelopes at version
+/// `n` and must return the equivalent envelope at version `n + 1`. The
+/// version header is bumped by [`Upcasters`] after each step, so an
+/// implementation only has to rewrite the [`Payload`](crate::Payload)
+/// (and headers if needed).
+///
+/// Any `Fn(Envelope) -> Result<Envelope>` closure is an `Upcaster`.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let upcasters = Upcasters::default()
+///     .current("user", 2)
+///     .register("user", 1, |msg: Envelope| {
+///         let payload = Payload::from("{\"name\":\"nil\",\"age\":0}");
+///         Ok(msg.with_payload(payload))
+///     });
+///
+/// let src = Address::parse("http://client.com").unwrap();
+/// let dst = Address::parse("http://users.com").unwrap();
+/// let msg = Envelope::new(src, dst, Payload::from("{\"name\":\"nil\"}"))
+///     .versioned("user", 1);
+///
+/// let msg = upcasters.upcast(msg).unwrap();
+/// assert_eq!(msg.version(), Some(2));
+/// ```
+pub trait Upcaster: Send + Sync {
+    fn upcast(&self, msg: Envelope) -> Result<Envelope>;
+}
+
+impl<F> Upcaster for F
+where
+    F: Fn(Envelope) -> Result<Envelope> + Send + Sync,
+{
+    fn upcast(&self, msg: Envelope) -> Result<Envelope> { self(msg) }
+}
+
+/// Upcasting chain of a single schema.
+#[derive(Default)]
+struct Chain {
+    current: u32,
+    steps:   HashMap<u32, Box<dyn Upcaster>>,
+}
+
+/// Registry of [`Upcaster`]s grouped by schema name.
+///
+/// For each schema, `Upcasters` knows the current version and one
+/// [`Upcaster`] per older version. [`upcast()`](Upcasters::upcast) applies
+/// them one after the other until the [`Envelope`] reaches the current
+/// version, so consumers only ever handle the latest format.
+///
+/// [`Envelope`]s without a [`SCHEMA_HEADER`], or with an unknown schema,
+/// are passed through untouched. A missing [`VERSION_HEADER`] is read as
+/// version `1`.
+#[derive(Default)]
+pub struct Upcasters(HashMap<String, Chain>);
+
+impl Upcasters {
+    /// Sets the current version of a schema and returns the updated
+    /// `Upcasters`.
+    pub fn current(mut self, schema: &str, version: u32) -> Self {
+        self.0
+            .entry(schema.to_string())
+            .or_default()
+            .current = version;
+        self
+    }
+
+    /// Registers the [`Upcaster`] turning `version` of `schema` into
+    /// `version + 1` and returns the updated `Upcasters`.
+    ///
+    /// The current version of the schema is raised to `version + 1` if it
+    /// was lower.
+    pub fn register(
+        mut self,
+        schema: &str,
+        version: u32,
+        upcaster: impl Upcaster + 'static,
+    ) -> Self {
+        let chain = self
+            .0
+            .entry(schema.to_string())
+            .or_default();
+
+        chain.current = chain.current.max(version + 1);
+        chain
+            .steps
+            .insert(version, Box::new(upcaster));
+        self
+    }
+
+    /// Returns the current version of a schema, if registered.
+    pub fn version(&self, schema: &str) -> Option<u32> {
+        self.0
+            .get(schema)
+            .map(|c| c.current)
+    }
+
+    /// Checks that an [`Envelope`] can be upcasted without consuming it.
+    ///
+    /// Returns [`Error::UnsupportedVersion`] if the [`Envelope`] is newer
+    /// than the current version of its schema or if an [`Upcaster`] is
+    /// missing from the chain.
+    pub fn check(&self, msg: &Envelope) -> Result<()> {
+        let Some(schema) = msg.schema()
+        else {
+            return Ok(());
+        };
+        let Some(chain) = self.0.get(schema)
+        else {
+            return Ok(());
+        };
+
+        let version = msg.version().unwrap_or(1);
+        let unsupported = version > chain.current
+            || (version..chain.current).any(|v| !chain.steps.contains_key(&v));
+
+        match unsupported {
+            true => Err(Error::UnsupportedVersion(
+                schema.to_string(),
+                version,
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// Upcasts an [`Envelope`] to the current version of its schema.
+    ///
+    /// The chain is [`check()`](Upcasters::check)ed first, so an
+    /// unsupported version is reported before any [`Upcaster`] runs.
+    pub fn upcast(&self, mut msg: Envelope) -> Result<Envelope> {
+        self.check(&msg)?;
+
+        let Some(chain) = msg
+            .schema()
+            .and_then(|s| self.0.get(s))
+        else {
+            return Ok(msg);
+        };
+
+        let version = msg.version().unwrap_or(1);
+        for v in version..chain.current {
+            msg = chain.steps[&v].upcast(msg)?;
+            msg.set_version(v + 1);
+        }
+
+        Ok(msg)
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Payload,
+            testing::{
+                SRC,
+                message,
+            },
+        },
+    };
+
+    const DST: &str = "http://destination.com";
+
+    fn append(suffix: &'static str) -> impl Upcaster {
+        move |msg: Envelope| {
+            let mut payload = msg.payload().to_vec();
+            payload.extend_from_slice(suffix.as_bytes());
+            Ok(msg.with_payload(Payload::from(payload)))
+        }
+    }
+
+    #[test]
+    fn unversioned_message_passes_through() -> Result<()> {
+        let upcasters = Upcasters::default().register("order", 1, append("+v2"));
+
+        let msg = upcasters.upcast(message(SRC, DST, "v1")?)?;
+        assert_eq!(msg.payload(), &Payload::from("v1"));
+        assert!(msg.version().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn upcast_through_chain() -> Result<()> {
+        let upcasters = Upcasters::default()
+            .register("order", 1, append("+v2"))
+            .register("order", 2, append("+v3"));
+        assert_eq!(upcasters.version("order"), Some(3));
+
+        let msg = message(SRC, DST, "v1")?.versioned("order", 1);
+        let msg = upcasters.upcast(msg)?;
+        assert_eq!(msg.payload(), &Payload::from("v1+v2+v3"));
+        assert_eq!(msg.version(), Some(3));
+
+        let msg = message(SRC, DST, "v2")?.versioned("order", 2);
+        let msg = upcasters.upcast(msg)?;
+        assert_eq!(msg.payload(), &Payload::from("v2+v3"));
+        assert_eq!(msg.version(), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn missing_version_header_reads_as_first_version() -> Result<()> {
+        let upcasters = Upcasters::default().register("order", 1, append("+v2"));
+
+        let msg = message(SRC, DST, "v1")?.header(SCHEMA_HEADER, "order");
+        let msg = upcasters.upcast(msg)?;
+        assert_eq!(msg.payload(), &Payload::from("v1+v2"));
+        assert_eq!(msg.version(), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn current_message_is_untouched() -> Result<()> {
+        let upcasters = Upcasters::default().register("order", 1, append("+v2"));
+
+        let msg = message(SRC, DST, "v2")?.versioned("order", 2);
+        let msg = upcasters.upcast(msg)?;
+        assert_eq!(msg.payload(), &Payload::from("v2"));
+        Ok(())
+    }
+
+    #[test]
+    fn newer_message_is_unsupported() -> Result<()> {
+        let upcasters = Upcasters::default().current("order", 2);
+
+        let msg = message(SRC, DST, "v3")?.versioned("order", 3);
+        let result = upcasters.upcast(msg);
+        assert!(matches!(
+            result,
+            Err(Error::UnsupportedVersion(_, 3))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn gap_in_chain_is_unsupported() -> Result<()> {
+        let upcasters = Upcasters::default().register("order", 2, append("+v3"));
+
+        let msg = message(SRC, DST, "v1")?.versioned("order", 1);
+        let result = upcasters.upcast(msg);
+        assert!(matches!(
+            result,
+            Err(Error::UnsupportedVersion(_, 1))
+        ));
+        Ok(())
+    }
+}
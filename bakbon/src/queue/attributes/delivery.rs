@@ -1,14 +1,12 @@
-#[derive(Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub(in crate::queue) enum DeliveryGuarantee {
+    #[default]
     AtLeastOnce,
     AtMostOnce,
     ExactlyOnce,
 }
 
-impl Default for DeliveryGuarantee {
-    fn default() -> Self { Self::AtLeastOnce }
-}
-
 impl From<&str> for DeliveryGuarantee {
     fn from(value: &str) -> Self {
         match value {
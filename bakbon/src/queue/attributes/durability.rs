@@ -1,14 +1,11 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub(in crate::queue) enum Durability {
+    #[default]
     Memory,
     Disk,
     Replicated,
 }
 
-impl Default for Durability {
-    fn default() -> Self { Self::Memory }
-}
-
 impl From<&str> for Durability {
     fn from(value: &str) -> Self {
         match value {
@@ -1,14 +1,11 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub(in crate::queue) enum Ordering {
+    #[default]
     Fifo,
     Priority,
     Unordered,
 }
 
-impl Default for Ordering {
-    fn default() -> Self { Self::Fifo }
-}
-
 impl From<&str> for Ordering {
     fn from(value: &str) -> Self {
         match value {
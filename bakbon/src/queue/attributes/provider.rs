@@ -1,5 +1,6 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub(in crate::queue) enum QueueProvider {
+    #[default]
     Memory,
     Kafka,
     RabbitMq,
@@ -7,10 +8,6 @@ pub(in crate::queue) enum QueueProvider {
     Custom(String),
 }
 
-impl Default for QueueProvider {
-    fn default() -> Self { Self::Memory }
-}
-
 impl From<&str> for QueueProvider {
     fn from(value: &str) -> Self {
         match value {
@@ -6,6 +6,7 @@ use {
         Ordering,
         Queue,
         QueueProvider,
+        Upcasters,
     },
     std::{
         collections::VecDeque,
@@ -23,6 +24,7 @@ pub struct QueueBuilder {
     ordering:           Ordering,
     durability:         Durability,
     delivery_guarantee: DeliveryGuarantee,
+    upcasters:          Upcasters,
 }
 
 impl QueueBuilder {
@@ -56,6 +58,13 @@ impl QueueBuilder {
         self
     }
 
+    /// Sets the [`Upcasters`] applied to every dequeued
+    /// [`Envelope`](crate::Envelope).
+    pub fn upcasters(mut self, upcasters: Upcasters) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
+
     pub fn build(self) -> Queue {
         Queue {
             provider:           self.provider,
@@ -65,6 +74,7 @@ impl QueueBuilder {
             ordering:           self.ordering,
             durability:         self.durability,
             delivery_guarantee: self.delivery_guarantee,
+            upcasters:          self.upcasters,
         }
     }
 }
@@ -7,7 +7,7 @@
 //! use bakbon::*;
 //!
 //! let queue = Queue::builder()
-//!     .provider(QueueProvider::Memory)
+//!     .provider("memory")
 //!     .build();
 //! ```
 
@@ -19,6 +19,7 @@ use {
         Envelope,
         Error,
         Result,
+        Upcasters,
     },
     attributes::{
         DeliveryGuarantee,
@@ -43,6 +44,7 @@ pub struct Queue {
     ordering:           Ordering,
     durability:         Durability,
     delivery_guarantee: DeliveryGuarantee,
+    upcasters:          Upcasters,
 }
 
 impl Queue {
@@ -51,13 +53,13 @@ impl Queue {
     pub fn enqueue(&self, mut msg: Envelope) -> Result<()> {
         let mut buffer = self.buffer.lock()?;
 
-        if let Some(capacity) = self.capacity {
-            if buffer.len() >= capacity {
-                return Err(Error::QueueFull(msg));
-            };
+        if let Some(capacity) = self.capacity
+            && buffer.len() >= capacity
+        {
+            return Err(Error::QueueFull(Box::new(msg)));
         }
-        if self.ttl.is_some() {
-            let time_to_live = format!("{:?}", self.ttl.unwrap());
+        if let Some(ttl) = self.ttl {
+            let time_to_live = format!("{:?}", ttl);
             msg.add_header("x-ttl", time_to_live.as_str())
         };
         match self.ordering {
@@ -71,12 +73,42 @@ impl Queue {
         Ok(())
     }
 
+    /// Pops the next [`Envelope`], upcasted to the current version of its
+    /// schema by the configured [`Upcasters`].
+    ///
+    /// An [`Envelope`] that cannot be upcasted is removed from the `Queue`
+    /// all the same, so it never blocks the ones behind it: it is handed
+    /// back in [`Error::UpcastFailed`], with [`Error::UnsupportedVersion`]
+    /// for a missing [`Upcaster`](crate::Upcaster), or the error of the
+    /// failing one. Upcasters run once the `Queue` is unlocked.
     pub fn dequeue(&self) -> Result<Option<Envelope>> {
-        let mut buffer = self.buffer.lock()?;
+        let Some(message) = self
+            .buffer
+            .lock()?
+            .pop_front()
+        else {
+            return Ok(None);
+        };
 
-        match buffer.pop_front() {
-            Some(message) => Ok(Some(message)),
-            None => Ok(None),
+        let registered = message
+            .schema()
+            .is_some_and(|s| {
+                self.upcasters
+                    .version(s)
+                    .is_some()
+            });
+        if !registered {
+            return Ok(Some(message));
+        }
+        match self
+            .upcasters
+            .upcast(message.clone())
+        {
+            Ok(upcasted) => Ok(Some(upcasted)),
+            Err(error) => Err(Error::UpcastFailed(
+                Box::new(message),
+                Box::new(error),
+            )),
         }
     }
 
@@ -99,6 +131,8 @@ impl Queue {
         let buffer = self.buffer.lock().unwrap();
         buffer.len()
     }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
 }
 
 //  +------------+
@@ -243,6 +277,90 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn dequeue_upcasts_message() -> Result<()> {
+        let src = Address::parse("http://service.com")?;
+        let dst = Address::parse(DST)?;
+        let upcasters = Upcasters::default().register("order", 1, |msg: Envelope| {
+            Ok(msg.with_payload(Payload::from("v2")))
+        });
+
+        let queue = Queue::builder()
+            .upcasters(upcasters)
+            .build();
+
+        let msg = Envelope::new(src, dst, Payload::from("v1")).versioned("order", 1);
+        queue.enqueue(msg)?;
+
+        let msg = queue.dequeue()?.unwrap();
+        assert_eq!(msg.payload(), &Payload::from("v2"));
+        assert_eq!(msg.version(), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_message_is_handed_back() -> Result<()> {
+        let src = Address::parse("http://service.com")?;
+        let dst = Address::parse(DST)?;
+        let queue = Queue::builder()
+            .upcasters(Upcasters::default().current("order", 1))
+            .build();
+
+        let msg =
+            Envelope::new(src.clone(), dst.clone(), Payload::from("v2")).versioned("order", 2);
+        queue.enqueue(msg)?;
+        queue.enqueue(Envelope::new(
+            src,
+            dst,
+            Payload::from("next"),
+        ))?;
+
+        let Err(Error::UpcastFailed(msg, error)) = queue.dequeue()
+        else {
+            panic!("expected the message to be handed back");
+        };
+        assert!(matches!(
+            *error,
+            Error::UnsupportedVersion(_, 2)
+        ));
+        assert_eq!(msg.version(), Some(2));
+
+        let next = queue.dequeue()?.unwrap();
+        assert_eq!(next.payload(), &Payload::from("next"));
+        assert!(queue.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn failed_upcast_hands_back_original() -> Result<()> {
+        let src = Address::parse("http://service.com")?;
+        let dst = Address::parse(DST)?;
+        let upcasters = Upcasters::default().register("order", 1, |_: Envelope| {
+            Err(Error::SerializationFailed(
+                "bad order".to_string(),
+            ))
+        });
+        let queue = Queue::builder()
+            .upcasters(upcasters)
+            .build();
+
+        let msg = Envelope::new(src, dst, Payload::from("v1")).versioned("order", 1);
+        queue.enqueue(msg)?;
+
+        let Err(Error::UpcastFailed(msg, error)) = queue.dequeue()
+        else {
+            panic!("expected the message to be handed back");
+        };
+        assert!(matches!(
+            *error,
+            Error::SerializationFailed(_)
+        ));
+        assert_eq!(msg.payload(), &Payload::from("v1"));
+        assert_eq!(msg.version(), Some(1));
+        assert!(queue.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn unordered_queue() -> Result<()> {
         let addr1 = Address::parse("http://service1.com")?;
@@ -1,8 +1,11 @@
 use {
-    super::Registry,
+    super::{
+        KeyMode,
+        Registry,
+    },
     crate::{
         Service,
-        ServiceMap,
+        ServiceVec,
     },
 };
 
@@ -11,26 +14,37 @@ use {
 /// `RegistryBuilder` let's you register one or more [`Service`] instances
 /// and then freeze the configuration into an immutable [`Registry`].
 #[derive(Default)]
-pub struct RegistryBuilder(ServiceMap);
+pub struct RegistryBuilder {
+    services: ServiceVec,
+    mode:     KeyMode,
+}
 
 impl RegistryBuilder {
     /// Registers a new service instance in the builder.
     ///
-    /// [`Service`]s are grouped in their [`Adrress`](crate::Address)
-    /// string representation. Multiple instances with the same
-    /// [`Address`](crate::Address) can be added later via
-    /// [`Registry::add_instance()`]
+    /// [`Service`]s are grouped by the [`ServiceKey`](super::ServiceKey)
+    /// of their [`Address`](crate::Address): registering several
+    /// services with the same key adds instances of the same logical
+    /// [`Service`]. More instances can be added later via
+    /// [`Registry::add_instance()`].
     pub fn register(mut self, service: impl Service + 'static) -> Self {
-        let name = service.address().to_string();
-
-        self.0
-            .insert(name, vec![Box::new(service)]);
+        self.services
+            .push(Box::new(service));
+        self
+    }
 
+    /// Sets the [`KeyMode`] by name.
+    ///
+    /// See [`KeyMode`] for supported values such as `"authority"`,
+    /// `"authority_path"` or `"full"`. Applies to every registered
+    /// [`Service`], whatever the call order.
+    pub fn key_mode(mut self, mode: &str) -> Self {
+        self.mode = KeyMode::from(mode);
         self
     }
 
     /// Finalizes the builder and returns an immutable [`Registry`].
-    pub fn build(self) -> Registry { Registry(self.0) }
+    pub fn build(self) -> Registry { Registry::with_mode(self.services, self.mode) }
 }
 
 //  +------------+
@@ -66,13 +80,14 @@ mod tests {
     #[test]
     fn default_registry_builder() {
         let builder = RegistryBuilder::default();
-        assert!(builder.0.is_empty());
+        assert!(builder.services.is_empty());
+        assert_eq!(builder.mode, KeyMode::Full);
     }
 
     #[test]
     fn build_default_registry() {
         let builder = Registry::builder();
-        assert!(builder.0.is_empty());
+        assert!(builder.services.is_empty());
 
         let registry = builder.build();
         assert!(registry.list().is_empty());
@@ -84,8 +99,8 @@ mod tests {
         let service = NilService(address);
 
         let builder = Registry::builder().register(service);
-        assert!(!builder.0.is_empty());
-        assert_eq!(builder.0.len(), 1);
+        assert!(!builder.services.is_empty());
+        assert_eq!(builder.services.len(), 1);
 
         let registry = builder.build();
         assert!(!registry.list().is_empty());
@@ -93,4 +108,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn build_registry_with_instances() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let registry = Registry::builder()
+            .register(NilService(address.clone()))
+            .register(NilService(address.clone()))
+            .build();
+
+        assert_eq!(registry.list().len(), 1);
+        assert_eq!(
+            registry
+                .get(&address)
+                .unwrap()
+                .len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn build_registry_with_key_mode() -> Result<()> {
+        let address = Address::parse("http://users.com/users")?;
+        let registry = Registry::builder()
+            .register(NilService(address))
+            .key_mode("authority")
+            .build();
+
+        assert_eq!(registry.key_mode(), KeyMode::Authority);
+        assert_eq!(registry.list(), vec!["users.com"]);
+
+        let destination = Address::parse("http://users.com/users/42")?;
+        assert!(
+            registry
+                .get(&destination)
+                .is_some()
+        );
+        Ok(())
+    }
 }
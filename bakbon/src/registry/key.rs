@@ -0,0 +1,283 @@
+use {
+    crate::Address,
+    std::fmt,
+};
+
+/// Parts of an [`Address`] making up a [`ServiceKey`].
+///
+/// - [`Authority`](KeyMode::Authority): `authority` only, so every path of
+///   a host reaches the same [`Service`](crate::Service) (e.g. a
+///   [`Dispatcher`](crate::Dispatcher)).
+/// - [`AuthorityPath`](KeyMode::AuthorityPath): `authority/path`, one
+///   [`Service`](crate::Service) per path whatever the scheme.
+/// - [`Full`](KeyMode::Full): `scheme://authority/path`.
+///
+/// Query and fragment never take part in the key: they describe a request,
+/// not a [`Service`](crate::Service).
+///
+/// ## Defaults
+///
+/// The default `KeyMode` is [`Full`](KeyMode::Full).
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum KeyMode {
+    Authority,
+    AuthorityPath,
+    #[default]
+    Full,
+}
+
+impl From<&str> for KeyMode {
+    /// Parse a key mode name into a [`KeyMode`] value.
+    ///
+    /// Recognized names:
+    /// - "authority"
+    /// - "authority_path"
+    /// - "full"
+    ///
+    /// Any unrecognized name falls back to the default mode (`full`).
+    fn from(value: &str) -> Self {
+        match value {
+            "authority" => Self::Authority,
+            "authority_path" => Self::AuthorityPath,
+            "full" => Self::Full,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl AsRef<str> for KeyMode {
+    /// Returns the string representation of the key mode.
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Authority => "authority",
+            Self::AuthorityPath => "authority_path",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// Canonical identity of a [`Service`](crate::Service) in the
+/// [`Registry`](super::Registry).
+///
+/// Derived from an [`Address`] with a [`KeyMode`], the same way for
+/// registering services and for looking up
+/// [`Envelope`](crate::Envelope) destinations, so that both always agree.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let address = Address::parse("http://users.com/users/42?full=true").unwrap();
+///
+/// let key = ServiceKey::new(&address, KeyMode::Authority);
+/// assert_eq!(key.as_ref(), "users.com");
+///
+/// let key = ServiceKey::new(&address, KeyMode::AuthorityPath);
+/// assert_eq!(key.as_ref(), "users.com/users/42");
+///
+/// let key = ServiceKey::new(&address, KeyMode::Full);
+/// assert_eq!(key.as_ref(), "http://users.com/users/42");
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct ServiceKey(String);
+
+impl ServiceKey {
+    /// Derives the key of an [`Address`] with the given [`KeyMode`].
+    pub fn new(address: &Address, mode: KeyMode) -> Self {
+        let path = address
+            .path()
+            .trim_end_matches('/');
+
+        let key = match mode {
+            KeyMode::Authority => address
+                .authority()
+                .to_string(),
+            KeyMode::AuthorityPath => format!("{}{path}", address.authority()),
+            KeyMode::Full => format!(
+                "{}://{}{path}",
+                address.scheme(),
+                address.authority()
+            ),
+        };
+
+        Self(key)
+    }
+}
+
+impl ServiceKey {
+    /// Returns whether the key matches a glob pattern.
+    ///
+    /// `*` matches any sequence of characters, including none; any other
+    /// character matches itself. For example `http://*.cache.com` matches
+    /// every cache host of a [`Full`](KeyMode::Full) registry, and `*`
+    /// matches every key.
+    pub fn matches(&self, pattern: &str) -> bool { matches_glob(pattern, &self.0) }
+
+    /// Returns the key followed by the key of each parent path, from the
+    /// longest to the bare authority.
+    ///
+    /// `http://users.com/users/42` yields itself, `http://users.com/users`
+    /// and `http://users.com`.
+    pub(crate) fn prefixes(&self) -> impl Iterator<Item = ServiceKey> {
+        let authority = self
+            .0
+            .find("://")
+            .map_or(0, |i| i + 3);
+
+        std::iter::successors(Some(self.clone()), move |key| {
+            key.0[authority..]
+                .rfind('/')
+                .map(|i| Self(key.0[..authority + i].to_string()))
+        })
+    }
+}
+
+/// Returns whether a text matches a glob pattern, with the syntax of
+/// [`ServiceKey::matches()`].
+pub(crate) fn matches_glob(pattern: &str, text: &str) -> bool {
+    glob(pattern.as_bytes(), text.as_bytes())
+}
+
+/// Matches `text` against a `*` wildcard pattern.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character.
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..]
+        .iter()
+        .all(|c| *c == b'*')
+}
+
+impl fmt::Display for ServiceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.0) }
+}
+
+impl AsRef<str> for ServiceKey {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Result,
+    };
+
+    const URI: &str = "grpc://orders.com/orders/7/?expand=items#top";
+
+    #[test]
+    fn default_key_mode() {
+        let mode = KeyMode::default();
+        assert_eq!(mode, KeyMode::Full);
+        assert_eq!(mode.as_ref(), "full");
+    }
+
+    #[test]
+    fn key_mode_from_str() {
+        assert_eq!(
+            KeyMode::from("authority"),
+            KeyMode::Authority
+        );
+        assert_eq!(
+            KeyMode::from("authority_path"),
+            KeyMode::AuthorityPath
+        );
+        assert_eq!(KeyMode::from("full"), KeyMode::Full);
+        assert_eq!(KeyMode::from("unknown"), KeyMode::Full);
+    }
+
+    #[test]
+    fn authority_key() -> Result<()> {
+        let address = Address::parse(URI)?;
+        let key = ServiceKey::new(&address, KeyMode::Authority);
+        assert_eq!(key.to_string(), "orders.com");
+        Ok(())
+    }
+
+    #[test]
+    fn authority_path_key() -> Result<()> {
+        let address = Address::parse(URI)?;
+        let key = ServiceKey::new(&address, KeyMode::AuthorityPath);
+        assert_eq!(key.to_string(), "orders.com/orders/7");
+        Ok(())
+    }
+
+    #[test]
+    fn key_prefixes() -> Result<()> {
+        let address = Address::parse(URI)?;
+
+        let full: Vec<_> = ServiceKey::new(&address, KeyMode::Full)
+            .prefixes()
+            .map(|k| k.to_string())
+            .collect();
+        assert_eq!(
+            full,
+            vec![
+                "grpc://orders.com/orders/7",
+                "grpc://orders.com/orders",
+                "grpc://orders.com",
+            ]
+        );
+
+        let authority: Vec<_> = ServiceKey::new(&address, KeyMode::Authority)
+            .prefixes()
+            .collect();
+        assert_eq!(authority.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn key_matches_pattern() -> Result<()> {
+        let address = Address::parse("http://eu.cache.com/entries")?;
+        let key = ServiceKey::new(&address, KeyMode::Full);
+
+        assert!(key.matches("*"));
+        assert!(key.matches("http://eu.cache.com/entries"));
+        assert!(key.matches("http://*.cache.com/*"));
+        assert!(key.matches("*cache*"));
+        assert!(!key.matches("grpc://*"));
+        assert!(!key.matches("http://*.cache.com"));
+        assert!(!key.matches(""));
+        Ok(())
+    }
+
+    #[test]
+    fn full_key() -> Result<()> {
+        let address = Address::parse(URI)?;
+        let key = ServiceKey::new(&address, KeyMode::Full);
+        assert_eq!(key.to_string(), "grpc://orders.com/orders/7");
+
+        let address = Address::parse("grpc://orders.com")?;
+        let key = ServiceKey::new(&address, KeyMode::Full);
+        assert_eq!(key.as_ref(), "grpc://orders.com");
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use {
+    super::Registry,
+    crate::{
+        Address,
+        KeyMode,
+        Result,
+        Service,
+        ServiceArc,
+        core::Recover,
+    },
+    std::sync::{
+        Arc,
+        RwLock,
+        RwLockReadGuard,
+        RwLockWriteGuard,
+    },
+};
+
+/// Shared handle on a [`Registry`] served by a [`Router`](crate::Router).
+///
+/// Cloning a `LiveRegistry` clones the handle, not the [`Registry`]: every
+/// change made through any clone is seen by the next lookup of the
+/// [`Router`](crate::Router), without rebuilding it.
+///
+/// The [`Router`](crate::Router) only holds the lock while it selects an
+/// instance, not while the instance processes the message. Instances are
+/// shared ([`ServiceArc`]), so one deregistered or replaced while it
+/// processes a request stays alive until that request is done.
+///
+/// A panic while the lock is held cannot leave the [`Registry`] half
+/// updated, so a poisoned lock is recovered rather than reported.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let router = Router::builder().build();
+/// let registry = router.registry();
+///
+/// let address = Address::parse("http://echo.com").unwrap();
+/// registry.register(service_fn(address.clone(), |msg| {
+///     let payload = msg.payload().clone();
+///     Ok(Some(msg.into_reply(payload)))
+/// }));
+///
+/// let src = Address::parse("http://client.com").unwrap();
+/// let msg = Envelope::new(src, address.clone(), Payload::from("hi"));
+/// assert!(router.route(msg).unwrap().is_some());
+///
+/// registry.deregister(&address).unwrap();
+/// assert!(registry.list().is_empty());
+/// ```
+#[derive(Clone, Default)]
+pub struct LiveRegistry(Arc<RwLock<Registry>>);
+
+impl LiveRegistry {
+    /// Wraps a [`Registry`] into a new shared handle.
+    pub fn new(registry: Registry) -> Self { Self(Arc::new(RwLock::new(registry))) }
+
+    /// Acquires the [`Registry`] for reading.
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, Registry> { self.0.read().recover() }
+
+    /// Acquires the [`Registry`] for writing.
+    fn write(&self) -> RwLockWriteGuard<'_, Registry> { self.0.write().recover() }
+
+    /// Registers a new service instance.
+    ///
+    /// See [`Registry::register()`].
+    pub fn register(&self, service: impl Service + 'static) { self.write().register(service) }
+
+    /// Removes every instance of a service and returns how many were
+    /// removed.
+    ///
+    /// See [`Registry::deregister()`].
+    pub fn deregister(&self, address: &Address) -> Result<usize> {
+        self.write()
+            .deregister(address)
+    }
+
+    /// Replaces every instance of a service with a new one and returns how
+    /// many were replaced.
+    ///
+    /// See [`Registry::replace()`].
+    pub fn replace(&self, service: impl Service + 'static) -> usize {
+        self.write().replace(service)
+    }
+
+    /// Adds a new instance for an existing service address.
+    ///
+    /// See [`Registry::add_instance()`].
+    pub fn add_instance(&self, address: &Address) -> Result<()> {
+        self.write()
+            .add_instance(address)
+    }
+
+    /// Removes the last instance registered for a service address.
+    ///
+    /// See [`Registry::remove_instance()`].
+    pub fn remove_instance(&self, address: &Address) -> Result<()> {
+        self.write()
+            .remove_instance(address)
+    }
+
+    /// Removes a given instance, as returned by
+    /// [`get()`](LiveRegistry::get), e.g. one found unhealthy.
+    ///
+    /// See [`Registry::remove()`].
+    pub fn remove(&self, instance: &ServiceArc) -> Result<()> { self.write().remove(instance) }
+
+    /// Returns the instances currently registered for the [`ServiceKey`]
+    /// of a given [`Address`], if any.
+    ///
+    /// [`ServiceKey`]: crate::ServiceKey
+    pub fn get(&self, address: &Address) -> Option<Vec<ServiceArc>> {
+        self.read()
+            .get(address)
+            .map(|instances| instances.to_vec())
+    }
+
+    /// Returns a list of all registered [`ServiceKey`]s string
+    /// representation.
+    ///
+    /// [`ServiceKey`]: crate::ServiceKey
+    pub fn list(&self) -> Vec<String> {
+        self.read()
+            .list()
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Returns the [`KeyMode`] used to derive
+    /// [`ServiceKey`](crate::ServiceKey)s.
+    pub fn key_mode(&self) -> KeyMode { self.read().key_mode() }
+}
+
+impl From<Registry> for LiveRegistry {
+    fn from(registry: Registry) -> Self { Self::new(registry) }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Envelope,
+            Error,
+            Reply,
+            ServiceBox,
+        },
+    };
+
+    const ADDRESS: &str = "http://no-service.com";
+
+    #[derive(Debug, Clone)]
+    struct NilService(Address);
+
+    impl Service for NilService {
+        fn address(&self) -> &Address { &self.0 }
+
+        fn duplicate(&self) -> ServiceBox { Box::new(self.clone()) }
+
+        fn process(&self, _message: Envelope) -> Result<Reply> { Ok(None) }
+    }
+
+    #[test]
+    fn shared_live_registry() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let live = LiveRegistry::default();
+        let handle = live.clone();
+
+        handle.register(NilService(address.clone()));
+        handle.add_instance(&address)?;
+        assert_eq!(live.list(), vec![ADDRESS]);
+        assert_eq!(
+            live.get(&address)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        assert_eq!(
+            handle.replace(NilService(address.clone())),
+            2
+        );
+        assert_eq!(
+            live.get(&address)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        handle.remove_instance(&address)?;
+        assert!(live.get(&address).is_none());
+        assert!(matches!(
+            live.deregister(&address),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn removed_instance_outlives_registry() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let live = LiveRegistry::from(
+            Registry::builder()
+                .key_mode("authority")
+                .build(),
+        );
+        live.register(NilService(address.clone()));
+
+        let in_flight = live.get(&address).unwrap();
+        live.deregister(&address)?;
+        assert_eq!(in_flight[0].address(), &address);
+        assert_eq!(live.key_mode(), KeyMode::Authority);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_given_instance() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let live = LiveRegistry::default();
+        live.register(NilService(address.clone()));
+        live.add_instance(&address)?;
+        live.add_instance(&address)?;
+
+        let instances = live.get(&address).unwrap();
+        live.remove(&instances[1])?;
+        let remaining = live.get(&address).unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(Arc::ptr_eq(&remaining[0], &instances[0]));
+        assert!(Arc::ptr_eq(&remaining[1], &instances[2]));
+        assert!(matches!(
+            live.remove(&instances[1]),
+            Err(Error::ServiceNotFound)
+        ));
+
+        live.remove(&instances[0])?;
+        live.remove(&instances[2])?;
+        assert!(live.get(&address).is_none());
+        Ok(())
+    }
+}
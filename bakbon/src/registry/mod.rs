@@ -1,37 +1,100 @@
 mod builder;
+mod key;
+mod live;
 
+pub(crate) use key::matches_glob;
 use {
     crate::{
+        Address,
         Error,
         Result,
+        Service,
+        ServiceArc,
         ServiceMap,
         ServiceVec,
     },
     builder::RegistryBuilder,
+    std::sync::Arc,
+};
+pub use {
+    key::{
+        KeyMode,
+        ServiceKey,
+    },
+    live::LiveRegistry,
 };
 
-/// Immutable registry of services keyed by address.
+/// Registry of services keyed by [`ServiceKey`].
 ///
 /// The `Registry` stores one or more instances for each logical
 /// [`Service`](crate::Service). It is used by [`Router`](crate::Router) to
 /// lookup candidate instances before delegating selection go
 /// [`Balancer`](super::balancer::Balancer).
+///
+/// Once handed to a [`Router`](crate::Router), the `Registry` is wrapped
+/// in a [`LiveRegistry`] through which services and instances can still be
+/// registered, deregistered or replaced while traffic is served.
+///
+/// Every registration and lookup derives the [`ServiceKey`] of an
+/// [`Address`] with the same [`KeyMode`], so a [`Service`](crate::Service)
+/// is always found under the [`Address`] it was registered with, whichever
+/// way the `Registry` was built.
+///
+/// A lookup falls back to the longest registered parent path of the
+/// [`Address`], so a [`Dispatcher`](crate::Dispatcher) registered at
+/// `http://users.com` also receives `http://users.com/users/42`. A
+/// [`Service`](crate::Service) registered for the exact path always wins.
 #[derive(Default)]
-pub struct Registry(pub(super) ServiceMap);
+pub struct Registry {
+    services: ServiceMap,
+    mode:     KeyMode,
+}
 
 impl Registry {
     /// Returns an empty [`RegistryBuilder`].
     pub fn builder() -> RegistryBuilder { RegistryBuilder::default() }
 
+    /// Builds a `Registry` from a flat list of [`Service`](crate::Service)
+    /// instances grouped with the given [`KeyMode`].
+    pub fn with_mode(services: ServiceVec, mode: KeyMode) -> Self {
+        let mut registry = Self {
+            services: ServiceMap::new(),
+            mode,
+        };
+
+        for service in services {
+            registry.insert(Arc::from(service));
+        }
+
+        registry
+    }
+
+    /// Appends an instance under the [`ServiceKey`] of its
+    /// [`Address`].
+    fn insert(&mut self, service: ServiceArc) {
+        self.services
+            .entry(self.key(service.address()))
+            .or_default()
+            .push(service);
+    }
+
+    /// Returns the [`ServiceKey`] of an [`Address`] in this `Registry`.
+    pub fn key(&self, address: &Address) -> ServiceKey { ServiceKey::new(address, self.mode) }
+
+    /// Returns the [`KeyMode`] used to derive [`ServiceKey`]s.
+    pub fn key_mode(&self) -> KeyMode { self.mode }
+
     /// Adds a new instance for an existing service address.
     ///
-    /// The new instance is created by calling [`Service::duplicate()`] on
-    /// the last registered instance. Returns [`Error::ServiceNotFound`] if
-    /// the address is unknown.
-    pub fn add_instance(&mut self, address: &str) -> Result<()> {
+    /// The new instance is created by calling
+    /// [`Service::duplicate()`](crate::Service::duplicate) on the last
+    /// registered instance. Returns [`Error::ServiceNotFound`] if the
+    /// address is unknown.
+    pub fn add_instance(&mut self, address: &Address) -> Result<()> {
+        let key = self.key(address);
         let instances = self
-            .0
-            .get_mut(address)
+            .services
+            .get_mut(&key)
             .ok_or(Error::ServiceNotFound)?;
 
         let new_instance = instances
@@ -39,50 +102,140 @@ impl Registry {
             .unwrap()
             .duplicate();
 
-        instances.push(new_instance);
+        instances.push(Arc::from(new_instance));
+        Ok(())
+    }
+
+    /// Registers a new service instance.
+    ///
+    /// The instance is added to the instances already registered under the
+    /// same [`ServiceKey`], if any.
+    pub fn register(&mut self, service: impl Service + 'static) { self.insert(Arc::new(service)); }
+
+    /// Removes every instance of the service registered under the
+    /// [`ServiceKey`] of an [`Address`] and returns how many were removed.
+    ///
+    /// Returns [`Error::ServiceNotFound`] if the address is unknown.
+    pub fn deregister(&mut self, address: &Address) -> Result<usize> {
+        let key = self.key(address);
+        self.services
+            .remove(&key)
+            .map(|instances| instances.len())
+            .ok_or(Error::ServiceNotFound)
+    }
+
+    /// Replaces every instance of the service registered under the
+    /// [`ServiceKey`] of its [`Address`] with a new one and returns how
+    /// many were replaced.
+    ///
+    /// Registers the service if its address is unknown.
+    pub fn replace(&mut self, service: impl Service + 'static) -> usize {
+        let key = self.key(service.address());
+        self.services
+            .insert(key, vec![Arc::new(service)])
+            .map_or(0, |instances| instances.len())
+    }
+
+    /// Removes the last instance registered for a service address.
+    ///
+    /// Removing the only instance deregisters the service. Returns
+    /// [`Error::ServiceNotFound`] if the address is unknown. See
+    /// [`remove()`](Registry::remove) to remove a given instance.
+    pub fn remove_instance(&mut self, address: &Address) -> Result<()> {
+        let key = self.key(address);
+        let instances = self
+            .services
+            .get_mut(&key)
+            .ok_or(Error::ServiceNotFound)?;
+
+        instances.pop();
+        if instances.is_empty() {
+            self.services.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Removes a given instance, as returned by
+    /// [`get()`](Registry::get), wherever it sits among the instances of
+    /// its service.
+    ///
+    /// Removing the only instance deregisters the service. Returns
+    /// [`Error::ServiceNotFound`] if the instance is not registered.
+    pub fn remove(&mut self, instance: &ServiceArc) -> Result<()> {
+        let key = self.key(instance.address());
+        let instances = self
+            .services
+            .get_mut(&key)
+            .ok_or(Error::ServiceNotFound)?;
+        let index = instances
+            .iter()
+            .position(|registered| Arc::ptr_eq(registered, instance))
+            .ok_or(Error::ServiceNotFound)?;
+
+        instances.remove(index);
+        if instances.is_empty() {
+            self.services.remove(&key);
+        }
         Ok(())
     }
 
-    /// Returns a list of all instances registered for a given address
-    /// string representation, if any.
-    pub fn get(&self, address: &str) -> Option<&ServiceVec> {
-        self.0
-            .get(address)
-            .map(|s| s.as_ref())
+    /// Returns a list of all instances registered for the [`ServiceKey`]
+    /// of a given [`Address`], or of its longest registered parent path,
+    /// if any.
+    pub fn get(&self, address: &Address) -> Option<&[ServiceArc]> {
+        self.lookup(address)
+            .map(|(_, instances)| instances)
+    }
+
+    /// Returns the [`ServiceKey`] the instances of [`get()`](Self::get)
+    /// are registered under, along with the instances, if any.
+    pub fn lookup(&self, address: &Address) -> Option<(&ServiceKey, &[ServiceArc])> {
+        self.key(address)
+            .prefixes()
+            .find_map(|key| {
+                self.services
+                    .get_key_value(&key)
+            })
+            .map(|(key, instances)| (key, instances.as_slice()))
     }
 
-    /// Returns a list of all registered [`Service`]
-    /// [`Address`](crate::Address)es string representation.
+    /// Returns the instances of every service whose [`ServiceKey`]
+    /// matches a glob pattern, ordered by key.
+    ///
+    /// See [`ServiceKey::matches()`] for the pattern syntax.
+    pub fn matching(&self, pattern: &str) -> Vec<(&ServiceKey, &[ServiceArc])> {
+        let mut matching: Vec<_> = self
+            .services
+            .iter()
+            .filter(|(key, _)| key.matches(pattern))
+            .map(|(key, instances)| (key, instances.as_slice()))
+            .collect();
+        matching.sort_by(|a, b| a.0.cmp(b.0));
+        matching
+    }
+
+    /// Consumes the `Registry` and returns its instances grouped by
+    /// [`ServiceKey`], along with the [`KeyMode`] used to derive them.
+    #[cfg(feature = "async")]
+    pub(crate) fn into_parts(self) -> (ServiceMap, KeyMode) { (self.services, self.mode) }
+
+    /// Returns a list of all registered [`ServiceKey`]s string
+    /// representation.
     pub fn list(&self) -> Vec<&str> {
-        self.0
+        self.services
             .keys()
-            .map(|k| k.as_str())
+            .map(|k| k.as_ref())
             .collect()
     }
 }
 
 impl From<ServiceVec> for Registry {
-    /// Builds a `Registry` from a flat list of [`Service`] instances.
+    /// Builds a `Registry` from a flat list of [`Service`](crate::Service)
+    /// instances.
     ///
-    /// Instances are grouped by their [`Address`](crate::Address)] string
-    /// representation.
-    fn from(services: ServiceVec) -> Self {
-        let mut service_map: ServiceMap = ServiceMap::new();
-
-        for service in services {
-            let key = service
-                .address()
-                .authority()
-                .to_string();
-
-            service_map
-                .entry(key)
-                .or_insert_with(ServiceVec::new)
-                .push(service);
-        }
-
-        Self(service_map)
-    }
+    /// Instances are grouped by their [`ServiceKey`] with the default
+    /// [`KeyMode`]. See [`Registry::with_mode()`] to pick another one.
+    fn from(services: ServiceVec) -> Self { Self::with_mode(services, KeyMode::default()) }
 }
 
 //  +------------+
@@ -94,7 +247,6 @@ mod tests {
     use {
         super::*,
         crate::{
-            Address,
             Envelope,
             Reply,
             Service,
@@ -119,6 +271,7 @@ mod tests {
     fn default_registry() {
         let registry = Registry::default();
         assert!(registry.list().is_empty());
+        assert_eq!(registry.key_mode(), KeyMode::Full);
     }
 
     #[test]
@@ -139,12 +292,12 @@ mod tests {
     #[test]
     fn get_instances_from_registry() -> Result<()> {
         let address = Address::parse(ADDRESS)?;
-        let instance = NilService(address);
+        let instance = NilService(address.clone());
         let registry = Registry::builder()
             .register(instance)
             .build();
 
-        let instances = registry.get(ADDRESS);
+        let instances = registry.get(&address);
         assert!(instances.is_some());
         assert_eq!(instances.unwrap().len(), 1);
         Ok(())
@@ -153,19 +306,106 @@ mod tests {
     #[test]
     fn new_service_instance() -> Result<()> {
         let address = Address::parse(ADDRESS)?;
-        let instance = NilService(address);
+        let instance = NilService(address.clone());
         let mut registry = Registry::builder()
             .register(instance)
             .build();
 
-        registry.add_instance(ADDRESS)?;
-        let instances = registry.get(ADDRESS);
+        registry.add_instance(&address)?;
+        let instances = registry.get(&address);
         assert!(instances.is_some());
         assert_eq!(instances.unwrap().len(), 2);
 
         Ok(())
     }
 
+    #[test]
+    fn new_instance_of_unknown_service() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let mut registry = Registry::default();
+        assert!(matches!(
+            registry.add_instance(&address),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn register_and_deregister_services() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let mut registry = Registry::default();
+
+        registry.register(NilService(address.clone()));
+        registry.register(NilService(address.clone()));
+        assert_eq!(
+            registry
+                .get(&address)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        registry.remove_instance(&address)?;
+        assert_eq!(
+            registry
+                .get(&address)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert_eq!(registry.deregister(&address)?, 1);
+        assert!(
+            registry
+                .get(&address)
+                .is_none()
+        );
+        assert!(matches!(
+            registry.deregister(&address),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn replace_service_instances() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let mut registry = Registry::default();
+        assert_eq!(
+            registry.replace(NilService(address.clone())),
+            0
+        );
+
+        registry.add_instance(&address)?;
+        assert_eq!(
+            registry.replace(NilService(address.clone())),
+            2
+        );
+        assert_eq!(
+            registry
+                .get(&address)
+                .unwrap()
+                .len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn remove_last_instance() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let mut registry = Registry::default();
+        registry.register(NilService(address.clone()));
+
+        registry.remove_instance(&address)?;
+        assert!(registry.list().is_empty());
+        assert!(matches!(
+            registry.remove_instance(&address),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+
     #[test]
     fn registry_from_vector_of_same_service() {
         let address = Address::parse(ADDRESS).unwrap();
@@ -180,7 +420,7 @@ mod tests {
         assert_eq!(registry.list().len(), 1);
         assert_eq!(
             registry
-                .get(address.authority())
+                .get(&address)
                 .unwrap()
                 .len(),
             3
@@ -200,4 +440,121 @@ mod tests {
 
         assert_eq!(registry.list().len(), 2);
     }
+
+    #[test]
+    fn matching_services() -> Result<()> {
+        let instances: ServiceVec = vec![
+            Box::new(NilService(Address::parse(
+                "http://eu.cache.com",
+            )?)),
+            Box::new(NilService(Address::parse(
+                "http://us.cache.com",
+            )?)),
+            Box::new(NilService(Address::parse(
+                "http://users.com",
+            )?)),
+        ];
+        let registry = Registry::from(instances);
+
+        let matching = registry.matching("http://*.cache.com");
+        let keys: Vec<&str> = matching
+            .iter()
+            .map(|(k, _)| k.as_ref())
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["http://eu.cache.com", "http://us.cache.com"]
+        );
+        assert!(
+            registry
+                .matching("grpc://*")
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn registry_with_authority_mode() -> Result<()> {
+        let service = Address::parse("http://users.com/users")?;
+        let destination = Address::parse("grpc://users.com/users/42?expand=orders")?;
+        let instances: ServiceVec = vec![Box::new(NilService(service))];
+
+        let registry = Registry::with_mode(instances, KeyMode::Authority);
+        assert_eq!(registry.list(), vec!["users.com"]);
+        assert!(
+            registry
+                .get(&destination)
+                .is_some()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn registry_with_authority_path_mode() -> Result<()> {
+        let service = Address::parse("http://users.com/users")?;
+        let instances: ServiceVec = vec![Box::new(NilService(service))];
+        let registry = Registry::with_mode(instances, KeyMode::AuthorityPath);
+        assert_eq!(registry.list(), vec!["users.com/users"]);
+
+        let destination = Address::parse("grpc://users.com/users/")?;
+        assert!(
+            registry
+                .get(&destination)
+                .is_some()
+        );
+
+        let destination = Address::parse("http://users.com/orders")?;
+        assert!(
+            registry
+                .get(&destination)
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn lookup_falls_back_to_parent_path() -> Result<()> {
+        let host = Address::parse("http://users.com")?;
+        let users = Address::parse("http://users.com/users")?;
+        let instances: ServiceVec = vec![
+            Box::new(NilService(host.clone())),
+            Box::new(NilService(users.clone())),
+        ];
+        let registry = Registry::from(instances);
+
+        let destination = Address::parse("http://users.com/users/42")?;
+        let (key, _) = registry
+            .lookup(&destination)
+            .unwrap();
+        assert_eq!(key, &registry.key(&users));
+
+        let destination = Address::parse("http://users.com/orders/7")?;
+        let (key, _) = registry
+            .lookup(&destination)
+            .unwrap();
+        assert_eq!(key, &registry.key(&host));
+
+        let destination = Address::parse("grpc://users.com/users")?;
+        assert!(
+            registry
+                .lookup(&destination)
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn registry_with_full_mode_ignores_query() -> Result<()> {
+        let service = Address::parse(ADDRESS)?;
+        let destination = Address::parse(format!("{ADDRESS}?id=1#top"))?;
+        let instances: ServiceVec = vec![Box::new(NilService(service))];
+
+        let registry = Registry::from(instances);
+        assert!(
+            registry
+                .get(&destination)
+                .is_some()
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,161 @@
+use {
+    super::AsyncRouter,
+    crate::{
+        AsyncService,
+        AsyncServiceBox,
+        Balancer,
+        Blocking,
+        KeyMode,
+        Registry,
+        Router,
+        Service,
+        ServiceKey,
+    },
+};
+
+/// Builder for constructing an [`AsyncRouter`].
+///
+/// Collects [`AsyncService`] instances, sync [`Service`]s wrapped in the
+/// [`Blocking`] adapter, the [`KeyMode`] and the balancing strategy before
+/// creating an immutable [`AsyncRouter`].
+#[derive(Default)]
+pub struct AsyncRouterBuilder {
+    services: Vec<AsyncServiceBox>,
+    mode:     KeyMode,
+    balancer: Balancer,
+    context:  Option<Router>,
+}
+
+impl AsyncRouterBuilder {
+    /// Registers a new [`AsyncService`] instance.
+    ///
+    /// Services with the same [`ServiceKey`] are instances of the same
+    /// logical service.
+    pub fn register(mut self, service: impl AsyncService + 'static) -> Self {
+        self.services
+            .push(Box::new(service));
+        self
+    }
+
+    /// Registers a sync [`Service`] instance through the [`Blocking`]
+    /// adapter.
+    pub fn register_sync(self, service: impl Service + 'static) -> Self {
+        self.register(Blocking::new(service))
+    }
+
+    /// Registers every instance of a sync [`Registry`] through the
+    /// [`Blocking`] adapter.
+    ///
+    /// The [`KeyMode`] of the [`Registry`] becomes the one of the
+    /// [`AsyncRouter`].
+    pub fn registry(mut self, registry: Registry) -> Self {
+        let (services, mode) = registry.into_parts();
+        self.mode = mode;
+        self.services.extend(
+            services
+                .into_values()
+                .flatten()
+                .map(|s| Box::new(Blocking::new(s)) as AsyncServiceBox),
+        );
+        self
+    }
+
+    /// Sets the [`KeyMode`] by name.
+    ///
+    /// See [`KeyMode`] for supported values such as `"authority"`,
+    /// `"authority_path"` or `"full"`.
+    pub fn key_mode(mut self, mode: &str) -> Self {
+        self.mode = KeyMode::from(mode);
+        self
+    }
+
+    /// Sets the balancing `Strategy` by name.
+    ///
+    /// See `Strategy` for supported values
+    /// such as `"round_robin"`, `"least_connections"`, or `"random"`.
+    pub fn balancer(mut self, strategy: &str) -> Self {
+        self.balancer = Balancer::new(strategy);
+        self
+    }
+
+    /// Sets the sync [`Router`] the
+    /// [`ServiceContext`](crate::ServiceContext) of the services is
+    /// bound to, for them to route messages, and reach its queues,
+    /// cache and configuration.
+    pub fn context(mut self, router: Router) -> Self {
+        self.context = Some(router);
+        self
+    }
+
+    /// Finalizes the builder and returns an [`AsyncRouter`].
+    pub fn build(self) -> AsyncRouter {
+        let mut router = AsyncRouter {
+            services: Default::default(),
+            mode:     self.mode,
+            balancer: self.balancer,
+            context:  self.context,
+        };
+
+        for service in self.services {
+            let key = ServiceKey::new(service.address(), router.mode);
+            router
+                .services
+                .entry(key)
+                .or_default()
+                .push(service);
+        }
+
+        router
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            Result,
+            service_fn,
+        },
+    };
+
+    #[test]
+    fn default_async_router_builder() {
+        let builder = AsyncRouterBuilder::default();
+        assert!(builder.services.is_empty());
+        assert_eq!(builder.mode, KeyMode::Full);
+        assert_eq!(builder.balancer.strategy(), "round_robin");
+    }
+
+    #[test]
+    fn build_async_router_from_registry() -> Result<()> {
+        let address = Address::parse("http://users.com/users")?;
+        let registry = Registry::builder()
+            .key_mode("authority")
+            .register(service_fn(address.clone(), |_| Ok(None)))
+            .register(service_fn(address, |_| Ok(None)))
+            .build();
+
+        let router = AsyncRouter::builder()
+            .registry(registry)
+            .balancer("random")
+            .build();
+        assert_eq!(router.list(), vec!["users.com"]);
+        assert_eq!(router.balancing_strategy(), "random");
+        assert_eq!(
+            router
+                .services
+                .values()
+                .next()
+                .unwrap()
+                .len(),
+            2
+        );
+        Ok(())
+    }
+}
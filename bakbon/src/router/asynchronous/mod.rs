@@ -0,0 +1,251 @@
+mod builder;
+
+pub use builder::AsyncRouterBuilder;
+use {
+    crate::{
+        AsyncServiceBox,
+        Balancer,
+        Envelope,
+        Error,
+        KeyMode,
+        Reply,
+        Result,
+        Router,
+        ServiceContext,
+        ServiceKey,
+    },
+    std::collections::HashMap,
+};
+
+/// Routes [`Envelope`]s to registered
+/// [`AsyncService`](crate::AsyncService) with load balancing.
+///
+/// Asynchronous counterpart of [`Router`](crate::Router): instances are
+/// grouped by [`ServiceKey`], selected by the same [`Balancer`], and their
+/// [`process()`](crate::AsyncService::process) future is awaited by
+/// [`route()`](AsyncRouter::route), which never blocks on its own.
+///
+/// The `AsyncRouter` is runtime-agnostic and `Send + Sync`, so it can be
+/// shared through an [`Arc`](std::sync::Arc) by tasks of any executor.
+/// Sync [`Service`](crate::Service)s are registered unchanged with
+/// [`AsyncRouterBuilder::register_sync()`] or
+/// [`AsyncRouterBuilder::registry()`].
+///
+/// Services get a [`ServiceContext`] bound to the sync [`Router`] set with
+/// [`AsyncRouterBuilder::context()`], or a detached one without it.
+///
+/// # Examples
+///
+/// ```ignore
+/// let router = AsyncRouter::builder()
+///     .register(async_service)
+///     .register_sync(sync_service)
+///     .build();
+///
+/// let reply = router.route(envelope).await?;
+/// ```
+pub struct AsyncRouter {
+    services: HashMap<ServiceKey, Vec<AsyncServiceBox>>,
+    mode:     KeyMode,
+    balancer: Balancer,
+    context:  Option<Router>,
+}
+
+impl AsyncRouter {
+    /// Returns a new [`AsyncRouterBuilder`] with default configuration.
+    pub fn builder() -> AsyncRouterBuilder { AsyncRouterBuilder::default() }
+
+    /// Routes a [`message`](Envelope) to a registered
+    /// [`AsyncService`](crate::AsyncService) and awaits its [`Reply`].
+    ///
+    /// Like the [`Registry`](crate::Registry), falls back to the longest
+    /// registered parent path of the destination
+    /// [`Address`](crate::Address). Returns [`Error::ServiceNotFound`]
+    /// if no service is registered under the [`ServiceKey`] of the
+    /// destination or of any of its parents.
+    pub async fn route(&self, msg: Envelope) -> Result<Reply> {
+        let instances = ServiceKey::new(msg.destination(), self.mode)
+            .prefixes()
+            .find_map(|key| self.services.get(&key))
+            .ok_or(Error::ServiceNotFound)?;
+
+        let service = self
+            .balancer
+            .select(instances)?;
+
+        let ctx = self
+            .context
+            .as_ref()
+            .map(ServiceContext::new)
+            .unwrap_or_default();
+        service
+            .process_with(msg, &ctx)
+            .await
+    }
+
+    /// Routes a [`message`](Envelope) on a new tokio task.
+    ///
+    /// The returned handle resolves to the [`Reply`] of
+    /// [`route()`](AsyncRouter::route). Must be called within a tokio
+    /// runtime.
+    #[cfg(feature = "tokio")]
+    pub fn spawn(
+        self: &std::sync::Arc<Self>,
+        msg: Envelope,
+    ) -> tokio::task::JoinHandle<Result<Reply>> {
+        let router = self.clone();
+        tokio::spawn(async move { router.route(msg).await })
+    }
+
+    /// Returns a list of all registered [`ServiceKey`]s string
+    /// representation.
+    pub fn list(&self) -> Vec<&str> {
+        self.services
+            .keys()
+            .map(|k| k.as_ref())
+            .collect()
+    }
+
+    /// Returns the active balancing `Strategy` as a string.
+    pub fn balancing_strategy(&self) -> &str { self.balancer.strategy() }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            AsyncService,
+            BoxFuture,
+            Payload,
+            Registry,
+            Service,
+            ServiceBox,
+            service_fn,
+            testing::ready,
+        },
+    };
+
+    #[derive(Debug)]
+    struct Upper(Address);
+
+    impl AsyncService for Upper {
+        fn address(&self) -> &Address { &self.0 }
+
+        fn duplicate(&self) -> AsyncServiceBox { Box::new(Self(self.0.clone())) }
+
+        fn process(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>> {
+            Box::pin(async move {
+                let payload = msg
+                    .payload()
+                    .to_ascii_uppercase();
+                Ok(Some(msg.into_reply(Payload::from(payload))))
+            })
+        }
+    }
+
+    /// Sync service forwarding every message through its context.
+    #[derive(Debug)]
+    struct Proxy(Address, Address);
+
+    impl Service for Proxy {
+        fn address(&self) -> &Address { &self.0 }
+
+        fn duplicate(&self) -> ServiceBox { Box::new(Self(self.0.clone(), self.1.clone())) }
+
+        fn process(&self, msg: Envelope) -> Result<Reply> {
+            self.process_with(msg, &ServiceContext::detached())
+        }
+
+        fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+            let payload = msg.payload().clone();
+            ctx.route(Envelope::new(
+                msg.source().clone(),
+                self.1.clone(),
+                payload,
+            ))
+        }
+    }
+
+    #[test]
+    fn router_is_thread_safe() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AsyncRouter>();
+    }
+
+    #[test]
+    fn build_default_async_router() {
+        let router = AsyncRouter::builder().build();
+        assert!(router.list().is_empty());
+        assert_eq!(router.balancing_strategy(), "round_robin");
+    }
+
+    #[test]
+    fn route_to_async_and_sync_services() -> Result<()> {
+        let client = Address::parse("http://client.com")?;
+        let upper = Address::parse("http://upper.com")?;
+        let echo = Address::parse("http://echo.com")?;
+        let router = AsyncRouter::builder()
+            .register(Upper(upper.clone()))
+            .register_sync(service_fn(echo.clone(), |msg| {
+                let payload = msg.payload().clone();
+                Ok(Some(msg.into_reply(payload)))
+            }))
+            .build();
+        assert_eq!(router.list().len(), 2);
+
+        let msg = Envelope::new(client.clone(), upper, Payload::from("hi"));
+        let reply = ready(router.route(msg))?.unwrap();
+        assert_eq!(reply.payload(), &Payload::from("HI"));
+
+        let msg = Envelope::new(client, echo, Payload::from("hi"));
+        let reply = ready(router.route(msg))?.unwrap();
+        assert_eq!(reply.payload(), &Payload::from("hi"));
+        Ok(())
+    }
+
+    #[test]
+    fn sync_services_get_the_context() -> Result<()> {
+        let client = Address::parse("http://client.com")?;
+        let echo = Address::parse("http://echo.com")?;
+        let proxy = Address::parse("http://proxy.com")?;
+        let backend = Router::builder()
+            .registry(
+                Registry::builder()
+                    .register(service_fn(echo.clone(), |msg| {
+                        let payload = msg.payload().clone();
+                        Ok(Some(msg.into_reply(payload)))
+                    }))
+                    .build(),
+            )
+            .build();
+
+        let router = AsyncRouter::builder()
+            .register_sync(Proxy(proxy.clone(), echo))
+            .context(backend)
+            .build();
+
+        let msg = Envelope::new(client, proxy, Payload::from("hi"));
+        let reply = ready(router.route(msg))?.unwrap();
+        assert_eq!(reply.payload(), &Payload::from("hi"));
+        Ok(())
+    }
+
+    #[test]
+    fn route_to_unknown_service() -> Result<()> {
+        let router = AsyncRouter::builder().build();
+        let src = Address::parse("http://client.com")?;
+        let dst = Address::parse("http://nowhere.com")?;
+        let msg = Envelope::new(src, dst, Payload::new());
+        assert!(matches!(
+            ready(router.route(msg)),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+}
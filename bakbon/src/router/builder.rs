@@ -1,24 +1,80 @@
-use crate::{
-    Balancer,
-    Registry,
-    Router,
+use {
+    super::{
+        federation::{
+            Federation,
+            local_peer,
+        },
+        limits::Limits,
+        pending::PendingReplies,
+        rules::Rules,
+        traffic::Traffic,
+        worker::Workers,
+    },
+    crate::{
+        Address,
+        Balancer,
+        BreakerPolicy,
+        Cache,
+        ConcurrencyLimit,
+        LiveRegistry,
+        Middleware,
+        PeerLink,
+        Queue,
+        RateLimit,
+        RetryPolicy,
+        Router,
+        Rule,
+        Service,
+        ServiceArc,
+        Split,
+        infra::Chain,
+    },
+    std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            Mutex,
+        },
+        time::Duration,
+    },
 };
 
-/// Builder for constructing a [`Router`] with a [`Registry`] and a
-/// [`Balancer`].
+/// Builder for constructing a [`Router`] with a
+/// [`Registry`](crate::Registry) and a [`Balancer`].
 ///
-/// Used to set up a the [`Registry`] and balancing strategy before
-/// creating an immutable [`Router`].
+/// Used to set up a the [`Registry`](crate::Registry) and balancing
+/// strategy before creating an immutable [`Router`].
 #[derive(Default)]
 pub struct RouterBuilder {
-    registry: Registry,
-    balancer: Balancer,
+    registry:    LiveRegistry,
+    balancer:    Balancer,
+    balancers:   Vec<(String, Balancer)>,
+    queues:      HashMap<String, Arc<Queue>>,
+    cache:       Cache,
+    config:      HashMap<String, String>,
+    fallback:    Option<ServiceArc>,
+    dead_letter: Option<Arc<Queue>>,
+    retries:     Vec<(String, RetryPolicy)>,
+    breaker:     Option<BreakerPolicy>,
+    timeouts:    Vec<(String, Duration)>,
+    workers:     Option<usize>,
+    limits:      Limits,
+    traffic:     Traffic,
+    rules:       Rules,
+    middleware:  Chain,
+    name:        Option<String>,
+    max_hops:    Option<usize>,
+    peers:       Vec<(String, Arc<dyn PeerLink>)>,
 }
 
 impl RouterBuilder {
-    /// Sets the service [`Registry`] used by the [`Router`].
-    pub fn registry(mut self, registry: Registry) -> Self {
-        self.registry = registry;
+    /// Sets the service [`Registry`](crate::Registry) used by the
+    /// [`Router`].
+    ///
+    /// Accepts a [`Registry`](crate::Registry), or a [`LiveRegistry`]
+    /// handle to keep updating it while the [`Router`] serves traffic.
+    pub fn registry(mut self, registry: impl Into<LiveRegistry>) -> Self {
+        self.registry = registry.into();
         self
     }
 
@@ -31,11 +87,309 @@ impl RouterBuilder {
         self
     }
 
+    /// Sets the [`Balancer`] of the services whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern, instead
+    /// of the default one.
+    ///
+    /// Routes are matched like in [`retry()`](RouterBuilder::retry).
+    pub fn service_balancer(mut self, pattern: &str, balancer: Balancer) -> Self {
+        self.balancers
+            .push((pattern.to_string(), balancer));
+        self
+    }
+
+    /// Adds a named [`Queue`] reachable from services through the
+    /// [`ServiceContext`](crate::ServiceContext).
+    ///
+    /// The [`Queue`] is shared, so consumers outside of the [`Router`] can
+    /// keep a handle on it.
+    pub fn queue(mut self, name: &str, queue: Arc<Queue>) -> Self {
+        self.queues
+            .insert(name.to_string(), queue);
+        self
+    }
+
+    /// Sets the [`Cache`] shared by the routed services.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Sets a configuration value readable by the routed services.
+    pub fn config(mut self, key: &str, value: &str) -> Self {
+        self.config
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Sets the [`Service`] receiving the messages no registered
+    /// [`Service`] can be found for.
+    ///
+    /// The messages are tagged with the `x-dead-letter-*` headers before
+    /// being handed over, see
+    /// [`DeadLetterReason`](crate::DeadLetterReason). The messages which
+    /// timed out are handed over as well.
+    pub fn fallback(mut self, service: impl Service + 'static) -> Self {
+        self.fallback = Some(Arc::new(service));
+        self
+    }
+
+    /// Sets the dead-letter [`Queue`] keeping the messages which could not
+    /// be routed nor processed.
+    ///
+    /// The messages are tagged with the `x-dead-letter-*` headers telling
+    /// why and where they failed, see
+    /// [`DeadLetterReason`](crate::DeadLetterReason). A failed message is
+    /// only kept once the [`RetryPolicy`] of its route gave up on it.
+    ///
+    /// A message a [`Service`] routes through its
+    /// [`ServiceContext`](crate::ServiceContext) is not kept: only the
+    /// message that [`Service`] processes is, if it fails in turn. The
+    /// messages the [`Queue`] refuses are counted by
+    /// [`Router::dead_letter_failures()`].
+    pub fn dead_letter(mut self, queue: Arc<Queue>) -> Self {
+        self.dead_letter = Some(queue);
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] of the services whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern.
+    ///
+    /// A failed delivery is retried on other instances, tagged with its
+    /// attempt number in the [`ATTEMPT_HEADER`](crate::ATTEMPT_HEADER), as
+    /// long as its error is retryable and the policy allows. The wait
+    /// between attempts is cut short at the
+    /// [`deadline()`](crate::Envelope::deadline) of the message, past
+    /// which it is no longer retried.
+    ///
+    /// Routes are tried in the order they were added and the first match
+    /// applies, so add the most specific ones first; `"*"` matches every
+    /// service. See [`ServiceKey::matches()`](crate::ServiceKey::matches)
+    /// for the pattern syntax.
+    pub fn retry(mut self, pattern: &str, policy: RetryPolicy) -> Self {
+        self.retries
+            .push((pattern.to_string(), policy));
+        self
+    }
+
+    /// Sets how long the services whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern may
+    /// process a message.
+    ///
+    /// A [`Service`] overrunning its timeout, or the
+    /// [`deadline()`](crate::Envelope::deadline) of the message, fails
+    /// with [`Error::TimedOut`](crate::Error::TimedOut). The call runs on
+    /// a worker thread, and cannot be interrupted: see
+    /// [`Router::overdue_workers()`]. Routes are matched like in
+    /// [`retry()`](RouterBuilder::retry).
+    pub fn timeout(mut self, pattern: &str, timeout: Duration) -> Self {
+        self.timeouts
+            .push((pattern.to_string(), timeout));
+        self
+    }
+
+    /// Sets the number of worker threads running the calls bounded by a
+    /// timeout, 64 by default.
+    ///
+    /// Threads are started on demand and stop once idle. As many calls can
+    /// wait for a busy thread, past which they fail with
+    /// [`Error::TaskFailed`](crate::Error::TaskFailed).
+    pub fn workers(mut self, size: usize) -> Self {
+        self.workers = Some(size);
+        self
+    }
+
+    /// Limits the rate of the messages to the services whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern, with a
+    /// token bucket per [`ServiceKey`](crate::ServiceKey).
+    ///
+    /// Messages over the limit fail with
+    /// [`Error::Throttled`](crate::Error::Throttled), unless the
+    /// [`RateLimit`] lets them wait for their turn. Routes are matched
+    /// like in [`retry()`](RouterBuilder::retry).
+    pub fn rate_limit(mut self, pattern: &str, limit: RateLimit) -> Self {
+        self.limits
+            .add_rate(pattern, limit);
+        self
+    }
+
+    /// Limits the rate of the messages each source sends to the services
+    /// whose [`ServiceKey`](crate::ServiceKey) matches a glob pattern,
+    /// with a token bucket per [`ServiceKey`](crate::ServiceKey) and
+    /// source.
+    ///
+    /// Sources are told apart by the [`ServiceKey`](crate::ServiceKey) of
+    /// their [`Address`], so that a noisy client cannot starve the others.
+    /// Applies on top of the [`rate_limit()`](RouterBuilder::rate_limit)
+    /// of the route, if any.
+    pub fn source_rate_limit(mut self, pattern: &str, limit: RateLimit) -> Self {
+        self.limits
+            .add_source_rate(pattern, limit);
+        self
+    }
+
+    /// Limits the number of messages in flight to each service whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern.
+    ///
+    /// Messages over the limit fail with
+    /// [`Error::Throttled`](crate::Error::Throttled), unless the
+    /// [`ConcurrencyLimit`] lets them wait for a slot. A message keeps its
+    /// slot across its retries, waits between attempts included. Routes
+    /// are matched like in [`retry()`](RouterBuilder::retry).
+    pub fn concurrency_limit(mut self, pattern: &str, limit: ConcurrencyLimit) -> Self {
+        self.limits
+            .add_concurrency(pattern, limit);
+        self
+    }
+
+    /// Adds a [`Middleware`] at the end of the chain run around every
+    /// routed message.
+    ///
+    /// Middlewares run in the order they were added, before the routing
+    /// [`Rule`]s, and may short-circuit the delivery with a reply or an
+    /// error.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware
+            .push(middleware);
+        self
+    }
+
+    /// Adds a routing [`Rule`], evaluated before the
+    /// [`Registry`](crate::Registry) lookup by descending priority.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.add(rule);
+        self
+    }
+
+    /// Sends part of the traffic of the services whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern to another
+    /// destination, see [`Split`].
+    ///
+    /// Several rules can apply to a route, in the order they were added.
+    pub fn split(mut self, pattern: &str, split: Split) -> Self {
+        self.traffic
+            .add_split(pattern, split);
+        self
+    }
+
+    /// Mirrors the traffic of the services whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern to a
+    /// shadow [`Service`], whose replies are discarded.
+    ///
+    /// The copies are tagged with the
+    /// [`MIRROR_HEADER`](crate::MIRROR_HEADER) and delivered by one
+    /// background worker, which drops them when it falls too far behind.
+    /// Their outcome is available from [`Router::mirror_stats()`].
+    pub fn mirror(mut self, pattern: &str, target: Address) -> Self {
+        self.traffic
+            .add_mirror(pattern, target);
+        self
+    }
+
+    /// Enables a circuit breaker per [`Service`] instance, with the given
+    /// [`BreakerPolicy`].
+    ///
+    /// Instances whose circuit is open are skipped by the [`Balancer`]
+    /// until their cool-down is over. When every circuit is open, messages
+    /// fail with [`Error::CircuitOpen`](crate::Error::CircuitOpen) without
+    /// being delivered.
+    pub fn circuit_breaker(mut self, policy: BreakerPolicy) -> Self {
+        self.breaker = Some(policy);
+        self
+    }
+
+    /// Sets the name of the [`Router`] among its peers.
+    ///
+    /// Names must be unique across linked routers: they are how forwarded
+    /// messages and advertised routes avoid going in circles. Without one,
+    /// a unique name is generated.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Sets how many times a message may be forwarded between routers, 8
+    /// by default.
+    ///
+    /// Routes longer than this are not learned from peers, and a message
+    /// which was already forwarded this many times fails with
+    /// [`Error::HopLimitExceeded`](crate::Error::HopLimitExceeded) rather
+    /// than being forwarded again.
+    pub fn max_hops(mut self, hops: usize) -> Self {
+        self.max_hops = Some(hops);
+        self
+    }
+
+    /// Links a peer [`Router`] over a [`PeerLink`]: the messages with no
+    /// local [`Service`] are forwarded to it, if it advertises their
+    /// destination.
+    ///
+    /// See [`Router::link()`] to link a peer once the [`Router`] is built.
+    pub fn peer(mut self, name: &str, link: impl PeerLink + 'static) -> Self {
+        self.peers
+            .push((name.to_string(), Arc::new(link)));
+        self
+    }
+
+    /// Links a peer [`Router`] of the same process, like
+    /// [`peer()`](RouterBuilder::peer).
+    ///
+    /// The peer is held weakly: keep a handle on it for as long as it
+    /// should be forwarded to. See [`Router::link_local()`] to link a peer
+    /// once the [`Router`] is built.
+    pub fn link_router(mut self, name: &str, peer: &Router) -> Self {
+        self.peers
+            .push((name.to_string(), local_peer(peer)));
+        self
+    }
+
     /// Finalizes the builder and returns a [`Router`].
     pub fn build(self) -> Router {
+        let breaker = self.breaker;
+        let federation = Federation::new(
+            self.name
+                .unwrap_or_else(PendingReplies::next_id),
+            self.max_hops.unwrap_or(8),
+            breaker
+                .clone()
+                .unwrap_or_default(),
+        );
+        for (name, link) in self.peers {
+            federation.link(&name, link);
+        }
+        let with_breakers = |balancer: Balancer| match &breaker {
+            Some(policy) => balancer.with_breakers(policy.clone()),
+            None => balancer,
+        };
+
         Router {
-            registry: self.registry,
-            balancer: self.balancer,
+            registry:             self.registry,
+            balancer:             Arc::new(with_breakers(self.balancer)),
+            balancers:            Arc::new(
+                self.balancers
+                    .into_iter()
+                    .map(|(pattern, balancer)| (pattern, with_breakers(balancer)))
+                    .collect(),
+            ),
+            queues:               Arc::new(self.queues),
+            cache:                Arc::new(Mutex::new(self.cache)),
+            config:               Arc::new(self.config),
+            fallback:             self.fallback,
+            dead_letter:          self.dead_letter,
+            pending:              Default::default(),
+            retries:              Arc::new(self.retries),
+            timeouts:             Arc::new(self.timeouts),
+            limits:               Arc::new(self.limits),
+            workers:              Arc::new(
+                self.workers
+                    .map(Workers::new)
+                    .unwrap_or_default(),
+            ),
+            traffic:              Arc::new(self.traffic),
+            dead_letter_failures: Default::default(),
+            rules:                Arc::new(self.rules),
+            middleware:           Arc::new(self.middleware),
+            federation:           Arc::new(federation),
         }
     }
 }
@@ -54,8 +408,28 @@ mod tests {
     #[test]
     fn default_router_builder() {
         let builder = RouterBuilder::default();
-        assert!(builder.registry.0.is_empty());
+        assert!(
+            builder
+                .registry
+                .list()
+                .is_empty()
+        );
         assert_eq!(builder.balancer.strategy(), "round_robin");
+        assert!(builder.balancers.is_empty());
+        assert!(builder.queues.is_empty());
+        assert!(builder.config.is_empty());
+        assert!(builder.fallback.is_none());
+        assert!(builder.dead_letter.is_none());
+        assert!(builder.retries.is_empty());
+        assert!(builder.breaker.is_none());
+        assert!(builder.timeouts.is_empty());
+        assert!(builder.limits.is_empty());
+        assert!(builder.traffic.is_empty());
+        assert!(builder.rules.is_empty());
+        assert_eq!(builder.middleware.len(), 0);
+        assert!(builder.name.is_none());
+        assert!(builder.max_hops.is_none());
+        assert!(builder.peers.is_empty());
     }
 
     #[test]
@@ -0,0 +1,159 @@
+use crate::{
+    Address,
+    Envelope,
+    Error,
+};
+
+/// Header telling why an [`Envelope`] was dead-lettered, see
+/// [`DeadLetterReason`].
+pub const DEAD_LETTER_REASON_HEADER: &str = "x-dead-letter-reason";
+
+/// Header carrying the [`Error`] message of a dead-lettered [`Envelope`].
+pub const DEAD_LETTER_ERROR_HEADER: &str = "x-dead-letter-error";
+
+/// Header carrying the original destination of a dead-lettered
+/// [`Envelope`].
+pub const DEAD_LETTER_DESTINATION_HEADER: &str = "x-dead-letter-destination";
+
+/// Header carrying the [`Address`] of the [`Service`](crate::Service)
+/// instance which failed to process a dead-lettered [`Envelope`].
+pub const DEAD_LETTER_SERVICE_HEADER: &str = "x-dead-letter-service";
+
+/// Why the [`Router`](crate::Router) gave up on an [`Envelope`].
+///
+/// - [`Unroutable`](DeadLetterReason::Unroutable): no
+///   [`Service`](crate::Service) is registered for its destination.
+/// - [`Failed`](DeadLetterReason::Failed): the selected
+///   [`Service`](crate::Service) returned an [`Error`].
+/// - [`TimedOut`](DeadLetterReason::TimedOut): the selected
+///   [`Service`](crate::Service) overran the timeout of the route or the
+///   deadline of the [`Envelope`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeadLetterReason {
+    Unroutable,
+    Failed,
+    TimedOut,
+}
+
+impl DeadLetterReason {
+    /// Returns the reason an [`Envelope`] was dead-lettered for, if any.
+    pub fn of(msg: &Envelope) -> Option<Self> {
+        match msg.get_header(DEAD_LETTER_REASON_HEADER)? {
+            "unroutable" => Some(Self::Unroutable),
+            "failed" => Some(Self::Failed),
+            "timed_out" => Some(Self::TimedOut),
+            _ => None,
+        }
+    }
+
+    /// Tags an [`Envelope`] with the dead-letter headers.
+    ///
+    /// The original destination is only recorded the first time, so an
+    /// [`Envelope`] failing again in the fallback
+    /// [`Service`](crate::Service) keeps pointing at where it was sent.
+    pub(super) fn mark(
+        self,
+        mut msg: Envelope,
+        error: &Error,
+        service: Option<&Address>,
+    ) -> Envelope {
+        if msg
+            .get_header(DEAD_LETTER_DESTINATION_HEADER)
+            .is_none()
+        {
+            let destination = msg.destination().to_string();
+            msg.add_header(DEAD_LETTER_DESTINATION_HEADER, &destination);
+        }
+        msg.add_header(DEAD_LETTER_REASON_HEADER, self.as_ref());
+        msg.add_header(DEAD_LETTER_ERROR_HEADER, &error.to_string());
+        if let Some(service) = service {
+            msg.add_header(
+                DEAD_LETTER_SERVICE_HEADER,
+                &service.to_string(),
+            );
+        }
+        msg
+    }
+}
+
+impl AsRef<str> for DeadLetterReason {
+    /// Returns the string representation of the reason, as written in the
+    /// [`DEAD_LETTER_REASON_HEADER`].
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Unroutable => "unroutable",
+            Self::Failed => "failed",
+            Self::TimedOut => "timed_out",
+        }
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Payload,
+            Result,
+        },
+    };
+
+    #[test]
+    fn mark_dead_letter() -> Result<()> {
+        let src = Address::parse("http://client.com")?;
+        let dst = Address::parse("http://orders.com")?;
+        let fallback = Address::parse("http://fallback.com")?;
+        let msg = Envelope::new(src, dst, Payload::new());
+        assert!(DeadLetterReason::of(&msg).is_none());
+
+        let msg = DeadLetterReason::Unroutable.mark(msg, &Error::ServiceNotFound, None);
+        assert_eq!(
+            DeadLetterReason::of(&msg),
+            Some(DeadLetterReason::Unroutable)
+        );
+        assert_eq!(
+            msg.get_header(DEAD_LETTER_DESTINATION_HEADER),
+            Some("http://orders.com")
+        );
+        assert_eq!(
+            msg.get_header(DEAD_LETTER_ERROR_HEADER),
+            Some("Service not found.")
+        );
+        assert!(
+            msg.get_header(DEAD_LETTER_SERVICE_HEADER)
+                .is_none()
+        );
+
+        let msg = msg.redirect(fallback.clone());
+        let error = Error::ProcessorNotFound("/".to_string());
+        let msg = DeadLetterReason::Failed.mark(msg, &error, Some(&fallback));
+        assert_eq!(
+            DeadLetterReason::of(&msg),
+            Some(DeadLetterReason::Failed)
+        );
+        assert_eq!(
+            msg.get_header(DEAD_LETTER_DESTINATION_HEADER),
+            Some("http://orders.com")
+        );
+        assert_eq!(
+            msg.get_header(DEAD_LETTER_SERVICE_HEADER),
+            Some("http://fallback.com")
+        );
+
+        let error = Error::TimedOut(std::time::Duration::from_millis(5));
+        let msg = DeadLetterReason::TimedOut.mark(msg, &error, Some(&fallback));
+        assert_eq!(
+            DeadLetterReason::of(&msg),
+            Some(DeadLetterReason::TimedOut)
+        );
+        assert_eq!(
+            msg.get_header(DEAD_LETTER_REASON_HEADER),
+            Some("timed_out")
+        );
+        Ok(())
+    }
+}
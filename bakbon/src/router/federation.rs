@@ -0,0 +1,810 @@
+use {
+    crate::{
+        BreakerPolicy,
+        CircuitState,
+        Envelope,
+        Error,
+        Payload,
+        Reply,
+        Result,
+        Router,
+        ServiceKey,
+        balancer::Circuit,
+        core::Recover,
+    },
+    std::{
+        sync::{
+            Arc,
+            Mutex,
+            MutexGuard,
+            RwLock,
+            Weak,
+        },
+        time::{
+            Instant,
+            SystemTime,
+        },
+    },
+};
+
+/// Header carrying the number of times an [`Envelope`] was forwarded
+/// between [`Router`]s.
+pub const HOPS_HEADER: &str = "x-hops";
+
+/// Header carrying the comma-separated names of the [`Router`]s which
+/// forwarded an [`Envelope`], in order.
+pub const FORWARDED_BY_HEADER: &str = "x-forwarded-by";
+
+/// Connection to a peer [`Router`] over a transport.
+///
+/// Routers in the same process are linked with
+/// [`Router::link_local()`] instead, which holds the peer weakly: routers
+/// linked to each other do not keep each other alive, and a dropped one
+/// fails with [`Error::Unavailable`]. To link routers over a network,
+/// implement this trait on top of the transport:
+/// send the forwarded [`Envelope`] and the request for an
+/// [`Advertisement`] to the remote [`Router`], which answers them with
+/// [`route()`](Router::route) and [`advertise()`](Router::advertise).
+/// [`Advertisement::to_payload()`] and [`Advertisement::parse()`] give its
+/// wire format.
+///
+/// Errors from the link count against the health of the peer when they
+/// are [retryable](Error::is_retryable), such as
+/// [`Error::Unavailable`] for a peer that cannot be reached.
+pub trait PeerLink: Send + Sync {
+    /// Hands a message over to the peer, and returns its [`Reply`].
+    fn forward(&self, msg: Envelope) -> Result<Reply>;
+
+    /// Returns the routes the peer advertises.
+    fn advertisement(&self) -> Result<Advertisement>;
+}
+
+/// In-process link to a peer [`Router`], holding its [`Federation`]
+/// weakly.
+///
+/// The peer links its own peers through its [`Federation`], often back to
+/// the linking [`Router`]: holding it strongly would make a cycle keeping
+/// both alive for good.
+struct LocalPeer {
+    // The peer, bound to an empty federation until it is upgraded.
+    router:     Router,
+    federation: Weak<Federation>,
+}
+
+impl LocalPeer {
+    fn new(router: Router) -> Self {
+        let federation = Arc::downgrade(&router.federation);
+        let router = Router {
+            federation: Arc::new(Federation::new(
+                String::new(),
+                0,
+                BreakerPolicy::default(),
+            )),
+            ..router
+        };
+        Self {
+            router,
+            federation,
+        }
+    }
+
+    /// Returns the peer [`Router`], or [`Error::Unavailable`] if it was
+    /// dropped.
+    fn upgrade(&self) -> Result<Router> {
+        let federation = self
+            .federation
+            .upgrade()
+            .ok_or_else(|| Error::Unavailable("peer router dropped".to_string()))?;
+        Ok(Router {
+            federation,
+            ..self.router.clone()
+        })
+    }
+}
+
+impl PeerLink for LocalPeer {
+    fn forward(&self, msg: Envelope) -> Result<Reply> { self.upgrade()?.route(msg) }
+
+    fn advertisement(&self) -> Result<Advertisement> { Ok(self.upgrade()?.advertise()) }
+}
+
+/// Returns an in-process link to a peer [`Router`], holding it weakly.
+pub(super) fn local_peer(router: &Router) -> Arc<dyn PeerLink> {
+    Arc::new(LocalPeer::new(router.clone()))
+}
+
+/// Route advertised by a [`Router`] to its peers: a
+/// [`ServiceKey`](crate::ServiceKey) it can reach, and the names of the
+/// [`Router`]s a message goes through to get there.
+///
+/// The path is empty for a [`Service`](crate::Service) registered with
+/// the advertising [`Router`] itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdvertisedRoute {
+    key:  String,
+    path: Vec<String>,
+}
+
+impl AdvertisedRoute {
+    /// Returns the [`ServiceKey`](crate::ServiceKey) reached by the route.
+    pub fn key(&self) -> &str { &self.key }
+
+    /// Returns the names of the [`Router`]s on the way, the last one
+    /// holding the [`Service`](crate::Service).
+    pub fn path(&self) -> &[String] { &self.path }
+
+    /// Returns the number of forwards needed past the advertising
+    /// [`Router`].
+    pub fn hops(&self) -> usize { self.path.len() }
+}
+
+/// Routes a [`Router`] can reach, advertised to its peers with
+/// [`advertise()`](Router::advertise).
+///
+/// Its wire format is one tab-separated record per line: the `origin`
+/// name, then each `route` with its key and path.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let advertisement = Advertisement::new("eu-1")
+///     .route("http://orders", &[])
+///     .route("http://users", &["us-1"]);
+///
+/// let parsed = Advertisement::parse(&advertisement.to_payload())?;
+/// assert_eq!(parsed, advertisement);
+/// assert_eq!(parsed.routes()[1].hops(), 1);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advertisement {
+    origin: String,
+    routes: Vec<AdvertisedRoute>,
+}
+
+impl Advertisement {
+    /// Creates an empty advertisement from the [`Router`] with a given
+    /// name.
+    pub fn new(origin: &str) -> Self {
+        Self {
+            origin: origin.to_string(),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Adds a route to a [`ServiceKey`](crate::ServiceKey) through the
+    /// [`Router`]s of `path`.
+    pub fn route(mut self, key: &str, path: &[&str]) -> Self {
+        self.routes
+            .push(AdvertisedRoute {
+                key:  key.to_string(),
+                path: path
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect(),
+            });
+        self
+    }
+
+    /// Returns the name of the advertising [`Router`].
+    pub fn origin(&self) -> &str { &self.origin }
+
+    /// Returns the advertised routes.
+    pub fn routes(&self) -> &[AdvertisedRoute] { &self.routes }
+
+    /// Encodes the advertisement into a [`Payload`].
+    pub fn to_payload(&self) -> Payload {
+        let mut lines = vec![format!("origin\t{}", self.origin)];
+        for route in &self.routes {
+            let mut line = format!("route\t{}", route.key);
+            for name in &route.path {
+                line.push('\t');
+                line.push_str(name);
+            }
+            lines.push(line);
+        }
+        Payload::from(lines.join("\n"))
+    }
+
+    /// Decodes an advertisement encoded by
+    /// [`to_payload()`](Advertisement::to_payload).
+    pub fn parse(payload: &Payload) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidAdvertisement(reason.to_string());
+        let text = std::str::from_utf8(payload).map_err(|_| invalid("not UTF-8"))?;
+
+        let mut lines = text.lines();
+        let origin = match lines
+            .next()
+            .map(|line| {
+                line.split('\t')
+                    .collect::<Vec<_>>()
+            })
+            .as_deref()
+        {
+            Some(["origin", origin]) => origin.to_string(),
+            _ => return Err(invalid("missing origin")),
+        };
+        let mut routes = Vec::new();
+        for line in lines {
+            match line
+                .split('\t')
+                .collect::<Vec<_>>()
+                .as_slice()
+            {
+                ["route", key, path @ ..] => routes.push(AdvertisedRoute {
+                    key:  key.to_string(),
+                    path: path
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect(),
+                }),
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(Self {
+            origin,
+            routes,
+        })
+    }
+}
+
+/// Health of a peer [`Router`], as seen from a linked [`Router`].
+///
+/// The [`CircuitState`] follows the [`BreakerPolicy`] of the [`Router`]:
+/// a peer whose link keeps failing is skipped until its cool-down is
+/// over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerHealth {
+    state:      CircuitState,
+    routes:     usize,
+    forwarded:  u64,
+    failed:     u64,
+    last_error: Option<String>,
+    advertised: Option<SystemTime>,
+}
+
+impl PeerHealth {
+    /// Returns the state of the circuit breaker of the peer.
+    pub fn state(&self) -> CircuitState { self.state }
+
+    /// Returns the number of routes learned from the peer.
+    pub fn routes(&self) -> usize { self.routes }
+
+    /// Returns the number of messages forwarded to the peer.
+    pub fn forwarded(&self) -> u64 { self.forwarded }
+
+    /// Returns the number of forwards and advertisement requests which
+    /// failed.
+    pub fn failed(&self) -> u64 { self.failed }
+
+    /// Returns the last error of the link, if any.
+    pub fn last_error(&self) -> Option<&str> { self.last_error.as_deref() }
+
+    /// Returns when the routes of the peer were last learned, if ever.
+    pub fn advertised(&self) -> Option<SystemTime> { self.advertised }
+}
+
+/// A linked peer, its routes and its health.
+struct Peer {
+    name:   String,
+    link:   Arc<dyn PeerLink>,
+    routes: Mutex<Vec<AdvertisedRoute>>,
+    health: Mutex<Health>,
+}
+
+struct Health {
+    circuit:    Circuit,
+    forwarded:  u64,
+    failed:     u64,
+    last_error: Option<String>,
+    advertised: Option<SystemTime>,
+}
+
+impl Peer {
+    fn routes(&self) -> MutexGuard<'_, Vec<AdvertisedRoute>> { self.routes.lock().recover() }
+
+    fn health(&self) -> MutexGuard<'_, Health> { self.health.lock().recover() }
+
+    /// Records the outcome of a call through the link.
+    fn record<T>(&self, policy: &BreakerPolicy, outcome: &Result<T>) {
+        let mut health = self.health();
+        let failed = match outcome {
+            Ok(_) => false,
+            Err(error) => {
+                health.failed += 1;
+                health.last_error = Some(error.to_string());
+                error.is_retryable()
+            }
+        };
+        health
+            .circuit
+            .record(policy, failed, Instant::now());
+    }
+}
+
+/// Peers of a [`Router`]: the routes they advertise and their health.
+pub(super) struct Federation {
+    name:     String,
+    max_hops: usize,
+    policy:   BreakerPolicy,
+    peers:    RwLock<Vec<Arc<Peer>>>,
+}
+
+impl Federation {
+    pub(super) fn new(name: String, max_hops: usize, policy: BreakerPolicy) -> Self {
+        Self {
+            name,
+            max_hops,
+            policy,
+            peers: RwLock::default(),
+        }
+    }
+
+    pub(super) fn name(&self) -> &str { &self.name }
+
+    fn peers(&self) -> Vec<Arc<Peer>> {
+        self.peers
+            .read()
+            .recover()
+            .clone()
+    }
+
+    /// Links a peer, replacing the one with the same name, if any.
+    pub(super) fn link(&self, name: &str, link: Arc<dyn PeerLink>) {
+        let peer = Arc::new(Peer {
+            name: name.to_string(),
+            link,
+            routes: Mutex::default(),
+            health: Mutex::new(Health {
+                circuit:    Circuit::new(Instant::now()),
+                forwarded:  0,
+                failed:     0,
+                last_error: None,
+                advertised: None,
+            }),
+        });
+        let mut peers = self.peers.write().recover();
+        peers.retain(|peer| peer.name != name);
+        peers.push(peer);
+    }
+
+    /// Unlinks a peer, returning whether it was linked.
+    pub(super) fn unlink(&self, name: &str) -> bool {
+        let mut peers = self.peers.write().recover();
+        let linked = peers.len();
+        peers.retain(|peer| peer.name != name);
+        peers.len() < linked
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.peers
+            .read()
+            .recover()
+            .is_empty()
+    }
+
+    /// Returns the names of the peers, in the order they were linked.
+    pub(super) fn names(&self) -> Vec<String> {
+        self.peers()
+            .iter()
+            .map(|peer| peer.name.clone())
+            .collect()
+    }
+
+    /// Asks every peer for its routes, and keeps the ones short enough and
+    /// not going through this [`Router`].
+    pub(super) fn refresh(&self) {
+        for peer in self.peers() {
+            let advertisement = peer.link.advertisement();
+            peer.record(&self.policy, &advertisement);
+            let Ok(advertisement) = advertisement
+            else {
+                continue;
+            };
+
+            let routes: Vec<AdvertisedRoute> = advertisement
+                .routes
+                .into_iter()
+                .map(|route| {
+                    let mut path = vec![advertisement.origin.clone()];
+                    path.extend(route.path);
+                    AdvertisedRoute {
+                        key: route.key,
+                        path,
+                    }
+                })
+                .filter(|route| {
+                    route.hops() <= self.max_hops
+                        && !route
+                            .path
+                            .contains(&self.name)
+                })
+                .collect();
+            peer.health().advertised = Some(SystemTime::now());
+            *peer.routes() = routes;
+        }
+    }
+
+    /// Returns the shortest route learned for each key, to advertise
+    /// further if a peer could still forward along it.
+    pub(super) fn learned(&self) -> Vec<AdvertisedRoute> {
+        let mut learned: Vec<AdvertisedRoute> = Vec::new();
+        for peer in self.peers() {
+            for route in peer.routes().iter() {
+                if route.hops() >= self.max_hops {
+                    continue;
+                }
+                match learned
+                    .iter_mut()
+                    .find(|known| known.key == route.key)
+                {
+                    Some(known) if known.hops() <= route.hops() => {}
+                    Some(known) => *known = route.clone(),
+                    None => learned.push(route.clone()),
+                }
+            }
+        }
+        learned
+    }
+
+    /// Returns the names of the [`Router`]s a message was forwarded by,
+    /// along with the peers advertising its destination which it did not
+    /// go through yet, closest first.
+    fn candidates(&self, key: &ServiceKey, msg: &Envelope) -> (Vec<String>, Vec<Arc<Peer>>) {
+        let forwarded_by: Vec<String> = msg
+            .get_header(FORWARDED_BY_HEADER)
+            .map(|names| {
+                names
+                    .split(',')
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut candidates: Vec<(usize, Arc<Peer>)> = self
+            .peers()
+            .into_iter()
+            .filter(|peer| !forwarded_by.contains(&peer.name))
+            .filter_map(|peer| {
+                let hops = peer
+                    .routes()
+                    .iter()
+                    .filter(|route| route.key == key.as_ref())
+                    .map(AdvertisedRoute::hops)
+                    .min()?;
+                Some((hops, peer))
+            })
+            .collect();
+        candidates.sort_by_key(|(hops, _)| *hops);
+        let candidates = candidates
+            .into_iter()
+            .map(|(_, peer)| peer)
+            .collect();
+        (forwarded_by, candidates)
+    }
+
+    /// Returns the number of times a message was forwarded.
+    fn hops(msg: &Envelope) -> usize {
+        msg.get_header(HOPS_HEADER)
+            .and_then(|hops| hops.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Tells which peer [`forward()`](Federation::forward) would hand a
+    /// message to first, without forwarding it.
+    ///
+    /// Returns `None` if no peer can take it.
+    pub(super) fn preview(&self, key: &ServiceKey, msg: &Envelope) -> Option<Result<String>> {
+        let (_, candidates) = self.candidates(key, msg);
+        if candidates.is_empty() {
+            return None;
+        }
+        if Self::hops(msg) >= self.max_hops {
+            return Some(Err(Error::HopLimitExceeded(self.max_hops)));
+        }
+
+        let now = Instant::now();
+        let peer = candidates
+            .iter()
+            .find(|peer| {
+                peer.health()
+                    .circuit
+                    .is_available(&self.policy, now)
+            })
+            .map(|peer| peer.name.clone())
+            .ok_or(Error::CircuitOpen);
+        Some(peer)
+    }
+
+    /// Forwards a message to the closest healthy peer advertising its
+    /// destination, trying the next one while the link fails.
+    ///
+    /// Returns `None` if no peer can take it.
+    pub(super) fn forward(&self, key: &ServiceKey, msg: &Envelope) -> Option<Result<Reply>> {
+        let (mut forwarded_by, candidates) = self.candidates(key, msg);
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let hops = Self::hops(msg);
+        if hops >= self.max_hops {
+            return Some(Err(Error::HopLimitExceeded(self.max_hops)));
+        }
+        let mut msg = msg.clone();
+        msg.add_header(HOPS_HEADER, &(hops + 1).to_string());
+        forwarded_by.push(self.name.clone());
+        msg.add_header(FORWARDED_BY_HEADER, &forwarded_by.join(","));
+
+        let mut outcome = Err(Error::CircuitOpen);
+        for peer in candidates {
+            {
+                let mut health = peer.health();
+                let now = Instant::now();
+                if !health
+                    .circuit
+                    .is_available(&self.policy, now)
+                {
+                    continue;
+                }
+                health.circuit.select(now);
+                health.forwarded += 1;
+            }
+
+            outcome = peer.link.forward(msg.clone());
+            peer.record(&self.policy, &outcome);
+            match &outcome {
+                Err(error) if error.is_retryable() => continue,
+                _ => break,
+            }
+        }
+        Some(outcome)
+    }
+
+    /// Returns the health of a peer, if linked.
+    pub(super) fn health(&self, name: &str) -> Option<PeerHealth> {
+        let peer = self
+            .peers()
+            .into_iter()
+            .find(|peer| peer.name == name)?;
+        let routes = peer.routes().len();
+        let health = peer.health();
+        Some(PeerHealth {
+            state: health.circuit.state(),
+            routes,
+            forwarded: health.forwarded,
+            failed: health.failed,
+            last_error: health.last_error.clone(),
+            advertised: health.advertised,
+        })
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            KeyMode,
+            testing::{
+                SRC,
+                message,
+            },
+        },
+        std::sync::atomic::{
+            AtomicBool,
+            Ordering,
+        },
+    };
+
+    /// Peer advertising fixed routes, which can be cut off.
+    struct Fake {
+        advertisement: Advertisement,
+        down:          AtomicBool,
+    }
+
+    impl PeerLink for Fake {
+        fn forward(&self, msg: Envelope) -> Result<Reply> {
+            match self
+                .down
+                .load(Ordering::SeqCst)
+            {
+                true => Err(Error::Unavailable("link down".to_string())),
+                false => Ok(Some(
+                    msg.into_reply(Payload::from(
+                        self.advertisement
+                            .origin
+                            .clone(),
+                    )),
+                )),
+            }
+        }
+
+        fn advertisement(&self) -> Result<Advertisement> {
+            match self
+                .down
+                .load(Ordering::SeqCst)
+            {
+                true => Err(Error::Unavailable("link down".to_string())),
+                false => Ok(self.advertisement.clone()),
+            }
+        }
+    }
+
+    fn fake(advertisement: Advertisement) -> Arc<Fake> {
+        Arc::new(Fake {
+            advertisement,
+            down: AtomicBool::new(false),
+        })
+    }
+
+    fn key(msg: &Envelope) -> ServiceKey { ServiceKey::new(msg.destination(), KeyMode::Full) }
+
+    fn reply(outcome: Option<Result<Reply>>) -> String {
+        let reply = outcome
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        String::from_utf8_lossy(reply.payload()).into_owned()
+    }
+
+    #[test]
+    fn advertisement_payload() -> Result<()> {
+        let advertisement = Advertisement::new("a")
+            .route("http://orders", &[])
+            .route("http://users", &["b", "c"]);
+        assert_eq!(
+            Advertisement::parse(&advertisement.to_payload())?,
+            advertisement
+        );
+        assert!(matches!(
+            Advertisement::parse(&Payload::from("route\thttp://orders")),
+            Err(Error::InvalidAdvertisement(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn learn_routes() {
+        let federation = Federation::new("a".to_string(), 2, BreakerPolicy::default());
+        federation.link(
+            "b",
+            fake(
+                Advertisement::new("b")
+                    .route("http://orders", &[])
+                    .route("http://users", &["c"])
+                    .route("http://stock", &["c", "d"])
+                    .route("http://loop", &["a"]),
+            ),
+        );
+        federation.refresh();
+
+        let health = federation
+            .health("b")
+            .unwrap();
+        assert_eq!(health.routes(), 2);
+        assert!(health.advertised().is_some());
+        assert_eq!(
+            federation.learned(),
+            [AdvertisedRoute {
+                key:  "http://orders".to_string(),
+                path: vec!["b".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn forward_to_closest_healthy_peer() -> Result<()> {
+        let policy = BreakerPolicy::default().consecutive_failures(1);
+        let federation = Federation::new("a".to_string(), 4, policy);
+        let far = fake(Advertisement::new("far").route("http://orders", &["x"]));
+        let near = fake(Advertisement::new("near").route("http://orders", &[]));
+        federation.link("far", far.clone());
+        federation.link("near", near.clone());
+        federation.refresh();
+
+        let msg = message(SRC, "http://orders", "")?;
+        assert_eq!(
+            reply(federation.forward(&key(&msg), &msg)),
+            "near"
+        );
+
+        near.down
+            .store(true, Ordering::SeqCst);
+        assert_eq!(
+            reply(federation.forward(&key(&msg), &msg)),
+            "far"
+        );
+        let health = federation
+            .health("near")
+            .unwrap();
+        assert_eq!(health.state(), CircuitState::Open);
+        assert_eq!(health.failed(), 1);
+        assert_eq!(
+            health.last_error(),
+            Some("Service unavailable: link down")
+        );
+
+        // The open circuit skips the peer.
+        assert_eq!(
+            reply(federation.forward(&key(&msg), &msg)),
+            "far"
+        );
+        assert_eq!(
+            federation
+                .health("near")
+                .unwrap()
+                .forwarded(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn forward_limits() -> Result<()> {
+        let federation = Federation::new("a".to_string(), 2, BreakerPolicy::default());
+        federation.link(
+            "b",
+            fake(Advertisement::new("b").route("http://orders", &[])),
+        );
+        federation.refresh();
+
+        let msg = message(SRC, "http://orders", "")?;
+        let other = msg
+            .clone()
+            .redirect(Address::parse("http://users")?);
+        assert!(
+            federation
+                .forward(&key(&other), &other)
+                .is_none()
+        );
+
+        let visited = msg
+            .clone()
+            .header(FORWARDED_BY_HEADER, "c,b");
+        assert!(
+            federation
+                .forward(&key(&visited), &visited)
+                .is_none()
+        );
+
+        let far = msg.header(HOPS_HEADER, "2");
+        assert!(matches!(
+            federation.forward(&key(&far), &far),
+            Some(Err(Error::HopLimitExceeded(2)))
+        ));
+        assert!(federation.unlink("b"));
+        assert!(federation.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn linked_routers_are_released() -> Result<()> {
+        let a = Router::builder()
+            .name("a")
+            .build();
+        let b = Router::builder()
+            .name("b")
+            .build();
+        a.link_local("b", &b);
+        b.link_local("a", &a);
+        assert_eq!(a.peers(), vec!["b"]);
+
+        let released = Arc::downgrade(&b.federation);
+        drop(b);
+        assert!(released.upgrade().is_none());
+        a.refresh_peers();
+        let health = a.peer_health("b").unwrap();
+        assert_eq!(health.failed(), 1);
+        assert_eq!(
+            health.last_error(),
+            Some("Service unavailable: peer router dropped")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use {
+    super::{
+        Aggregator,
+        Gather,
+        first_reply,
+    },
+    crate::Address,
+    std::{
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+/// Builder for constructing a [`Gather`] operation.
+///
+/// Without a deadline, replies are waited for until every target answered.
+/// Without a quorum, every reply is waited for. Without an
+/// [`Aggregator`], the first successful reply in target order is kept.
+#[derive(Default)]
+pub struct GatherBuilder {
+    targets:    Vec<Address>,
+    deadline:   Option<Duration>,
+    quorum:     Option<usize>,
+    aggregator: Option<Arc<dyn Aggregator>>,
+}
+
+impl GatherBuilder {
+    /// Adds a target [`Address`].
+    pub fn target(mut self, address: Address) -> Self {
+        self.targets.push(address);
+        self
+    }
+
+    /// Adds several target [`Address`]es.
+    pub fn targets(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.targets.extend(addresses);
+        self
+    }
+
+    /// Sets how long replies are waited for.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stops gathering once `quorum` successful replies are received.
+    pub fn quorum(mut self, quorum: usize) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    /// Sets the [`Aggregator`] merging the gathered replies.
+    pub fn aggregator(mut self, aggregator: impl Aggregator + 'static) -> Self {
+        self.aggregator = Some(Arc::new(aggregator));
+        self
+    }
+
+    /// Finalizes the builder and returns a [`Gather`].
+    pub fn build(self) -> Gather {
+        Gather {
+            targets:    self.targets,
+            deadline:   self.deadline,
+            quorum:     self.quorum,
+            aggregator: self
+                .aggregator
+                .unwrap_or_else(|| Arc::new(first_reply)),
+        }
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Result,
+    };
+
+    #[test]
+    fn default_gather() {
+        let gather = Gather::builder().build();
+        assert!(gather.targets().is_empty());
+        assert!(gather.deadline().is_none());
+        assert!(gather.quorum().is_none());
+    }
+
+    #[test]
+    fn build_gather() -> Result<()> {
+        let eu = Address::parse("http://eu.prices.com")?;
+        let us = Address::parse("http://us.prices.com")?;
+        let gather = Gather::builder()
+            .target(eu.clone())
+            .targets([us.clone()])
+            .deadline(Duration::from_millis(50))
+            .quorum(1)
+            .build();
+
+        assert_eq!(gather.targets(), &[eu, us]);
+        assert_eq!(
+            gather.deadline(),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(gather.quorum(), Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+mod builder;
+mod report;
+
+use {
+    crate::{
+        Address,
+        Envelope,
+        Reply,
+        Result,
+        Router,
+        core::Recover,
+    },
+    std::{
+        sync::{
+            Arc,
+            Mutex,
+            mpsc,
+        },
+        thread,
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+};
+pub use {
+    builder::GatherBuilder,
+    report::GatherReport,
+};
+
+/// Merges the replies gathered by a [`Gather`] into a single [`Reply`].
+///
+/// Receives the original request and the [`GatherReport`] of every
+/// target. Any `Fn(Envelope, &GatherReport) -> Result<Reply>` closure is
+/// an `Aggregator`.
+pub trait Aggregator: Send + Sync {
+    fn aggregate(&self, request: Envelope, report: &GatherReport) -> Result<Reply>;
+}
+
+impl<F> Aggregator for F
+where
+    F: Fn(Envelope, &GatherReport) -> Result<Reply> + Send + Sync,
+{
+    fn aggregate(&self, request: Envelope, report: &GatherReport) -> Result<Reply> {
+        self(request, report)
+    }
+}
+
+/// Scatter-gather operation run by
+/// [`Router::scatter_gather()`](crate::Router::scatter_gather).
+///
+/// A `Gather` sends a copy of one [`Envelope`] to each of its targets in
+/// parallel, then waits for their replies until all of them answered, the
+/// quorum of successful replies is reached, or the deadline expires. The
+/// gathered replies are merged into a single [`Reply`] by the
+/// [`Aggregator`].
+///
+/// Each target runs on its own thread. The ones which had not answered
+/// when the deadline expired are reported as timed out, and the ones left
+/// once the quorum was reached as skipped. They are left to finish,
+/// counted by [`Router::overdue_workers()`]; their replies are dropped.
+/// Without a deadline, each target is only bounded by the timeout of its
+/// route.
+///
+/// # Examples
+///
+/// ```ignore
+/// let gather = Gather::builder()
+///     .target(prices_eu)
+///     .target(prices_us)
+///     .deadline(Duration::from_millis(200))
+///     .quorum(1)
+///     .aggregator(|request: Envelope, report: &GatherReport| {
+///         let best = report.replies().first().cloned();
+///         Ok(best.and_then(|(_, reply)| reply))
+///     })
+///     .build();
+///
+/// let (reply, report) = router.scatter_gather(request, &gather)?;
+/// ```
+pub struct Gather {
+    targets:    Vec<Address>,
+    deadline:   Option<Duration>,
+    quorum:     Option<usize>,
+    aggregator: Arc<dyn Aggregator>,
+}
+
+impl Gather {
+    /// Returns a new [`GatherBuilder`].
+    pub fn builder() -> GatherBuilder { GatherBuilder::default() }
+
+    /// Returns the target [`Address`]es.
+    pub fn targets(&self) -> &[Address] { &self.targets }
+
+    /// Returns how long replies are waited for, if bounded.
+    pub fn deadline(&self) -> Option<Duration> { self.deadline }
+
+    /// Returns the number of successful replies after which gathering
+    /// stops, if any.
+    pub fn quorum(&self) -> Option<usize> { self.quorum }
+
+    /// Scatters `msg` to every target through `router`, gathers the
+    /// replies and merges them with the [`Aggregator`].
+    pub(super) fn run(&self, router: &Router, msg: Envelope) -> Result<(Reply, GatherReport)> {
+        let started = Instant::now();
+        let (tx, rx) = mpsc::channel();
+        let progress = Arc::new(Mutex::new(Progress::default()));
+
+        for (index, target) in self
+            .targets
+            .iter()
+            .enumerate()
+        {
+            let worker = (router.clone(), tx.clone(), progress.clone());
+            let copy = msg
+                .clone()
+                .redirect(target.clone());
+            progress
+                .lock()
+                .recover()
+                .running += 1;
+
+            thread::spawn(move || {
+                let (router, tx, progress) = worker;
+                // The receiver is gone if gathering already stopped.
+                let _ = tx.send((index, router.route(copy)));
+                progress
+                    .lock()
+                    .recover()
+                    .finish(&router);
+            });
+        }
+        drop(tx);
+
+        let mut outcomes: Vec<Option<Result<Reply>>> = self
+            .targets
+            .iter()
+            .map(|_| None)
+            .collect();
+        let mut received = 0;
+        let mut succeeded = 0;
+
+        while received < self.targets.len()
+            && self
+                .quorum
+                .is_none_or(|q| succeeded < q)
+        {
+            let next = match self.deadline {
+                Some(deadline) => deadline
+                    .checked_sub(started.elapsed())
+                    .and_then(|left| rx.recv_timeout(left).ok()),
+                None => rx.recv().ok(),
+            };
+            let Some((index, outcome)) = next
+            else {
+                break;
+            };
+
+            received += 1;
+            if outcome.is_ok() {
+                succeeded += 1;
+            }
+            outcomes[index] = Some(outcome);
+        }
+
+        progress
+            .lock()
+            .recover()
+            .stop(router);
+
+        let quorum_reached = self
+            .quorum
+            .is_some_and(|q| succeeded >= q);
+        let report = GatherReport::new(
+            self.targets
+                .iter()
+                .cloned()
+                .zip(outcomes)
+                .collect(),
+            quorum_reached,
+        );
+        let reply = self
+            .aggregator
+            .aggregate(msg, &report)?;
+        Ok((reply, report))
+    }
+}
+
+/// Targets of a running [`Gather`] not done yet, and whether gathering
+/// stopped.
+#[derive(Default)]
+struct Progress {
+    running: usize,
+    stopped: bool,
+}
+
+impl Progress {
+    /// Stops gathering, counting the targets still running as overdue.
+    fn stop(&mut self, router: &Router) {
+        self.stopped = true;
+        router
+            .workers
+            .abandon(self.running);
+    }
+
+    /// Marks a target done, releasing it from the overdue count if
+    /// gathering already stopped.
+    fn finish(&mut self, router: &Router) {
+        self.running -= 1;
+        if self.stopped {
+            router.workers.release();
+        }
+    }
+}
+
+/// Default [`Aggregator`]: the first successful reply, in target order.
+fn first_reply(_request: Envelope, report: &GatherReport) -> Result<Reply> {
+    Ok(report
+        .replies()
+        .iter()
+        .find_map(|(_, reply)| reply.clone()))
+}
@@ -0,0 +1,112 @@
+use crate::{
+    Address,
+    Error,
+    Reply,
+};
+
+/// Outcome of each target of a [`Gather`](super::Gather).
+///
+/// Every target is listed exactly once, in target order within each
+/// list: among the [`replies()`](GatherReport::replies) if it answered
+/// successfully, the [`failed()`](GatherReport::failed) targets if it
+/// returned an [`Error`], the [`timed_out()`](GatherReport::timed_out)
+/// targets if it had not answered by the deadline, or the
+/// [`skipped()`](GatherReport::skipped) ones if it had not answered when
+/// the quorum was reached.
+#[derive(Debug, Default)]
+pub struct GatherReport {
+    replies:   Vec<(Address, Reply)>,
+    failed:    Vec<(Address, Error)>,
+    timed_out: Vec<Address>,
+    skipped:   Vec<Address>,
+}
+
+impl GatherReport {
+    /// Builds a report from the outcome of each target, in target order.
+    ///
+    /// `None` outcomes are targets which did not answer in time, skipped
+    /// if gathering stopped because the quorum was reached.
+    pub(crate) fn new(
+        outcomes: Vec<(Address, Option<crate::Result<Reply>>)>,
+        quorum_reached: bool,
+    ) -> Self {
+        let mut report = Self::default();
+        for (target, outcome) in outcomes {
+            match outcome {
+                Some(Ok(reply)) => report
+                    .replies
+                    .push((target, reply)),
+                Some(Err(e)) => report
+                    .failed
+                    .push((target, e)),
+                None if quorum_reached => report.skipped.push(target),
+                None => report.timed_out.push(target),
+            }
+        }
+        report
+    }
+
+    /// Returns the targets which replied successfully with their
+    /// [`Reply`].
+    pub fn replies(&self) -> &[(Address, Reply)] { &self.replies }
+
+    /// Returns the targets which failed with their [`Error`].
+    pub fn failed(&self) -> &[(Address, Error)] { &self.failed }
+
+    /// Returns the targets which had not answered by the deadline.
+    pub fn timed_out(&self) -> &[Address] { &self.timed_out }
+
+    /// Returns the targets which had not answered when the quorum was
+    /// reached.
+    pub fn skipped(&self) -> &[Address] { &self.skipped }
+
+    /// Returns whether every target replied successfully.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.timed_out.is_empty() && self.skipped.is_empty()
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Result,
+    };
+
+    #[test]
+    fn report_from_outcomes() -> Result<()> {
+        let a = Address::parse("http://a.com")?;
+        let b = Address::parse("http://b.com")?;
+        let c = Address::parse("http://c.com")?;
+
+        let report = GatherReport::new(
+            vec![
+                (a.clone(), Some(Ok(None))),
+                (b.clone(), Some(Err(Error::ServiceNotFound))),
+                (c.clone(), None),
+            ],
+            false,
+        );
+
+        assert_eq!(report.replies().len(), 1);
+        assert_eq!(report.replies()[0].0, a);
+        assert_eq!(report.failed()[0].0, b);
+        assert_eq!(report.timed_out(), std::slice::from_ref(&c));
+        assert!(report.skipped().is_empty());
+        assert!(!report.is_complete());
+        assert!(GatherReport::default().is_complete());
+
+        let report = GatherReport::new(
+            vec![(a.clone(), Some(Ok(None))), (c.clone(), None)],
+            true,
+        );
+        assert!(report.timed_out().is_empty());
+        assert_eq!(report.skipped(), &[c]);
+        assert!(!report.is_complete());
+        Ok(())
+    }
+}
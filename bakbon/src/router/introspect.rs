@@ -0,0 +1,584 @@
+use {
+    super::{
+        RetryPolicy,
+        Router,
+        RuleCheck,
+    },
+    crate::{
+        Address,
+        Balancer,
+        CircuitState,
+        Envelope,
+        SemVer,
+        ServiceArc,
+        VersionRange,
+    },
+    std::{
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+/// Instance of a [`Service`](crate::Service) as seen by a [`Router`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceInfo {
+    address: Address,
+    version: Option<SemVer>,
+    circuit: CircuitState,
+}
+
+impl InstanceInfo {
+    fn new(balancer: &Balancer, instance: &ServiceArc) -> Self {
+        Self {
+            address: instance.address().clone(),
+            version: instance.version().cloned(),
+            circuit: balancer.circuit_state(instance.as_ref()),
+        }
+    }
+
+    /// Returns the [`Address`] of the instance.
+    pub fn address(&self) -> &Address { &self.address }
+
+    /// Returns the version of the instance, if it is
+    /// [`Versioned`](crate::Versioned).
+    pub fn version(&self) -> Option<&SemVer> { self.version.as_ref() }
+
+    /// Returns the state of the circuit breaker of the instance.
+    pub fn circuit(&self) -> CircuitState { self.circuit }
+
+    #[cfg(feature = "json")]
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "address": self.address.to_string(),
+            "version": self.version.as_ref().map(ToString::to_string),
+            "circuit": self.circuit.as_ref(),
+        })
+    }
+}
+
+/// Entry of the routing table of a [`Router`]: a
+/// [`ServiceKey`](crate::ServiceKey), its instances, and the settings of
+/// its route, returned by [`Router::routes()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    key:          String,
+    strategy:     &'static str,
+    turn:         Option<usize>,
+    hash_header:  Option<String>,
+    instances:    Vec<InstanceInfo>,
+    max_attempts: Option<u32>,
+    timeout:      Option<Duration>,
+}
+
+impl RouteInfo {
+    /// Returns the routing table of a [`Router`], ordered by key.
+    pub(super) fn list(router: &Router) -> Vec<Self> {
+        let registry = router.registry.read();
+        registry
+            .matching("*")
+            .into_iter()
+            .map(|(key, instances)| {
+                let balancer = router.balancer_of_key(key);
+                Self {
+                    key:          key.to_string(),
+                    strategy:     balancer.strategy(),
+                    turn:         balancer.turn(),
+                    hash_header:  balancer
+                        .hash_header()
+                        .map(str::to_string),
+                    instances:    instances
+                        .iter()
+                        .map(|instance| InstanceInfo::new(balancer, instance))
+                        .collect(),
+                    max_attempts: Router::route_setting_of_key(&router.retries, key)
+                        .map(RetryPolicy::max_attempts),
+                    timeout:      Router::route_setting_of_key(&router.timeouts, key).copied(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the [`ServiceKey`](crate::ServiceKey) of the route.
+    pub fn key(&self) -> &str { &self.key }
+
+    /// Returns the balancing strategy of the route as a string.
+    pub fn strategy(&self) -> &str { self.strategy }
+
+    /// Returns the next turn of the balancing strategy, for the
+    /// strategies taking turns, such as `round_robin`.
+    pub fn turn(&self) -> Option<usize> { self.turn }
+
+    /// Returns the header hashed by the `consistent_hash` strategy, if
+    /// the route uses it.
+    pub fn hash_header(&self) -> Option<&str> { self.hash_header.as_deref() }
+
+    /// Returns the registered instances, in registration order.
+    pub fn instances(&self) -> &[InstanceInfo] { &self.instances }
+
+    /// Returns the number of attempts of the [`RetryPolicy`] of the
+    /// route, if any.
+    pub fn max_attempts(&self) -> Option<u32> { self.max_attempts }
+
+    /// Returns the timeout of the route, if any.
+    pub fn timeout(&self) -> Option<Duration> { self.timeout }
+
+    /// Exports the route as JSON.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "key": self.key,
+            "strategy": self.strategy,
+            "turn": self.turn,
+            "hash_header": self.hash_header,
+            "instances": self
+                .instances
+                .iter()
+                .map(InstanceInfo::to_json)
+                .collect::<Vec<_>>(),
+            "max_attempts": self.max_attempts,
+            "timeout_ms": self.timeout.map(|timeout| timeout.as_millis() as u64),
+        })
+    }
+}
+
+/// Instance considered for a message by [`Router::explain()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    instance: InstanceInfo,
+    selected: bool,
+    skipped:  Option<String>,
+}
+
+impl Candidate {
+    /// Returns the considered instance.
+    pub fn instance(&self) -> &InstanceInfo { &self.instance }
+
+    /// Returns whether the instance would receive the message.
+    pub fn selected(&self) -> bool { self.selected }
+
+    /// Returns why the instance could not receive the message, if it was
+    /// ruled out.
+    pub fn skipped(&self) -> Option<&str> { self.skipped.as_deref() }
+}
+
+/// What a [`Router`] would do with a message, told by
+/// [`Router::explain()`].
+///
+/// - [`Deliver`](Decision::Deliver): deliver it to the selected
+///   [`Candidate`].
+/// - [`Forward`](Decision::Forward): forward it to the named peer
+///   [`Router`].
+/// - [`Fallback`](Decision::Fallback): hand it to the fallback
+///   [`Service`](crate::Service), since it is unroutable.
+/// - [`DeadLetter`](Decision::DeadLetter): dead-letter it, since it is
+///   unroutable.
+/// - [`Throttle`](Decision::Throttle): fail with
+///   [`Error::Throttled`](crate::Error::Throttled), since a
+///   [`RateLimit`](crate::RateLimit) or
+///   [`ConcurrencyLimit`](crate::ConcurrencyLimit) of its route is
+///   reached.
+/// - [`Fail`](Decision::Fail): fail without delivering it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Deliver,
+    Forward(String),
+    Fallback,
+    DeadLetter,
+    Throttle,
+    Fail,
+}
+
+impl AsRef<str> for Decision {
+    /// Returns the string representation of the decision.
+    ///
+    /// Values are "deliver", "forward", "fallback", "dead_letter",
+    /// "throttle" and "fail".
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Deliver => "deliver",
+            Self::Forward(_) => "forward",
+            Self::Fallback => "fallback",
+            Self::DeadLetter => "dead_letter",
+            Self::Throttle => "throttle",
+            Self::Fail => "fail",
+        }
+    }
+}
+
+/// Dry run of the routing of a message, returned by
+/// [`Router::explain()`]: the trail of decisions from the routing
+/// [`Rule`](crate::Rule)s to the instance it would be delivered to.
+///
+/// The [`Middleware`](crate::Middleware)s are not run, only listed. A
+/// [`Split`](crate::Split) the message asks for with its header replaces
+/// the destination, while the ones rolled per message are listed with
+/// their weight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    destination:      Address,
+    rule:             Option<String>,
+    checks:           Vec<RuleCheck>,
+    middleware:       Vec<String>,
+    split:            bool,
+    weighted_splits:  Vec<String>,
+    key:              String,
+    strategy:         &'static str,
+    accepted_version: Option<String>,
+    candidates:       Vec<Candidate>,
+    decision:         Decision,
+    reason:           String,
+}
+
+impl Explanation {
+    /// Creates the explanation of the routing rules, before the
+    /// [`Registry`](crate::Registry) lookup is traced.
+    pub(super) fn new(destination: Address, rule: Option<String>, checks: Vec<RuleCheck>) -> Self {
+        Self {
+            destination,
+            rule,
+            checks,
+            middleware: Vec::new(),
+            split: false,
+            weighted_splits: Vec::new(),
+            key: String::new(),
+            strategy: "",
+            accepted_version: None,
+            candidates: Vec::new(),
+            decision: Decision::Fail,
+            reason: String::new(),
+        }
+    }
+
+    /// Traces the routing of a message through a [`Router`], without
+    /// routing it.
+    pub(super) fn trace(router: &Router, msg: &Envelope) -> Self {
+        let mut explanation = router.rules.explain(msg);
+        explanation.middleware = router.middleware.names();
+        let registry = router.registry.read();
+
+        let routed = msg.clone().redirect(
+            explanation
+                .destination
+                .clone(),
+        );
+        let routed_key = registry.key(routed.destination());
+        match router
+            .traffic
+            .requested(&routed_key, &routed)
+        {
+            Some(destination) => {
+                explanation.split = true;
+                explanation.destination = destination;
+            }
+            None => {
+                explanation.weighted_splits = router
+                    .traffic
+                    .weighted(&routed_key, &routed)
+                    .into_iter()
+                    .map(|(target, weight)| {
+                        format!("may be split to {target} with weight {weight}")
+                    })
+                    .collect();
+            }
+        }
+        let routed = routed.redirect(
+            explanation
+                .destination
+                .clone(),
+        );
+
+        let requested = registry.key(&explanation.destination);
+        let lookup = registry.lookup(&explanation.destination);
+        let key = lookup.map_or_else(|| requested.clone(), |(key, _)| key.clone());
+        let balancer = router.balancer_of_key(&key);
+        explanation.key = key.to_string();
+        explanation.strategy = balancer.strategy();
+        explanation.accepted_version = msg
+            .accepted_version()
+            .map(str::to_string);
+
+        // Limits apply to the route before any lookup.
+        if let Err(error) = router
+            .limits
+            .check(&requested, &registry.key(routed.source()))
+        {
+            explanation.decision = Decision::Throttle;
+            explanation.reason = error.to_string();
+            return explanation;
+        }
+
+        let (decision, reason) = match lookup {
+            Some((_, instances)) => explanation.select(balancer, instances, &routed),
+            None => match router
+                .federation
+                .preview(&key, &routed)
+            {
+                Some(Ok(peer)) => (
+                    Decision::Forward(peer.clone()),
+                    format!("no local service, {key} is advertised by peer {peer}"),
+                ),
+                Some(Err(error)) => (Decision::Fail, error.to_string()),
+                None => {
+                    let reason = format!("no service registered for {key}");
+                    match (&router.fallback, &router.dead_letter) {
+                        (Some(_), _) => (Decision::Fallback, reason),
+                        (None, Some(_)) => (Decision::DeadLetter, reason),
+                        (None, None) => (Decision::Fail, reason),
+                    }
+                }
+            },
+        };
+        explanation.decision = decision;
+        explanation.reason = reason;
+        explanation
+    }
+
+    /// Rules out the instances of another version or with an open
+    /// circuit, then tells which of the others the [`Balancer`] would
+    /// select.
+    fn select(
+        &mut self,
+        balancer: &Balancer,
+        instances: &[ServiceArc],
+        msg: &Envelope,
+    ) -> (Decision, String) {
+        self.candidates = instances
+            .iter()
+            .map(|instance| Candidate {
+                instance: InstanceInfo::new(balancer, instance),
+                selected: false,
+                skipped:  None,
+            })
+            .collect();
+
+        let eligible = match msg.accepted_version() {
+            Some(range) => match Router::highest_compatible(range, instances) {
+                Ok(compatible) => {
+                    self.skip_versions(range, instances, &compatible);
+                    compatible
+                }
+                Err(error) => return (Decision::Fail, error.to_string()),
+            },
+            None => instances.to_vec(),
+        };
+        for (instance, candidate) in instances
+            .iter()
+            .zip(&mut self.candidates)
+        {
+            if candidate.skipped.is_none() && !balancer.is_available(instance.as_ref()) {
+                candidate.skipped = Some("circuit open".to_string());
+            }
+        }
+
+        match balancer.preview(&eligible, msg) {
+            Ok((index, reason)) => {
+                let selected = &eligible[index];
+                if let Some(position) = instances
+                    .iter()
+                    .position(|instance| Arc::ptr_eq(instance, selected))
+                {
+                    self.candidates[position].selected = true;
+                }
+                (Decision::Deliver, reason)
+            }
+            Err(error) => (Decision::Fail, error.to_string()),
+        }
+    }
+
+    /// Tells why the instances out of the highest compatible version are
+    /// ruled out.
+    fn skip_versions(&mut self, range: &str, instances: &[ServiceArc], compatible: &[ServiceArc]) {
+        let Ok(range) = VersionRange::parse(range)
+        else {
+            return;
+        };
+        let highest = compatible
+            .first()
+            .and_then(|instance| instance.version());
+        for (instance, candidate) in instances
+            .iter()
+            .zip(&mut self.candidates)
+        {
+            if compatible
+                .iter()
+                .any(|other| Arc::ptr_eq(other, instance))
+            {
+                continue;
+            }
+            candidate.skipped = Some(match (instance.version(), highest) {
+                (None, _) => "no version".to_string(),
+                (Some(version), Some(highest)) if range.matches(version) => {
+                    format!("version {version} is lower than {highest}")
+                }
+                (Some(version), _) => format!("version {version} is not in {range}"),
+            });
+        }
+    }
+
+    /// Returns the destination the message would be looked up with in the
+    /// [`Registry`](crate::Registry).
+    pub fn destination(&self) -> &Address { &self.destination }
+
+    /// Returns the name of the [`Rule`](crate::Rule) which matched, if
+    /// any.
+    pub fn rule(&self) -> Option<&str> { self.rule.as_deref() }
+
+    /// Returns the rules evaluated, in evaluation order, up to the one
+    /// which matched.
+    pub fn checks(&self) -> &[RuleCheck] { &self.checks }
+
+    /// Returns the names of the [`Middleware`](crate::Middleware)s which
+    /// would run around the delivery, in the order they run.
+    pub fn middleware(&self) -> &[String] { &self.middleware }
+
+    /// Returns whether the message asks to be [`Split`](crate::Split) to
+    /// the destination with the header of the split.
+    pub fn split(&self) -> bool { self.split }
+
+    /// Returns the [`Split`](crate::Split)s the message may be rolled to
+    /// instead of the destination, as "may be split to X with weight W".
+    pub fn weighted_splits(&self) -> &[String] { &self.weighted_splits }
+
+    /// Returns the [`ServiceKey`](crate::ServiceKey) the destination is
+    /// looked up with.
+    pub fn key(&self) -> &str { &self.key }
+
+    /// Returns the balancing strategy of the route as a string.
+    pub fn strategy(&self) -> &str { self.strategy }
+
+    /// Returns the [`VersionRange`] the message accepts, if any.
+    pub fn accepted_version(&self) -> Option<&str> {
+        self.accepted_version
+            .as_deref()
+    }
+
+    /// Returns the registered instances, in registration order, and
+    /// whether each of them would be selected or why it was ruled out.
+    pub fn candidates(&self) -> &[Candidate] { &self.candidates }
+
+    /// Returns the instance which would receive the message, if any.
+    pub fn selected(&self) -> Option<&InstanceInfo> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.selected)
+            .map(Candidate::instance)
+    }
+
+    /// Returns what the [`Router`] would do with the message.
+    pub fn decision(&self) -> &Decision { &self.decision }
+
+    /// Returns why the [`Router`] would do so: how the instance was
+    /// selected, or the error the message would fail with.
+    pub fn reason(&self) -> &str { &self.reason }
+
+    /// Exports the decision trail as JSON.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let checks: Vec<serde_json::Value> = self
+            .checks
+            .iter()
+            .map(|check| {
+                serde_json::json!({
+                    "rule": check.rule(),
+                    "priority": check.priority(),
+                    "matched": check.matched(),
+                    "unmet": check.unmet(),
+                })
+            })
+            .collect();
+        let candidates: Vec<serde_json::Value> = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let mut json = candidate.instance.to_json();
+                json["selected"] = candidate.selected.into();
+                json["skipped"] = candidate
+                    .skipped
+                    .clone()
+                    .into();
+                json
+            })
+            .collect();
+        let peer = match &self.decision {
+            Decision::Forward(peer) => Some(peer.as_str()),
+            _ => None,
+        };
+
+        serde_json::json!({
+            "destination": self.destination.to_string(),
+            "rule": self.rule,
+            "checks": checks,
+            "middleware": self.middleware,
+            "split": self.split,
+            "weighted_splits": self.weighted_splits,
+            "key": self.key,
+            "strategy": self.strategy,
+            "accepted_version": self.accepted_version,
+            "candidates": candidates,
+            "decision": self.decision.as_ref(),
+            "peer": peer,
+            "reason": self.reason,
+        })
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decision_names() {
+        let decisions = [
+            Decision::Deliver,
+            Decision::Forward("eu-1".to_string()),
+            Decision::Fallback,
+            Decision::DeadLetter,
+            Decision::Throttle,
+            Decision::Fail,
+        ];
+        let names: Vec<&str> = decisions
+            .iter()
+            .map(AsRef::as_ref)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "deliver",
+                "forward",
+                "fallback",
+                "dead_letter",
+                "throttle",
+                "fail"
+            ]
+        );
+    }
+
+    #[test]
+    fn explanation_of_rules() -> crate::Result<()> {
+        let destination = Address::parse("http://orders")?;
+        let explanation = Explanation::new(destination.clone(), None, Vec::new());
+        assert_eq!(explanation.destination(), &destination);
+        assert!(
+            explanation
+                .selected()
+                .is_none()
+        );
+        assert!(!explanation.split());
+        assert!(
+            explanation
+                .middleware()
+                .is_empty()
+        );
+        assert!(
+            explanation
+                .weighted_splits()
+                .is_empty()
+        );
+        assert_eq!(explanation.decision(), &Decision::Fail);
+        Ok(())
+    }
+}
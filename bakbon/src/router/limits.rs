@@ -0,0 +1,717 @@
+use {
+    crate::{
+        Error,
+        Result,
+        ServiceKey,
+        core::Recover,
+    },
+    std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            Condvar,
+            Mutex,
+            MutexGuard,
+        },
+        thread,
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+};
+
+/// Number of per-source buckets of a route past which the full ones are
+/// forgotten.
+const MAX_IDLE_BUCKETS: usize = 1024;
+
+/// Token bucket limiting the rate of the messages routed to a
+/// [`Service`](crate::Service).
+///
+/// The bucket holds up to `burst` tokens (the rate by default), and is
+/// refilled continuously at the rate. Each message takes a token; a
+/// message finding the bucket empty fails with
+/// [`Error::Throttled`], telling when a token will be available, unless
+/// the limit lets it [`wait()`](RateLimit::wait) for its turn. A message
+/// does not wait past its deadline.
+///
+/// Limits are set per route when building the
+/// [`Router`](crate::Router), with `RouterBuilder::rate_limit()` for a
+/// bucket per [`ServiceKey`], or `RouterBuilder::source_rate_limit()` for
+/// a bucket per [`ServiceKey`] and source of the messages.
+///
+/// # Examples
+///
+/// ```rust
+/// use {
+///     bakbon::*,
+///     std::time::Duration,
+/// };
+///
+/// let limit = RateLimit::new(100, Duration::from_secs(1))
+///     .burst(20)
+///     .wait(Duration::from_millis(50));
+///
+/// assert_eq!(limit.requests(), 100);
+/// assert_eq!(limit.burst_size(), 20);
+/// assert_eq!(limit.max_wait(), Duration::from_millis(50));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    requests: u32,
+    per:      Duration,
+    burst:    u32,
+    wait:     Duration,
+}
+
+impl RateLimit {
+    /// Creates a limit of `requests` messages per period, at least one,
+    /// with bursts of as many messages.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            requests,
+            per: per.max(Duration::from_nanos(1)),
+            burst: requests,
+            wait: Duration::ZERO,
+        }
+    }
+
+    /// Sets how many messages can go through at once after an idle
+    /// period, at least one.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Sets how long a message may wait for a token before it is
+    /// throttled. Messages are throttled right away by default.
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Returns the number of messages allowed per period.
+    pub fn requests(&self) -> u32 { self.requests }
+
+    /// Returns the period the messages are counted over.
+    pub fn period(&self) -> Duration { self.per }
+
+    /// Returns how many messages can go through at once.
+    pub fn burst_size(&self) -> u32 { self.burst }
+
+    /// Returns how long a message may wait for a token.
+    pub fn max_wait(&self) -> Duration { self.wait }
+
+    /// Returns the number of tokens added per second.
+    fn refill_rate(&self) -> f64 { self.requests as f64 / self.per.as_secs_f64() }
+}
+
+/// Limit on the number of messages a [`Service`](crate::Service) processes
+/// at once.
+///
+/// A message coming in while the limit is reached fails with
+/// [`Error::Throttled`], carrying the [retry-after
+/// hint](ConcurrencyLimit::retry_after), unless the limit lets it
+/// [`wait()`](ConcurrencyLimit::wait) for a message to complete.
+///
+/// Limits are set per [`ServiceKey`] when building the
+/// [`Router`](crate::Router), with `RouterBuilder::concurrency_limit()`.
+/// A message counts until its [`Service`](crate::Service) returns, even
+/// when the [`Router`](crate::Router) stopped waiting for it at its
+/// timeout.
+///
+/// # Examples
+///
+/// ```rust
+/// use {
+///     bakbon::*,
+///     std::time::Duration,
+/// };
+///
+/// let limit = ConcurrencyLimit::new(8)
+///     .wait(Duration::from_millis(100))
+///     .retry_after(Duration::from_millis(250));
+///
+/// assert_eq!(limit.max_in_flight(), 8);
+/// assert_eq!(limit.max_wait(), Duration::from_millis(100));
+/// assert_eq!(limit.retry_after_hint(), Duration::from_millis(250));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    max:         usize,
+    wait:        Duration,
+    retry_after: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// Creates a limit of `max` messages in flight, at least one.
+    ///
+    /// Messages are throttled right away, with a retry-after hint of one
+    /// second.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max:         max.max(1),
+            wait:        Duration::ZERO,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets how long a message may wait for a slot before it is
+    /// throttled.
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Sets the retry-after hint of the throttled messages.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Returns the number of messages allowed in flight.
+    pub fn max_in_flight(&self) -> usize { self.max }
+
+    /// Returns how long a message may wait for a slot.
+    pub fn max_wait(&self) -> Duration { self.wait }
+
+    /// Returns the retry-after hint of the throttled messages.
+    pub fn retry_after_hint(&self) -> Duration { self.retry_after }
+}
+
+/// Tokens left in a bucket, as of its last update.
+#[derive(Debug)]
+struct Bucket {
+    tokens:  f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Returns the tokens the bucket would hold by now, once refilled.
+    fn tokens(&self, limit: &RateLimit, now: Instant) -> f64 {
+        let elapsed = now
+            .saturating_duration_since(self.updated)
+            .as_secs_f64();
+        (self.tokens + elapsed * limit.refill_rate()).min(limit.burst as f64)
+    }
+
+    /// Returns how long to wait for a token out of `tokens`, or, if it is
+    /// too long, when to retry.
+    fn delay(limit: &RateLimit, tokens: f64) -> std::result::Result<Duration, Duration> {
+        let delay = match tokens >= 1.0 {
+            true => Duration::ZERO,
+            false => Duration::from_secs_f64((1.0 - tokens) / limit.refill_rate()),
+        };
+        match delay > limit.wait {
+            true => Err(delay),
+            false => Ok(delay),
+        }
+    }
+
+    /// Refills the bucket, then takes a token, borrowing it ahead of time
+    /// if the message may wait for it.
+    ///
+    /// Returns how long to wait for the token, or, if it is too long, when
+    /// to retry.
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> std::result::Result<Duration, Duration> {
+        self.tokens = self.tokens(limit, now);
+        self.updated = now;
+        let delay = Self::delay(limit, self.tokens)?;
+        self.tokens -= 1.0;
+        Ok(delay)
+    }
+
+    /// Returns whether the bucket would be full by now.
+    fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+        self.tokens(limit, now) >= limit.burst as f64
+    }
+}
+
+/// Token buckets of the routes matching a pattern, by [`ServiceKey`], or
+/// by [`ServiceKey`] and source.
+#[derive(Debug)]
+struct Buckets {
+    limit:   RateLimit,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Buckets {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::default(),
+        }
+    }
+
+    fn buckets(&self) -> MutexGuard<'_, HashMap<String, Bucket>> { self.buckets.lock().recover() }
+
+    /// Takes a token from a bucket, and returns how long to wait for it.
+    fn take(&self, name: &str) -> Result<Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets();
+        if !buckets.contains_key(name) && buckets.len() >= MAX_IDLE_BUCKETS {
+            buckets.retain(|_, bucket| !bucket.is_full(&self.limit, now));
+        }
+        buckets
+            .entry(name.to_string())
+            .or_insert_with(|| Bucket {
+                tokens:  self.limit.burst as f64,
+                updated: now,
+            })
+            .take(&self.limit, now)
+            .map_err(Error::Throttled)
+    }
+
+    /// Gives back a token taken from a bucket by a message another limit
+    /// throttled.
+    fn refund(&self, name: &str) {
+        if let Some(bucket) = self.buckets().get_mut(name) {
+            bucket.tokens = (bucket.tokens + 1.0).min(self.limit.burst as f64);
+        }
+    }
+
+    /// Tells whether a token could be taken from a bucket, without taking
+    /// it.
+    fn check(&self, name: &str) -> Result<()> {
+        match self.buckets().get(name) {
+            Some(bucket) => {
+                let tokens = bucket.tokens(&self.limit, Instant::now());
+                Bucket::delay(&self.limit, tokens)
+                    .map(|_| ())
+                    .map_err(Error::Throttled)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Messages in flight to the routes matching a pattern, by
+/// [`ServiceKey`].
+#[derive(Debug)]
+struct Slots {
+    limit:     ConcurrencyLimit,
+    in_flight: Mutex<HashMap<String, usize>>,
+    freed:     Condvar,
+}
+
+impl Slots {
+    fn in_flight(&self) -> MutexGuard<'_, HashMap<String, usize>> {
+        self.in_flight
+            .lock()
+            .recover()
+    }
+
+    /// Takes a slot, waiting for one as long as the limit allows.
+    fn acquire(&self, key: &str) -> Result<()> {
+        let is_full = |in_flight: &mut HashMap<String, usize>| {
+            in_flight
+                .get(key)
+                .is_some_and(|count| *count >= self.limit.max)
+        };
+        let mut in_flight = self.in_flight();
+        if is_full(&mut in_flight) && !self.limit.wait.is_zero() {
+            in_flight = self
+                .freed
+                .wait_timeout_while(in_flight, self.limit.wait, is_full)
+                .recover()
+                .0;
+        }
+        if is_full(&mut in_flight) {
+            return Err(Error::Throttled(self.limit.retry_after));
+        }
+        *in_flight
+            .entry(key.to_string())
+            .or_default() += 1;
+        Ok(())
+    }
+
+    /// Tells whether a slot could be taken, without taking it.
+    ///
+    /// A message which may wait for a slot is let through.
+    fn check(&self, key: &str) -> Result<()> {
+        let is_full = self
+            .in_flight()
+            .get(key)
+            .is_some_and(|count| *count >= self.limit.max);
+        match is_full && self.limit.wait.is_zero() {
+            true => Err(Error::Throttled(self.limit.retry_after)),
+            false => Ok(()),
+        }
+    }
+
+    fn release(&self, key: &str) {
+        let mut in_flight = self.in_flight();
+        if let Some(count) = in_flight.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(key);
+            }
+        }
+        self.freed.notify_all();
+    }
+}
+
+/// Slot taken by a message under a [`ConcurrencyLimit`], given back once
+/// the permit and all its clones are dropped.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Permit(
+    // Only held to be dropped.
+    #[allow(unused)] Option<Arc<Taken>>,
+);
+
+/// Slot held by a [`Permit`], given back when dropped.
+#[derive(Debug)]
+struct Taken {
+    slots: Arc<Slots>,
+    key:   String,
+}
+
+impl Drop for Taken {
+    fn drop(&mut self) { self.slots.release(&self.key); }
+}
+
+/// Rate and concurrency limits of a [`Router`](crate::Router), by glob
+/// pattern.
+///
+/// For each kind of limit, the first pattern matching the
+/// [`ServiceKey`] of a message applies.
+#[derive(Debug, Default)]
+pub(super) struct Limits {
+    rates:   Vec<(String, Buckets)>,
+    sources: Vec<(String, Buckets)>,
+    slots:   Vec<(String, Arc<Slots>)>,
+}
+
+impl Limits {
+    pub(super) fn add_rate(&mut self, pattern: &str, limit: RateLimit) {
+        self.rates
+            .push((pattern.to_string(), Buckets::new(limit)));
+    }
+
+    pub(super) fn add_source_rate(&mut self, pattern: &str, limit: RateLimit) {
+        self.sources
+            .push((pattern.to_string(), Buckets::new(limit)));
+    }
+
+    pub(super) fn add_concurrency(&mut self, pattern: &str, limit: ConcurrencyLimit) {
+        let slots = Slots {
+            limit,
+            in_flight: Mutex::default(),
+            freed: Condvar::new(),
+        };
+        self.slots
+            .push((pattern.to_string(), Arc::new(slots)));
+    }
+
+    /// Returns whether no limit is set.
+    pub(super) fn is_empty(&self) -> bool {
+        self.rates.is_empty() && self.sources.is_empty() && self.slots.is_empty()
+    }
+
+    /// Returns the buckets of the first pattern matching a [`ServiceKey`],
+    /// if any.
+    fn matching<'a>(limits: &'a [(String, Buckets)], key: &ServiceKey) -> Option<&'a Buckets> {
+        limits
+            .iter()
+            .find(|(pattern, _)| key.matches(pattern))
+            .map(|(_, buckets)| buckets)
+    }
+
+    /// Admits a message to a [`ServiceKey`] from a source, waiting for
+    /// its turn as long as the limits allow, and no longer than the time
+    /// `left` before its deadline, if any.
+    ///
+    /// The tokens are waited for before a slot is taken, so that a message
+    /// waiting for its turn does not hold a slot. They are given back if
+    /// another limit or the deadline throttles the message. Returns
+    /// [`Error::Throttled`] with the retry-after hint of the first limit
+    /// reached.
+    pub(super) fn admit(
+        &self,
+        key: &ServiceKey,
+        source: &ServiceKey,
+        left: Option<Duration>,
+    ) -> Result<Permit> {
+        let route = Self::matching(&self.rates, key);
+        let mut delay = match route {
+            Some(buckets) => buckets.take(key.as_ref())?,
+            None => Duration::ZERO,
+        };
+        let sources = Self::matching(&self.sources, key)
+            .map(|buckets| (buckets, Self::source_bucket(key, source)));
+        let refund = |error: Error| {
+            if let Some(buckets) = route {
+                buckets.refund(key.as_ref());
+            }
+            if let Some((buckets, name)) = &sources {
+                buckets.refund(name);
+            }
+            Err(error)
+        };
+        if let Some((buckets, name)) = &sources {
+            match buckets.take(name) {
+                Ok(wait) => delay = delay.max(wait),
+                Err(error) => {
+                    if let Some(buckets) = route {
+                        buckets.refund(key.as_ref());
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        if !delay.is_zero() {
+            let left = left.unwrap_or(Duration::MAX);
+            thread::sleep(delay.min(left));
+            // The token would come after the deadline.
+            if delay > left {
+                return refund(Error::Throttled(delay - left));
+            }
+        }
+
+        match self.matching_slots(key) {
+            Some(slots) => match slots.acquire(key.as_ref()) {
+                Ok(()) => Ok(Permit(Some(Arc::new(Taken {
+                    slots: slots.clone(),
+                    key:   key.to_string(),
+                })))),
+                Err(error) => refund(error),
+            },
+            None => Ok(Permit::default()),
+        }
+    }
+
+    /// Tells whether [`admit()`](Limits::admit) would throttle a message
+    /// right away, without taking anything.
+    pub(super) fn check(&self, key: &ServiceKey, source: &ServiceKey) -> Result<()> {
+        if let Some(slots) = self.matching_slots(key) {
+            slots.check(key.as_ref())?;
+        }
+        if let Some(buckets) = Self::matching(&self.rates, key) {
+            buckets.check(key.as_ref())?;
+        }
+        if let Some(buckets) = Self::matching(&self.sources, key) {
+            buckets.check(&Self::source_bucket(key, source))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the slots of the first pattern matching a [`ServiceKey`],
+    /// if any.
+    fn matching_slots(&self, key: &ServiceKey) -> Option<&Arc<Slots>> {
+        self.slots
+            .iter()
+            .find(|(pattern, _)| key.matches(pattern))
+            .map(|(_, slots)| slots)
+    }
+
+    /// Returns the name of the bucket of a source for a [`ServiceKey`].
+    fn source_bucket(key: &ServiceKey, source: &ServiceKey) -> String { format!("{key}\t{source}") }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            KeyMode,
+        },
+    };
+
+    fn key(uri: &str) -> Result<ServiceKey> {
+        Ok(ServiceKey::new(
+            &Address::parse(uri)?,
+            KeyMode::default(),
+        ))
+    }
+
+    #[test]
+    fn token_bucket() {
+        let limit = RateLimit::new(10, Duration::from_secs(1)).burst(2);
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            tokens:  2.0,
+            updated: start,
+        };
+
+        assert_eq!(
+            bucket.take(&limit, start),
+            Ok(Duration::ZERO)
+        );
+        assert_eq!(
+            bucket.take(&limit, start),
+            Ok(Duration::ZERO)
+        );
+        assert_eq!(
+            bucket.take(&limit, start),
+            Err(Duration::from_millis(100))
+        );
+
+        // Refilled at 10 tokens per second, up to the burst.
+        let later = start + Duration::from_millis(150);
+        assert_eq!(
+            bucket.take(&limit, later),
+            Ok(Duration::ZERO)
+        );
+        assert!(!bucket.is_full(&limit, later));
+        assert!(bucket.is_full(&limit, later + Duration::from_secs(1)));
+
+        // Waiting borrows the next token ahead of time.
+        let limit = limit.wait(Duration::from_millis(100));
+        let delay = bucket
+            .take(&limit, later)
+            .unwrap();
+        assert!(delay > Duration::ZERO && delay <= Duration::from_millis(100));
+        assert!(
+            bucket
+                .take(&limit, later)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rate_limits_by_route_and_source() -> Result<()> {
+        let mut limits = Limits::default();
+        limits.add_rate(
+            "http://orders",
+            RateLimit::new(2, Duration::from_secs(60)),
+        );
+        limits.add_source_rate(
+            "*",
+            RateLimit::new(1, Duration::from_secs(60)),
+        );
+        let orders = key("http://orders")?;
+        let users = key("http://users")?;
+        let (alice, bob) = (key("http://alice")?, key("http://bob")?);
+
+        limits.admit(&orders, &alice, None)?;
+        limits.admit(&users, &alice, None)?;
+        assert!(matches!(
+            limits.admit(&users, &alice, None),
+            Err(Error::Throttled(_))
+        ));
+        limits.admit(&orders, &bob, None)?;
+
+        // The route is out of tokens, whoever the source.
+        match limits.admit(&orders, &key("http://carol")?, None) {
+            Err(error) => assert!(error.retry_after() > Some(Duration::from_secs(29))),
+            Ok(_) => panic!("orders should be throttled"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn throttled_source_keeps_route_token() -> Result<()> {
+        let mut limits = Limits::default();
+        limits.add_rate(
+            "http://orders",
+            RateLimit::new(2, Duration::from_secs(60)),
+        );
+        limits.add_source_rate(
+            "http://orders",
+            RateLimit::new(1, Duration::from_secs(60)),
+        );
+        let orders = key("http://orders")?;
+        let (alice, bob) = (key("http://alice")?, key("http://bob")?);
+
+        limits.admit(&orders, &alice, None)?;
+        for _ in 0..2 {
+            assert!(matches!(
+                limits.check(&orders, &alice),
+                Err(Error::Throttled(_))
+            ));
+            assert!(matches!(
+                limits.admit(&orders, &alice, None),
+                Err(Error::Throttled(_))
+            ));
+        }
+
+        // Checking takes nothing, and alice's throttled messages left the
+        // route its token.
+        limits.check(&orders, &bob)?;
+        limits.check(&orders, &bob)?;
+        limits.admit(&orders, &bob, None)?;
+        assert!(matches!(
+            limits.check(&orders, &key("http://carol")?),
+            Err(Error::Throttled(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn rate_wait_stops_at_the_deadline() -> Result<()> {
+        let mut limits = Limits::default();
+        limits.add_rate(
+            "http://orders",
+            RateLimit::new(1, Duration::from_secs(60)).wait(Duration::from_secs(60)),
+        );
+        let orders = key("http://orders")?;
+        let source = key("http://alice")?;
+        limits.admit(&orders, &source, None)?;
+
+        let start = Instant::now();
+        match limits.admit(
+            &orders,
+            &source,
+            Some(Duration::from_millis(20)),
+        ) {
+            Err(error) => assert!(error.retry_after() > Some(Duration::from_secs(59))),
+            Ok(_) => panic!("orders should be throttled"),
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_secs(1));
+
+        // The token borrowed for the message was given back.
+        limits.check(&orders, &source)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rate_wait_holds_no_slot() -> Result<()> {
+        let mut limits = Limits::default();
+        limits.add_source_rate(
+            "http://orders",
+            RateLimit::new(1, Duration::from_millis(200)).wait(Duration::from_secs(1)),
+        );
+        limits.add_concurrency("http://orders", ConcurrencyLimit::new(1));
+        let orders = key("http://orders")?;
+        let (alice, bob) = (key("http://alice")?, key("http://bob")?);
+        limits.admit(&orders, &alice, None)?;
+
+        thread::scope(|scope| -> Result<()> {
+            let waiting = scope.spawn(|| limits.admit(&orders, &alice, None));
+            thread::sleep(Duration::from_millis(50));
+            // Alice waits for her token without taking the slot.
+            drop(limits.admit(&orders, &bob, None)?);
+            waiting.join().unwrap()?;
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn concurrency_limits() -> Result<()> {
+        let mut limits = Limits::default();
+        limits.add_concurrency(
+            "http://orders",
+            ConcurrencyLimit::new(1).retry_after(Duration::from_millis(5)),
+        );
+        let orders = key("http://orders")?;
+        let source = key("http://alice")?;
+
+        let permit = limits.admit(&orders, &source, None)?;
+        assert!(matches!(
+            limits.admit(&orders, &source, None),
+            Err(Error::Throttled(retry_after)) if retry_after == Duration::from_millis(5)
+        ));
+        limits.admit(&key("http://users")?, &source, None)?;
+
+        drop(permit);
+        let _permit = limits.admit(&orders, &source, None)?;
+        Ok(())
+    }
+}
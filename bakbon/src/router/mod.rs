@@ -2,42 +2,197 @@
 //! driving the [`Router`], which turns [`Envelope`] destinations into
 //! concrete [`Service`](crate::Service) instances.
 
+#[cfg(feature = "async")]
+mod asynchronous;
 mod builder;
+mod dead_letter;
+mod federation;
+mod gather;
+mod introspect;
+mod limits;
+mod pending;
+mod random;
+mod retry;
+mod rules;
+mod saga;
+mod traffic;
+mod worker;
 
-pub use builder::RouterBuilder;
-
-use crate::{
-    Balancer,
-    Envelope,
-    Error,
-    Registry,
-    Reply,
-    Result,
+#[cfg(feature = "async")]
+pub use asynchronous::{
+    AsyncRouter,
+    AsyncRouterBuilder,
+};
+use {
+    crate::{
+        Address,
+        Balancer,
+        CORRELATION_HEADER,
+        Cache,
+        CircuitState,
+        Envelope,
+        Error,
+        LiveRegistry,
+        Queue,
+        Replies,
+        Reply,
+        Result,
+        SemVer,
+        Service,
+        ServiceArc,
+        ServiceContext,
+        ServiceKey,
+        VersionRange,
+        infra::Chain,
+    },
+    federation::{
+        Federation,
+        local_peer,
+    },
+    limits::{
+        Limits,
+        Permit,
+    },
+    pending::PendingReplies,
+    rules::Rules,
+    std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            Mutex,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+        },
+        thread,
+        time::{
+            Duration,
+            SystemTime,
+        },
+    },
+    traffic::Traffic,
+    worker::Workers,
+};
+pub use {
+    builder::RouterBuilder,
+    dead_letter::{
+        DEAD_LETTER_DESTINATION_HEADER,
+        DEAD_LETTER_ERROR_HEADER,
+        DEAD_LETTER_REASON_HEADER,
+        DEAD_LETTER_SERVICE_HEADER,
+        DeadLetterReason,
+    },
+    federation::{
+        AdvertisedRoute,
+        Advertisement,
+        FORWARDED_BY_HEADER,
+        HOPS_HEADER,
+        PeerHealth,
+        PeerLink,
+    },
+    gather::{
+        Aggregator,
+        Gather,
+        GatherBuilder,
+        GatherReport,
+    },
+    introspect::{
+        Candidate,
+        Decision,
+        Explanation,
+        InstanceInfo,
+        RouteInfo,
+    },
+    limits::{
+        ConcurrencyLimit,
+        RateLimit,
+    },
+    retry::{
+        ATTEMPT_HEADER,
+        RetryPolicy,
+    },
+    rules::{
+        Condition,
+        Rule,
+        RuleCheck,
+    },
+    saga::{
+        FileSagaStore,
+        MemorySagaStore,
+        SAGA_ID_HEADER,
+        SAGA_STEP_HEADER,
+        Saga,
+        SagaBuilder,
+        SagaState,
+        SagaStatus,
+        SagaStore,
+        Step,
+        StepState,
+        StepStatus,
+    },
+    traffic::{
+        MIRROR_HEADER,
+        MirrorStats,
+        Split,
+    },
 };
 
 /// Routes [`Envelope`]s to registered [`Service`](crate::Service) with
 /// load balancing.
 ///
 /// The `Router` looks up [`Service`](crate::Service) instances in the
-/// [`Registry`] based on the [`Envelope`] destination
-/// [`Address`](crate::Address) string representation, then delegates
-/// instance selection to the internal [`Balancer`] before calling
-/// [`process()`](crate::Service::process) on the chosen
-/// instance.
+/// [`Registry`](crate::Registry) under the
+/// [`ServiceKey`](crate::ServiceKey) of the [`Envelope`] destination
+/// [`Address`](crate::Address), then delegates instance selection to the
+/// internal [`Balancer`] before calling
+/// [`process_with()`](crate::Service::process_with) on the chosen
+/// instance. See [`route()`](Router::route) for the steps in between, each
+/// set up with [`Router::builder()`].
+///
+/// The `Router` also holds the resources shared with its services through
+/// the [`ServiceContext`]: named [`Queue`]s, a [`Cache`] and configuration
+/// values. Routing only needs a shared reference, so a
+/// [`Service`](crate::Service) can route new messages through the same
+/// `Router` while it is being processed.
+///
+/// The `Router` is `Send + Sync`: wrap it in an [`Arc`] to route from
+/// many threads at once. The [`Balancer`] keeps its state in atomics and
+/// the [`Cache`] is behind a [`Mutex`], so no external locking is needed.
+/// Cloning a `Router` is cheap, and clones share everything: the
+/// [`Registry`](crate::Registry), the [`Balancer`], the resources and the
+/// pending replies.
 ///
 /// # Examples
 ///
 /// ```ignore
-/// let mut router = Router::builder()
+/// let router = Router::builder()
 ///     .registry(registry)
 ///     .balancer("least_connections")
 ///     .build();
 ///
 /// let reply = router.route(envelope)?;
 /// ```
+#[derive(Clone)]
 pub struct Router {
-    registry: Registry,
-    balancer: Balancer,
+    registry:             LiveRegistry,
+    balancer:             Arc<Balancer>,
+    balancers:            Arc<Vec<(String, Balancer)>>,
+    queues:               Arc<HashMap<String, Arc<Queue>>>,
+    cache:                Arc<Mutex<Cache>>,
+    config:               Arc<HashMap<String, String>>,
+    fallback:             Option<ServiceArc>,
+    dead_letter:          Option<Arc<Queue>>,
+    pending:              Arc<PendingReplies>,
+    retries:              Arc<Vec<(String, RetryPolicy)>>,
+    timeouts:             Arc<Vec<(String, Duration)>>,
+    limits:               Arc<Limits>,
+    workers:              Arc<Workers>,
+    dead_letter_failures: Arc<AtomicUsize>,
+    traffic:              Arc<Traffic>,
+    rules:                Arc<Rules>,
+    middleware:           Arc<Chain>,
+    federation:           Arc<Federation>,
 }
 
 impl Router {
@@ -47,39 +202,950 @@ impl Router {
     /// Routes a [`message`](Envelope) to a registered
     /// [`Service`](crate::Service) and returns its [`Reply`].
     ///
-    /// This method:
-    /// 1. Looks up instances for
-    ///    [`msg.destination()`](Envelope::destination) in the
-    ///    [`Registry`].
-    /// 2. Uses the [`Balancer`] to select one instance.
-    /// 3. Calls [`process()`](crate::Service::process) on that instance.
+    /// The message goes through these steps, each set up with the
+    /// `RouterBuilder` method named:
+    /// 1. A reply awaited by [`send_and_wait()`](Router::send_and_wait) is
+    ///    handed over to its caller, and `None` is returned.
+    /// 2. The `middleware()` chain runs around the next steps, and may
+    ///    answer or fail in their place.
+    /// 3. The first matching `rule()` may send it to another destination,
+    ///    then a `split()` to yet another one.
+    /// 4. If a `rate_limit()` or `concurrency_limit()` of its route is
+    ///    reached, [`Error::Throttled`] is returned, without
+    ///    dead-lettering it.
+    /// 5. The [`Balancer`] selects an instance registered under the
+    ///    [`ServiceKey`](crate::ServiceKey) of its destination, among the
+    ///    highest versions in the [`VersionRange`] of its
+    ///    [`ACCEPT_VERSION_HEADER`](crate::ACCEPT_VERSION_HEADER), if any,
+    ///    whose `circuit_breaker()` is not open.
+    /// 6. With no [`Service`](crate::Service) registered, it is forwarded
+    ///    to a `peer()` advertising its destination, or else handed to the
+    ///    `fallback()`. Without one, it is dead-lettered and
+    ///    [`Error::ServiceNotFound`] is returned.
+    /// 7. [`process_with()`](crate::Service::process_with) is called on
+    ///    the instance with a [`ServiceContext`] bound to this `Router`,
+    ///    within the `timeout()` of its route, and retried on other
+    ///    instances as its `retry()` policy allows.
+    /// 8. If it still fails, it is handed to the `fallback()` when it
+    ///    timed out, or kept in the `dead_letter()` [`Queue`], and its
+    ///    error is returned.
+    /// 9. Copies of it are `mirror()`ed in the background once its
+    ///    [`Reply`] is known.
+    pub fn route(&self, msg: Envelope) -> Result<Reply> {
+        self.route_with(msg, &ServiceContext::new(self))
+    }
+
+    /// Routes a message sent by a [`Service`](crate::Service) through its
+    /// [`ServiceContext`], which is not dead-lettered if it fails.
+    pub(crate) fn route_nested(&self, msg: Envelope) -> Result<Reply> {
+        self.route_with(msg, &ServiceContext::nested(self))
+    }
+
+    /// Routes a message, handing the services the given context.
+    fn route_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        let Some(msg) = self.pending.resolve(msg)
+        else {
+            return Ok(None);
+        };
+
+        self.middleware
+            .run(msg, |msg| self.steer(msg, ctx))
+    }
+
+    /// Applies the routing [`Rule`]s and traffic rules to a message, then
+    /// dispatches it.
+    fn steer(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        let msg = match self.rules.is_empty() {
+            true => msg,
+            false => self.rules.apply(msg),
+        };
+        let (msg, mirrors) = self.shape_traffic(msg);
+        let permit = self.admit(&msg)?;
+        let outcome = self.dispatch(msg, ctx, &permit);
+        if !mirrors.is_empty() {
+            self.shadow(mirrors, &outcome);
+        }
+        outcome
+    }
+
+    /// Admits a message under the rate and concurrency limits of its
+    /// route, waiting for its turn if they allow it, but not past its
+    /// deadline.
     ///
-    /// Returns [`Error::ServiceNotFound`] if no
-    /// [`Service`](crate::Service) is registered under the destination
-    /// [`Address`](crate::Address) string representation.
-    pub fn route(&mut self, msg: Envelope) -> Result<Reply> {
-        let instances = self
+    /// The returned [`Permit`] holds its slot until it and its clones are
+    /// dropped.
+    fn admit(&self, msg: &Envelope) -> Result<Permit> {
+        if self.limits.is_empty() {
+            return Ok(Permit::default());
+        }
+
+        let (key, source) = {
+            let registry = self.registry.read();
+            (
+                registry.key(msg.destination()),
+                registry.key(msg.source()),
+            )
+        };
+        self.limits
+            .admit(&key, &source, Self::time_left(msg))
+    }
+
+    /// Delivers a message to the [`Service`](crate::Service) registered
+    /// for its destination.
+    fn dispatch(&self, msg: Envelope, ctx: &ServiceContext, permit: &Permit) -> Result<Reply> {
+        let Some(service) = self.select(&msg, &[])?
+        else {
+            return self.forward(msg, ctx);
+        };
+
+        match self.retry_policy(msg.destination()) {
+            Some(policy) => self.deliver_with_retries(policy, service, msg, ctx, permit),
+            None => self.deliver(&service, msg, ctx, permit),
+        }
+    }
+
+    /// Forwards a message with no local [`Service`](crate::Service) to a
+    /// peer advertising its destination, if any, or handles it as
+    /// unroutable.
+    fn forward(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        if self.federation.is_empty() {
+            return self.unroutable(msg, ctx);
+        }
+
+        let key = self
+            .registry
+            .read()
+            .key(msg.destination());
+        match self
+            .federation
+            .forward(&key, &msg)
+        {
+            Some(outcome) => outcome,
+            None => self.unroutable(msg, ctx),
+        }
+    }
+
+    /// Selects an instance of the [`Service`](crate::Service) registered
+    /// for the destination of a message, if any, preferably one not tried
+    /// yet.
+    ///
+    /// The [`Registry`](crate::Registry) lock is released before the
+    /// instance is used, so the [`LiveRegistry`] can change while it
+    /// processes a message.
+    fn select(&self, msg: &Envelope, tried: &[ServiceArc]) -> Result<Option<ServiceArc>> {
+        let registry = self.registry.read();
+        let Some((key, instances)) = registry.lookup(msg.destination())
+        else {
+            return Ok(None);
+        };
+        let balancer = self.balancer_of_key(key);
+        let compatible;
+        let instances = match msg.accepted_version() {
+            Some(range) => {
+                compatible = Self::highest_compatible(range, instances)?;
+                &compatible[..]
+            }
+            None => instances,
+        };
+
+        let was_tried = |instance: &ServiceArc| {
+            tried
+                .iter()
+                .any(|t| Arc::ptr_eq(t, instance))
+        };
+        let all_tried = instances
+            .iter()
+            .all(was_tried);
+
+        balancer
+            .select_instance(instances, msg, |instance| {
+                !all_tried && was_tried(instance)
+            })
+            .cloned()
+            .map(Some)
+    }
+
+    /// Keeps the instances with the highest version in a
+    /// [`VersionRange`], whatever their build metadata.
+    ///
+    /// Returns [`Error::NoMatchingVersion`] along with the registered
+    /// versions if none is in the range.
+    fn highest_compatible(range: &str, instances: &[ServiceArc]) -> Result<Vec<ServiceArc>> {
+        let range = VersionRange::parse(range)?;
+        let Some(highest) = instances
+            .iter()
+            .filter_map(|instance| instance.version())
+            .filter(|version| range.matches(version))
+            .max_by(|a, b| a.cmp_precedence(b))
+        else {
+            let mut registered: Vec<&SemVer> = instances
+                .iter()
+                .filter_map(|instance| instance.version())
+                .collect();
+            registered.sort();
+            registered.dedup_by(|a, b| a.cmp_precedence(b).is_eq());
+            return Err(Error::NoMatchingVersion(
+                range.to_string(),
+                registered
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            ));
+        };
+
+        Ok(instances
+            .iter()
+            .filter(|instance| {
+                instance
+                    .version()
+                    .is_some_and(|version| {
+                        version
+                            .cmp_precedence(highest)
+                            .is_eq()
+                    })
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Returns the setting of the first route matching a destination, if
+    /// any.
+    fn route_setting<'a, T>(
+        &self,
+        routes: &'a [(String, T)],
+        destination: &Address,
+    ) -> Option<&'a T> {
+        if routes.is_empty() {
+            return None;
+        }
+
+        let key = self
             .registry
-            .get(
-                msg.destination()
-                    .to_string()
-                    .as_str(),
+            .read()
+            .key(destination);
+        Self::route_setting_of_key(routes, &key)
+    }
+
+    /// Returns the setting of the first route matching a
+    /// [`ServiceKey`], if any.
+    fn route_setting_of_key<'a, T>(routes: &'a [(String, T)], key: &ServiceKey) -> Option<&'a T> {
+        routes
+            .iter()
+            .find(|(pattern, _)| key.matches(pattern))
+            .map(|(_, setting)| setting)
+    }
+
+    /// Returns the [`Balancer`] of the route of a destination, or the
+    /// default one.
+    fn balancer_of(&self, destination: &Address) -> &Balancer {
+        self.route_setting(&self.balancers, destination)
+            .unwrap_or(&self.balancer)
+    }
+
+    /// Returns the [`Balancer`] of the route of a [`ServiceKey`], or the
+    /// default one.
+    fn balancer_of_key(&self, key: &ServiceKey) -> &Balancer {
+        Self::route_setting_of_key(&self.balancers, key).unwrap_or(&self.balancer)
+    }
+
+    /// Returns the [`RetryPolicy`] of the route of a destination, if any.
+    fn retry_policy(&self, destination: &Address) -> Option<&RetryPolicy> {
+        self.route_setting(&self.retries, destination)
+    }
+
+    /// Returns how long a message may be processed for: the timeout of its
+    /// route or the time left until its deadline, whichever is shorter.
+    fn timeout(&self, msg: &Envelope) -> Option<Duration> {
+        let route = self
+            .route_setting(&self.timeouts, msg.destination())
+            .copied();
+        route
+            .into_iter()
+            .chain(Self::time_left(msg))
+            .min()
+    }
+
+    /// Returns the time left until the deadline of a message, if any,
+    /// zero once it passed.
+    fn time_left(msg: &Envelope) -> Option<Duration> {
+        msg.deadline()
+            .map(|deadline| {
+                deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+            })
+    }
+
+    /// Applies the [`Split`] rules of the route of a message, and returns
+    /// it along with the copies to mirror.
+    fn shape_traffic(&self, msg: Envelope) -> (Envelope, Vec<(Address, Envelope)>) {
+        if self.traffic.is_empty() {
+            return (msg, Vec::new());
+        }
+
+        let registry = self.registry.read();
+        let msg = match self
+            .traffic
+            .split(&registry.key(msg.destination()), &msg)
+        {
+            Some(destination) => msg.redirect(destination),
+            None => msg,
+        };
+        let mirrors = self
+            .traffic
+            .mirrors(&registry.key(msg.destination()), &msg);
+        (msg, mirrors)
+    }
+
+    /// Delivers mirrored copies on the mirror worker and records their
+    /// outcome against the one of the primary delivery.
+    ///
+    /// Copies are neither retried nor dead-lettered, and their replies are
+    /// discarded.
+    fn shadow(&self, mirrors: Vec<(Address, Envelope)>, primary: &Result<Reply>) {
+        let primary = match primary {
+            Ok(Some(reply)) => Some(reply.payload().clone()),
+            _ => None,
+        };
+        let targets: Vec<Address> = mirrors
+            .iter()
+            .map(|(target, _)| target.clone())
+            .collect();
+        let router = self.clone();
+        let job = Box::new(move || {
+            let ctx = ServiceContext::new(&router);
+            for (target, copy) in mirrors {
+                let outcome = match router.select(&copy, &[]) {
+                    Ok(Some(service)) => router.process(&service, copy, &ctx, &Permit::default()),
+                    Ok(None) => Err(Error::ServiceNotFound),
+                    Err(error) => Err(error),
+                };
+                router
+                    .traffic
+                    .record(&target, primary.as_ref(), &outcome);
+            }
+        });
+        self.traffic
+            .shadow(&targets, job);
+    }
+
+    /// Hands an unroutable message to the fallback
+    /// [`Service`](crate::Service), or dead-letters it.
+    ///
+    /// A message the fallback fails to process is dead-lettered too.
+    fn unroutable(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        let error = Error::ServiceNotFound;
+        let msg = DeadLetterReason::Unroutable.mark(msg, &error, None);
+
+        let Some(fallback) = &self.fallback
+        else {
+            self.dead_letter(msg, ctx);
+            return Err(error);
+        };
+        let copy = msg.clone();
+        self.process(fallback, msg, ctx, &Permit::default())
+            .inspect_err(|error| {
+                let copy = DeadLetterReason::Failed.mark(copy, error, Some(fallback.address()));
+                self.dead_letter(copy, ctx);
+            })
+    }
+
+    /// Processes a message with a [`Service`](crate::Service) instance,
+    /// handing it to the fallback [`Service`](crate::Service) if it timed
+    /// out.
+    ///
+    /// Without a [`RetryPolicy`], nothing tells whether a failure would
+    /// last, so the message is not dead-lettered.
+    fn deliver(
+        &self,
+        service: &ServiceArc,
+        msg: Envelope,
+        ctx: &ServiceContext,
+        permit: &Permit,
+    ) -> Result<Reply> {
+        // Only pay for a copy when there is someone to hand it to.
+        if self.fallback.is_none() {
+            return self.process(service, msg, ctx, permit);
+        }
+
+        let copy = msg.clone();
+        self.process(service, msg, ctx, permit)
+            .or_else(
+                |error| match self.falls_back(&error, service) {
+                    true => self.fall_back(copy, error, service, ctx),
+                    false => Err(error),
+                },
             )
-            .ok_or(Error::ServiceNotFound)?;
+    }
+
+    /// Processes a message with a [`Service`](crate::Service) instance,
+    /// within the timeout of the message if any, and records the outcome
+    /// in its circuit breaker.
+    ///
+    /// A call past its timeout keeps a clone of the [`Permit`] of the
+    /// message, so that its slot is held until the call returns.
+    fn process(
+        &self,
+        service: &ServiceArc,
+        msg: Envelope,
+        ctx: &ServiceContext,
+        permit: &Permit,
+    ) -> Result<Reply> {
+        let balancer = self.balancer_of(msg.destination());
+        let outcome = match self.timeout(&msg) {
+            Some(timeout) => {
+                let router = self.clone();
+                let service = service.clone();
+                let nested = ctx.is_nested();
+                let permit = permit.clone();
+                self.workers
+                    .run(timeout, move || {
+                        let _permit = permit;
+                        let ctx = match nested {
+                            true => ServiceContext::nested(&router),
+                            false => ServiceContext::new(&router),
+                        };
+                        service.process_with(msg, &ctx)
+                    })
+            }
+            None => worker::catch(|| service.process_with(msg, ctx)),
+        };
+        balancer.record(service.as_ref(), &outcome);
+        outcome
+    }
+
+    /// Processes a message with a [`Service`](crate::Service) instance,
+    /// then with other instances while the [`RetryPolicy`] allows it, and
+    /// dead-letters it once the policy gives up.
+    ///
+    /// The wait between attempts is cut short at the deadline of the
+    /// message, past which it is not retried. The concurrency slot of the
+    /// route stays held during the wait, so a retried message keeps its
+    /// place.
+    fn deliver_with_retries(
+        &self,
+        policy: &RetryPolicy,
+        mut service: ServiceArc,
+        mut msg: Envelope,
+        ctx: &ServiceContext,
+        permit: &Permit,
+    ) -> Result<Reply> {
+        let mut tried = Vec::new();
+        let mut attempt = 1;
+        loop {
+            msg.add_header(ATTEMPT_HEADER, &attempt.to_string());
+            let error = match self.process(&service, msg.clone(), ctx, permit) {
+                Ok(reply) => return Ok(reply),
+                Err(error) => error,
+            };
+            if attempt >= policy.max_attempts() || !policy.is_retryable(&error) {
+                return self.failed(msg, error, &service, ctx);
+            }
+
+            let left = Self::time_left(&msg);
+            thread::sleep(
+                policy
+                    .wait(attempt)
+                    .min(left.unwrap_or(Duration::MAX)),
+            );
+            // Another attempt could only time out.
+            if Self::time_left(&msg).is_some_and(|left| left.is_zero()) {
+                return self.failed(msg, error, &service, ctx);
+            }
+            tried.push(service.clone());
+            service = match self.select(&msg, &tried) {
+                Ok(Some(next)) => next,
+                // Deregistered or tripped in the meantime.
+                _ => return self.failed(msg, error, &service, ctx),
+            };
+            attempt += 1;
+        }
+    }
+
+    /// Dead-letters a message the [`RetryPolicy`] of its route gave up on,
+    /// and returns its error.
+    ///
+    /// A message which timed out is handed to the fallback
+    /// [`Service`](crate::Service) instead, if any and if it is not the
+    /// one which timed out.
+    fn failed(
+        &self,
+        msg: Envelope,
+        error: Error,
+        service: &ServiceArc,
+        ctx: &ServiceContext,
+    ) -> Result<Reply> {
+        if self.falls_back(&error, service) {
+            return self.fall_back(msg, error, service, ctx);
+        }
+
+        let reason = match error {
+            Error::TimedOut(_) => DeadLetterReason::TimedOut,
+            _ => DeadLetterReason::Failed,
+        };
+        self.dead_letter(
+            reason.mark(msg, &error, Some(service.address())),
+            ctx,
+        );
+        Err(error)
+    }
+
+    /// Returns whether a message failing with an error is handed to the
+    /// fallback [`Service`](crate::Service): it timed out, and the
+    /// fallback is not the instance which timed out.
+    fn falls_back(&self, error: &Error, service: &ServiceArc) -> bool {
+        matches!(error, Error::TimedOut(_))
+            && self
+                .fallback
+                .as_ref()
+                .is_some_and(|fallback| !Arc::ptr_eq(fallback, service))
+    }
+
+    /// Hands a message which timed out to the fallback
+    /// [`Service`](crate::Service), without a timeout.
+    fn fall_back(
+        &self,
+        msg: Envelope,
+        error: Error,
+        service: &ServiceArc,
+        ctx: &ServiceContext,
+    ) -> Result<Reply> {
+        let Some(fallback) = &self.fallback
+        else {
+            return Err(error);
+        };
+        let msg = DeadLetterReason::TimedOut.mark(msg, &error, Some(service.address()));
+        worker::catch(|| fallback.process_with(msg, ctx))
+    }
+
+    /// Enqueues a message in the dead-letter [`Queue`], if any, unless it
+    /// was routed by a [`Service`](crate::Service) processing another one.
+    ///
+    /// A message the [`Queue`] refuses is counted by
+    /// [`dead_letter_failures()`](Router::dead_letter_failures).
+    fn dead_letter(&self, msg: Envelope, ctx: &ServiceContext) {
+        if ctx.is_nested() {
+            return;
+        }
+        if let Some(queue) = &self.dead_letter
+            && queue.enqueue(msg).is_err()
+        {
+            self.dead_letter_failures
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Delivers a copy of a [`message`](Envelope) to every instance of
+    /// the destination [`Service`](crate::Service) and returns their
+    /// [`Replies`], in registration order.
+    ///
+    /// Useful to push a change to all instances at once, such as a cache
+    /// invalidation or a configuration update. A failing instance does not
+    /// stop the delivery to the others; its error is in the [`Replies`].
+    /// Every reply comes with the [`ServiceKey`](crate::ServiceKey) of the
+    /// destination.
+    ///
+    /// Each copy is processed like a [`route()`](Router::route)d message,
+    /// within the timeout of the route and recorded by its circuit
+    /// breaker. An instance whose circuit is open is skipped with
+    /// [`Error::CircuitOpen`].
+    ///
+    /// Returns [`Error::ServiceNotFound`] if no
+    /// [`Service`](crate::Service) is registered under the
+    /// [`ServiceKey`](crate::ServiceKey) of the destination
+    /// [`Address`](crate::Address).
+    pub fn broadcast(&self, msg: Envelope) -> Result<Replies> {
+        let (key, instances) = {
+            let registry = self.registry.read();
+            let (key, instances) = registry
+                .lookup(msg.destination())
+                .ok_or(Error::ServiceNotFound)?;
+            (key.clone(), instances.to_vec())
+        };
+
+        let balancer = self.balancer_of_key(&key);
+        let ctx = ServiceContext::new(self);
+        let replies = instances
+            .iter()
+            .map(|service| {
+                let reply = balancer
+                    .admit(service)
+                    .and_then(|_| {
+                        self.process(
+                            service,
+                            msg.clone(),
+                            &ctx,
+                            &Permit::default(),
+                        )
+                    });
+                (key.clone(), reply)
+            })
+            .collect();
+
+        Ok(replies)
+    }
+
+    /// Delivers a copy of a [`message`](Envelope) to every
+    /// [`Service`](crate::Service) whose
+    /// [`ServiceKey`](crate::ServiceKey) matches a glob pattern and
+    /// returns their [`Replies`], ordered by key. A service with no
+    /// available instance fails on its own, under its key.
+    ///
+    /// Each copy is [`redirect`](Envelope::redirect)ed to the
+    /// [`Address`](crate::Address) of the selected instance, and one
+    /// instance per [`Service`](crate::Service) is selected by the
+    /// [`Balancer`], skipping open circuits. Copies are then processed
+    /// like [`route()`](Router::route)d messages, within the timeout of
+    /// their route and recorded by its circuit breakers. The destination
+    /// of the original message is ignored.
+    /// See [`ServiceKey::matches()`](crate::ServiceKey::matches) for the
+    /// pattern syntax.
+    ///
+    /// Returns [`Error::ServiceNotFound`] if no
+    /// [`Service`](crate::Service) matches the pattern.
+    pub fn multicast(&self, pattern: &str, msg: Envelope) -> Result<Replies> {
+        let selected: Vec<(ServiceKey, Result<ServiceArc>)> = self
+            .registry
+            .read()
+            .matching(pattern)
+            .into_iter()
+            .map(|(key, instances)| {
+                let service = self
+                    .balancer_of_key(key)
+                    .select_instance(instances, &msg, |_| false)
+                    .cloned();
+                (key.clone(), service)
+            })
+            .collect();
+        if selected.is_empty() {
+            return Err(Error::ServiceNotFound);
+        }
+
+        let ctx = ServiceContext::new(self);
+        let replies = selected
+            .into_iter()
+            .map(|(key, service)| {
+                let reply = service.and_then(|service| {
+                    let copy = msg
+                        .clone()
+                        .redirect(service.address().clone());
+                    self.process(&service, copy, &ctx, &Permit::default())
+                });
+                (key, reply)
+            })
+            .collect();
+
+        Ok(replies)
+    }
+
+    /// Sends a copy of a [`message`](Envelope) to each target of a
+    /// [`Gather`] in parallel and merges their replies into one [`Reply`].
+    ///
+    /// Each copy is [`redirect`](Envelope::redirect)ed to its target and
+    /// [`route`](Router::route)d on its own thread, by a clone of the
+    /// `Router`. Replies are waited for until every target answered, the
+    /// [`Gather`] quorum is reached or its deadline expires, then passed
+    /// to its [`Aggregator`] with the original message.
+    ///
+    /// Returns the aggregated [`Reply`] along with the [`GatherReport`]
+    /// telling which targets replied, failed, timed out or were skipped,
+    /// or the error of the [`Aggregator`].
+    pub fn scatter_gather(&self, msg: Envelope, gather: &Gather) -> Result<(Reply, GatherReport)> {
+        gather.run(self, msg)
+    }
+
+    /// Runs a [`Saga`] for a request: routes its steps in order, and
+    /// compensates the completed ones in reverse order if one fails.
+    ///
+    /// Returns the final [`SagaState`] of the run, also kept in the
+    /// [`SagaStore`] of the [`Saga`]. A failed step is reported there, not
+    /// as an error: errors are the ones of the [`SagaStore`], in which
+    /// case the run can be [`resume`](Router::resume_saga)d from its
+    /// last saved state.
+    pub fn run_saga(&self, saga: &Saga, request: Envelope) -> Result<SagaState> {
+        saga.start(self, request)
+    }
+
+    /// Resumes a run of a [`Saga`] from its stored [`SagaState`], e.g.
+    /// after a crash, and drives it to its end.
+    ///
+    /// A [`Stuck`](SagaStatus::Stuck) run tries its failed compensation
+    /// again, and a finished one is returned as is. Returns
+    /// [`Error::SagaNotFound`] if no run of this [`Saga`] has this id, and
+    /// [`Error::PersistenceFailed`] if its steps changed since the run
+    /// started.
+    pub fn resume_saga(&self, saga: &Saga, id: &str) -> Result<SagaState> { saga.resume(self, id) }
+
+    /// Routes a request and waits for its reply, even when the
+    /// [`Service`](crate::Service) answers asynchronously.
+    ///
+    /// The request is tagged with a new correlation id, unless it already
+    /// has one, then [`route`](Router::route)d. A [`Reply`] returned by
+    /// the [`Service`](crate::Service) is returned right away. Otherwise
+    /// the `Router` waits for an [`Envelope`] with the same correlation id
+    /// to be routed to the [`reply_to()`](Envelope::reply_to) address of
+    /// the request, or its source, which is what
+    /// [`into_reply()`](Envelope::into_reply) produces.
+    ///
+    /// Returns [`Error::TimedOut`] if no reply came back in time; a reply
+    /// arriving later is routed like any other message. Returns
+    /// [`Error::DuplicateCorrelation`] if a request with the same
+    /// correlation id is already waiting, and the error of
+    /// [`route()`](Router::route) if the request cannot be delivered.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use {
+    ///     bakbon::*,
+    ///     std::{sync::Arc, thread, time::Duration},
+    /// };
+    ///
+    /// let outbox = Arc::new(Queue::default());
+    /// let address = Address::parse("http://async-echo.com").unwrap();
+    ///
+    /// // Answers later, through a queue.
+    /// let pending = outbox.clone();
+    /// let echo = service_fn(address.clone(), move |msg| {
+    ///     let payload = msg.payload().clone();
+    ///     pending.enqueue(msg.into_reply(payload))?;
+    ///     Ok(None)
+    /// });
+    /// let router = Arc::new(
+    ///     Router::builder()
+    ///         .registry(Registry::builder().register(echo).build())
+    ///         .build(),
+    /// );
+    ///
+    /// // Routes the replies back to their caller.
+    /// let relay = router.clone();
+    /// thread::spawn(move || loop {
+    ///     match outbox.dequeue() {
+    ///         Ok(Some(reply)) => drop(relay.route(reply)),
+    ///         _ => thread::sleep(Duration::from_millis(1)),
+    ///     }
+    /// });
+    ///
+    /// let src = Address::parse("http://client.com").unwrap();
+    /// let msg = Envelope::new(src, address, Payload::from("hi"));
+    /// let reply = router
+    ///     .send_and_wait(msg, Duration::from_secs(1))
+    ///     .unwrap();
+    /// assert_eq!(reply.payload(), &Payload::from("hi"));
+    /// ```
+    pub fn send_and_wait(&self, msg: Envelope, timeout: Duration) -> Result<Envelope> {
+        let id = msg
+            .correlation_id()
+            .map(String::from)
+            .unwrap_or_else(PendingReplies::next_id);
+        let msg = msg.header(CORRELATION_HEADER, &id);
+        let reply_to = msg
+            .reply_to()
+            .unwrap_or_else(|| msg.source().clone());
+        let replies = self
+            .pending
+            .register(&id, reply_to)?;
+
+        let reply = self
+            .route(msg)
+            .inspect_err(|_| self.pending.cancel(&id))?;
+        if let Some(reply) = reply {
+            self.pending.cancel(&id);
+            return Ok(reply);
+        }
+
+        replies
+            .recv_timeout(timeout)
+            .or_else(|_| {
+                self.pending.cancel(&id);
+                // The reply may have come in just before the cancellation.
+                replies
+                    .try_recv()
+                    .map_err(|_| Error::TimedOut(timeout))
+            })
+    }
+
+    /// Returns the number of calls the `Router` stopped waiting for that
+    /// are still queued or running, because they overran their timeout or
+    /// a [`Gather`] stopped.
+    ///
+    /// Such calls cannot be interrupted: their outcome is dropped when
+    /// they complete. A growing count means a
+    /// [`Service`](crate::Service) is stuck.
+    pub fn overdue_workers(&self) -> usize { self.workers.overdue() }
 
-        let service = self
-            .balancer
-            .select(instances)?;
+    /// Returns the number of messages the dead-letter [`Queue`] refused,
+    /// e.g. because it was full.
+    ///
+    /// The caller still gets the error which failed the message.
+    pub fn dead_letter_failures(&self) -> usize {
+        self.dead_letter_failures
+            .load(Ordering::Relaxed)
+    }
+
+    /// Traces the routing of a message without routing it, and tells
+    /// which [`Rule`] would apply, which instances of which
+    /// [`ServiceKey`](crate::ServiceKey) would be considered, and which
+    /// one would be selected and why, or where the message would go
+    /// instead, including whether a limit of its route would throttle it.
+    ///
+    /// Nothing changes along the way: the balancing strategies do not
+    /// take their turn, no probe is let through an open circuit, and no
+    /// token or slot of the limits is taken.
+    pub fn explain(&self, msg: &Envelope) -> Explanation { Explanation::trace(self, msg) }
+
+    /// Returns the routing table: every registered
+    /// [`ServiceKey`](crate::ServiceKey), ordered by key, with its
+    /// instances, their health, and the balancing strategy and settings of
+    /// its route.
+    ///
+    /// The routes learned from the peer routers are advertised with
+    /// [`advertise()`](Router::advertise).
+    pub fn routes(&self) -> Vec<RouteInfo> { RouteInfo::list(self) }
+
+    /// Returns the outcome of the copies mirrored to a shadow
+    /// [`Service`](crate::Service), by the [`Address`] its mirror rule
+    /// targets, or `None` if nothing was mirrored to it yet.
+    ///
+    /// Copies are delivered in the background by a single worker, so the
+    /// stats lag behind the primary replies. Copies are dropped, and
+    /// counted as such, while too many are waiting for it.
+    pub fn mirror_stats(&self, target: &Address) -> Option<MirrorStats> {
+        self.traffic.stats(target)
+    }
+
+    /// Returns the name of the `Router` among its peers.
+    pub fn name(&self) -> &str { self.federation.name() }
+
+    /// Links a peer `Router` over a [`PeerLink`], replacing the one with
+    /// the same name, if any.
+    ///
+    /// Its routes are only learned on the next
+    /// [`refresh_peers()`](Router::refresh_peers).
+    pub fn link(&self, name: &str, link: impl PeerLink + 'static) {
+        self.federation
+            .link(name, Arc::new(link));
+    }
+
+    /// Links a peer `Router` of the same process, replacing the one with
+    /// the same name, if any.
+    ///
+    /// The peer is held weakly, so routers linked to each other do not
+    /// keep each other alive: keep a handle on it for as long as it should
+    /// be forwarded to. Once dropped, forwarding to it fails with
+    /// [`Error::Unavailable`]. Its routes are only learned on the next
+    /// [`refresh_peers()`](Router::refresh_peers).
+    pub fn link_local(&self, name: &str, peer: &Router) {
+        self.federation
+            .link(name, local_peer(peer));
+    }
+
+    /// Unlinks a peer `Router`, returning whether it was linked.
+    pub fn unlink(&self, name: &str) -> bool { self.federation.unlink(name) }
+
+    /// Returns the names of the peer routers, in the order they were
+    /// linked.
+    pub fn peers(&self) -> Vec<String> { self.federation.names() }
+
+    /// Returns the [`PeerHealth`] of a peer `Router`, if linked.
+    pub fn peer_health(&self, name: &str) -> Option<PeerHealth> { self.federation.health(name) }
 
-        service.process(msg)
+    /// Asks every peer for its [`Advertisement`], replacing the routes
+    /// learned from it.
+    ///
+    /// Call it periodically, e.g. from a background thread, to follow the
+    /// services coming and going on the peers. A peer failing to answer
+    /// keeps its previous routes, and the failure counts against its
+    /// [`PeerHealth`].
+    pub fn refresh_peers(&self) { self.federation.refresh() }
+
+    /// Returns the [`Advertisement`] of the routes reachable through this
+    /// `Router`: the [`ServiceKey`](crate::ServiceKey)s of its
+    /// [`Registry`](crate::Registry), then the routes learned from its
+    /// peers which can still be forwarded along.
+    pub fn advertise(&self) -> Advertisement {
+        let local = self.registry.list();
+        let mut advertisement = Advertisement::new(self.name());
+        for key in &local {
+            advertisement = advertisement.route(key, &[]);
+        }
+        for route in self.federation.learned() {
+            if !local.contains(&route.key().to_string()) {
+                let path: Vec<&str> = route
+                    .path()
+                    .iter()
+                    .map(String::as_str)
+                    .collect();
+                advertisement = advertisement.route(route.key(), &path);
+            }
+        }
+        advertisement
     }
 
-    /// Returns a reference to the underlying [`Service`](crate::Service)
-    /// [`Registry`].
-    pub fn registry(&self) -> &Registry { &self.registry }
+    /// Returns the number of requests waiting for their reply in
+    /// [`send_and_wait()`](Router::send_and_wait).
+    pub fn pending_replies(&self) -> usize { self.pending.len() }
 
-    /// Returns the active balancing `Strategy` as a string.
+    /// Returns a handle on the live [`Service`](crate::Service)
+    /// [`Registry`](crate::Registry).
+    ///
+    /// Services registered, deregistered or replaced through the handle
+    /// are seen by the next [`route()`](Router::route).
+    ///
+    /// This returns a [`LiveRegistry`] handle rather than the `&Registry`
+    /// it used to. The handle offers the same lookups, e.g.
+    /// [`get()`](LiveRegistry::get) and [`list()`](LiveRegistry::list),
+    /// but they return owned values.
+    pub fn registry(&self) -> LiveRegistry { self.registry.clone() }
+
+    /// Returns the [`CircuitState`] of each instance of the
+    /// [`Service`](crate::Service) registered for an
+    /// [`Address`](crate::Address), in registration order, if any.
+    ///
+    /// Every circuit is [`Closed`](CircuitState::Closed) unless circuit
+    /// breakers were enabled with [`Router::builder()`].
+    pub fn circuit_states(&self, address: &Address) -> Option<Vec<CircuitState>> {
+        let registry = self.registry.read();
+        let (key, instances) = registry.lookup(address)?;
+        let balancer = self.balancer_of_key(key);
+        let states = instances
+            .iter()
+            .map(|instance| balancer.circuit_state(instance.as_ref()))
+            .collect();
+        Some(states)
+    }
+
+    /// Returns the default balancing `Strategy` as a string.
     pub fn balancing_strategy(&self) -> &str { self.balancer.strategy() }
+
+    /// Returns the balancing `Strategy` of the
+    /// [`Service`](crate::Service) registered for an [`Address`] as a
+    /// string, which is the default one unless it was given its own
+    /// [`Balancer`].
+    pub fn balancing_strategy_of(&self, address: &Address) -> &str {
+        self.balancer_of(address)
+            .strategy()
+    }
+
+    /// Returns a named [`Queue`], if configured.
+    pub fn queue(&self, name: &str) -> Option<&Queue> {
+        self.queues
+            .get(name)
+            .map(|q| q.as_ref())
+    }
+
+    /// Returns the [`Cache`] shared by the routed services.
+    pub fn cache(&self) -> &Mutex<Cache> { &self.cache }
+
+    /// Returns the dead-letter [`Queue`], if configured.
+    pub fn dead_letter_queue(&self) -> Option<&Queue> { self.dead_letter.as_deref() }
+
+    /// Returns a configuration value, if set.
+    pub fn config(&self, key: &str) -> Option<&str> {
+        self.config
+            .get(key)
+            .map(|v| v.as_str())
+    }
 }
 
 //  +------------+
@@ -88,7 +1154,73 @@ impl Router {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {
+        super::*,
+        crate::{
+            Address,
+            BreakerPolicy,
+            Payload,
+            Registry,
+            Service,
+            ServiceBox,
+            service_fn,
+        },
+        std::time::Instant,
+    };
+
+    #[derive(Debug)]
+    struct Front(Address);
+
+    impl Service for Front {
+        fn address(&self) -> &Address { &self.0 }
+
+        fn duplicate(&self) -> ServiceBox { Box::new(Self(self.0.clone())) }
+
+        fn process(&self, msg: Envelope) -> Result<Reply> {
+            self.process_with(msg, &ServiceContext::detached())
+        }
+
+        fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+            let prefix = ctx
+                .config("prefix")
+                .unwrap_or_default();
+            let backend = Address::parse("http://back.com")?;
+            let call = Envelope::new(
+                self.0.clone(),
+                backend,
+                msg.payload().clone(),
+            );
+
+            let answer = ctx.route(call)?.unwrap();
+            let payload = format!(
+                "{prefix}{}",
+                String::from_utf8_lossy(answer.payload())
+            );
+            Ok(Some(msg.into_reply(Payload::from(payload))))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Relay(Address);
+
+    impl Service for Relay {
+        fn address(&self) -> &Address { &self.0 }
+
+        fn duplicate(&self) -> ServiceBox { Box::new(Self(self.0.clone())) }
+
+        fn process(&self, _msg: Envelope) -> Result<Reply> { Ok(None) }
+
+        fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+            ctx.route(msg.into_reply(Payload::from("relayed")))?;
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn router_is_thread_safe() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Router>();
+    }
 
     #[test]
     fn build_default_router() {
@@ -112,6 +1244,147 @@ mod tests {
         assert!(list.is_empty());
     }
 
+    #[test]
+    fn service_routes_through_context() -> Result<()> {
+        let front = Address::parse("http://front.com")?;
+        let back = Address::parse("http://back.com")?;
+        let registry = Registry::builder()
+            .register(Front(front.clone()))
+            .register(service_fn(back, |msg| {
+                let payload = msg
+                    .payload()
+                    .to_ascii_uppercase();
+                Ok(Some(msg.into_reply(Payload::from(payload))))
+            }))
+            .build();
+
+        let router = Router::builder()
+            .registry(registry)
+            .config("prefix", "> ")
+            .build();
+
+        let client = Address::parse("http://client.com")?;
+        let msg = Envelope::new(client, front, Payload::from("hello"));
+        let reply = router.route(msg)?.unwrap();
+        assert_eq!(reply.payload(), &Payload::from("> HELLO"));
+        Ok(())
+    }
+
+    #[test]
+    fn nested_failures_are_dead_lettered_once() -> Result<()> {
+        let front = Address::parse("http://front.com")?;
+        let back = Address::parse("http://back.com")?;
+        let client = Address::parse("http://client.com")?;
+
+        for timeout in [None, Some(Duration::from_secs(5))] {
+            let registry = Registry::builder()
+                .register(Front(front.clone()))
+                .register(service_fn(back.clone(), |_| {
+                    Err(Error::ProcessorNotFound("/".to_string()))
+                }))
+                .build();
+            let dead_letters = Arc::new(Queue::default());
+            let mut builder = Router::builder()
+                .registry(registry)
+                .retry("*", RetryPolicy::new(1))
+                .dead_letter(dead_letters.clone());
+            if let Some(timeout) = timeout {
+                builder = builder.timeout("*", timeout);
+            }
+            let router = builder.build();
+
+            let msg = Envelope::new(client.clone(), front.clone(), Payload::new());
+            assert!(matches!(
+                router.route(msg),
+                Err(Error::ProcessorNotFound(_))
+            ));
+            assert_eq!(dead_letters.len(), 1);
+            let dead = dead_letters
+                .dequeue()?
+                .unwrap();
+            assert_eq!(dead.destination(), &front);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn router_resources() -> Result<()> {
+        let queue = Arc::new(Queue::default());
+        let router = Router::builder()
+            .queue("audit", queue.clone())
+            .config("region", "eu")
+            .build();
+
+        assert!(
+            router
+                .queue("audit")
+                .is_some()
+        );
+        assert!(
+            router
+                .queue("missing")
+                .is_none()
+        );
+        assert_eq!(router.config("region"), Some("eu"));
+        assert!(
+            router
+                .config("missing")
+                .is_none()
+        );
+
+        let ctx = ServiceContext::new(&router);
+        assert!(ctx.queue("audit").is_some());
+        assert!(ctx.cache().is_some());
+        assert_eq!(ctx.config("region"), Some("eu"));
+        Ok(())
+    }
+
+    #[test]
+    fn send_and_wait_for_replies() -> Result<()> {
+        let client = Address::parse("http://client.com")?;
+        let echo = Address::parse("http://echo.com")?;
+        let relay = Address::parse("http://relay.com")?;
+        let silent = Address::parse("http://silent.com")?;
+        let registry = Registry::builder()
+            .register(service_fn(echo.clone(), |msg| {
+                let payload = msg.payload().clone();
+                Ok(Some(msg.into_reply(payload)))
+            }))
+            .register(Relay(relay.clone()))
+            .register(service_fn(silent.clone(), |_| Ok(None)))
+            .build();
+        let router = Router::builder()
+            .registry(registry)
+            .build();
+        let timeout = Duration::from_millis(20);
+
+        // Replied right away.
+        let msg = Envelope::new(client.clone(), echo, Payload::from("hi"));
+        let reply = router.send_and_wait(msg, timeout)?;
+        assert_eq!(reply.payload(), &Payload::from("hi"));
+        assert!(
+            reply
+                .correlation_id()
+                .is_some()
+        );
+
+        // Replied with an independent envelope routed back.
+        let msg = Envelope::new(client.clone(), relay, Payload::from("hi"))
+            .header(CORRELATION_HEADER, "42");
+        let reply = router.send_and_wait(msg, timeout)?;
+        assert_eq!(reply.correlation_id(), Some("42"));
+        assert_eq!(reply.payload(), &Payload::from("relayed"));
+
+        // Never replied.
+        let msg = Envelope::new(client, silent, Payload::new());
+        assert!(matches!(
+            router.send_and_wait(msg, timeout),
+            Err(Error::TimedOut(_))
+        ));
+        assert_eq!(router.pending_replies(), 0);
+        Ok(())
+    }
+
     #[test]
     fn build_router_with_registry() {
         let registry = Registry::builder().build();
@@ -124,4 +1397,244 @@ mod tests {
         assert!(list.is_empty());
         assert_eq!(router.balancer.strategy(), "round_robin");
     }
+
+    #[test]
+    fn broadcast_to_every_instance() -> Result<()> {
+        let cache = Address::parse("http://cache.com")?;
+        let mut builder = Registry::builder();
+        for id in ["a", "b", "c"] {
+            builder = builder.register(service_fn(cache.clone(), move |msg| {
+                if id == "b" {
+                    return Err(Error::ProcessorNotFound("/".to_string()));
+                }
+                Ok(Some(msg.into_reply(Payload::from(id))))
+            }));
+        }
+        let router = Router::builder()
+            .registry(builder.build())
+            .build();
+
+        let client = Address::parse("http://client.com")?;
+        let msg = Envelope::new(client.clone(), cache, Payload::from("flush"));
+        let replies = router.broadcast(msg)?;
+        assert_eq!(replies.len(), 3);
+        assert!(
+            replies
+                .iter()
+                .all(|(key, _)| key.as_ref() == "http://cache.com")
+        );
+        assert_eq!(
+            replies[0]
+                .1
+                .as_ref()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .payload(),
+            &Payload::from("a")
+        );
+        assert!(replies[1].1.is_err());
+        assert!(replies[2].1.is_ok());
+
+        let unknown = Address::parse("http://unknown.com")?;
+        let msg = Envelope::new(client, unknown, Payload::new());
+        assert!(matches!(
+            router.broadcast(msg),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn broadcast_and_multicast_trip_circuits() -> Result<()> {
+        let flaky = Address::parse("http://flaky.com")?;
+        let mut builder = Registry::builder();
+        for up in [false, true] {
+            builder = builder.register(service_fn(
+                flaky.clone(),
+                move |_| match up {
+                    true => Ok(None),
+                    false => Err(Error::Unavailable("down".to_string())),
+                },
+            ));
+        }
+        let router = Router::builder()
+            .registry(builder.build())
+            .circuit_breaker(BreakerPolicy::default().consecutive_failures(1))
+            .build();
+        let client = Address::parse("http://client.com")?;
+        let msg = Envelope::new(client, flaky.clone(), Payload::new());
+
+        let replies = router.broadcast(msg.clone())?;
+        assert!(matches!(
+            replies[0].1,
+            Err(Error::Unavailable(_))
+        ));
+        assert_eq!(
+            router.circuit_states(&flaky),
+            Some(vec![
+                CircuitState::Open,
+                CircuitState::Closed
+            ])
+        );
+        let replies = router.broadcast(msg.clone())?;
+        assert!(matches!(
+            replies[0].1,
+            Err(Error::CircuitOpen)
+        ));
+        assert!(replies[1].1.is_ok());
+
+        // Multicast selects around the open circuit.
+        for _ in 0..2 {
+            let replies = router.multicast("http://flaky*", msg.clone())?;
+            assert!(replies[0].1.is_ok());
+        }
+        assert_eq!(
+            router.circuit_states(&flaky),
+            Some(vec![
+                CircuitState::Open,
+                CircuitState::Closed
+            ])
+        );
+
+        // And its failures trip the circuit of the selected instance.
+        router
+            .registry()
+            .deregister(&flaky)?;
+        router
+            .registry()
+            .register(service_fn(flaky.clone(), |_| {
+                Err(Error::Unavailable("down".to_string()))
+            }));
+        let replies = router.multicast("http://flaky*", msg.clone())?;
+        assert!(matches!(
+            replies[0].1,
+            Err(Error::Unavailable(_))
+        ));
+        let replies = router.multicast("http://flaky*", msg)?;
+        assert!(matches!(
+            replies[0].1,
+            Err(Error::CircuitOpen)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn panicking_service_fails_the_same_with_a_timeout() -> Result<()> {
+        let boom = Address::parse("http://boom.com")?;
+        let registry = || {
+            Registry::builder()
+                .register(service_fn(boom.clone(), |_| panic!("boom")))
+                .build()
+        };
+        let untimed = Router::builder()
+            .registry(registry())
+            .build();
+        let timed = Router::builder()
+            .registry(registry())
+            .timeout("*", Duration::from_secs(5))
+            .build();
+
+        let client = Address::parse("http://client.com")?;
+        for router in [untimed, timed] {
+            let msg = Envelope::new(client.clone(), boom.clone(), Payload::new());
+            assert!(matches!(
+                router.route(msg),
+                Err(Error::TaskFailed(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn retries_stop_at_the_deadline() -> Result<()> {
+        let down = Address::parse("http://down.com")?;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let registry = Registry::builder()
+            .register(service_fn(down.clone(), move |_| {
+                counted.fetch_add(1, Ordering::Relaxed);
+                Err(Error::Unavailable("down".to_string()))
+            }))
+            .build();
+        let policy = RetryPolicy::new(5).backoff(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+        );
+        let router = Router::builder()
+            .registry(registry)
+            .retry("*", policy)
+            .build();
+
+        let client = Address::parse("http://client.com")?;
+        let msg = Envelope::new(client, down, Payload::new())
+            .with_deadline(SystemTime::now() + Duration::from_millis(50));
+        let started = Instant::now();
+        assert!(matches!(
+            router.route(msg),
+            Err(Error::Unavailable(_))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn multicast_to_matching_services() -> Result<()> {
+        let echo = |msg: Envelope| {
+            let payload = msg.destination().to_string();
+            Ok(Some(msg.into_reply(Payload::from(payload))))
+        };
+        let registry = Registry::builder()
+            .register(service_fn(
+                Address::parse("http://us.cache.com")?,
+                echo,
+            ))
+            .register(service_fn(
+                Address::parse("http://eu.cache.com")?,
+                echo,
+            ))
+            .register(service_fn(
+                Address::parse("http://users.com")?,
+                echo,
+            ))
+            .build();
+        let router = Router::builder()
+            .registry(registry)
+            .build();
+
+        let client = Address::parse("http://client.com")?;
+        let msg = Envelope::new(client.clone(), client, Payload::new());
+        let replies = router.multicast("http://*.cache.com", msg.clone())?;
+        let payloads: Vec<(String, Payload)> = replies
+            .into_iter()
+            .map(|(key, reply)| {
+                let payload = reply
+                    .unwrap()
+                    .unwrap()
+                    .payload()
+                    .clone();
+                (key.to_string(), payload)
+            })
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![
+                (
+                    "http://eu.cache.com".to_string(),
+                    Payload::from("http://eu.cache.com")
+                ),
+                (
+                    "http://us.cache.com".to_string(),
+                    Payload::from("http://us.cache.com")
+                ),
+            ]
+        );
+
+        assert!(matches!(
+            router.multicast("grpc://*", msg),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
 }
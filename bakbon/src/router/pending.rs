@@ -0,0 +1,226 @@
+use {
+    crate::{
+        Address,
+        Envelope,
+        Error,
+        Result,
+        core::Recover,
+    },
+    std::{
+        collections::HashMap,
+        sync::{
+            Mutex,
+            atomic::{
+                AtomicU64,
+                Ordering,
+            },
+            mpsc::{
+                self,
+                Receiver,
+                Sender,
+            },
+        },
+        time::{
+            SystemTime,
+            UNIX_EPOCH,
+        },
+    },
+};
+
+/// Requests waiting for their reply, keyed by correlation id.
+///
+/// Each entry remembers the [`Address`] the reply is expected at, so a
+/// request forwarded with its correlation id is not mistaken for its own
+/// reply.
+#[derive(Debug, Default)]
+pub(super) struct PendingReplies {
+    waiters: Mutex<HashMap<String, Waiter>>,
+}
+
+/// A request waiting for its reply.
+#[derive(Debug)]
+struct Waiter {
+    reply_to: Address,
+    sender:   Sender<Envelope>,
+}
+
+impl PendingReplies {
+    /// Returns a new correlation id, unique within the process.
+    ///
+    /// The time prefix keeps ids from colliding with the ones of a
+    /// previous run still travelling through queues.
+    pub(super) fn next_id() -> String {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let count = NEXT.fetch_add(1, Ordering::Relaxed);
+        format!("{now:x}-{count:x}")
+    }
+
+    /// Registers a request waiting for a reply at a given [`Address`] and
+    /// returns the receiving end of its reply.
+    ///
+    /// Returns [`Error::DuplicateCorrelation`] if a request with the same
+    /// id is already waiting.
+    pub(super) fn register(&self, id: &str, reply_to: Address) -> Result<Receiver<Envelope>> {
+        let mut waiters = self.waiters.lock().recover();
+        if waiters.contains_key(id) {
+            return Err(Error::DuplicateCorrelation(id.to_string()));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        waiters.insert(
+            id.to_string(),
+            Waiter {
+                reply_to,
+                sender,
+            },
+        );
+        Ok(receiver)
+    }
+
+    /// Stops waiting for the reply of a request.
+    pub(super) fn cancel(&self, id: &str) {
+        self.waiters
+            .lock()
+            .recover()
+            .remove(id);
+    }
+
+    /// Hands a reply over to the request waiting for it.
+    ///
+    /// Returns the [`Envelope`] back when no request waits for it, so it
+    /// can be routed like any other message.
+    pub(super) fn resolve(&self, msg: Envelope) -> Option<Envelope> {
+        let Some(id) = msg.correlation_id()
+        else {
+            return Some(msg);
+        };
+
+        let mut waiters = self.waiters.lock().recover();
+        match waiters.get(id) {
+            Some(waiter) if &waiter.reply_to == msg.destination() => {
+                let waiter = waiters.remove(id).unwrap();
+                // The caller may have given up in the meantime.
+                let _ = waiter.sender.send(msg);
+                None
+            }
+            _ => Some(msg),
+        }
+    }
+
+    /// Returns the number of requests waiting for their reply.
+    pub(super) fn len(&self) -> usize {
+        self.waiters
+            .lock()
+            .recover()
+            .len()
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            CORRELATION_HEADER,
+            testing::message,
+        },
+    };
+
+    const CLIENT: &str = "http://client.com";
+    const SERVICE: &str = "http://service.com";
+
+    #[test]
+    fn unique_correlation_ids() {
+        assert_ne!(
+            PendingReplies::next_id(),
+            PendingReplies::next_id()
+        );
+    }
+
+    #[test]
+    fn resolve_pending_reply() -> Result<()> {
+        let pending = PendingReplies::default();
+        let replies = pending.register("42", Address::parse(CLIENT)?)?;
+        assert!(matches!(
+            pending.register("42", Address::parse(CLIENT)?),
+            Err(Error::DuplicateCorrelation(_))
+        ));
+        assert_eq!(pending.len(), 1);
+
+        // The request itself carries the id but goes to the service.
+        let request = message(CLIENT, SERVICE, "")?.header(CORRELATION_HEADER, "42");
+        assert!(
+            pending
+                .resolve(request)
+                .is_some()
+        );
+
+        let unknown = message(SERVICE, CLIENT, "")?.header(CORRELATION_HEADER, "7");
+        assert!(
+            pending
+                .resolve(unknown)
+                .is_some()
+        );
+
+        let reply = message(SERVICE, CLIENT, "")?.header(CORRELATION_HEADER, "42");
+        assert!(
+            pending
+                .resolve(reply)
+                .is_none()
+        );
+        assert_eq!(
+            replies
+                .try_recv()
+                .unwrap()
+                .correlation_id(),
+            Some("42")
+        );
+        assert_eq!(pending.len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn cancel_pending_reply() -> Result<()> {
+        let pending = PendingReplies::default();
+        let _replies = pending.register("42", Address::parse(CLIENT)?)?;
+        pending.cancel("42");
+
+        let late = message(SERVICE, CLIENT, "")?.header(CORRELATION_HEADER, "42");
+        assert!(
+            pending
+                .resolve(late)
+                .is_some()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn poisoned_waiters_still_resolve() -> Result<()> {
+        let pending = PendingReplies::default();
+        let replies = pending.register("42", Address::parse(CLIENT)?)?;
+        let _ = std::panic::catch_unwind(|| {
+            let _waiters = pending.waiters.lock();
+            panic!("poison the waiters");
+        });
+
+        pending.cancel("7");
+        let reply = message(SERVICE, CLIENT, "")?.header(CORRELATION_HEADER, "42");
+        assert!(
+            pending
+                .resolve(reply)
+                .is_none()
+        );
+        assert!(replies.try_recv().is_ok());
+        assert_eq!(pending.len(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use {
+    super::random,
+    crate::Error,
+    std::{
+        fmt,
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+/// Header carrying the attempt number of an [`Envelope`](crate::Envelope)
+/// delivered under a [`RetryPolicy`], starting at `1`.
+pub const ATTEMPT_HEADER: &str = "x-attempt";
+
+/// Predicate telling whether an [`Error`] is worth another attempt.
+type RetryIf = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// How the [`Router`](crate::Router) retries a failed delivery.
+///
+/// Each retry goes to another instance of the
+/// [`Service`](crate::Service) selected by the
+/// [`Balancer`](crate::Balancer), when there is one left to try, after an
+/// exponential backoff: the delay doubles after every attempt, from the
+/// base delay up to the maximum delay. With jitter (the default), each
+/// delay is drawn between half and all of its value, so that callers
+/// failing together do not retry together.
+///
+/// Only [retryable](Error::is_retryable) errors are retried by default;
+/// use [`retry_if()`](RetryPolicy::retry_if) to choose otherwise.
+///
+/// Policies are set per route when building the
+/// [`Router`](crate::Router), with `RouterBuilder::retry()`.
+///
+/// # Examples
+///
+/// ```rust
+/// use {
+///     bakbon::*,
+///     std::time::Duration,
+/// };
+///
+/// let policy = RetryPolicy::new(3)
+///     .backoff(Duration::from_millis(10), Duration::from_millis(100))
+///     .jitter(false);
+///
+/// assert_eq!(policy.max_attempts(), 3);
+/// assert_eq!(policy.delay(1), Duration::from_millis(10));
+/// assert_eq!(policy.delay(2), Duration::from_millis(20));
+/// assert!(policy.is_retryable(&Error::Unavailable("restarting".into())));
+/// assert!(!policy.is_retryable(&Error::InvalidAddress));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay:   Duration,
+    max_delay:    Duration,
+    jitter:       bool,
+    retry_if:     RetryIf,
+}
+
+impl RetryPolicy {
+    /// Creates a policy making at most `max_attempts` deliveries, the
+    /// first one included.
+    ///
+    /// A policy always makes at least one attempt. Backoff starts at
+    /// 50ms and is capped at 5s, with jitter.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay:   Duration::from_millis(50),
+            max_delay:    Duration::from_secs(5),
+            jitter:       true,
+            retry_if:     Arc::new(Error::is_retryable),
+        }
+    }
+
+    /// Sets the delay before the first retry and the maximum delay between
+    /// two attempts.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max.max(base);
+        self
+    }
+
+    /// Enables or disables the jitter applied to the backoff delays.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Sets the predicate telling which errors are retried.
+    pub fn retry_if<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Arc::new(predicate);
+        self
+    }
+
+    /// Returns the maximum number of attempts, the first one included.
+    pub fn max_attempts(&self) -> u32 { self.max_attempts }
+
+    /// Returns whether an [`Error`] is worth another attempt.
+    pub fn is_retryable(&self, error: &Error) -> bool { (self.retry_if)(error) }
+
+    /// Returns the backoff delay after a given failed attempt, starting at
+    /// `1`, before jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+
+    /// Returns the time to wait after a given failed attempt, with jitter
+    /// if enabled.
+    pub(super) fn wait(&self, attempt: u32) -> Duration {
+        let delay = self.delay(attempt);
+        if !self.jitter {
+            return delay;
+        }
+
+        let half = delay / 2;
+        let spread = (delay - half).as_nanos() as u64;
+        half + Duration::from_nanos(random::next() % spread.saturating_add(1))
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Returns a policy making up to 3 attempts.
+    fn default() -> Self { Self::new(3) }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_retry_policy() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.max_attempts(), 3);
+        assert!(policy.is_retryable(&Error::TimedOut(Duration::ZERO)));
+        assert!(!policy.is_retryable(&Error::ServiceNotFound));
+        assert_eq!(RetryPolicy::new(0).max_attempts(), 1);
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy::new(10)
+            .backoff(
+                Duration::from_millis(100),
+                Duration::from_millis(500),
+            )
+            .jitter(false);
+
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(64), Duration::from_millis(500));
+        assert_eq!(policy.wait(2), policy.delay(2));
+    }
+
+    #[test]
+    fn jittered_backoff() {
+        let policy = RetryPolicy::new(3).backoff(
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+        );
+
+        for _ in 0..100 {
+            let wait = policy.wait(2);
+            assert!(wait >= Duration::from_millis(100));
+            assert!(wait <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn custom_retryable_errors() {
+        let policy =
+            RetryPolicy::new(2).retry_if(|error| matches!(error, Error::ProcessorNotFound(_)));
+
+        assert!(policy.is_retryable(&Error::ProcessorNotFound("/".into())));
+        assert!(!policy.is_retryable(&Error::Unavailable("down".into())));
+    }
+}
@@ -0,0 +1,473 @@
+use {
+    super::Explanation,
+    crate::{
+        Address,
+        Envelope,
+        Payload,
+        Protocol,
+        registry::matches_glob,
+    },
+    std::{
+        fmt,
+        sync::Arc,
+    },
+};
+
+/// Predicate checked on an [`Envelope`].
+type Check = Arc<dyn Fn(&Envelope) -> bool + Send + Sync>;
+
+/// Condition a [`Rule`] checks on an [`Envelope`].
+///
+/// Each condition carries a description, reported by
+/// [`Router::explain()`](crate::Router::explain) when it is not met.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let msg = Envelope::new(
+///     Address::parse("http://eu.client.com")?,
+///     Address::parse("grpc://orders")?,
+///     Payload::from("order"),
+/// )
+/// .header("x-tenant", "acme");
+///
+/// assert!(Condition::header("x-tenant", "acme").is_met(&msg));
+/// assert!(Condition::source("http://eu.*").is_met(&msg));
+/// assert!(Condition::protocol(Protocol::Grpc).is_met(&msg));
+/// assert!(!Condition::has_header("x-canary").is_met(&msg));
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Clone)]
+pub struct Condition {
+    description: String,
+    check:       Check,
+}
+
+impl Condition {
+    /// Met when a header has a given value.
+    pub fn header(name: &str, value: &str) -> Self {
+        let (header, expected) = (name.to_string(), value.to_string());
+        Self::new(
+            format!("header {name} = {value}"),
+            move |msg| msg.get_header(&header) == Some(expected.as_str()),
+        )
+    }
+
+    /// Met when a header is set, whatever its value.
+    pub fn has_header(name: &str) -> Self {
+        let header = name.to_string();
+        Self::new(format!("header {name} is set"), move |msg| {
+            msg.get_header(&header)
+                .is_some()
+        })
+    }
+
+    /// Met when the source [`Address`] matches a glob pattern.
+    ///
+    /// See [`ServiceKey::matches()`](crate::ServiceKey::matches) for the
+    /// pattern syntax.
+    pub fn source(pattern: &str) -> Self {
+        let glob = pattern.to_string();
+        Self::new(
+            format!("source matches {pattern}"),
+            move |msg| matches_glob(&glob, &msg.source().to_string()),
+        )
+    }
+
+    /// Met when the destination [`Address`] matches a glob pattern.
+    pub fn destination(pattern: &str) -> Self {
+        let glob = pattern.to_string();
+        Self::new(
+            format!("destination matches {pattern}"),
+            move |msg| matches_glob(&glob, &msg.destination().to_string()),
+        )
+    }
+
+    /// Met when the destination [`Address`] uses a given [`Protocol`].
+    pub fn protocol(protocol: Protocol) -> Self {
+        Self::new(
+            format!("protocol is {protocol}"),
+            move |msg| msg.destination().scheme() == &protocol,
+        )
+    }
+
+    /// Met when a predicate holds on the [`Payload`].
+    pub fn payload<F>(description: &str, predicate: F) -> Self
+    where
+        F: Fn(&Payload) -> bool + Send + Sync + 'static,
+    {
+        Self::new(description.to_string(), move |msg| {
+            predicate(msg.payload())
+        })
+    }
+
+    /// Met when the [`Payload`] is a JSON document whose field at a JSON
+    /// pointer (e.g. `/customer/tier`) equals a value.
+    #[cfg(feature = "json")]
+    pub fn json_field(pointer: &str, value: impl Into<serde_json::Value>) -> Self {
+        let (field, expected) = (pointer.to_string(), value.into());
+        Self::new(
+            format!("json {pointer} = {expected}"),
+            move |msg| {
+                serde_json::from_slice::<serde_json::Value>(msg.payload())
+                    .is_ok_and(|json| json.pointer(&field) == Some(&expected))
+            },
+        )
+    }
+
+    fn new<F>(description: String, check: F) -> Self
+    where
+        F: Fn(&Envelope) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            description,
+            check: Arc::new(check),
+        }
+    }
+
+    /// Returns whether the condition is met by a message.
+    pub fn is_met(&self, msg: &Envelope) -> bool { (self.check)(msg) }
+
+    /// Returns the description of the condition.
+    pub fn description(&self) -> &str { &self.description }
+}
+
+impl fmt::Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Condition")
+            .field(&self.description)
+            .finish()
+    }
+}
+
+/// What a matching [`Rule`] does to the destination of a message.
+#[derive(Debug, Clone)]
+enum Action {
+    // Replaces the whole destination.
+    Redirect(Address),
+    // Replaces the scheme and authority, keeping the path.
+    Rebase(Address),
+}
+
+/// Routing rule picking the destination of the messages meeting all its
+/// [`Condition`]s.
+///
+/// Rules are evaluated by the [`Router`](crate::Router) before the
+/// [`Registry`](crate::Registry) lookup, by descending priority (`0` by
+/// default), then in the order they were added. The first matching rule
+/// applies, and the others are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let eu = Address::parse("http://orders-eu")?;
+/// let rule = Rule::rebase("eu-orders", eu)
+///     .when(Condition::destination("http://orders/*"))
+///     .when(Condition::header("x-region", "eu"))
+///     .priority(10);
+///
+/// let router = Router::builder()
+///     .rule(rule)
+///     .build();
+///
+/// let msg = Envelope::new(
+///     Address::parse("http://client.com")?,
+///     Address::parse("http://orders/orders/42")?,
+///     Payload::new(),
+/// )
+/// .header("x-region", "eu");
+///
+/// let explanation = router.explain(&msg);
+/// assert_eq!(explanation.rule(), Some("eu-orders"));
+/// assert_eq!(
+///     explanation.destination().to_string(),
+///     "http://orders-eu/orders/42"
+/// );
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rule {
+    name:       String,
+    priority:   i32,
+    conditions: Vec<Condition>,
+    action:     Action,
+}
+
+impl Rule {
+    /// Creates a rule sending the matching messages to a destination.
+    pub fn redirect(name: &str, destination: Address) -> Self {
+        Self::new(name, Action::Redirect(destination))
+    }
+
+    /// Creates a rule moving the matching messages onto the scheme and
+    /// authority of an [`Address`], keeping their path, query and
+    /// fragment.
+    pub fn rebase(name: &str, base: Address) -> Self { Self::new(name, Action::Rebase(base)) }
+
+    fn new(name: &str, action: Action) -> Self {
+        Self {
+            name: name.to_string(),
+            priority: 0,
+            conditions: Vec::new(),
+            action,
+        }
+    }
+
+    /// Adds a [`Condition`] the messages must meet. A rule without
+    /// conditions matches every message.
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.conditions
+            .push(condition);
+        self
+    }
+
+    /// Sets the priority of the rule; higher priorities are evaluated
+    /// first.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Returns the name of the rule.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns the first [`Condition`] a message does not meet, if any.
+    fn unmet(&self, msg: &Envelope) -> Option<&Condition> {
+        self.conditions
+            .iter()
+            .find(|condition| !condition.is_met(msg))
+    }
+
+    /// Returns the destination of a matching message.
+    fn destination(&self, msg: &Envelope) -> Address {
+        match &self.action {
+            Action::Redirect(destination) => destination.clone(),
+            Action::Rebase(base) => msg.destination().rebase(base),
+        }
+    }
+}
+
+/// Outcome of the evaluation of a [`Rule`] on a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCheck {
+    rule:     String,
+    priority: i32,
+    unmet:    Option<String>,
+}
+
+impl RuleCheck {
+    /// Returns the name of the evaluated [`Rule`].
+    pub fn rule(&self) -> &str { &self.rule }
+
+    /// Returns the priority of the evaluated [`Rule`].
+    pub fn priority(&self) -> i32 { self.priority }
+
+    /// Returns whether the [`Rule`] matched.
+    pub fn matched(&self) -> bool { self.unmet.is_none() }
+
+    /// Returns the description of the first [`Condition`] which was not
+    /// met, if the [`Rule`] did not match.
+    pub fn unmet(&self) -> Option<&str> { self.unmet.as_deref() }
+}
+
+/// Routing rules of a [`Router`](crate::Router), in evaluation order.
+#[derive(Debug, Default)]
+pub(super) struct Rules(Vec<Rule>);
+
+impl Rules {
+    /// Adds a rule after the ones of the same or a higher priority.
+    pub(super) fn add(&mut self, rule: Rule) {
+        let index = self
+            .0
+            .partition_point(|other| other.priority >= rule.priority);
+        self.0.insert(index, rule);
+    }
+
+    /// Returns whether no rule is set.
+    pub(super) fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Sends a message to the destination of the first matching rule, if
+    /// any.
+    pub(super) fn apply(&self, msg: Envelope) -> Envelope {
+        match self
+            .0
+            .iter()
+            .find(|rule| rule.unmet(&msg).is_none())
+        {
+            Some(rule) => {
+                let destination = rule.destination(&msg);
+                msg.redirect(destination)
+            }
+            None => msg,
+        }
+    }
+
+    /// Evaluates the rules on a message without applying them.
+    pub(super) fn explain(&self, msg: &Envelope) -> Explanation {
+        let mut checks = Vec::new();
+        for rule in &self.0 {
+            let unmet = rule.unmet(msg);
+            checks.push(RuleCheck {
+                rule:     rule.name.clone(),
+                priority: rule.priority,
+                unmet:    unmet.map(|condition| condition.description.clone()),
+            });
+            if unmet.is_none() {
+                return Explanation::new(
+                    rule.destination(msg),
+                    Some(rule.name.clone()),
+                    checks,
+                );
+            }
+        }
+
+        Explanation::new(msg.destination().clone(), None, checks)
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Result,
+            testing::message,
+        },
+    };
+
+    #[test]
+    fn conditions() -> Result<()> {
+        let msg = message(
+            "http://eu.client.com",
+            "grpc://orders/42",
+            "order",
+        )?
+        .header("x-tenant", "acme");
+
+        assert!(Condition::header("x-tenant", "acme").is_met(&msg));
+        assert!(!Condition::header("x-tenant", "other").is_met(&msg));
+        assert!(Condition::has_header("x-tenant").is_met(&msg));
+        assert!(Condition::source("http://eu.*").is_met(&msg));
+        assert!(!Condition::source("http://us.*").is_met(&msg));
+        assert!(Condition::destination("grpc://orders/*").is_met(&msg));
+        assert!(Condition::protocol(Protocol::Grpc).is_met(&msg));
+        assert!(!Condition::protocol(Protocol::Tcp).is_met(&msg));
+
+        let order = Condition::payload("payload is an order", |payload| {
+            payload.as_ref() == b"order"
+        });
+        assert!(order.is_met(&msg));
+        assert_eq!(order.description(), "payload is an order");
+        assert_eq!(
+            Condition::header("x-tenant", "acme").description(),
+            "header x-tenant = acme"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_field_condition() -> Result<()> {
+        let gold = Condition::json_field("/customer/tier", "gold");
+        let msg = message("http://client.com", "http://orders", "order")?;
+
+        let msg = msg.with_payload(Payload::from(
+            r#"{"customer":{"tier":"gold"}}"#,
+        ));
+        assert!(gold.is_met(&msg));
+
+        let msg = msg.with_payload(Payload::from(
+            r#"{"customer":{"tier":"free"}}"#,
+        ));
+        assert!(!gold.is_met(&msg));
+
+        let msg = msg.with_payload(Payload::from("not json"));
+        assert!(!gold.is_met(&msg));
+        assert!(
+            Condition::json_field("/count", 3)
+                .is_met(&msg.with_payload(Payload::from(r#"{"count":3}"#)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rules_by_priority() -> Result<()> {
+        let mut rules = Rules::default();
+        rules.add(Rule::redirect(
+            "catch-all",
+            Address::parse("http://default")?,
+        ));
+        rules.add(
+            Rule::rebase("canary", Address::parse("http://orders-v2")?)
+                .when(Condition::header("x-canary", "true"))
+                .priority(10),
+        );
+        rules.add(
+            Rule::redirect("tenant", Address::parse("http://acme")?)
+                .when(Condition::header("x-tenant", "acme"))
+                .priority(10),
+        );
+
+        let msg = message(
+            "http://client.com",
+            "http://orders/42",
+            "order",
+        )?;
+        let explanation = rules.explain(&msg);
+        assert_eq!(explanation.rule(), Some("catch-all"));
+        assert_eq!(
+            explanation
+                .checks()
+                .iter()
+                .map(RuleCheck::rule)
+                .collect::<Vec<_>>(),
+            ["canary", "tenant", "catch-all"]
+        );
+        assert_eq!(
+            explanation.checks()[0].unmet(),
+            Some("header x-canary = true")
+        );
+        assert!(explanation.checks()[2].matched());
+
+        let msg = msg
+            .header("x-canary", "true")
+            .header("x-tenant", "acme");
+        assert_eq!(
+            rules
+                .apply(msg)
+                .destination()
+                .to_string(),
+            "http://orders-v2/42"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn no_matching_rule() -> Result<()> {
+        let mut rules = Rules::default();
+        rules.add(
+            Rule::redirect("tenant", Address::parse("http://acme")?)
+                .when(Condition::has_header("x-tenant")),
+        );
+
+        let msg = message("http://client.com", "http://orders", "order")?;
+        let explanation = rules.explain(&msg);
+        assert_eq!(explanation.rule(), None);
+        assert_eq!(explanation.destination(), msg.destination());
+        assert_eq!(
+            rules
+                .apply(msg.clone())
+                .destination(),
+            msg.destination()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use {
+    super::{
+        MemorySagaStore,
+        Saga,
+        SagaStore,
+        Step,
+    },
+    std::sync::Arc,
+};
+
+/// Builder for constructing a [`Saga`].
+///
+/// Without a [`SagaStore`], the state of runs is kept in a
+/// [`MemorySagaStore`], which does not survive the process.
+pub struct SagaBuilder {
+    name:  String,
+    steps: Vec<Step>,
+    store: Option<Arc<dyn SagaStore>>,
+}
+
+impl SagaBuilder {
+    pub(super) fn new(name: &str) -> Self {
+        Self {
+            name:  name.to_string(),
+            steps: Vec::new(),
+            store: None,
+        }
+    }
+
+    /// Adds a [`Step`] after the previous ones.
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Sets the [`SagaStore`] the state of runs is saved to.
+    pub fn store(mut self, store: impl SagaStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Finalizes the builder and returns a [`Saga`].
+    pub fn build(self) -> Saga {
+        Saga {
+            name:  self.name,
+            steps: self.steps,
+            store: self
+                .store
+                .unwrap_or_else(|| Arc::new(MemorySagaStore::new())),
+        }
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            Result,
+        },
+    };
+
+    #[test]
+    fn build_saga() -> Result<()> {
+        let reserve = Address::parse("http://stock.com/reserve")?;
+        let release = Address::parse("http://stock.com/release")?;
+        let charge = Address::parse("http://payments.com/charge")?;
+        let saga = Saga::builder("checkout")
+            .step(Step::new("reserve", reserve.clone()).compensate_with(release.clone()))
+            .step(Step::new("charge", charge))
+            .build();
+
+        assert_eq!(saga.name(), "checkout");
+        assert_eq!(saga.steps().len(), 2);
+        assert_eq!(saga.steps()[0].name(), "reserve");
+        assert_eq!(saga.steps()[0].action(), &reserve);
+        assert_eq!(
+            saga.steps()[0].compensation(),
+            Some(&release)
+        );
+        assert_eq!(saga.steps()[1].compensation(), None);
+        assert!(saga.unfinished()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+mod builder;
+mod state;
+mod store;
+
+use {
+    super::pending::PendingReplies,
+    crate::{
+        Address,
+        DEADLINE_HEADER,
+        Envelope,
+        Error,
+        Result,
+        Router,
+    },
+    std::{
+        sync::Arc,
+        time::SystemTime,
+    },
+};
+pub use {
+    builder::SagaBuilder,
+    state::{
+        SagaState,
+        SagaStatus,
+        StepState,
+        StepStatus,
+    },
+    store::{
+        FileSagaStore,
+        MemorySagaStore,
+        SagaStore,
+    },
+};
+
+/// Header carrying the id of the [`Saga`] run an [`Envelope`] belongs to.
+pub const SAGA_ID_HEADER: &str = "x-saga-id";
+
+/// Header carrying the name of the [`Step`] an [`Envelope`] runs or
+/// compensates.
+pub const SAGA_STEP_HEADER: &str = "x-saga-step";
+
+/// Step of a [`Saga`]: the [`Address`] its action is sent to, and the one
+/// undoing it, if anything has to be undone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    name:         String,
+    action:       Address,
+    compensation: Option<Address>,
+}
+
+impl Step {
+    /// Creates a step sending its action to an [`Address`].
+    pub fn new(name: &str, action: Address) -> Self {
+        Self {
+            name: name.to_string(),
+            action,
+            compensation: None,
+        }
+    }
+
+    /// Sets the [`Address`] undoing the action when a later step fails.
+    pub fn compensate_with(mut self, compensation: Address) -> Self {
+        self.compensation = Some(compensation);
+        self
+    }
+
+    /// Returns the name of the step.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns the [`Address`] the action is sent to.
+    pub fn action(&self) -> &Address { &self.action }
+
+    /// Returns the [`Address`] undoing the action, if any.
+    pub fn compensation(&self) -> Option<&Address> { self.compensation.as_ref() }
+}
+
+/// Multi-step business flow run by
+/// [`Router::run_saga()`](crate::Router::run_saga), such as reserving
+/// stock, charging then shipping an order.
+///
+/// Each [`Step`] is a copy of the request
+/// [`redirect`](Envelope::redirect)ed to its action and
+/// [`route`](Router::route)d in order, tagged with the [`SAGA_ID_HEADER`]
+/// and [`SAGA_STEP_HEADER`]. A step fails when routing it returns an
+/// error. The completed steps are then compensated in reverse order, by
+/// routing a copy of the request to their compensation.
+///
+/// The [`SagaState`] of a run is saved to the [`SagaStore`] of the `Saga`
+/// after every step and compensation. A run interrupted by a crash, or
+/// [`Stuck`](SagaStatus::Stuck) on a failed compensation, is picked up
+/// where it stopped by
+/// [`Router::resume_saga()`](crate::Router::resume_saga): the step or
+/// compensation running at the time is sent again, so services should
+/// deduplicate them by [`SAGA_ID_HEADER`].
+///
+/// The [`deadline()`](Envelope::deadline) of the request bounds the steps
+/// and compensations of the run it starts, but is not saved: a resumed
+/// run is only bounded by the timeouts of its routes.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let reserve = Address::parse("http://stock.com/reserve")?;
+/// let release = Address::parse("http://stock.com/release")?;
+/// let charge = Address::parse("http://payments.com/charge")?;
+/// let saga = Saga::builder("checkout")
+///     .step(Step::new("reserve", reserve).compensate_with(release))
+///     .step(Step::new("charge", charge))
+///     .build();
+///
+/// let router = Router::builder().build();
+/// let request = Envelope::new(
+///     Address::parse("http://shop.com")?,
+///     Address::parse("http://orders.com")?,
+///     Payload::from("order 42"),
+/// );
+///
+/// // Nothing listens to the stock service: the run is unwound.
+/// let state = router.run_saga(&saga, request)?;
+/// assert_eq!(state.status(), SagaStatus::Compensated);
+/// assert_eq!(state.step("reserve").unwrap().status(), StepStatus::Failed);
+/// assert_eq!(saga.state(state.id())?.unwrap().status(), state.status());
+/// # Ok::<(), Error>(())
+/// ```
+pub struct Saga {
+    name:  String,
+    steps: Vec<Step>,
+    store: Arc<dyn SagaStore>,
+}
+
+impl Saga {
+    /// Returns a new [`SagaBuilder`] for a `Saga` with a given name.
+    pub fn builder(name: &str) -> SagaBuilder { SagaBuilder::new(name) }
+
+    /// Returns the name of the `Saga`.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns the steps, in order.
+    pub fn steps(&self) -> &[Step] { &self.steps }
+
+    /// Returns the [`SagaState`] of a run given its id, if any.
+    pub fn state(&self, id: &str) -> Result<Option<SagaState>> {
+        Ok(self
+            .store
+            .load(id)?
+            .filter(|state| state.saga == self.name))
+    }
+
+    /// Returns the [`SagaState`] of every run of this `Saga` still
+    /// running, compensating or stuck.
+    ///
+    /// After a crash, these are the runs to
+    /// [`resume`](crate::Router::resume_saga).
+    pub fn unfinished(&self) -> Result<Vec<SagaState>> {
+        Ok(self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|state| state.saga == self.name && !state.status.is_finished())
+            .collect())
+    }
+
+    /// Starts a run for a request and drives it to its end.
+    pub(super) fn start(&self, router: &Router, mut request: Envelope) -> Result<SagaState> {
+        let deadline = request.deadline();
+        request.remove_header(DEADLINE_HEADER);
+        let state = SagaState {
+            id: PendingReplies::next_id(),
+            saga: self.name.clone(),
+            request,
+            status: SagaStatus::Running,
+            steps: self
+                .steps
+                .iter()
+                .map(|step| StepState {
+                    name:   step.name.clone(),
+                    status: StepStatus::Pending,
+                    error:  None,
+                })
+                .collect(),
+        };
+        self.store.save(&state)?;
+        self.drive(router, state, deadline)
+    }
+
+    /// Picks up a stored run where it stopped and drives it to its end.
+    pub(super) fn resume(&self, router: &Router, id: &str) -> Result<SagaState> {
+        let mut state = self
+            .state(id)?
+            .ok_or_else(|| Error::SagaNotFound(id.to_string()))?;
+        let same_steps = state.steps.len() == self.steps.len()
+            && state
+                .steps
+                .iter()
+                .zip(&self.steps)
+                .all(|(state, step)| state.name == step.name);
+        if !same_steps {
+            return Err(Error::PersistenceFailed(format!(
+                "steps of saga '{}' changed since run {id} started",
+                self.name
+            )));
+        }
+
+        if state.status == SagaStatus::Stuck {
+            state.status = SagaStatus::Compensating;
+        }
+        self.drive(router, state, None)
+    }
+
+    /// Runs the pending steps, or the pending compensations, saving the
+    /// state after each of them.
+    fn drive(
+        &self,
+        router: &Router,
+        mut state: SagaState,
+        deadline: Option<SystemTime>,
+    ) -> Result<SagaState> {
+        loop {
+            match state.status {
+                SagaStatus::Running => {
+                    let Some(index) = state
+                        .steps
+                        .iter()
+                        .position(|step| step.status == StepStatus::Pending)
+                    else {
+                        state.status = SagaStatus::Completed;
+                        self.store.save(&state)?;
+                        return Ok(state);
+                    };
+
+                    let step = &self.steps[index];
+                    let msg = Self::message(&state, step, &step.action, deadline);
+                    let progress = &mut state.steps[index];
+                    match router.route(msg) {
+                        Ok(_) => progress.status = StepStatus::Done,
+                        Err(e) => {
+                            progress.status = StepStatus::Failed;
+                            progress.error = Some(e.to_string());
+                            state.status = SagaStatus::Compensating;
+                        }
+                    }
+                }
+                SagaStatus::Compensating => {
+                    let Some(index) = state
+                        .steps
+                        .iter()
+                        .rposition(|step| step.status == StepStatus::Done)
+                    else {
+                        state.status = SagaStatus::Compensated;
+                        self.store.save(&state)?;
+                        return Ok(state);
+                    };
+
+                    let step = &self.steps[index];
+                    let outcome = match &step.compensation {
+                        Some(compensation) => router
+                            .route(Self::message(
+                                &state,
+                                step,
+                                compensation,
+                                deadline,
+                            ))
+                            .map(drop),
+                        None => Ok(()),
+                    };
+                    let progress = &mut state.steps[index];
+                    match outcome {
+                        Ok(()) => {
+                            progress.status = StepStatus::Compensated;
+                            progress.error = None;
+                        }
+                        Err(e) => {
+                            progress.error = Some(e.to_string());
+                            state.status = SagaStatus::Stuck;
+                        }
+                    }
+                }
+                SagaStatus::Completed | SagaStatus::Compensated | SagaStatus::Stuck => {
+                    return Ok(state);
+                }
+            }
+            self.store.save(&state)?;
+        }
+    }
+
+    /// Returns the copy of the request sent for a step to an [`Address`],
+    /// with the deadline of the run if any.
+    fn message(
+        state: &SagaState,
+        step: &Step,
+        destination: &Address,
+        deadline: Option<SystemTime>,
+    ) -> Envelope {
+        let msg = state
+            .request
+            .clone()
+            .redirect(destination.clone())
+            .header(SAGA_ID_HEADER, &state.id)
+            .header(SAGA_STEP_HEADER, &step.name);
+        match deadline {
+            Some(deadline) => msg.with_deadline(deadline),
+            None => msg,
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::Envelope;
+
+/// Where a [`Saga`](super::Saga) run stands.
+///
+/// - [`Running`](SagaStatus::Running): its steps are being run.
+/// - [`Compensating`](SagaStatus::Compensating): a step failed, and the
+///   completed ones are being compensated in reverse order.
+/// - [`Completed`](SagaStatus::Completed): every step succeeded.
+/// - [`Compensated`](SagaStatus::Compensated): a step failed, and every
+///   completed step was compensated.
+/// - [`Stuck`](SagaStatus::Stuck): a compensation failed; resuming the run
+///   tries it again.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SagaStatus {
+    Running,
+    Compensating,
+    Completed,
+    Compensated,
+    Stuck,
+}
+
+impl SagaStatus {
+    /// Returns whether the run is over, successfully or not.
+    pub fn is_finished(&self) -> bool { matches!(self, Self::Completed | Self::Compensated) }
+
+    pub(super) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "running" => Some(Self::Running),
+            "compensating" => Some(Self::Compensating),
+            "completed" => Some(Self::Completed),
+            "compensated" => Some(Self::Compensated),
+            "stuck" => Some(Self::Stuck),
+            _ => None,
+        }
+    }
+}
+
+impl AsRef<str> for SagaStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Running => "running",
+            Self::Compensating => "compensating",
+            Self::Completed => "completed",
+            Self::Compensated => "compensated",
+            Self::Stuck => "stuck",
+        }
+    }
+}
+
+/// Where a step of a [`Saga`](super::Saga) run stands.
+///
+/// A step without compensation is marked
+/// [`Compensated`](StepStatus::Compensated) as soon as the run unwinds,
+/// since there is nothing to undo.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StepStatus {
+    Pending,
+    Done,
+    Failed,
+    Compensated,
+}
+
+impl StepStatus {
+    pub(super) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(Self::Pending),
+            "done" => Some(Self::Done),
+            "failed" => Some(Self::Failed),
+            "compensated" => Some(Self::Compensated),
+            _ => None,
+        }
+    }
+}
+
+impl AsRef<str> for StepStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Pending => "pending",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Compensated => "compensated",
+        }
+    }
+}
+
+/// Progress of one step of a [`Saga`](super::Saga) run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepState {
+    pub(super) name:   String,
+    pub(super) status: StepStatus,
+    pub(super) error:  Option<String>,
+}
+
+impl StepState {
+    /// Returns the name of the step.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns where the step stands.
+    pub fn status(&self) -> StepStatus { self.status }
+
+    /// Returns the error of the step, or of its compensation, if any.
+    pub fn error(&self) -> Option<&str> { self.error.as_deref() }
+}
+
+/// Persisted progress of a [`Saga`](super::Saga) run.
+///
+/// Saved to the [`SagaStore`](super::SagaStore) of the
+/// [`Saga`](super::Saga) after every step and compensation, so a run
+/// interrupted by a crash can be inspected, then resumed with
+/// [`Router::resume_saga()`](crate::Router::resume_saga).
+#[derive(Debug, Clone)]
+pub struct SagaState {
+    pub(super) id:      String,
+    pub(super) saga:    String,
+    pub(super) request: Envelope,
+    pub(super) status:  SagaStatus,
+    pub(super) steps:   Vec<StepState>,
+}
+
+impl SagaState {
+    /// Returns the id of the run.
+    pub fn id(&self) -> &str { &self.id }
+
+    /// Returns the name of the [`Saga`](super::Saga).
+    pub fn saga(&self) -> &str { &self.saga }
+
+    /// Returns the request the run was started with.
+    pub fn request(&self) -> &Envelope { &self.request }
+
+    /// Returns where the run stands.
+    pub fn status(&self) -> SagaStatus { self.status }
+
+    /// Returns the progress of every step, in order.
+    pub fn steps(&self) -> &[StepState] { &self.steps }
+
+    /// Returns the progress of a step given its name, if it exists.
+    pub fn step(&self, name: &str) -> Option<&StepState> {
+        self.steps
+            .iter()
+            .find(|step| step.name == name)
+    }
+
+    /// Returns the error of the failed step, if any.
+    pub fn error(&self) -> Option<&str> {
+        self.steps
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+            .and_then(StepState::error)
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saga_status_names() {
+        for status in [
+            SagaStatus::Running,
+            SagaStatus::Compensating,
+            SagaStatus::Completed,
+            SagaStatus::Compensated,
+            SagaStatus::Stuck,
+        ] {
+            assert_eq!(
+                SagaStatus::parse(status.as_ref()),
+                Some(status)
+            );
+        }
+        assert_eq!(SagaStatus::parse("unknown"), None);
+        assert!(SagaStatus::Compensated.is_finished());
+        assert!(!SagaStatus::Stuck.is_finished());
+    }
+
+    #[test]
+    fn step_status_names() {
+        for status in [
+            StepStatus::Pending,
+            StepStatus::Done,
+            StepStatus::Failed,
+            StepStatus::Compensated,
+        ] {
+            assert_eq!(
+                StepStatus::parse(status.as_ref()),
+                Some(status)
+            );
+        }
+        assert_eq!(StepStatus::parse("unknown"), None);
+    }
+}
@@ -0,0 +1,437 @@
+use {
+    super::{
+        SagaState,
+        SagaStatus,
+        StepState,
+        StepStatus,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Error,
+        Payload,
+        Result,
+    },
+    std::{
+        collections::HashMap,
+        fs,
+        io,
+        path::PathBuf,
+        sync::Mutex,
+    },
+};
+
+/// Storage of the [`SagaState`] of [`Saga`](super::Saga) runs.
+///
+/// The state of a run is saved after every step and compensation, so a
+/// store outliving the process lets runs interrupted by a crash be
+/// resumed. [`MemorySagaStore`] keeps them in memory, and
+/// [`FileSagaStore`] in a directory; implement this trait to keep them
+/// anywhere else.
+pub trait SagaStore: Send + Sync {
+    /// Saves the state of a run, replacing the previous one.
+    fn save(&self, state: &SagaState) -> Result<()>;
+
+    /// Returns the state of a run given its id, if any.
+    fn load(&self, id: &str) -> Result<Option<SagaState>>;
+
+    /// Returns the state of every run.
+    fn list(&self) -> Result<Vec<SagaState>>;
+}
+
+/// [`SagaStore`] keeping the state of runs in memory, for as long as the
+/// process lives.
+#[derive(Debug, Default)]
+pub struct MemorySagaStore {
+    states: Mutex<HashMap<String, SagaState>>,
+}
+
+impl MemorySagaStore {
+    /// Creates an empty store.
+    pub fn new() -> Self { Self::default() }
+}
+
+impl SagaStore for MemorySagaStore {
+    fn save(&self, state: &SagaState) -> Result<()> {
+        self.states
+            .lock()?
+            .insert(state.id.clone(), state.clone());
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Option<SagaState>> {
+        Ok(self
+            .states
+            .lock()?
+            .get(id)
+            .cloned())
+    }
+
+    fn list(&self) -> Result<Vec<SagaState>> {
+        Ok(self
+            .states
+            .lock()?
+            .values()
+            .cloned()
+            .collect())
+    }
+}
+
+/// [`SagaStore`] keeping the state of each run in a file of a directory,
+/// named after its id.
+///
+/// A state is written to a temporary file first, then renamed over the
+/// previous one, so a crash never leaves a half-written state behind.
+///
+/// Ids are checked to be ones the [`Router`](crate::Router) generates,
+/// made of lowercase hexadecimal digits and dashes, so an id from a
+/// request can never name a file outside the directory: no run is found
+/// under another id, and saving one fails.
+#[derive(Debug, Clone)]
+pub struct FileSagaStore {
+    dir: PathBuf,
+}
+
+impl FileSagaStore {
+    const EXTENSION: &str = "saga";
+
+    /// Creates a store in a directory, created if missing.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(failed)?;
+        Ok(Self { dir })
+    }
+
+    /// Returns the path of the file of a run, or `None` if the id is not
+    /// one the [`Router`](crate::Router) generates.
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        let generated = |c: char| c.is_ascii_digit() || ('a'..='f').contains(&c) || c == '-';
+        if id.is_empty() || !id.chars().all(generated) {
+            return None;
+        }
+        Some(
+            self.dir
+                .join(id)
+                .with_extension(Self::EXTENSION),
+        )
+    }
+}
+
+impl SagaStore for FileSagaStore {
+    fn save(&self, state: &SagaState) -> Result<()> {
+        let path = self
+            .path(&state.id)
+            .ok_or_else(|| Error::PersistenceFailed(format!("invalid saga id: {:?}", state.id)))?;
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, encode(state)).map_err(failed)?;
+        fs::rename(&temporary, &path).map_err(failed)
+    }
+
+    fn load(&self, id: &str) -> Result<Option<SagaState>> {
+        let Some(path) = self.path(id)
+        else {
+            return Ok(None);
+        };
+        match fs::read_to_string(path) {
+            Ok(text) => decode(&text).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(failed(e)),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<SagaState>> {
+        let mut states = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(failed)? {
+            let path = entry.map_err(failed)?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == Self::EXTENSION)
+            {
+                let text = fs::read_to_string(path).map_err(failed)?;
+                states.push(decode(&text)?);
+            }
+        }
+        Ok(states)
+    }
+}
+
+fn failed(error: io::Error) -> Error { Error::PersistenceFailed(error.to_string()) }
+
+fn corrupted(line: &str) -> Error {
+    Error::PersistenceFailed(format!("corrupted saga state: {line}"))
+}
+
+/// Writes a state as one tab-separated record per line.
+fn encode(state: &SagaState) -> String {
+    let request = &state.request;
+    let mut lines = vec![
+        vec!["saga", &state.saga],
+        vec!["id", &state.id],
+        vec!["status", state.status.as_ref()],
+    ];
+    let source = request.source().to_string();
+    let destination = request
+        .destination()
+        .to_string();
+    let payload = to_hex(request.payload());
+    lines.push(vec!["source", &source]);
+    lines.push(vec!["destination", &destination]);
+    lines.push(vec!["payload", &payload]);
+    for (key, value) in request.headers() {
+        lines.push(vec!["header", key, value]);
+    }
+    for step in &state.steps {
+        let mut line = vec!["step", &step.name, step.status.as_ref()];
+        line.extend(step.error.as_deref());
+        lines.push(line);
+    }
+
+    lines
+        .iter()
+        .map(|fields| {
+            fields
+                .iter()
+                .map(|field| escape(field))
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads a state written by [`encode()`].
+fn decode(text: &str) -> Result<SagaState> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut headers = Vec::new();
+    let mut steps = Vec::new();
+    for line in text.lines() {
+        let record: Vec<String> = line
+            .split('\t')
+            .map(unescape)
+            .collect();
+        match record.as_slice() {
+            [kind, key, value] if kind == "header" => headers.push((key.clone(), value.clone())),
+            [kind, name, status, error @ ..] if kind == "step" && error.len() <= 1 => {
+                steps.push(StepState {
+                    name:   name.clone(),
+                    status: StepStatus::parse(status).ok_or_else(|| corrupted(line))?,
+                    error:  error.first().cloned(),
+                })
+            }
+            [key, value] => {
+                fields.insert(key.clone(), value.clone());
+            }
+            _ => return Err(corrupted(line)),
+        }
+    }
+
+    let mut field = |name: &str| {
+        fields
+            .remove(name)
+            .ok_or_else(|| corrupted(name))
+    };
+    let saga = field("saga")?;
+    let id = field("id")?;
+    let status = field("status")?;
+    let status = SagaStatus::parse(&status).ok_or_else(|| corrupted(&status))?;
+    let source = Address::parse(field("source")?)?;
+    let destination = Address::parse(field("destination")?)?;
+    let payload = from_hex(&field("payload")?).ok_or_else(|| corrupted("payload"))?;
+
+    let mut request = Envelope::new(source, destination, Payload::from(payload));
+    for (key, value) in headers {
+        request.add_header(&key, &value);
+    }
+    Ok(SagaState {
+        id,
+        saga,
+        request,
+        status,
+        steps,
+    })
+}
+
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{
+            env,
+            process,
+        },
+    };
+
+    fn state() -> Result<SagaState> {
+        let request = Envelope::new(
+            Address::parse("http://shop.com")?,
+            Address::parse("http://orders.com/checkout")?,
+            Payload::from(vec![0, 1, 0xfe, b'\n']),
+        )
+        .header("x-order", "42\twith\ttabs")
+        .header("x-note", "multi\nline \\ note");
+
+        Ok(SagaState {
+            id: "18f2a6c-1".to_string(),
+            saga: "checkout".to_string(),
+            request,
+            status: SagaStatus::Compensating,
+            steps: vec![
+                StepState {
+                    name:   "reserve".to_string(),
+                    status: StepStatus::Done,
+                    error:  None,
+                },
+                StepState {
+                    name:   "charge".to_string(),
+                    status: StepStatus::Failed,
+                    error:  Some("card\tdeclined".to_string()),
+                },
+            ],
+        })
+    }
+
+    fn assert_same(loaded: &SagaState, state: &SagaState) {
+        assert_eq!(loaded.id(), state.id());
+        assert_eq!(loaded.saga(), state.saga());
+        assert_eq!(loaded.status(), state.status());
+        assert_eq!(loaded.steps(), state.steps());
+        assert_eq!(
+            loaded.request().headers(),
+            state.request().headers()
+        );
+        assert_eq!(
+            loaded.request().payload(),
+            state.request().payload()
+        );
+        assert_eq!(
+            loaded.request().destination(),
+            state.request().destination()
+        );
+    }
+
+    #[test]
+    fn memory_store() -> Result<()> {
+        let store = MemorySagaStore::new();
+        assert!(
+            store
+                .load("18f2a6c-1")?
+                .is_none()
+        );
+
+        let state = state()?;
+        store.save(&state)?;
+        assert_same(
+            &store
+                .load("18f2a6c-1")?
+                .unwrap(),
+            &state,
+        );
+        assert_eq!(store.list()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn file_store() -> Result<()> {
+        let dir = env::temp_dir().join(format!("bakbon-sagas-{}", process::id()));
+        let store = FileSagaStore::new(&dir)?;
+        assert!(
+            store
+                .load("18f2a6c-1")?
+                .is_none()
+        );
+
+        let mut state = state()?;
+        store.save(&state)?;
+        state.status = SagaStatus::Compensated;
+        store.save(&state)?;
+
+        let reopened = FileSagaStore::new(&dir)?;
+        assert_same(
+            &reopened
+                .load("18f2a6c-1")?
+                .unwrap(),
+            &state,
+        );
+        assert_eq!(reopened.list()?.len(), 1);
+
+        fs::remove_dir_all(dir).map_err(failed)?;
+        Ok(())
+    }
+
+    #[test]
+    fn file_store_rejects_foreign_ids() -> Result<()> {
+        let dir = env::temp_dir().join(format!("bakbon-saga-ids-{}", process::id()));
+        let store = FileSagaStore::new(&dir)?;
+
+        for id in ["", "../escape", "/etc/passwd", "run-1", "18F2A"] {
+            assert!(store.load(id)?.is_none());
+        }
+        let mut state = state()?;
+        state.id = "../../escape".to_string();
+        assert!(matches!(
+            store.save(&state),
+            Err(Error::PersistenceFailed(_))
+        ));
+        assert!(store.list()?.is_empty());
+
+        fs::remove_dir_all(dir).map_err(failed)?;
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_state() {
+        assert!(matches!(
+            decode("saga\tcheckout\nstatus\tlost"),
+            Err(Error::PersistenceFailed(_))
+        ));
+        assert!(from_hex("0g").is_none());
+        assert_eq!(unescape(&escape("a\\t\tb")), "a\\t\tb");
+    }
+}
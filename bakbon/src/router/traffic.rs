@@ -0,0 +1,495 @@
+use {
+    super::{
+        random,
+        worker,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Payload,
+        Reply,
+        Result,
+        ServiceKey,
+        core::Recover,
+    },
+    std::{
+        collections::HashMap,
+        sync::{
+            Mutex,
+            OnceLock,
+            mpsc::{
+                self,
+                SyncSender,
+            },
+        },
+        thread,
+    },
+};
+
+/// Header carrying the original destination of an [`Envelope`] mirrored
+/// to a shadow [`Service`](crate::Service).
+pub const MIRROR_HEADER: &str = "x-mirror-of";
+
+/// Number of messages whose copies can wait for the mirror worker, past
+/// which they are dropped.
+const MIRROR_BACKLOG: usize = 1024;
+
+/// Delivery of the copies of a message, run by the mirror worker.
+pub(super) type MirrorJob = Box<dyn FnOnce() + Send>;
+
+/// Rule sending part of the traffic of a route to another
+/// [`Service`](crate::Service), such as a canary release.
+///
+/// Messages carrying the header set with
+/// [`when_header()`](Split::when_header) always go to the target, the
+/// others go there with the given percentage. When several rules match a
+/// route, their percentages add up in the order they were added.
+///
+/// The target replaces the scheme and authority of the destination: its
+/// path, query and fragment are kept, so a
+/// [`Dispatcher`](crate::Dispatcher) keeps matching.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let v2 = Address::parse("http://orders-v2")?;
+/// let canary = Split::to(v2)
+///     .percent(5.0)
+///     .when_header("x-canary", "true");
+///
+/// let router = Router::builder()
+///     .split("http://orders-v1", canary)
+///     .build();
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Split {
+    target:  Address,
+    percent: f64,
+    header:  Option<(String, String)>,
+}
+
+impl Split {
+    /// Creates a rule sending traffic to `target`, once a percentage or a
+    /// header is set.
+    pub fn to(target: Address) -> Self {
+        Self {
+            target,
+            percent: 0.0,
+            header: None,
+        }
+    }
+
+    /// Sets the percentage of the traffic sent to the target, between `0`
+    /// and `100`.
+    pub fn percent(mut self, percent: f64) -> Self {
+        self.percent = percent.clamp(0.0, 100.0);
+        self
+    }
+
+    /// Sends the messages carrying a header with a given value to the
+    /// target, whatever the percentage.
+    pub fn when_header(mut self, name: &str, value: &str) -> Self {
+        self.header = Some((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the [`Address`] the traffic is sent to.
+    pub fn target(&self) -> &Address { &self.target }
+
+    /// Returns whether a message asks for the target with the header.
+    fn is_requested(&self, msg: &Envelope) -> bool {
+        match &self.header {
+            Some((name, value)) => msg.get_header(name) == Some(value),
+            None => false,
+        }
+    }
+}
+
+/// Outcome of the copies mirrored to a shadow
+/// [`Service`](crate::Service).
+///
+/// The replies of a shadow [`Service`](crate::Service) are discarded, and
+/// only counted here: a reply is `diverged` when its [`Payload`] differs
+/// from the one of the primary reply. Copies are `dropped` when the
+/// shadow falls too far behind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MirrorStats {
+    mirrored: u64,
+    replied:  u64,
+    failed:   u64,
+    diverged: u64,
+    dropped:  u64,
+}
+
+impl MirrorStats {
+    /// Returns the number of copies delivered to the shadow.
+    pub fn mirrored(&self) -> u64 { self.mirrored }
+
+    /// Returns the number of copies the shadow processed successfully.
+    pub fn replied(&self) -> u64 { self.replied }
+
+    /// Returns the number of copies the shadow failed to process.
+    pub fn failed(&self) -> u64 { self.failed }
+
+    /// Returns the number of replies differing from the primary one.
+    pub fn diverged(&self) -> u64 { self.diverged }
+
+    /// Returns the number of copies dropped without being delivered,
+    /// because too many were waiting already.
+    pub fn dropped(&self) -> u64 { self.dropped }
+
+    fn record(&mut self, primary: Option<&Payload>, outcome: &Result<Reply>) {
+        self.mirrored += 1;
+        match outcome {
+            Ok(reply) => {
+                self.replied += 1;
+                let shadow = reply
+                    .as_ref()
+                    .map(Envelope::payload);
+                if primary.is_some() && shadow != primary {
+                    self.diverged += 1;
+                }
+            }
+            Err(_) => self.failed += 1,
+        }
+    }
+}
+
+/// Traffic rules of a [`Router`](crate::Router): splits and mirrors keyed
+/// by glob pattern, and the outcome of the mirrored copies.
+///
+/// Copies are delivered by a single mirror worker thread, started with the
+/// first one, in the order they were mirrored. A delivery which panics
+/// does not stop the worker.
+#[derive(Debug, Default)]
+pub(super) struct Traffic {
+    splits:  Vec<(String, Split)>,
+    mirrors: Vec<(String, Address)>,
+    stats:   Mutex<HashMap<String, MirrorStats>>,
+    worker:  OnceLock<SyncSender<MirrorJob>>,
+}
+
+impl Traffic {
+    pub(super) fn add_split(&mut self, pattern: &str, split: Split) {
+        self.splits
+            .push((pattern.to_string(), split));
+    }
+
+    pub(super) fn add_mirror(&mut self, pattern: &str, target: Address) {
+        self.mirrors
+            .push((pattern.to_string(), target));
+    }
+
+    /// Returns whether no rule is set.
+    pub(super) fn is_empty(&self) -> bool { self.splits.is_empty() && self.mirrors.is_empty() }
+
+    /// Returns the destination a message is split to, if any.
+    pub(super) fn split(&self, key: &ServiceKey, msg: &Envelope) -> Option<Address> {
+        let splits: Vec<&Split> = self
+            .splits
+            .iter()
+            .filter(|(pattern, _)| key.matches(pattern))
+            .map(|(_, split)| split)
+            .collect();
+        if splits.is_empty() {
+            return None;
+        }
+
+        let split = match splits
+            .iter()
+            .find(|split| split.is_requested(msg))
+        {
+            Some(split) => split,
+            None => {
+                let roll = (random::next() % 10_000) as f64 / 100.0;
+                let mut threshold = 0.0;
+                splits.iter().find(|split| {
+                    threshold += split.percent;
+                    roll < threshold
+                })?
+            }
+        };
+        Some(
+            msg.destination()
+                .rebase(&split.target),
+        )
+    }
+
+    /// Returns the destinations a message may be rolled to by the
+    /// percentages of the [`Split`]s, along with the weight of each of
+    /// them once the percentages are added up.
+    pub(super) fn weighted(&self, key: &ServiceKey, msg: &Envelope) -> Vec<(Address, f64)> {
+        let mut threshold: f64 = 0.0;
+        self.splits
+            .iter()
+            .filter(|(pattern, _)| key.matches(pattern))
+            .filter_map(|(_, split)| {
+                let weight = split
+                    .percent
+                    .min(100.0 - threshold);
+                threshold += weight;
+                (weight > 0.0).then(|| {
+                    (
+                        msg.destination()
+                            .rebase(&split.target),
+                        weight,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the destination a message asks to be split to with the
+    /// header of a [`Split`], if any.
+    pub(super) fn requested(&self, key: &ServiceKey, msg: &Envelope) -> Option<Address> {
+        self.splits
+            .iter()
+            .filter(|(pattern, _)| key.matches(pattern))
+            .find(|(_, split)| split.is_requested(msg))
+            .map(|(_, split)| {
+                msg.destination()
+                    .rebase(&split.target)
+            })
+    }
+
+    /// Returns the copies of a message to mirror, along with the target of
+    /// their rule.
+    ///
+    /// A rule targeting the destination itself is skipped.
+    pub(super) fn mirrors(&self, key: &ServiceKey, msg: &Envelope) -> Vec<(Address, Envelope)> {
+        let destination = msg.destination();
+        self.mirrors
+            .iter()
+            .filter(|(pattern, _)| key.matches(pattern))
+            .map(|(_, target)| (target, destination.rebase(target)))
+            .filter(|(_, mirrored)| mirrored != destination)
+            .map(|(target, mirrored)| {
+                let copy = msg
+                    .clone()
+                    .redirect(mirrored)
+                    .header(MIRROR_HEADER, &destination.to_string());
+                (target.clone(), copy)
+            })
+            .collect()
+    }
+
+    /// Hands the delivery of the copies mirrored to some targets over to
+    /// the mirror worker.
+    ///
+    /// If [`MIRROR_BACKLOG`] deliveries are waiting already, the copies
+    /// are dropped and counted as such.
+    pub(super) fn shadow(&self, targets: &[Address], job: MirrorJob) {
+        let worker = self.worker.get_or_init(|| {
+            let (sender, jobs) = mpsc::sync_channel::<MirrorJob>(MIRROR_BACKLOG);
+            // Stops once the `Router` is dropped along with the sender.
+            thread::spawn(move || {
+                for job in jobs {
+                    let _ = worker::catch(|| {
+                        job();
+                        Ok(None)
+                    });
+                }
+            });
+            sender
+        });
+        if worker.try_send(job).is_err() {
+            for target in targets {
+                self.update(target, |stats| stats.dropped += 1);
+            }
+        }
+    }
+
+    /// Records the outcome of a copy mirrored to a target.
+    pub(super) fn record(
+        &self,
+        target: &Address,
+        primary: Option<&Payload>,
+        outcome: &Result<Reply>,
+    ) {
+        self.update(target, |stats| {
+            stats.record(primary, outcome)
+        });
+    }
+
+    /// Updates the outcome of the copies mirrored to a target.
+    fn update(&self, target: &Address, update: impl FnOnce(&mut MirrorStats)) {
+        update(
+            self.stats
+                .lock()
+                .recover()
+                .entry(target.to_string())
+                .or_default(),
+        );
+    }
+
+    /// Returns the outcome of the copies mirrored to a target, if any.
+    pub(super) fn stats(&self, target: &Address) -> Option<MirrorStats> {
+        self.stats
+            .lock()
+            .recover()
+            .get(&target.to_string())
+            .copied()
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Error,
+            KeyMode,
+            testing::{
+                SRC,
+                message,
+            },
+        },
+        std::time::Duration,
+    };
+
+    const V1: &str = "http://orders-v1";
+    const V2: &str = "http://orders-v2";
+
+    fn key(msg: &Envelope) -> ServiceKey { ServiceKey::new(msg.destination(), KeyMode::Authority) }
+
+    #[test]
+    fn split_by_header() -> Result<()> {
+        let mut traffic = Traffic::default();
+        traffic.add_split(
+            "orders-v1",
+            Split::to(Address::parse(V2)?).when_header("x-canary", "true"),
+        );
+
+        let msg = message(SRC, &format!("{V1}/orders/42"), "order")?;
+        assert_eq!(traffic.split(&key(&msg), &msg), None);
+
+        let msg = msg.header("x-canary", "true");
+        assert_eq!(
+            traffic.split(&key(&msg), &msg),
+            Some(Address::parse(format!("{V2}/orders/42"))?)
+        );
+
+        let other = message(SRC, "http://users", "order")?.header("x-canary", "true");
+        assert_eq!(traffic.split(&key(&other), &other), None);
+        Ok(())
+    }
+
+    #[test]
+    fn split_by_percentage() -> Result<()> {
+        let v2 = Address::parse(V2)?;
+        let mut traffic = Traffic::default();
+        traffic.add_split("*", Split::to(v2.clone()).percent(20.0));
+
+        let msg = message(SRC, V1, "order")?;
+        let split = (0..2_000)
+            .filter(|_| traffic.split(&key(&msg), &msg) == Some(v2.clone()))
+            .count();
+        assert!(
+            (200..600).contains(&split),
+            "{split} messages split"
+        );
+
+        let mut traffic = Traffic::default();
+        traffic.add_split("*", Split::to(v2.clone()).percent(150.0));
+        assert_eq!(traffic.split(&key(&msg), &msg), Some(v2));
+        Ok(())
+    }
+
+    #[test]
+    fn mirror_copies() -> Result<()> {
+        let v2 = Address::parse(V2)?;
+        let mut traffic = Traffic::default();
+        traffic.add_mirror("orders-v1", v2.clone());
+        traffic.add_mirror("*", Address::parse(V1)?);
+
+        let msg = message(SRC, &format!("{V1}/orders"), "order")?;
+        let mirrors = traffic.mirrors(&key(&msg), &msg);
+        assert_eq!(mirrors.len(), 1);
+
+        let (target, copy) = &mirrors[0];
+        assert_eq!(target, &v2);
+        assert_eq!(
+            copy.destination().to_string(),
+            format!("{V2}/orders")
+        );
+        assert_eq!(
+            copy.get_header(MIRROR_HEADER),
+            Some("http://orders-v1/orders")
+        );
+
+        let primary = Payload::from("order");
+        let reply = msg.into_reply(Payload::from("other"));
+        traffic.record(&v2, Some(&primary), &Ok(Some(reply)));
+        traffic.record(
+            &v2,
+            Some(&primary),
+            &Err(Error::ServiceNotFound),
+        );
+
+        let stats = traffic.stats(&v2).unwrap();
+        assert_eq!(stats.mirrored(), 2);
+        assert_eq!(stats.replied(), 1);
+        assert_eq!(stats.failed(), 1);
+        assert_eq!(stats.diverged(), 1);
+        assert!(
+            traffic
+                .stats(&Address::parse(V1)?)
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn full_mirror_backlog_drops_copies() -> Result<()> {
+        let traffic = Traffic::default();
+        let target = Address::parse(V2)?;
+        let targets = std::slice::from_ref(&target);
+
+        // Keeps the worker busy while the backlog fills up.
+        let (release, released) = mpsc::channel::<()>();
+        traffic.shadow(
+            targets,
+            Box::new(move || {
+                let _ = released.recv();
+            }),
+        );
+        for _ in 0..=MIRROR_BACKLOG {
+            traffic.shadow(targets, Box::new(|| {}));
+        }
+        let stats = traffic
+            .stats(&target)
+            .unwrap();
+        assert!(stats.dropped() >= 1);
+        assert_eq!(stats.mirrored(), 0);
+
+        release.send(()).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn panicking_mirror_job_keeps_worker() -> Result<()> {
+        let traffic = Traffic::default();
+        let target = Address::parse(V2)?;
+        let targets = std::slice::from_ref(&target);
+
+        let (done, finished) = mpsc::channel();
+        traffic.shadow(targets, Box::new(|| panic!("mirror")));
+        traffic.shadow(
+            targets,
+            Box::new(move || done.send(()).unwrap()),
+        );
+        assert!(
+            finished
+                .recv_timeout(Duration::from_secs(5))
+                .is_ok()
+        );
+        Ok(())
+    }
+}
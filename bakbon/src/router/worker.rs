@@ -0,0 +1,324 @@
+use {
+    crate::{
+        Error,
+        Reply,
+        Result,
+        core::Recover,
+    },
+    std::{
+        collections::VecDeque,
+        panic::{
+            self,
+            AssertUnwindSafe,
+        },
+        sync::{
+            Arc,
+            Condvar,
+            Mutex,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+/// Default number of worker threads of a [`Router`](crate::Router).
+pub(super) const DEFAULT_WORKERS: usize = 64;
+
+/// How long an idle worker thread waits for a call before it stops.
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Call handed over to a worker thread.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Calls waiting for a worker thread, and the threads running them.
+#[derive(Default)]
+struct Backlog {
+    jobs:    VecDeque<Job>,
+    threads: usize,
+    idle:    usize,
+}
+
+/// Outcome of a call handed over to a worker thread.
+enum Slot {
+    Waiting,
+    Done(Box<Result<Reply>>),
+    Abandoned,
+}
+
+/// Bounded pool of worker threads running the calls of a
+/// [`Router`](crate::Router), shared by its clones.
+///
+/// Threads are started on demand, up to `size`, and stop once idle for a
+/// while. Calls coming in while every thread is busy wait in a backlog of
+/// at most `size` calls, past which they fail with
+/// [`Error::TaskFailed`].
+pub(super) struct Workers {
+    size:    usize,
+    backlog: Mutex<Backlog>,
+    ready:   Condvar,
+    overdue: AtomicUsize,
+}
+
+impl Default for Workers {
+    fn default() -> Self { Self::new(DEFAULT_WORKERS) }
+}
+
+impl Workers {
+    /// Creates a pool of at most `size` threads, at least one.
+    pub(super) fn new(size: usize) -> Self {
+        Self {
+            size:    size.max(1),
+            backlog: Mutex::default(),
+            ready:   Condvar::new(),
+            overdue: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of calls the pool stopped waiting for that are
+    /// still queued or running.
+    pub(super) fn overdue(&self) -> usize {
+        self.overdue
+            .load(Ordering::Relaxed)
+    }
+
+    /// Counts calls the caller stopped waiting for as overdue, until they
+    /// are [`release`](Workers::release)d.
+    pub(super) fn abandon(&self, calls: usize) {
+        self.overdue
+            .fetch_add(calls, Ordering::Relaxed);
+    }
+
+    /// Releases an overdue call once it completed.
+    pub(super) fn release(&self) {
+        self.overdue
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Hands a job over to a worker thread, starting one if none is idle.
+    ///
+    /// Returns [`Error::TaskFailed`] if the backlog is full.
+    pub(super) fn submit(self: &Arc<Self>, job: impl FnOnce() + Send + 'static) -> Result<()> {
+        let mut backlog = self.backlog.lock().recover();
+        if backlog.jobs.len() >= self.size {
+            return Err(Error::TaskFailed(
+                "every worker is busy".to_string(),
+            ));
+        }
+
+        backlog
+            .jobs
+            .push_back(Box::new(job));
+        if backlog.jobs.len() > backlog.idle && backlog.threads < self.size {
+            backlog.threads += 1;
+            let workers = self.clone();
+            thread::spawn(move || workers.work());
+        }
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// Runs the queued jobs until none came in for [`KEEP_ALIVE`].
+    fn work(&self) {
+        let mut backlog = self.backlog.lock().recover();
+        loop {
+            if let Some(job) = backlog.jobs.pop_front() {
+                drop(backlog);
+                // Calls catch their own panics; this keeps the thread
+                // count right whatever a job does.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                backlog = self.backlog.lock().recover();
+                continue;
+            }
+
+            backlog.idle += 1;
+            let (guard, wait) = self
+                .ready
+                .wait_timeout(backlog, KEEP_ALIVE)
+                .recover();
+            backlog = guard;
+            backlog.idle -= 1;
+            if wait.timed_out() && backlog.jobs.is_empty() {
+                backlog.threads -= 1;
+                return;
+            }
+        }
+    }
+
+    /// Runs a call on a worker thread and waits for its outcome for at
+    /// most `timeout`.
+    ///
+    /// A thread cannot be stopped, so a call overrunning its timeout keeps
+    /// running: it is counted as [`overdue()`](Workers::overdue) until it
+    /// completes, and its outcome is then dropped. A call still queued
+    /// when its timeout expires is never started.
+    ///
+    /// Returns [`Error::TimedOut`] if the call overran, or
+    /// [`Error::TaskFailed`] if it panicked or the backlog is full.
+    pub(super) fn run<F>(self: &Arc<Self>, timeout: Duration, call: F) -> Result<Reply>
+    where
+        F: FnOnce() -> Result<Reply> + Send + 'static,
+    {
+        if timeout.is_zero() {
+            return Err(Error::TimedOut(timeout));
+        }
+
+        let slot = Arc::new((Mutex::new(Slot::Waiting), Condvar::new()));
+        let worker = (slot.clone(), self.clone());
+        self.submit(move || {
+            let (slot, workers) = worker;
+            let (state, done) = &*slot;
+            let abandoned =
+                |state: &Mutex<Slot>| matches!(*state.lock().recover(), Slot::Abandoned);
+
+            let outcome = match abandoned(state) {
+                true => None,
+                false => Some(catch(call)),
+            };
+            let mut state = state.lock().recover();
+            match (outcome, &*state) {
+                (Some(outcome), Slot::Waiting) => {
+                    *state = Slot::Done(Box::new(outcome));
+                    done.notify_one();
+                }
+                _ => workers.release(),
+            }
+        })?;
+
+        let (state, done) = &*slot;
+        let state = state.lock().recover();
+        let (mut state, _) = done
+            .wait_timeout_while(state, timeout, |state| {
+                matches!(state, Slot::Waiting)
+            })
+            .recover();
+
+        match std::mem::replace(&mut *state, Slot::Abandoned) {
+            Slot::Done(outcome) => *outcome,
+            _ => {
+                self.abandon(1);
+                Err(Error::TimedOut(timeout))
+            }
+        }
+    }
+}
+
+/// Runs a call on the current thread, turning a panic into
+/// [`Error::TaskFailed`].
+pub(super) fn catch<F>(call: F) -> Result<Reply>
+where
+    F: FnOnce() -> Result<Reply>,
+{
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|_| {
+        Err(Error::TaskFailed(
+            "service panicked".to_string(),
+        ))
+    })
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{
+            sync::mpsc,
+            time::Instant,
+        },
+    };
+
+    #[test]
+    fn call_within_timeout() {
+        let workers = Arc::new(Workers::default());
+        let outcome = workers.run(Duration::from_secs(5), || Ok(None));
+        assert!(matches!(outcome, Ok(None)));
+        assert_eq!(workers.overdue(), 0);
+
+        let outcome = workers.run(Duration::from_secs(5), || panic!("boom"));
+        assert!(matches!(outcome, Err(Error::TaskFailed(_))));
+
+        let outcome = catch(|| panic!("boom"));
+        assert!(matches!(outcome, Err(Error::TaskFailed(_))));
+    }
+
+    #[test]
+    fn overdue_call() {
+        let workers = Arc::new(Workers::default());
+        let (release, released) = mpsc::channel::<()>();
+        let (finish, finished) = mpsc::channel();
+
+        let started = Instant::now();
+        let outcome = workers.run(Duration::from_millis(20), move || {
+            let _ = released.recv();
+            let _ = finish.send(());
+            Ok(None)
+        });
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(outcome, Err(Error::TimedOut(_))));
+        assert_eq!(workers.overdue(), 1);
+
+        // The worker is accounted for until it completes.
+        release.send(()).unwrap();
+        finished.recv().unwrap();
+        let started = Instant::now();
+        while workers.overdue() != 0 {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::yield_now();
+        }
+
+        let outcome = workers.run(Duration::ZERO, || Ok(None));
+        assert!(matches!(outcome, Err(Error::TimedOut(_))));
+    }
+
+    #[test]
+    fn bounded_pool() {
+        let workers = Arc::new(Workers::new(1));
+        let (release, released) = mpsc::channel::<()>();
+
+        // Keeps the only thread busy, then fills the backlog.
+        workers
+            .submit(move || {
+                let _ = released.recv();
+            })
+            .unwrap();
+        let started = Instant::now();
+        while !workers
+            .backlog
+            .lock()
+            .unwrap()
+            .jobs
+            .is_empty()
+        {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::yield_now();
+        }
+        let queued = workers.run(Duration::from_millis(20), || Ok(None));
+        assert!(matches!(queued, Err(Error::TimedOut(_))));
+        assert_eq!(workers.overdue(), 1);
+
+        let full = workers.run(Duration::from_millis(20), || Ok(None));
+        assert!(matches!(full, Err(Error::TaskFailed(_))));
+        assert_eq!(
+            workers
+                .backlog
+                .lock()
+                .unwrap()
+                .threads,
+            1
+        );
+
+        // The expired call is dropped without running.
+        release.send(()).unwrap();
+        let started = Instant::now();
+        while workers.overdue() != 0 {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::yield_now();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use {
+    super::{
+        Processor,
+        Service,
+        ServiceContext,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Reply,
+        Result,
+    },
+    std::{
+        fmt::Debug,
+        pin::Pin,
+    },
+};
+
+/// Boxed, `Send` [`Future`] returned by the async traits.
+///
+/// Boxing keeps [`AsyncService`] and [`AsyncProcessor`] object safe, so
+/// they can be registered as trait objects like their sync counterparts.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Asynchronous counterpart of [`Service`].
+///
+/// Routed by an [`AsyncRouter`](crate::AsyncRouter), which awaits
+/// [`process()`](AsyncService::process) instead of blocking the caller
+/// while the service does I/O. Existing [`Service`]s are registered
+/// unchanged through the [`Blocking`] adapter.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// #[derive(Debug)]
+/// struct Echo(Address);
+///
+/// impl AsyncService for Echo {
+///     fn address(&self) -> &Address { &self.0 }
+///
+///     fn duplicate(&self) -> AsyncServiceBox { Box::new(Self(self.0.clone())) }
+///
+///     fn process(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>> {
+///         Box::pin(async move {
+///             let payload = msg.payload().clone();
+///             Ok(Some(msg.into_reply(payload)))
+///         })
+///     }
+/// }
+/// ```
+pub trait AsyncService: Debug + Send + Sync {
+    fn address(&self) -> &Address;
+    fn duplicate(&self) -> AsyncServiceBox;
+    fn process(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>>;
+
+    /// Processes an [`Envelope`] with access to the [`ServiceContext`] of
+    /// the [`AsyncRouter`](crate::AsyncRouter) it came through.
+    ///
+    /// This is what the [`AsyncRouter`](crate::AsyncRouter) calls. The
+    /// default implementation ignores the context and calls
+    /// [`process()`](AsyncService::process).
+    fn process_with<'a>(
+        &'a self,
+        msg: Envelope,
+        _ctx: &'a ServiceContext<'a>,
+    ) -> BoxFuture<'a, Result<Reply>> {
+        self.process(msg)
+    }
+}
+
+/// A boxed async service.
+pub type AsyncServiceBox = Box<dyn AsyncService>;
+
+/// Asynchronous counterpart of [`Processor`].
+///
+/// Existing [`Processor`]s are used unchanged through the [`Blocking`]
+/// adapter.
+pub trait AsyncProcessor: Debug + Send + Sync {
+    fn execute(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>>;
+}
+
+/// Adapter running a sync [`Service`] or [`Processor`] as its async
+/// counterpart.
+///
+/// The wrapped value is called inline when the returned future is polled,
+/// with the [`ServiceContext`] of the [`AsyncRouter`](crate::AsyncRouter),
+/// so it works with any runtime but occupies the polling task while it
+/// runs. With the `tokio` feature, prefer
+/// [`SpawnBlocking`](crate::SpawnBlocking) for services doing blocking
+/// I/O.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let address = Address::parse("http://nil.com").unwrap();
+/// let nil = Blocking::new(service_fn(address, |_| Ok(None)));
+/// assert_eq!(AsyncService::address(&nil).to_string(), "http://nil.com");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Blocking<T>(T);
+
+impl<T> Blocking<T> {
+    /// Wraps a sync [`Service`] or [`Processor`].
+    pub fn new(inner: T) -> Self { Self(inner) }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<S: Service + 'static> AsyncService for Blocking<S> {
+    fn address(&self) -> &Address { self.0.address() }
+
+    fn duplicate(&self) -> AsyncServiceBox { Box::new(Blocking(self.0.duplicate())) }
+
+    fn process(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>> {
+        Box::pin(async move { self.0.process(msg) })
+    }
+
+    fn process_with<'a>(
+        &'a self,
+        msg: Envelope,
+        ctx: &'a ServiceContext<'a>,
+    ) -> BoxFuture<'a, Result<Reply>> {
+        Box::pin(async move { self.0.process_with(msg, ctx) })
+    }
+}
+
+impl<P: Processor> AsyncProcessor for Blocking<P> {
+    fn execute(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>> {
+        Box::pin(async move { self.0.execute(msg) })
+    }
+}
+
+/// Adapter running a sync [`Service`] on the tokio blocking thread pool.
+///
+/// Each [`process()`](AsyncService::process) call is moved to
+/// [`tokio::task::spawn_blocking`], so a service doing blocking I/O does
+/// not stall the runtime worker threads, with a context bound to a clone
+/// of the [`Router`](crate::Router) of the [`ServiceContext`]. Must be
+/// awaited within a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone)]
+pub struct SpawnBlocking<S>(std::sync::Arc<S>);
+
+#[cfg(feature = "tokio")]
+impl<S> SpawnBlocking<S> {
+    /// Wraps a sync [`Service`].
+    pub fn new(service: S) -> Self { Self(std::sync::Arc::new(service)) }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: Service + 'static> AsyncService for SpawnBlocking<S> {
+    fn address(&self) -> &Address { self.0.address() }
+
+    fn duplicate(&self) -> AsyncServiceBox { Box::new(SpawnBlocking::new(self.0.duplicate())) }
+
+    fn process(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>> {
+        let service = self.0.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || service.process(msg))
+                .await
+                .map_err(|e| crate::Error::TaskFailed(e.to_string()))?
+        })
+    }
+
+    fn process_with<'a>(
+        &'a self,
+        msg: Envelope,
+        ctx: &'a ServiceContext<'a>,
+    ) -> BoxFuture<'a, Result<Reply>> {
+        let service = self.0.clone();
+        let router = ctx.router().cloned();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let ctx = router
+                    .as_ref()
+                    .map(ServiceContext::new)
+                    .unwrap_or_default();
+                service.process_with(msg, &ctx)
+            })
+            .await
+            .map_err(|e| crate::Error::TaskFailed(e.to_string()))?
+        })
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Payload,
+            processor_fn,
+            service_fn,
+            testing::{
+                SRC,
+                message,
+                ready,
+            },
+        },
+    };
+
+    const DST: &str = "http://echo.com";
+
+    #[test]
+    fn blocking_service() -> Result<()> {
+        let address = Address::parse("http://echo.com")?;
+        let echo = Blocking::new(service_fn(address.clone(), |msg| {
+            let payload = msg.payload().clone();
+            Ok(Some(msg.into_reply(payload)))
+        }));
+        assert_eq!(AsyncService::address(&echo), &address);
+
+        let reply = ready(AsyncService::process(
+            &echo,
+            message(SRC, DST, "hello")?,
+        ))?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("hello")
+        );
+
+        let dupe = AsyncService::duplicate(&echo);
+        assert_eq!(dupe.address(), &address);
+        assert!(ready(dupe.process(message(SRC, DST, "")?))?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn blocking_processor() -> Result<()> {
+        let nil = Blocking::new(processor_fn(|_| Ok(None)));
+        assert!(ready(nil.execute(message(SRC, DST, "")?))?.is_none());
+        Ok(())
+    }
+}
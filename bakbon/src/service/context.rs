@@ -0,0 +1,147 @@
+use {
+    crate::{
+        Cache,
+        Envelope,
+        Error,
+        Queue,
+        Reply,
+        Result,
+        Router,
+    },
+    std::sync::Mutex,
+};
+
+/// Handle given to a [`Service`](super::Service) while it processes an
+/// [`Envelope`].
+///
+/// The context exposes the [`Router`] the [`Envelope`] came through along
+/// with the resources configured on it with [`Router::builder()`]: named
+/// [`Queue`]s, the shared [`Cache`] and string configuration values. It
+/// lets a
+/// [`Service`](super::Service) call other services, publish messages or
+/// read cached replies without global state.
+///
+/// A context created with [`ServiceContext::detached()`] (or `default()`)
+/// is not bound to any [`Router`]: [`route()`](ServiceContext::route)
+/// returns [`Error::ServiceNotFound`] and every resource is missing.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// #[derive(Debug)]
+/// struct Orders(Address);
+///
+/// impl Service for Orders {
+///     fn address(&self) -> &Address { &self.0 }
+///
+///     fn duplicate(&self) -> ServiceBox { Box::new(Self(self.0.clone())) }
+///
+///     fn process(&self, msg: Envelope) -> Result<Reply> {
+///         self.process_with(msg, &ServiceContext::detached())
+///     }
+///
+///     fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+///         if let Some(audit) = ctx.queue("audit") {
+///             audit.enqueue(msg)?;
+///         }
+///         Ok(None)
+///     }
+/// }
+/// ```
+#[derive(Default, Clone, Copy)]
+pub struct ServiceContext<'a> {
+    router: Option<&'a Router>,
+    nested: bool,
+}
+
+impl<'a> ServiceContext<'a> {
+    /// Creates a context bound to a [`Router`].
+    pub(crate) fn new(router: &'a Router) -> Self {
+        Self {
+            router: Some(router),
+            nested: false,
+        }
+    }
+
+    /// Creates a context for the messages a [`Service`](super::Service)
+    /// routes while it processes another one.
+    pub(crate) fn nested(router: &'a Router) -> Self {
+        Self {
+            router: Some(router),
+            nested: true,
+        }
+    }
+
+    /// Returns whether the processed [`Envelope`] was routed by a
+    /// [`Service`](super::Service) processing another one.
+    pub(crate) fn is_nested(&self) -> bool { self.nested }
+
+    /// Creates a context bound to no [`Router`].
+    ///
+    /// Useful to call [`Service::process_with()`](super::Service) outside
+    /// of a [`Router`], e.g. from [`Service::process()`](super::Service)
+    /// or in tests.
+    pub fn detached() -> Self { Self::default() }
+
+    /// Returns the [`Router`] the processed [`Envelope`] came through, if
+    /// any.
+    pub fn router(&self) -> Option<&'a Router> { self.router }
+
+    /// Routes an [`Envelope`] to another [`Service`](super::Service)
+    /// through the same [`Router`] and returns its [`Reply`].
+    ///
+    /// A message failing there is not dead-lettered: its error is returned
+    /// to the calling [`Service`](super::Service), and the message it
+    /// processes is dead-lettered instead if the error fails it too.
+    ///
+    /// Returns [`Error::ServiceNotFound`] if the context is detached.
+    pub fn route(&self, msg: Envelope) -> Result<Reply> {
+        self.router
+            .ok_or(Error::ServiceNotFound)?
+            .route_nested(msg)
+    }
+
+    /// Returns a named [`Queue`] configured on the [`Router`], if any.
+    pub fn queue(&self, name: &str) -> Option<&'a Queue> { self.router?.queue(name) }
+
+    /// Returns the [`Cache`] shared by the [`Router`] services, if any.
+    pub fn cache(&self) -> Option<&'a Mutex<Cache>> { self.router.map(|r| r.cache()) }
+
+    /// Returns a configuration value of the [`Router`], if any.
+    pub fn config(&self, key: &str) -> Option<&'a str> { self.router?.config(key) }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            Payload,
+        },
+    };
+
+    #[test]
+    fn detached_context() -> Result<()> {
+        let ctx = ServiceContext::detached();
+        assert!(ctx.router().is_none());
+        assert!(ctx.queue("audit").is_none());
+        assert!(ctx.cache().is_none());
+        assert!(ctx.config("region").is_none());
+
+        let src = Address::parse("http://client.com")?;
+        let dst = Address::parse("http://service.com")?;
+        let msg = Envelope::new(src, dst, Payload::new());
+        assert!(matches!(
+            ctx.route(msg),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use {
+    super::{
+        Dispatcher,
+        Endpoint,
+        Method,
+        PathPattern,
+    },
+    crate::{
+        Address,
+        ProcMap,
+        Processor,
+    },
+    std::sync::Arc,
+};
+
+/// Builder for constructing a [`Dispatcher`] endpoint table.
+///
+/// Used to declare which [`Processor`] handles which [`Method`] and path
+/// template before creating an immutable [`Dispatcher`].
+pub struct DispatcherBuilder {
+    address:   Address,
+    endpoints: Vec<Endpoint>,
+}
+
+impl DispatcherBuilder {
+    pub(super) fn new(address: Address) -> Self {
+        Self {
+            address,
+            endpoints: Vec::new(),
+        }
+    }
+
+    /// Registers a [`Processor`] for a [`Method`] and a path template.
+    ///
+    /// See [`Method`] for supported verbs such as `"GET"` or `"POST"`;
+    /// `"*"` accepts any verb. Path segments starting with `:` are path
+    /// parameters, e.g. `"/users/:id"`.
+    pub fn route(mut self, method: &str, path: &str, processor: impl Processor + 'static) -> Self {
+        self.endpoints.push(Endpoint {
+            method:    Method::from(method),
+            pattern:   PathPattern::parse(path),
+            processor: Arc::new(processor),
+        });
+        self
+    }
+
+    /// Registers every entry of a [`ProcMap`] as a path template accepting
+    /// any [`Method`].
+    ///
+    /// Entries are registered from the most to the least specific path
+    /// template, comparing segment by segment with static segments before
+    /// path parameters, so `/users/:id` is tried before `/:kind/:id` on
+    /// every run.
+    pub fn processors(mut self, processors: ProcMap) -> Self {
+        let mut processors: Vec<_> = processors
+            .into_iter()
+            .map(|(path, processor)| (PathPattern::parse(&path), processor))
+            .collect();
+        processors.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (pattern, processor) in processors {
+            self.endpoints.push(Endpoint {
+                method: Method::Any,
+                pattern,
+                processor: Arc::from(processor),
+            });
+        }
+        self
+    }
+
+    /// Finalizes the builder and returns a [`Dispatcher`].
+    pub fn build(self) -> Dispatcher {
+        Dispatcher {
+            address:   self.address,
+            endpoints: Arc::new(self.endpoints),
+        }
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Envelope,
+            Reply,
+            Result,
+        },
+    };
+
+    const ADDRESS: &str = "http://users.com";
+
+    #[derive(Debug)]
+    struct NilProc;
+
+    impl Processor for NilProc {
+        fn execute(&self, _message: Envelope) -> Result<Reply> { Ok(None) }
+    }
+
+    #[test]
+    fn build_empty_dispatcher() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let dispatcher = Dispatcher::builder(address.clone()).build();
+        assert_eq!(dispatcher.address, address);
+        assert!(
+            dispatcher
+                .endpoints
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn build_dispatcher_with_routes() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let dispatcher = Dispatcher::builder(address)
+            .route("get", "/users/:id", NilProc)
+            .route("post", "/users", NilProc)
+            .build();
+
+        assert_eq!(dispatcher.endpoints.len(), 2);
+        assert_eq!(dispatcher.endpoints[0].method, Method::Get);
+        assert_eq!(
+            dispatcher.endpoints[0].pattern,
+            PathPattern::parse("/users/:id")
+        );
+        assert_eq!(dispatcher.endpoints[1].method, Method::Post);
+        Ok(())
+    }
+
+    #[test]
+    fn build_dispatcher_from_proc_map() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let mut processors = ProcMap::new();
+        processors.insert("/users".to_string(), Box::new(NilProc));
+
+        let dispatcher = Dispatcher::builder(address)
+            .processors(processors)
+            .build();
+
+        assert_eq!(dispatcher.endpoints.len(), 1);
+        assert_eq!(dispatcher.endpoints[0].method, Method::Any);
+        Ok(())
+    }
+
+    #[test]
+    fn proc_map_registered_by_specificity() -> Result<()> {
+        let address = Address::parse(ADDRESS)?;
+        let mut processors = ProcMap::new();
+        for path in ["/users/:id", "/:kind/:id", "/orders", "/:kind"] {
+            processors.insert(path.to_string(), Box::new(NilProc));
+        }
+
+        let dispatcher = Dispatcher::builder(address)
+            .processors(processors)
+            .build();
+
+        let patterns: Vec<_> = ["/orders", "/users/:id", "/:kind", "/:kind/:id"]
+            .map(PathPattern::parse)
+            .into();
+        let registered: Vec<_> = dispatcher
+            .endpoints
+            .iter()
+            .map(|e| e.pattern.clone())
+            .collect();
+        assert_eq!(registered, patterns);
+
+        let (endpoint, params) = dispatcher
+            .resolve(None, "/users/42")
+            .unwrap();
+        assert_eq!(
+            endpoint.pattern,
+            PathPattern::parse("/users/:id")
+        );
+        assert_eq!(
+            params,
+            vec![("id".to_string(), "42".to_string())]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+/// Header carrying the [`Method`] of an [`Envelope`](crate::Envelope).
+pub const METHOD_HEADER: &str = "x-method";
+
+/// Method-like verb used by the [`Dispatcher`](super::Dispatcher) to pick
+/// a [`Processor`](crate::Processor) next to the destination path.
+///
+/// Read from the [`METHOD_HEADER`] of incoming
+/// [`Envelope`](crate::Envelope)s. Verbs are case-insensitive, and any
+/// unknown verb is kept as a [`Custom`](Method::Custom) one.
+///
+/// ## Defaults
+///
+/// The default `Method` is [`Any`](Method::Any), which matches every verb,
+/// including [`Envelope`](crate::Envelope)s without [`METHOD_HEADER`].
+///
+/// # Examples
+///
+/// ```
+/// use bakbon::Method;
+///
+/// let method = Method::from("get");
+/// assert_eq!(method, Method::Get);
+/// assert_eq!(method.as_ref(), "GET");
+///
+/// let method = Method::from("subscribe");
+/// assert_eq!(method, Method::Custom("SUBSCRIBE".to_string()));
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum Method {
+    #[default]
+    Any,
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Custom(String),
+}
+
+impl Method {
+    /// Returns whether a route registered with this `Method` accepts the
+    /// `Method` of an incoming [`Envelope`](crate::Envelope).
+    pub fn accepts(&self, incoming: Option<&Method>) -> bool {
+        match (self, incoming) {
+            (Self::Any, _) => true,
+            (expected, Some(incoming)) => expected == incoming,
+            (_, None) => false,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    /// Format the method as a string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_ref()) }
+}
+
+impl From<&str> for Method {
+    /// Create a method from a case-insensitive string.
+    fn from(value: &str) -> Self {
+        match value
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            "*" | "ANY" => Self::Any,
+            "GET" => Self::Get,
+            "POST" => Self::Post,
+            "PUT" => Self::Put,
+            "PATCH" => Self::Patch,
+            "DELETE" => Self::Delete,
+            verb => Self::Custom(verb.to_string()),
+        }
+    }
+}
+
+impl AsRef<str> for Method {
+    /// Return the method as an uppercase string.
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Any => "*",
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+            Self::Custom(verb) => verb,
+        }
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_method() {
+        let method = Method::default();
+        assert_eq!(method, Method::Any);
+        assert_eq!(method.as_ref(), "*");
+    }
+
+    #[test]
+    fn method_from_str() {
+        assert_eq!(Method::from("GET"), Method::Get);
+        assert_eq!(Method::from("post"), Method::Post);
+        assert_eq!(Method::from("Put"), Method::Put);
+        assert_eq!(Method::from("patch"), Method::Patch);
+        assert_eq!(Method::from("delete"), Method::Delete);
+        assert_eq!(Method::from("any"), Method::Any);
+        assert_eq!(
+            Method::from("notify"),
+            Method::Custom("NOTIFY".to_string())
+        );
+    }
+
+    #[test]
+    fn format_method() {
+        let method = Method::from("get");
+        assert_eq!(format!("{method}"), "GET");
+    }
+
+    #[test]
+    fn method_accepts() {
+        assert!(Method::Any.accepts(None));
+        assert!(Method::Any.accepts(Some(&Method::Delete)));
+        assert!(Method::Get.accepts(Some(&Method::Get)));
+        assert!(!Method::Get.accepts(Some(&Method::Post)));
+        assert!(!Method::Get.accepts(None));
+    }
+}
@@ -0,0 +1,269 @@
+//! Path-aware dispatching of [`Envelope`]s to [`Processor`]s.
+//!
+//! - [`Dispatcher`] is a reusable [`Service`] holding a table of
+//!   [`Processor`]s keyed by [`Method`] and path template.
+//! - [`Method`] is the method-like verb read from [`METHOD_HEADER`].
+
+mod builder;
+mod method;
+mod pattern;
+
+use {
+    super::{
+        Processor,
+        Service,
+        ServiceBox,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Error,
+        Params,
+        Reply,
+        Result,
+    },
+    pattern::PathPattern,
+    std::sync::Arc,
+};
+pub use {
+    builder::DispatcherBuilder,
+    method::{
+        METHOD_HEADER,
+        Method,
+    },
+};
+
+/// Single entry of the [`Dispatcher`] table.
+#[derive(Debug)]
+struct Endpoint {
+    method:    Method,
+    pattern:   PathPattern,
+    processor: Arc<dyn Processor>,
+}
+
+/// [`Service`] routing each [`Envelope`] to the [`Processor`] registered
+/// for its destination path.
+///
+/// Endpoints are declared with a [`Method`] and a path template where
+/// segments starting with `:` are path parameters (e.g. `/users/:id`).
+/// On dispatch, the `Dispatcher`:
+/// 1. Reads the [`Method`] from the [`METHOD_HEADER`] of the [`Envelope`],
+///    if any.
+/// 2. Matches the [`destination`](Envelope::destination) path against the
+///    registered templates. Templates without parameters win over
+///    parameterized ones, then registration order decides.
+/// 3. Stores the captured path parameters in the [`Envelope`] (see
+///    [`Envelope::param()`]) and calls [`execute()`](Processor::execute)
+///    on the matching [`Processor`].
+///
+/// Returns [`Error::ProcessorNotFound`] with the destination path when no
+/// endpoint matches.
+///
+/// The endpoint table is shared between
+/// [`duplicate()`](Service::duplicate)d instances.
+///
+/// Registered in a [`Registry`](crate::Registry), a `Dispatcher` receives
+/// every path under its [`Address`] that no more specific
+/// [`Service`] is registered for.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// #[derive(Debug)]
+/// struct GetUser;
+///
+/// impl Processor for GetUser {
+///     fn execute(&self, msg: Envelope) -> Result<Reply> {
+///         let id = msg.param("id").unwrap_or_default().to_string();
+///         Ok(Some(msg.into_reply(Payload::from(id))))
+///     }
+/// }
+///
+/// let address = Address::parse("http://users.com").unwrap();
+/// let dispatcher = Dispatcher::builder(address)
+///     .route("GET", "/users/:id", GetUser)
+///     .build();
+///
+/// let src = Address::parse("http://client.com").unwrap();
+/// let dst = Address::parse("http://users.com/users/42").unwrap();
+/// let msg = Envelope::new(src, dst, Payload::new()).header(METHOD_HEADER, "GET");
+///
+/// let reply = dispatcher.process(msg).unwrap().unwrap();
+/// assert_eq!(reply.payload(), &Payload::from("42"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dispatcher {
+    address:   Address,
+    endpoints: Arc<Vec<Endpoint>>,
+}
+
+impl Dispatcher {
+    /// Returns a new [`DispatcherBuilder`] for a [`Service`] located at
+    /// `address`.
+    pub fn builder(address: Address) -> DispatcherBuilder { DispatcherBuilder::new(address) }
+
+    /// Returns the [`Processor`] matching a [`Method`] and a path along
+    /// with the captured path parameters, if any.
+    fn resolve(&self, method: Option<&Method>, path: &str) -> Option<(&Endpoint, Params)> {
+        let mut candidates = self
+            .endpoints
+            .iter()
+            .filter(|e| e.method.accepts(method))
+            .filter_map(|e| {
+                e.pattern
+                    .matches(path)
+                    .map(|params| (e, params))
+            });
+
+        let first = candidates.next()?;
+        match first.0.pattern.is_static() {
+            true => Some(first),
+            false => candidates
+                .find(|(e, _)| e.pattern.is_static())
+                .or(Some(first)),
+        }
+    }
+}
+
+impl Service for Dispatcher {
+    fn address(&self) -> &Address { &self.address }
+
+    fn duplicate(&self) -> ServiceBox { Box::new(self.clone()) }
+
+    fn process(&self, mut msg: Envelope) -> Result<Reply> {
+        let method = msg
+            .get_header(METHOD_HEADER)
+            .map(Method::from);
+        let path = msg
+            .destination()
+            .path()
+            .to_string();
+
+        let (endpoint, params) = self
+            .resolve(method.as_ref(), &path)
+            .ok_or(Error::ProcessorNotFound(path))?;
+
+        for (name, value) in params {
+            msg.add_param(&name, &value);
+        }
+
+        endpoint
+            .processor
+            .execute(msg)
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Payload,
+    };
+
+    const SRC: &str = "http://client.com";
+    const SRV: &str = "http://users.com";
+
+    #[derive(Debug)]
+    struct Tag(&'static str);
+
+    impl Processor for Tag {
+        fn execute(&self, msg: Envelope) -> Result<Reply> {
+            let params = msg
+                .params()
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>()
+                .join("&");
+            let payload = Payload::from(format!("{}?{params}", self.0));
+            Ok(Some(msg.into_reply(payload)))
+        }
+    }
+
+    fn dispatcher() -> Result<Dispatcher> {
+        let address = Address::parse(SRV)?;
+        let dispatcher = Dispatcher::builder(address)
+            .route("GET", "/users/:id", Tag("get_user"))
+            .route("DELETE", "/users/:id", Tag("delete_user"))
+            .route("GET", "/users/me", Tag("get_me"))
+            .route("*", "/health", Tag("health"))
+            .build();
+        Ok(dispatcher)
+    }
+
+    fn dispatch(method: Option<&str>, path: &str) -> Result<String> {
+        let src = Address::parse(SRC)?;
+        let dst = Address::parse(format!("{SRV}{path}"))?;
+        let mut msg = Envelope::new(src, dst, Payload::new());
+        if let Some(method) = method {
+            msg.add_header(METHOD_HEADER, method);
+        }
+
+        let reply = dispatcher()?
+            .process(msg)?
+            .unwrap();
+        Ok(String::from_utf8_lossy(reply.payload()).to_string())
+    }
+
+    #[test]
+    fn dispatch_by_method_and_path() -> Result<()> {
+        assert_eq!(
+            dispatch(Some("GET"), "/users/42")?,
+            "get_user?id=42"
+        );
+        assert_eq!(
+            dispatch(Some("delete"), "/users/42")?,
+            "delete_user?id=42"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn static_path_wins_over_params() -> Result<()> {
+        assert_eq!(
+            dispatch(Some("GET"), "/users/me")?,
+            "get_me?"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn any_method_accepts_missing_header() -> Result<()> {
+        assert_eq!(dispatch(None, "/health")?, "health?");
+        assert_eq!(dispatch(Some("POST"), "/health")?, "health?");
+        Ok(())
+    }
+
+    #[test]
+    fn processor_not_found_with_path() {
+        let result = dispatch(Some("POST"), "/users/42");
+        assert!(matches!(
+            result,
+            Err(Error::ProcessorNotFound(path)) if path == "/users/42"
+        ));
+
+        let result = dispatch(Some("GET"), "/orders");
+        assert!(matches!(
+            result,
+            Err(Error::ProcessorNotFound(path)) if path == "/orders"
+        ));
+    }
+
+    #[test]
+    fn duplicated_dispatcher_shares_endpoints() -> Result<()> {
+        let dispatcher = dispatcher()?;
+        let dupe = dispatcher.duplicate();
+        assert_eq!(dupe.address(), dispatcher.address());
+
+        let src = Address::parse(SRC)?;
+        let dst = Address::parse(format!("{SRV}/health"))?;
+        let reply = dupe.process(Envelope::new(src, dst, Payload::new()))?;
+        assert!(reply.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use crate::Params;
+
+/// Single segment of a [`PathPattern`].
+///
+/// Variants are declared from the most to the least specific, so a
+/// `Static` segment orders before a `Param`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+enum Segment {
+    // Segment matching the exact same text.
+    Static(String),
+
+    // Segment matching any text, captured under the given name.
+    Param(String),
+}
+
+/// Path template such as `/users/:id/orders/:order`.
+///
+/// Segments starting with `:` are path parameters matching any single
+/// segment. Empty segments are ignored, so `/users/`, `users` and
+/// `/users` are the same pattern, and `""` is the root pattern `/`.
+///
+/// Patterns order by specificity, segment by segment: a static segment
+/// comes before a path parameter at the same position, so `/users/:id`
+/// orders before `/:kind/:id`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub(super) struct PathPattern(Vec<Segment>);
+
+impl PathPattern {
+    /// Parses a path template.
+    pub(super) fn parse(template: &str) -> Self {
+        let segments = segments(template)
+            .map(|s| match s.strip_prefix(':') {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Static(s.to_string()),
+            })
+            .collect();
+
+        Self(segments)
+    }
+
+    /// Returns whether the pattern has no path parameter.
+    pub(super) fn is_static(&self) -> bool {
+        self.0
+            .iter()
+            .all(|s| matches!(s, Segment::Static(_)))
+    }
+
+    /// Matches a concrete path against the pattern and returns the
+    /// captured path parameters, in order, on success.
+    pub(super) fn matches(&self, path: &str) -> Option<Params> {
+        let mut params = Params::new();
+        let mut segments = segments(path);
+
+        for expected in &self.0 {
+            let actual = segments.next()?;
+            match expected {
+                Segment::Static(s) if s == actual => {}
+                Segment::Static(_) => return None,
+                Segment::Param(name) => params.push((name.clone(), actual.to_string())),
+            }
+        }
+
+        match segments.next() {
+            Some(_) => None,
+            None => Some(params),
+        }
+    }
+}
+
+/// Splits a path into its non-empty segments.
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_pattern() {
+        let pattern = PathPattern::parse("");
+        assert!(pattern.is_static());
+        assert_eq!(pattern, PathPattern::parse("/"));
+        assert_eq!(pattern.matches(""), Some(Params::new()));
+        assert_eq!(pattern.matches("/"), Some(Params::new()));
+        assert!(
+            pattern
+                .matches("/users")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn static_pattern() {
+        let pattern = PathPattern::parse("/users/active");
+        assert!(pattern.is_static());
+        assert!(
+            pattern
+                .matches("/users/active/")
+                .is_some()
+        );
+        assert!(
+            pattern
+                .matches("/users")
+                .is_none()
+        );
+        assert!(
+            pattern
+                .matches("/users/inactive")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn pattern_with_params() {
+        let pattern = PathPattern::parse("/users/:id/orders/:order");
+        assert!(!pattern.is_static());
+
+        let params = pattern.matches("/users/42/orders/7");
+        assert_eq!(
+            params,
+            Some(vec![
+                ("id".to_string(), "42".to_string()),
+                ("order".to_string(), "7".to_string()),
+            ])
+        );
+        assert!(
+            pattern
+                .matches("/users/42/orders")
+                .is_none()
+        );
+        assert!(
+            pattern
+                .matches("/users/42/orders/7/items")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn patterns_order_by_specificity() {
+        let mut patterns: Vec<_> = ["/:kind/:id", "/:kind", "/users/:id", "/users/active"]
+            .map(PathPattern::parse)
+            .into();
+        patterns.sort();
+
+        let expected: Vec<_> = ["/users/active", "/users/:id", "/:kind", "/:kind/:id"]
+            .map(PathPattern::parse)
+            .into();
+        assert_eq!(patterns, expected);
+    }
+}
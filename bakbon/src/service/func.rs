@@ -0,0 +1,213 @@
+use {
+    super::{
+        Processor,
+        Service,
+        ServiceBox,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Reply,
+        Result,
+    },
+    std::{
+        fmt,
+        sync::Arc,
+    },
+};
+
+/// [`Processor`] backed by a closure.
+///
+/// Created with [`processor_fn()`] or `<dyn Processor>::from_fn()`.
+#[derive(Clone)]
+pub struct FnProcessor<F>(F);
+
+impl<F> fmt::Debug for FnProcessor<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("FnProcessor") }
+}
+
+impl<F> Processor for FnProcessor<F>
+where
+    F: Fn(Envelope) -> Result<Reply> + Send + Sync,
+{
+    fn execute(&self, message: Envelope) -> Result<Reply> { (self.0)(message) }
+}
+
+impl dyn Processor {
+    /// Creates a [`Processor`] from a closure.
+    ///
+    /// Equivalent to [`processor_fn()`].
+    ///
+    /// ```rust
+    /// use bakbon::*;
+    ///
+    /// let nil = <dyn Processor>::from_fn(|_msg| Ok(None));
+    /// ```
+    pub fn from_fn<F>(f: F) -> FnProcessor<F>
+    where
+        F: Fn(Envelope) -> Result<Reply> + Send + Sync,
+    {
+        FnProcessor(f)
+    }
+}
+
+/// Creates a [`Processor`] from a closure.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let echo = processor_fn(|msg| {
+///     let payload = msg.payload().clone();
+///     Ok(Some(msg.into_reply(payload)))
+/// });
+///
+/// let src = Address::parse("http://client.com").unwrap();
+/// let dst = Address::parse("http://echo.com").unwrap();
+/// let reply = echo.execute(Envelope::new(src, dst, Payload::from("hi")));
+/// assert_eq!(reply.unwrap().unwrap().payload(), &Payload::from("hi"));
+/// ```
+pub fn processor_fn<F>(f: F) -> FnProcessor<F>
+where
+    F: Fn(Envelope) -> Result<Reply> + Send + Sync,
+{
+    FnProcessor(f)
+}
+
+/// [`Service`] backed by an [`Address`] and a closure.
+///
+/// Created with [`service_fn()`]. The closure is shared between
+/// [`duplicate()`](Service::duplicate)d instances.
+pub struct FnService<F> {
+    address: Address,
+    handler: Arc<F>,
+}
+
+impl<F> fmt::Debug for FnService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnService")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> Service for FnService<F>
+where
+    F: Fn(Envelope) -> Result<Reply> + Send + Sync + 'static,
+{
+    fn address(&self) -> &Address { &self.address }
+
+    fn duplicate(&self) -> ServiceBox {
+        Box::new(Self {
+            address: self.address.clone(),
+            handler: self.handler.clone(),
+        })
+    }
+
+    fn process(&self, msg: Envelope) -> Result<Reply> { (self.handler)(msg) }
+}
+
+/// Creates a [`Service`] located at `address` from a closure.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let address = Address::parse("http://echo.com").unwrap();
+/// let echo = service_fn(address.clone(), |msg| {
+///     let payload = msg.payload().clone();
+///     Ok(Some(msg.into_reply(payload)))
+/// });
+///
+/// let registry = Registry::builder().register(echo).build();
+/// assert_eq!(registry.list(), vec!["http://echo.com"]);
+/// ```
+pub fn service_fn<F>(address: Address, handler: F) -> FnService<F>
+where
+    F: Fn(Envelope) -> Result<Reply> + Send + Sync + 'static,
+{
+    FnService {
+        address,
+        handler: Arc::new(handler),
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Payload,
+            testing::{
+                SRC,
+                message,
+            },
+        },
+    };
+
+    const DST: &str = "http://echo.com";
+
+    #[test]
+    fn closure_processor() -> Result<()> {
+        let upper = processor_fn(|msg| {
+            let payload = msg
+                .payload()
+                .to_ascii_uppercase();
+            Ok(Some(msg.into_reply(Payload::from(payload))))
+        });
+
+        let reply = upper.execute(message(SRC, DST, "hello")?)?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("HELLO")
+        );
+        assert_eq!(format!("{upper:?}"), "FnProcessor");
+        Ok(())
+    }
+
+    #[test]
+    fn processor_from_fn() -> Result<()> {
+        let nil = <dyn Processor>::from_fn(|_| Ok(None));
+        assert!(
+            nil.execute(message(SRC, DST, "")?)?
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn closure_service() -> Result<()> {
+        let address = Address::parse(DST)?;
+        let echo = service_fn(address.clone(), |msg| {
+            let payload = msg.payload().clone();
+            Ok(Some(msg.into_reply(payload)))
+        });
+        assert_eq!(echo.address(), &address);
+
+        let reply = echo.process(message(SRC, DST, "hello")?)?;
+        let reply = reply.unwrap();
+        assert_eq!(reply.payload(), &Payload::from("hello"));
+        assert_eq!(reply.source(), &address);
+        Ok(())
+    }
+
+    #[test]
+    fn duplicated_closure_service() -> Result<()> {
+        let address = Address::parse(DST)?;
+        let nil = service_fn(address.clone(), |_| Ok(None));
+
+        let dupe = nil.duplicate();
+        assert_eq!(dupe.address(), &address);
+        assert!(
+            dupe.process(message(SRC, DST, "")?)?
+                .is_none()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+use {
+    crate::{
+        Address,
+        Envelope,
+        Error,
+        Payload,
+        Result,
+    },
+    std::str::FromStr,
+};
+
+/// Types that can be extracted from an incoming [`Envelope`].
+///
+/// Extractors are the arguments of a [`Handler`](super::Handler). They
+/// borrow the [`Envelope`] so that any number of them can run before the
+/// [`Envelope`] is handed to the [`Responder`](super::Responder).
+///
+/// Returns [`Error::ExtractionFailed`] when the [`Envelope`] does not hold
+/// the expected part.
+pub trait FromEnvelope: Sized {
+    fn from_envelope(msg: &Envelope) -> Result<Self>;
+}
+
+/// Name of a header read by the [`Header`] extractor.
+///
+/// Usually declared with the [`header_name!`](crate::header_name) macro.
+pub trait HeaderName {
+    /// The header key, e.g. `"x-tenant"`.
+    const NAME: &'static str;
+
+    /// The type the header value is parsed into.
+    type Value: FromStr;
+}
+
+/// Declares a [`HeaderName`] type for the [`Header`] extractor.
+///
+/// The value type defaults to `String` and can be any `FromStr` type.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// header_name!(XTenant, "x-tenant");
+/// header_name!(XRetries: u32, "x-retries");
+///
+/// fn handle(Header(tenant): Header<XTenant>, Header(retries): Header<XRetries>) -> String {
+///     format!("{tenant}:{retries}")
+/// }
+/// ```
+#[macro_export]
+macro_rules! header_name {
+    ($name:ident, $key:literal) => {
+        $crate::header_name!($name: String, $key);
+    };
+    ($name:ident: $value:ty, $key:literal) => {
+        #[derive(Debug)]
+        pub struct $name;
+
+        impl $crate::HeaderName for $name {
+            type Value = $value;
+
+            const NAME: &'static str = $key;
+        }
+    };
+}
+
+/// Name of a path parameter read by the [`Param`] extractor.
+///
+/// Usually declared with the [`param_name!`](crate::param_name) macro.
+pub trait ParamName {
+    /// The parameter name, e.g. `"id"` for a `/users/:id` template.
+    const NAME: &'static str;
+
+    /// The type the parameter value is parsed into.
+    type Value: FromStr;
+}
+
+/// Declares a [`ParamName`] type for the [`Param`] extractor.
+///
+/// The value type defaults to `String` and can be any `FromStr` type.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// param_name!(UserId: u64, "id");
+/// param_name!(OrderId, "order");
+///
+/// // Registered as "/users/:id/orders/:order".
+/// fn get_order(Param(user): Param<UserId>, Param(order): Param<OrderId>) -> String {
+///     format!("{user}:{order}")
+/// }
+/// ```
+#[macro_export]
+macro_rules! param_name {
+    ($name:ident, $key:literal) => {
+        $crate::param_name!($name: String, $key);
+    };
+    ($name:ident: $value:ty, $key:literal) => {
+        #[derive(Debug)]
+        pub struct $name;
+
+        impl $crate::ParamName for $name {
+            type Value = $value;
+
+            const NAME: &'static str = $key;
+        }
+    };
+}
+
+/// Extracts the first path parameter captured by the
+/// [`Dispatcher`](crate::Dispatcher), parsed into `T`.
+///
+/// Templates with several parameters are read by name with [`Param`].
+///
+/// ```ignore
+/// // Registered as "/users/:id".
+/// fn get_user(Path(id): Path<u64>) -> String { ... }
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct Path<T>(pub T);
+
+impl<T: FromStr> FromEnvelope for Path<T> {
+    fn from_envelope(msg: &Envelope) -> Result<Self> {
+        let (name, value) = msg
+            .params()
+            .first()
+            .ok_or_else(|| Error::ExtractionFailed("missing path parameter".to_string()))?;
+
+        value
+            .parse()
+            .map(Path)
+            .map_err(|_| Error::ExtractionFailed(format!("invalid path parameter '{name}'")))
+    }
+}
+
+/// Extracts a path parameter declared with [`ParamName`], parsed into its
+/// value type.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Param<N: ParamName>(pub N::Value);
+
+impl<N: ParamName> FromEnvelope for Param<N> {
+    fn from_envelope(msg: &Envelope) -> Result<Self> {
+        let value = msg
+            .param(N::NAME)
+            .ok_or_else(|| {
+                Error::ExtractionFailed(format!(
+                    "missing path parameter '{}'",
+                    N::NAME
+                ))
+            })?;
+
+        value
+            .parse()
+            .map(Param)
+            .map_err(|_| {
+                Error::ExtractionFailed(format!(
+                    "invalid path parameter '{}'",
+                    N::NAME
+                ))
+            })
+    }
+}
+
+/// Extracts a header declared with [`HeaderName`], parsed into its value
+/// type.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Header<N: HeaderName>(pub N::Value);
+
+impl<N: HeaderName> FromEnvelope for Header<N> {
+    fn from_envelope(msg: &Envelope) -> Result<Self> {
+        let value = msg
+            .get_header(N::NAME)
+            .ok_or_else(|| Error::ExtractionFailed(format!("missing header '{}'", N::NAME)))?;
+
+        value
+            .parse()
+            .map(Header)
+            .map_err(|_| Error::ExtractionFailed(format!("invalid header '{}'", N::NAME)))
+    }
+}
+
+/// Extracts the [`Envelope`] source [`Address`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Source(pub Address);
+
+impl FromEnvelope for Source {
+    fn from_envelope(msg: &Envelope) -> Result<Self> { Ok(Source(msg.source().clone())) }
+}
+
+impl FromEnvelope for Payload {
+    /// Extracts the raw [`Payload`] bytes.
+    fn from_envelope(msg: &Envelope) -> Result<Self> { Ok(msg.payload().clone()) }
+}
+
+impl<T: FromEnvelope> FromEnvelope for Option<T> {
+    /// Turns a failed extraction into `None`.
+    fn from_envelope(msg: &Envelope) -> Result<Self> { Ok(T::from_envelope(msg).ok()) }
+}
+
+/// Extracts and deserializes a JSON [`Payload`] into `T`.
+///
+/// As a [`Responder`](super::Responder), serializes `T` into the reply
+/// [`Payload`] with a `content-type: application/json` header.
+#[cfg(feature = "json")]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "json")]
+impl<T: serde::de::DeserializeOwned> FromEnvelope for Json<T> {
+    fn from_envelope(msg: &Envelope) -> Result<Self> {
+        serde_json::from_slice(msg.payload())
+            .map(Json)
+            .map_err(|e| Error::ExtractionFailed(format!("invalid json payload: {e}")))
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testing::{
+            SRC,
+            message,
+        },
+    };
+
+    const DST: &str = "http://users.com/users/42";
+
+    header_name!(XTenant, "x-tenant");
+    header_name!(XRetries: u32, "x-retries");
+    param_name!(UserId: u64, "id");
+    param_name!(OrderId, "order");
+
+    #[test]
+    fn extract_path() -> Result<()> {
+        let mut msg = message(SRC, DST, "body")?;
+        assert!(matches!(
+            Path::<u64>::from_envelope(&msg),
+            Err(Error::ExtractionFailed(_))
+        ));
+
+        msg.add_param("id", "42");
+        assert_eq!(Path::<u64>::from_envelope(&msg)?, Path(42));
+
+        msg = message(SRC, DST, "body")?;
+        msg.add_param("id", "forty-two");
+        assert!(matches!(
+            Path::<u64>::from_envelope(&msg),
+            Err(Error::ExtractionFailed(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn extract_param() -> Result<()> {
+        let mut msg = message(SRC, DST, "body")?;
+        msg.add_param("id", "42");
+        msg.add_param("order", "a-7");
+
+        let Param(user) = Param::<UserId>::from_envelope(&msg)?;
+        let Param(order) = Param::<OrderId>::from_envelope(&msg)?;
+        assert_eq!(user, 42);
+        assert_eq!(order, "a-7");
+
+        let mut msg = message(SRC, DST, "body")?;
+        msg.add_param("order", "a-7");
+        assert!(matches!(
+            Param::<UserId>::from_envelope(&msg),
+            Err(Error::ExtractionFailed(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn extract_header() -> Result<()> {
+        let msg = message(SRC, DST, "body")?
+            .header("x-tenant", "acme")
+            .header("x-retries", "3");
+
+        let Header(tenant) = Header::<XTenant>::from_envelope(&msg)?;
+        let Header(retries) = Header::<XRetries>::from_envelope(&msg)?;
+        assert_eq!(tenant, "acme");
+        assert_eq!(retries, 3);
+
+        let msg = message(SRC, DST, "body")?;
+        assert!(matches!(
+            Header::<XTenant>::from_envelope(&msg),
+            Err(Error::ExtractionFailed(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn extract_optional() -> Result<()> {
+        let msg = message(SRC, DST, "body")?;
+        let tenant = Option::<Header<XTenant>>::from_envelope(&msg)?;
+        assert!(tenant.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn extract_source_and_payload() -> Result<()> {
+        let msg = message(SRC, DST, "body")?;
+        let Source(source) = Source::from_envelope(&msg)?;
+        assert_eq!(source.to_string(), "http://client.com");
+        assert_eq!(
+            Payload::from_envelope(&msg)?,
+            Payload::from("body")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+//! Typed handlers adapted into [`Processor`]s.
+//!
+//! - [`FromEnvelope`] extracts typed arguments ([`Path`], [`Param`],
+//!   [`Header`], `Json`, ...) from an incoming [`Envelope`].
+//! - [`Responder`] turns return values into a [`Reply`].
+//! - [`Handler`] ties both together for plain functions, and [`handler()`]
+//!   adapts one into a [`Processor`].
+
+mod extract;
+mod respond;
+
+#[cfg(feature = "json")]
+pub use extract::Json;
+use {
+    super::Processor,
+    crate::{
+        Envelope,
+        Reply,
+        Result,
+    },
+    std::{
+        fmt,
+        marker::PhantomData,
+    },
+};
+pub use {
+    extract::{
+        FromEnvelope,
+        Header,
+        HeaderName,
+        Param,
+        ParamName,
+        Path,
+        Source,
+    },
+    respond::Responder,
+};
+
+/// Function taking [`FromEnvelope`] arguments and returning a
+/// [`Responder`].
+///
+/// Implemented for any `Fn` of up to eight extractors. `Args` is the tuple
+/// of argument types and only exists to tell implementations apart.
+pub trait Handler<Args>: Send + Sync + 'static {
+    fn call(&self, msg: Envelope) -> Result<Reply>;
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: Responder,
+            $($arg: FromEnvelope,)*
+        {
+            #[allow(non_snake_case)]
+            fn call(&self, msg: Envelope) -> Result<Reply> {
+                $(let $arg = $arg::from_envelope(&msg)?;)*
+                self($($arg),*).respond(msg)
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(A1);
+impl_handler!(A1, A2);
+impl_handler!(A1, A2, A3);
+impl_handler!(A1, A2, A3, A4);
+impl_handler!(A1, A2, A3, A4, A5);
+impl_handler!(A1, A2, A3, A4, A5, A6);
+impl_handler!(A1, A2, A3, A4, A5, A6, A7);
+impl_handler!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+/// [`Processor`] adapting a typed [`Handler`].
+///
+/// Created with [`handler()`].
+pub struct HandlerProcessor<H, Args> {
+    handler: H,
+    args:    PhantomData<fn() -> Args>,
+}
+
+impl<H, Args> fmt::Debug for HandlerProcessor<H, Args> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("HandlerProcessor") }
+}
+
+impl<H, Args> Processor for HandlerProcessor<H, Args>
+where
+    H: Handler<Args>,
+    Args: 'static,
+{
+    fn execute(&self, message: Envelope) -> Result<Reply> { self.handler.call(message) }
+}
+
+/// Adapts a typed [`Handler`] into a [`Processor`].
+///
+/// Each argument is extracted with [`FromEnvelope`], in order, before the
+/// handler runs; the first failing extractor short-circuits with its
+/// error. The return value is turned into a [`Reply`] with [`Responder`].
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// header_name!(XTenant, "x-tenant");
+///
+/// fn get_user(Path(id): Path<u64>, Header(tenant): Header<XTenant>) -> Result<String> {
+///     Ok(format!("{tenant}/{id}"))
+/// }
+///
+/// let address = Address::parse("http://users.com").unwrap();
+/// let users = Dispatcher::builder(address)
+///     .route("GET", "/users/:id", handler(get_user))
+///     .build();
+///
+/// let src = Address::parse("http://client.com").unwrap();
+/// let dst = Address::parse("http://users.com/users/42").unwrap();
+/// let msg = Envelope::new(src, dst, Payload::new())
+///     .header(METHOD_HEADER, "GET")
+///     .header("x-tenant", "acme");
+///
+/// let reply = users.process(msg).unwrap().unwrap();
+/// assert_eq!(reply.payload(), &Payload::from("acme/42"));
+/// ```
+pub fn handler<H, Args>(handler: H) -> HandlerProcessor<H, Args>
+where
+    H: Handler<Args>,
+{
+    HandlerProcessor {
+        handler,
+        args: PhantomData,
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Error,
+            Payload,
+            testing::{
+                SRC,
+                message,
+            },
+        },
+    };
+
+    crate::header_name!(XTenant, "x-tenant");
+
+    /// Creates a request for `/users/42` matched against `/users/:id`.
+    fn request() -> Result<Envelope> {
+        let mut msg = message(SRC, "http://users.com/users/42", "body")?;
+        msg.add_param("id", "42");
+        Ok(msg)
+    }
+
+    #[test]
+    fn handler_without_arguments() -> Result<()> {
+        let processor = handler(|| "pong");
+        let reply = processor.execute(request()?)?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("pong")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn handler_with_extractors() -> Result<()> {
+        fn get(Path(id): Path<u64>, Header(tenant): Header<XTenant>, body: Payload) -> String {
+            format!(
+                "{tenant}/{id}/{}",
+                String::from_utf8_lossy(&body)
+            )
+        }
+
+        let processor = handler(get);
+        let reply = processor.execute(request()?.header("x-tenant", "acme"))?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("acme/42/body")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn failed_extraction_short_circuits() -> Result<()> {
+        let processor = handler(|Header(_): Header<XTenant>| -> () {
+            panic!("handler must not run");
+        });
+        assert!(matches!(
+            processor.execute(request()?),
+            Err(Error::ExtractionFailed(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_handler() -> Result<()> {
+        #[derive(serde::Deserialize)]
+        struct Req {
+            name: String,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Resp {
+            id:   u64,
+            name: String,
+        }
+
+        fn create(Path(id): Path<u64>, Json(req): Json<Req>) -> Result<Json<Resp>> {
+            Ok(Json(Resp {
+                id,
+                name: req.name,
+            }))
+        }
+
+        let msg = request()?.with_payload(Payload::from(r#"{"name":"nil"}"#));
+        let reply = handler(create)
+            .execute(msg)?
+            .unwrap();
+        assert_eq!(
+            reply.payload(),
+            &Payload::from(r#"{"id":42,"name":"nil"}"#)
+        );
+        assert_eq!(
+            reply.get_header("content-type"),
+            Some("application/json")
+        );
+
+        let msg = request()?.with_payload(Payload::from("not json"));
+        assert!(matches!(
+            handler(create).execute(msg),
+            Err(Error::ExtractionFailed(_))
+        ));
+        Ok(())
+    }
+}
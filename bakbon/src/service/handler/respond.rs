@@ -0,0 +1,132 @@
+use crate::{
+    Envelope,
+    Payload,
+    Reply,
+    Result,
+};
+
+/// Types that can be turned into a [`Reply`] to an [`Envelope`].
+///
+/// Responders are the return values of a [`Handler`](super::Handler).
+/// Bodies such as [`Payload`] or `String` are wrapped into a reply with
+/// [`Envelope::into_reply()`], so the reply travels back to the original
+/// sender with the request [`Headers`](crate::Headers).
+pub trait Responder {
+    fn respond(self, request: Envelope) -> Result<Reply>;
+}
+
+impl Responder for () {
+    /// Replies nothing.
+    fn respond(self, _request: Envelope) -> Result<Reply> { Ok(None) }
+}
+
+impl Responder for Reply {
+    /// Replies the given [`Reply`] as is.
+    fn respond(self, _request: Envelope) -> Result<Reply> { Ok(self) }
+}
+
+impl Responder for Envelope {
+    /// Replies the given [`Envelope`] as is.
+    fn respond(self, _request: Envelope) -> Result<Reply> { Ok(Some(self)) }
+}
+
+impl Responder for Payload {
+    fn respond(self, request: Envelope) -> Result<Reply> { Ok(Some(request.into_reply(self))) }
+}
+
+impl Responder for String {
+    fn respond(self, request: Envelope) -> Result<Reply> { Payload::from(self).respond(request) }
+}
+
+impl Responder for &'static str {
+    fn respond(self, request: Envelope) -> Result<Reply> { Payload::from(self).respond(request) }
+}
+
+impl<T: Responder> Responder for Result<T> {
+    /// Propagates the error or responds with the value.
+    fn respond(self, request: Envelope) -> Result<Reply> { self?.respond(request) }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize> Responder for super::Json<T> {
+    fn respond(self, request: Envelope) -> Result<Reply> {
+        let body = serde_json::to_vec(&self.0)
+            .map_err(|e| crate::Error::SerializationFailed(e.to_string()))?;
+
+        let reply = request
+            .into_reply(Payload::from(body))
+            .header("content-type", "application/json");
+        Ok(Some(reply))
+    }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Address,
+            Error,
+        },
+    };
+
+    const SRC: &str = "http://client.com";
+    const DST: &str = "http://users.com";
+
+    fn request() -> Result<Envelope> {
+        let src = Address::parse(SRC)?;
+        let dst = Address::parse(DST)?;
+        Ok(Envelope::new(src, dst, Payload::new()))
+    }
+
+    #[test]
+    fn respond_nothing() -> Result<()> {
+        assert!(().respond(request()?)?.is_none());
+        assert!(
+            None.respond(request()?)?
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn respond_with_body() -> Result<()> {
+        let reply = "hello"
+            .respond(request()?)?
+            .unwrap();
+        assert_eq!(reply.payload(), &Payload::from("hello"));
+        assert_eq!(reply.source().to_string(), DST);
+        assert_eq!(
+            reply
+                .destination()
+                .to_string(),
+            SRC
+        );
+
+        let reply = String::from("hi")
+            .respond(request()?)?
+            .unwrap();
+        assert_eq!(reply.payload(), &Payload::from("hi"));
+        Ok(())
+    }
+
+    #[test]
+    fn respond_with_result() -> Result<()> {
+        let ok: Result<&str> = Ok("ok");
+        assert!(
+            ok.respond(request()?)?
+                .is_some()
+        );
+
+        let err: Result<&str> = Err(Error::ServiceNotFound);
+        assert!(matches!(
+            err.respond(request()?),
+            Err(Error::ServiceNotFound)
+        ));
+        Ok(())
+    }
+}
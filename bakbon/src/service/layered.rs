@@ -0,0 +1,160 @@
+use {
+    super::{
+        Service,
+        ServiceBox,
+        ServiceContext,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Middleware,
+        Reply,
+        Result,
+        SemVer,
+        infra::Chain,
+    },
+    std::fmt,
+};
+
+/// [`Service`] running a middleware chain around another one.
+///
+/// The chain runs in the order the middlewares were added, and only for
+/// this [`Service`]; to run one in front of every [`Service`], set it on
+/// the [`Router`](crate::Router) instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// struct Validate;
+///
+/// impl Middleware for Validate {
+///     fn intercept(&self, msg: Envelope) -> Result<Envelope> {
+///         match msg.payload().is_empty() {
+///             true => Err(Error::ExtractionFailed("empty payload".into())),
+///             false => Ok(msg),
+///         }
+///     }
+/// }
+///
+/// let address = Address::parse("http://echo.com")?;
+/// let echo = service_fn(address.clone(), |msg| {
+///     let payload = msg.payload().clone();
+///     Ok(Some(msg.into_reply(payload)))
+/// });
+/// let echo = Layered::new(echo).layer(Validate);
+///
+/// let empty = Envelope::new(address.clone(), address, Payload::new());
+/// assert!(echo.process(empty).is_err());
+/// # Ok::<(), Error>(())
+/// ```
+pub struct Layered<S> {
+    service: S,
+    chain:   Chain,
+}
+
+impl<S: Service> Layered<S> {
+    /// Wraps a [`Service`] with an empty middleware chain.
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            chain: Chain::default(),
+        }
+    }
+
+    /// Adds a [`Middleware`] at the end of the chain.
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.chain.push(middleware);
+        self
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Layered<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Layered")
+            .field("service", &self.service)
+            .field("middlewares", &self.chain.len())
+            .finish()
+    }
+}
+
+impl<S: Service> Service for Layered<S> {
+    fn address(&self) -> &Address { self.service.address() }
+
+    fn duplicate(&self) -> ServiceBox {
+        Box::new(Layered {
+            service: self.service.duplicate(),
+            chain:   self.chain.clone(),
+        })
+    }
+
+    fn process(&self, msg: Envelope) -> Result<Reply> {
+        self.process_with(msg, &ServiceContext::detached())
+    }
+
+    fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        self.chain.run(msg, |msg| {
+            self.service
+                .process_with(msg, ctx)
+        })
+    }
+
+    fn version(&self) -> Option<&SemVer> { self.service.version() }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            Next,
+            Payload,
+            service_fn,
+        },
+    };
+
+    /// Upper-cases the payload of replies.
+    struct Shout;
+
+    impl Middleware for Shout {
+        fn around(&self, message: Envelope, next: Next<'_>) -> Result<Reply> {
+            let reply = next.run(message)?;
+            Ok(reply.map(|reply| {
+                let payload = reply
+                    .payload()
+                    .to_ascii_uppercase();
+                reply.with_payload(Payload::from(payload))
+            }))
+        }
+    }
+
+    #[test]
+    fn layered_service() -> Result<()> {
+        let address = Address::parse("http://echo.com")?;
+        let echo = service_fn(address.clone(), |msg| {
+            let payload = msg.payload().clone();
+            Ok(Some(msg.into_reply(payload)))
+        });
+        let service = Layered::new(echo).layer(Shout);
+        assert_eq!(service.address(), &address);
+
+        let msg = Envelope::new(
+            address.clone(),
+            address,
+            Payload::from("hello"),
+        );
+        let reply = service
+            .duplicate()
+            .process(msg)?;
+        assert_eq!(
+            reply.unwrap().payload(),
+            &Payload::from("HELLO")
+        );
+        Ok(())
+    }
+}
@@ -1,25 +1,85 @@
+#[cfg(feature = "async")]
+mod asynchronous;
+mod context;
+mod dispatcher;
+mod func;
+mod handler;
+mod layered;
 mod processor;
+mod versioned;
 
-pub use processor::{
-    ProcMap,
-    Processor,
+#[cfg(feature = "tokio")]
+pub use asynchronous::SpawnBlocking;
+#[cfg(feature = "async")]
+pub use asynchronous::{
+    AsyncProcessor,
+    AsyncService,
+    AsyncServiceBox,
+    Blocking,
+    BoxFuture,
 };
+#[cfg(feature = "json")]
+pub use handler::Json;
 use {
     super::{
         Envelope,
         Reply,
         Result,
     },
-    crate::Address,
+    crate::{
+        Address,
+        SemVer,
+        ServiceKey,
+    },
     std::{
         collections::HashMap,
         fmt::Debug,
+        sync::Arc,
+    },
+};
+pub use {
+    context::ServiceContext,
+    dispatcher::{
+        Dispatcher,
+        DispatcherBuilder,
+        METHOD_HEADER,
+        Method,
+    },
+    func::{
+        FnProcessor,
+        FnService,
+        processor_fn,
+        service_fn,
+    },
+    handler::{
+        FromEnvelope,
+        Handler,
+        HandlerProcessor,
+        Header,
+        HeaderName,
+        Param,
+        ParamName,
+        Path,
+        Responder,
+        Source,
+        handler,
+    },
+    layered::Layered,
+    processor::{
+        ProcMap,
+        Processor,
     },
+    versioned::Versioned,
 };
 
 /// A service that processes envelopes and returns replies.
 ///
-/// Implement this trait to create custom services in BakBon.
+/// Implement this trait to create custom services in BakBon, or use
+/// [`service_fn()`] for small closure-based services.
+///
+/// Services must be `Send + Sync`: a [`Router`](crate::Router) and the
+/// instances in its [`Registry`](crate::Registry) are shared by every
+/// thread routing through it.
 ///
 /// # Examples
 ///
@@ -42,15 +102,72 @@ use {
 ///     }
 /// }
 /// ```
-pub trait Service: Debug {
+pub trait Service: Debug + Send + Sync {
     fn address(&self) -> &Address;
     fn duplicate(&self) -> ServiceBox;
     fn process(&self, msg: Envelope) -> Result<Reply>;
+
+    /// Processes an [`Envelope`] with access to the [`ServiceContext`] of
+    /// the [`Router`](crate::Router) it came through.
+    ///
+    /// This is what the [`Router`](crate::Router) calls. The default
+    /// implementation ignores the context and calls
+    /// [`process()`](Service::process); services composing other services
+    /// override it, and usually implement `process()` by calling it with
+    /// [`ServiceContext::detached()`].
+    fn process_with(&self, msg: Envelope, _ctx: &ServiceContext) -> Result<Reply> {
+        self.process(msg)
+    }
+
+    /// Returns the version of this instance, if any, matched against the
+    /// [`ACCEPT_VERSION_HEADER`](crate::ACCEPT_VERSION_HEADER) of requests
+    /// by the [`Router`](crate::Router). See [`Versioned`].
+    fn version(&self) -> Option<&SemVer> { None }
 }
 
 /// A boxed service that can be cloned and processed.
 pub type ServiceBox = Box<dyn Service>;
 
+impl Service for ServiceBox {
+    fn address(&self) -> &Address { self.as_ref().address() }
+
+    fn duplicate(&self) -> ServiceBox { self.as_ref().duplicate() }
+
+    fn process(&self, msg: Envelope) -> Result<Reply> { self.as_ref().process(msg) }
+
+    fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        self.as_ref()
+            .process_with(msg, ctx)
+    }
+
+    fn version(&self) -> Option<&SemVer> { self.as_ref().version() }
+}
+
+/// A shared service instance.
+///
+/// Instances are shared between the [`Registry`](crate::Registry) and the
+/// requests they process, so an instance removed from a live
+/// [`Registry`](crate::Registry) stays alive until its in-flight requests
+/// are done.
+pub type ServiceArc = Arc<dyn Service>;
+
+impl Service for ServiceArc {
+    fn address(&self) -> &Address { self.as_ref().address() }
+
+    fn duplicate(&self) -> ServiceBox { self.as_ref().duplicate() }
+
+    fn process(&self, msg: Envelope) -> Result<Reply> { self.as_ref().process(msg) }
+
+    fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        self.as_ref()
+            .process_with(msg, ctx)
+    }
+
+    fn version(&self) -> Option<&SemVer> { self.as_ref().version() }
+}
+
 /// A vector of boxed services.
 pub type ServiceVec = Vec<ServiceBox>;
-pub type ServiceMap = HashMap<String, ServiceVec>;
+
+/// Instances of each logical service grouped by [`ServiceKey`].
+pub type ServiceMap = HashMap<ServiceKey, Vec<ServiceArc>>;
@@ -12,6 +12,12 @@ use {
 
 pub type ProcMap = HashMap<String, Box<dyn Processor>>;
 
-pub trait Processor: Debug {
+/// A unit of work executed by a [`Service`](crate::Service) on an
+/// [`Envelope`], typically selected by a
+/// [`Dispatcher`](crate::Dispatcher).
+///
+/// Processors are shared between [`Service`](crate::Service) instances,
+/// hence the `Send + Sync` bound.
+pub trait Processor: Debug + Send + Sync {
     fn execute(&self, message: Envelope) -> Result<Reply>;
 }
@@ -0,0 +1,117 @@
+use {
+    super::{
+        Service,
+        ServiceBox,
+        ServiceContext,
+    },
+    crate::{
+        Address,
+        Envelope,
+        Reply,
+        Result,
+        SemVer,
+    },
+};
+
+/// [`Service`] instance registered with a [`SemVer`] version.
+///
+/// Several versions of a [`Service`] can be registered under the same
+/// [`Address`]: a request carrying a
+/// [`VersionRange`](crate::VersionRange) in its
+/// [`ACCEPT_VERSION_HEADER`](crate::ACCEPT_VERSION_HEADER) is routed to
+/// the highest version in the range, and requests without one to any
+/// instance.
+///
+/// # Examples
+///
+/// ```rust
+/// use bakbon::*;
+///
+/// let address = Address::parse("http://orders.com")?;
+/// let reply = |version: &'static str| {
+///     service_fn(address.clone(), move |msg| {
+///         Ok(Some(msg.into_reply(Payload::from(version))))
+///     })
+/// };
+///
+/// let registry = Registry::builder()
+///     .register(Versioned::new(reply("v2.1"), SemVer::parse("2.1.0")?))
+///     .register(Versioned::new(reply("v2.4"), SemVer::parse("2.4.1")?))
+///     .register(Versioned::new(reply("v3"), SemVer::parse("3.0.0")?))
+///     .build();
+/// let router = Router::builder()
+///     .registry(registry)
+///     .build();
+///
+/// let msg = Envelope::new(address.clone(), address.clone(), Payload::new())
+///     .header(ACCEPT_VERSION_HEADER, "^2.1");
+/// let reply = router.route(msg)?;
+/// assert_eq!(reply.unwrap().payload(), &Payload::from("v2.4"));
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Versioned<S> {
+    service: S,
+    version: SemVer,
+}
+
+impl<S: Service> Versioned<S> {
+    /// Registers a [`Service`] with a version.
+    pub fn new(service: S, version: SemVer) -> Self {
+        Self {
+            service,
+            version,
+        }
+    }
+}
+
+impl<S: Service> Service for Versioned<S> {
+    fn address(&self) -> &Address { self.service.address() }
+
+    fn duplicate(&self) -> ServiceBox {
+        Box::new(Versioned {
+            service: self.service.duplicate(),
+            version: self.version.clone(),
+        })
+    }
+
+    fn process(&self, msg: Envelope) -> Result<Reply> { self.service.process(msg) }
+
+    fn process_with(&self, msg: Envelope, ctx: &ServiceContext) -> Result<Reply> {
+        self.service
+            .process_with(msg, ctx)
+    }
+
+    fn version(&self) -> Option<&SemVer> { Some(&self.version) }
+}
+
+//  +------------+
+//  | UNIT TESTS |
+//  +------------+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::service_fn,
+    };
+
+    #[test]
+    fn versioned_service() -> Result<()> {
+        let address = Address::parse("http://orders.com")?;
+        let service = service_fn(address.clone(), |_| Ok(None));
+        assert!(service.version().is_none());
+
+        let service = Versioned::new(service, SemVer::new(2, 1, 0));
+        assert_eq!(service.address(), &address);
+        assert_eq!(
+            service.version(),
+            Some(&SemVer::new(2, 1, 0))
+        );
+        assert_eq!(
+            service.duplicate().version(),
+            Some(&SemVer::new(2, 1, 0))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the unit tests.
+
+#[cfg(feature = "async")]
+use std::{
+    pin::pin,
+    task::{
+        Context,
+        Poll,
+        Waker,
+    },
+};
+
+use crate::{
+    Address,
+    Envelope,
+    Payload,
+    Result,
+};
+
+/// Source of the messages sent by the tests.
+pub(crate) const SRC: &str = "http://client.com";
+
+/// Creates a message from `src` to `dst` carrying a text payload.
+pub(crate) fn message(src: &str, dst: &str, payload: &str) -> Result<Envelope> {
+    Ok(Envelope::new(
+        Address::parse(src)?,
+        Address::parse(dst)?,
+        Payload::copy_from_slice(payload.as_bytes()),
+    ))
+}
+
+/// Polls a future that never waits to completion.
+#[cfg(feature = "async")]
+pub(crate) fn ready<T>(future: impl Future<Output = T>) -> T {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    match future.as_mut().poll(&mut cx) {
+        Poll::Ready(value) => value,
+        Poll::Pending => panic!("future is pending"),
+    }
+}
@@ -1,7 +1,7 @@
 use bakbon::{
     Address,
+    Dispatcher,
     Envelope,
-    Error,
     ProcMap,
     Processor,
     Reply,
@@ -22,37 +22,25 @@ impl Processor for EchoProc {
 }
 
 #[derive(Debug)]
-pub struct EchoService {
-    address:    Address,
-    processors: ProcMap,
-}
+pub struct EchoService(Dispatcher);
 
 impl EchoService {
     pub fn new(address: Address) -> Self {
         let mut processors = ProcMap::new();
         processors.insert(String::from(""), Box::new(EchoProc));
 
-        Self {
-            address,
-            processors,
-        }
+        let dispatcher = Dispatcher::builder(address)
+            .processors(processors)
+            .build();
+
+        Self(dispatcher)
     }
 }
 
 impl Service for EchoService {
-    fn address(&self) -> &Address { &self.address }
+    fn address(&self) -> &Address { self.0.address() }
 
-    fn duplicate(&self) -> ServiceBox {
-        let address = self.address.clone();
-        let dupe_service = Self::new(address);
-        Box::new(dupe_service)
-    }
+    fn duplicate(&self) -> ServiceBox { Box::new(Self(self.0.clone())) }
 
-    fn process(&self, message: Envelope) -> Result<Reply> {
-        let path = self.address.path();
-        match self.processors.get(path) {
-            Some(processor) => processor.execute(message),
-            None => Err(Error::ProcessorNotFound),
-        }
-    }
+    fn process(&self, message: Envelope) -> Result<Reply> { self.0.process(message) }
 }
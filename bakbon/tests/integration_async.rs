@@ -0,0 +1,110 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use {
+    crate::common::EchoService,
+    bakbon::{
+        AsyncRouter,
+        AsyncService,
+        AsyncServiceBox,
+        BoxFuture,
+        SpawnBlocking,
+        prelude::*,
+    },
+    std::{
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+#[derive(Debug)]
+struct SlowEcho(Address);
+
+impl AsyncService for SlowEcho {
+    fn address(&self) -> &Address { &self.0 }
+
+    fn duplicate(&self) -> AsyncServiceBox { Box::new(Self(self.0.clone())) }
+
+    fn process(&self, msg: Envelope) -> BoxFuture<'_, Result<Reply>> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            let payload = msg.payload().clone();
+            Ok(Some(msg.into_reply(payload)))
+        })
+    }
+}
+
+#[tokio::test(
+    flavor = "multi_thread",
+    worker_threads = 4
+)]
+async fn async_router_to_mixed_services() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let slow_addr = Address::parse("http://slow-echo")?;
+    let sync_addr = Address::parse("http://echo")?;
+    let blocking_addr = Address::parse("http://blocking-echo")?;
+
+    // Sync services keep working unchanged through the registry.
+    let registry = Registry::builder()
+        .register(EchoService::new(sync_addr.clone()))
+        .build();
+
+    let router = Arc::new(
+        AsyncRouter::builder()
+            .registry(registry)
+            .register(SlowEcho(slow_addr.clone()))
+            .register(SpawnBlocking::new(service_fn(
+                blocking_addr.clone(),
+                |msg| {
+                    std::thread::sleep(Duration::from_millis(1));
+                    let payload = msg.payload().clone();
+                    Ok(Some(msg.into_reply(payload)))
+                },
+            )))
+            .build(),
+    );
+    assert_eq!(router.list().len(), 3);
+
+    // Route many messages concurrently on tokio tasks.
+    let mut handles = Vec::new();
+    for i in 0..60 {
+        let destination = match i % 3 {
+            0 => slow_addr.clone(),
+            1 => sync_addr.clone(),
+            _ => blocking_addr.clone(),
+        };
+        let payload = Payload::from(format!("msg-{i}"));
+        let msg = Envelope::new(
+            client_addr.clone(),
+            destination.clone(),
+            payload.clone(),
+        );
+        handles.push((destination, payload, router.spawn(msg)));
+    }
+
+    for (destination, payload, handle) in handles {
+        let reply = handle
+            .await
+            .map_err(|e| Error::TaskFailed(e.to_string()))??
+            .unwrap();
+        assert_eq!(reply.payload(), &payload);
+        assert_eq!(reply.source(), &destination);
+        assert_eq!(reply.destination(), &client_addr);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn async_router_to_unknown_service() -> Result<()> {
+    let router = AsyncRouter::builder().build();
+    let src = Address::parse("http://client-service.com")?;
+    let dst = Address::parse("http://nowhere")?;
+
+    let reply = router
+        .route(Envelope::new(src, dst, Payload::new()))
+        .await;
+    assert!(matches!(reply, Err(Error::ServiceNotFound)));
+    Ok(())
+}
@@ -24,7 +24,7 @@ fn gateway_to_router_to_service() -> Result<()> {
     assert!(!registry.list().is_empty());
 
     // Build Router.
-    let mut router = Router::builder()
+    let router = Router::builder()
         .registry(registry)
         .build();
     assert_eq!(router.balancing_strategy(), "round_robin");
@@ -49,7 +49,7 @@ fn queue_to_router_to_service() -> Result<()> {
     assert!(!registry.list().is_empty());
 
     // Build Router.
-    let mut router = Router::builder()
+    let router = Router::builder()
         .registry(registry)
         .build();
     assert_eq!(router.balancing_strategy(), "round_robin");
@@ -71,7 +71,7 @@ fn queue_to_router_to_service() -> Result<()> {
     assert!(msg.is_some());
     let msg = msg.unwrap();
 
-    queue.enqueue(msg3)?;
+    queue.enqueue(*msg3)?;
     assert_eq!(queue.len(), 2);
 
     // Get Reply from Router.
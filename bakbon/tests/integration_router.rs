@@ -2,7 +2,38 @@ mod common;
 
 use {
     crate::common::EchoService,
-    bakbon::prelude::*,
+    bakbon::{
+        ACCEPT_VERSION_HEADER,
+        ATTEMPT_HEADER,
+        DEAD_LETTER_DESTINATION_HEADER,
+        DEAD_LETTER_ERROR_HEADER,
+        DEAD_LETTER_SERVICE_HEADER,
+        FORWARDED_BY_HEADER,
+        FileSagaStore,
+        HOPS_HEADER,
+        MIRROR_HEADER,
+        SAGA_ID_HEADER,
+        SagaStore,
+        prelude::*,
+    },
+    std::{
+        sync::{
+            Arc,
+            Barrier,
+            Mutex,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            mpsc,
+        },
+        thread,
+        time::{
+            Duration,
+            Instant,
+            SystemTime,
+        },
+    },
 };
 
 #[test]
@@ -24,7 +55,7 @@ fn router_to_service() -> Result<()> {
     assert!(!registry.list().is_empty());
 
     // Build Router.
-    let mut router = Router::builder()
+    let router = Router::builder()
         .registry(registry)
         .build();
     assert_eq!(router.balancing_strategy(), "round_robin");
@@ -62,3 +93,1979 @@ fn router_to_service() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn router_to_service_from_vector() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let srv_addr = Address::parse("http://echo/")?;
+    let services: ServiceVec = vec![Box::new(EchoService::new(srv_addr.clone()))];
+
+    let mut registry = Registry::from(services);
+    registry.add_instance(&srv_addr)?;
+    assert_eq!(
+        registry
+            .get(&srv_addr)
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let router = Router::builder()
+        .registry(registry)
+        .build();
+
+    let destination = Address::parse("http://echo?lang=en")?;
+    let msg = Envelope::new(
+        client_addr,
+        destination,
+        Payload::from("Hello..."),
+    );
+    let reply = router.route(msg)?;
+    assert_eq!(
+        reply.unwrap().payload(),
+        &Payload::from("Hello...")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn router_with_key_modes() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let srv_addr = Address::parse("http://echo/greetings")?;
+
+    for (mode, destination, found) in [
+        ("authority", "grpc://echo/greetings/42", true),
+        (
+            "authority_path",
+            "grpc://echo/greetings",
+            true,
+        ),
+        (
+            "authority_path",
+            "http://echo/farewells",
+            false,
+        ),
+        ("full", "http://echo/greetings?lang=en", true),
+        ("full", "http://echo/greetings/42", true),
+        ("full", "http://echo/farewells", false),
+        ("full", "grpc://echo/greetings", false),
+    ] {
+        let registry = Registry::builder()
+            .key_mode(mode)
+            .register(EchoService::new(srv_addr.clone()))
+            .build();
+        let router = Router::builder()
+            .registry(registry)
+            .build();
+
+        let address = Address::parse(destination)?;
+        let msg = Envelope::new(client_addr.clone(), address, Payload::new());
+        // Subpaths fall back to the echo service, which only handles its
+        // root path: they fail in its dispatcher, not in the router lookup.
+        let result = router.route(msg);
+        let not_found = matches!(result, Err(Error::ServiceNotFound));
+        assert_eq!(!not_found, found, "{mode}: {destination}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn router_shared_between_threads() -> Result<()> {
+    const THREADS: usize = 8;
+    const MESSAGES: usize = 250;
+    const INSTANCES: usize = 4;
+
+    let client_addr = Address::parse("http://client-service.com")?;
+    let srv_addr = Address::parse("http://counter")?;
+
+    // Register several instances counting the messages they process.
+    let counters: Vec<Arc<AtomicUsize>> = (0..INSTANCES)
+        .map(|_| Arc::new(AtomicUsize::new(0)))
+        .collect();
+    let mut builder = Registry::builder();
+    for counter in &counters {
+        let counter = counter.clone();
+        builder = builder.register(service_fn(srv_addr.clone(), move |msg| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            let payload = msg.payload().clone();
+            Ok(Some(msg.into_reply(payload)))
+        }));
+    }
+
+    let router = Arc::new(
+        Router::builder()
+            .registry(builder.build())
+            .build(),
+    );
+
+    // Hammer the same router from many threads.
+    let workers: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let router = Arc::clone(&router);
+            let client_addr = client_addr.clone();
+            let srv_addr = srv_addr.clone();
+            thread::spawn(move || -> Result<()> {
+                for m in 0..MESSAGES {
+                    let payload = Payload::from(format!("{t}-{m}"));
+                    let msg = Envelope::new(
+                        client_addr.clone(),
+                        srv_addr.clone(),
+                        payload.clone(),
+                    );
+                    let reply = router.route(msg)?.unwrap();
+                    assert_eq!(reply.payload(), &payload);
+                }
+                Ok(())
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap()?;
+    }
+
+    // Round-robin spreads the load evenly, whatever the interleaving.
+    for counter in &counters {
+        assert_eq!(
+            counter.load(Ordering::Relaxed),
+            THREADS * MESSAGES / INSTANCES
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn router_scatter_gather() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let fast_addr = Address::parse("http://fast-prices")?;
+    let slow_addr = Address::parse("http://slow-prices")?;
+    let broken_addr = Address::parse("http://broken-prices")?;
+    let missing_addr = Address::parse("http://missing-prices")?;
+
+    let price = |price: &'static str, delay: u64| {
+        move |msg: Envelope| {
+            thread::sleep(Duration::from_millis(delay));
+            Ok(Some(msg.into_reply(Payload::from(price))))
+        }
+    };
+    let registry = Registry::builder()
+        .register(service_fn(fast_addr.clone(), price("12", 0)))
+        .register(service_fn(
+            slow_addr.clone(),
+            price("10", 2_000),
+        ))
+        .register(service_fn(broken_addr.clone(), |_| {
+            Err(Error::ProcessorNotFound(
+                "/prices".to_string(),
+            ))
+        }))
+        .build();
+    let router = Router::builder()
+        .registry(registry)
+        .build();
+
+    // Keep the lowest price among the replies.
+    let gather = Gather::builder()
+        .targets([
+            fast_addr.clone(),
+            slow_addr.clone(),
+            broken_addr.clone(),
+            missing_addr.clone(),
+        ])
+        .deadline(Duration::from_millis(300))
+        .aggregator(|request: Envelope, report: &GatherReport| {
+            let lowest = report
+                .replies()
+                .iter()
+                .filter_map(|(_, reply)| reply.as_ref())
+                .map(|reply| reply.payload().clone())
+                .min();
+            Ok(lowest.map(|price| request.into_reply(price)))
+        })
+        .build();
+
+    let msg = Envelope::new(
+        client_addr.clone(),
+        client_addr.clone(),
+        Payload::from("price?"),
+    );
+    let started = Instant::now();
+    let (reply, report) = router.scatter_gather(msg, &gather)?;
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let reply = reply.unwrap();
+    assert_eq!(reply.payload(), &Payload::from("12"));
+    assert_eq!(reply.destination(), &client_addr);
+
+    assert_eq!(report.replies().len(), 1);
+    assert_eq!(report.replies()[0].0, fast_addr);
+    let failed: Vec<&Address> = report
+        .failed()
+        .iter()
+        .map(|(target, _)| target)
+        .collect();
+    assert_eq!(failed, vec![&broken_addr, &missing_addr]);
+    assert_eq!(report.timed_out().len(), 1);
+    assert_eq!(report.timed_out()[0], slow_addr);
+    assert!(!report.is_complete());
+    // The slow target keeps running on a worker thread.
+    assert!(router.overdue_workers() >= 1);
+
+    // A quorum of one stops at the first successful reply.
+    let gather = Gather::builder()
+        .targets([slow_addr.clone(), fast_addr.clone()])
+        .quorum(1)
+        .build();
+    let msg = Envelope::new(
+        client_addr.clone(),
+        client_addr,
+        Payload::new(),
+    );
+    let started = Instant::now();
+    let (reply, report) = router.scatter_gather(msg, &gather)?;
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(
+        reply.unwrap().payload(),
+        &Payload::from("12")
+    );
+    assert!(report.timed_out().is_empty());
+    assert_eq!(report.skipped(), &[slow_addr]);
+
+    Ok(())
+}
+
+#[test]
+fn router_dead_letters_unroutable_and_failing_messages() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let broken_addr = Address::parse("http://broken")?;
+    let missing_addr = Address::parse("http://missing")?;
+
+    let registry = Registry::builder()
+        .register(service_fn(broken_addr.clone(), |_| {
+            Err(Error::ProcessorNotFound("/".to_string()))
+        }))
+        .build();
+    let dead_letters = Arc::new(
+        Queue::builder()
+            .capacity(2)
+            .build(),
+    );
+    let router = Router::builder()
+        .registry(registry)
+        .retry("http://broken", RetryPolicy::new(2))
+        .dead_letter(dead_letters.clone())
+        .build();
+
+    // Unroutable message.
+    let msg = Envelope::new(
+        client_addr.clone(),
+        missing_addr.clone(),
+        Payload::from("lost"),
+    );
+    assert!(matches!(
+        router.route(msg),
+        Err(Error::ServiceNotFound)
+    ));
+
+    let dead = dead_letters
+        .dequeue()?
+        .unwrap();
+    assert_eq!(dead.payload(), &Payload::from("lost"));
+    assert_eq!(
+        DeadLetterReason::of(&dead),
+        Some(DeadLetterReason::Unroutable)
+    );
+    assert_eq!(
+        dead.get_header(DEAD_LETTER_DESTINATION_HEADER),
+        Some("http://missing")
+    );
+
+    // Failing message, once its retry policy gave up on it.
+    let msg = Envelope::new(
+        client_addr.clone(),
+        broken_addr.clone(),
+        Payload::from("boom"),
+    );
+    assert!(matches!(
+        router.route(msg),
+        Err(Error::ProcessorNotFound(_))
+    ));
+
+    let dead = dead_letters
+        .dequeue()?
+        .unwrap();
+    assert_eq!(dead.payload(), &Payload::from("boom"));
+    assert_eq!(
+        DeadLetterReason::of(&dead),
+        Some(DeadLetterReason::Failed)
+    );
+    assert_eq!(
+        dead.get_header(DEAD_LETTER_SERVICE_HEADER),
+        Some("http://broken")
+    );
+    assert_eq!(
+        dead.get_header(DEAD_LETTER_ERROR_HEADER),
+        Some("Processor not found: /")
+    );
+    assert!(dead_letters.is_empty());
+
+    // Without a retry policy, a failure is not dead-lettered.
+    let registry = Registry::builder()
+        .register(service_fn(broken_addr.clone(), |_| {
+            Err(Error::ProcessorNotFound("/".to_string()))
+        }))
+        .build();
+    let router = Router::builder()
+        .registry(registry)
+        .dead_letter(dead_letters.clone())
+        .build();
+    let msg = Envelope::new(
+        client_addr.clone(),
+        broken_addr,
+        Payload::from("boom"),
+    );
+    assert!(matches!(
+        router.route(msg),
+        Err(Error::ProcessorNotFound(_))
+    ));
+    assert!(dead_letters.is_empty());
+
+    // A full dead-letter queue does not hide the error.
+    for _ in 0..3 {
+        let msg = Envelope::new(
+            client_addr.clone(),
+            missing_addr.clone(),
+            Payload::new(),
+        );
+        assert!(matches!(
+            router.route(msg),
+            Err(Error::ServiceNotFound)
+        ));
+    }
+    assert_eq!(dead_letters.len(), 2);
+    assert_eq!(router.dead_letter_failures(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn router_hands_unroutable_messages_to_fallback() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let fallback_addr = Address::parse("http://fallback")?;
+    let missing_addr = Address::parse("http://missing/users/42")?;
+
+    let dead_letters = Arc::new(Queue::default());
+    let router = Router::builder()
+        .fallback(service_fn(fallback_addr.clone(), |msg| {
+            let requested = msg
+                .get_header(DEAD_LETTER_DESTINATION_HEADER)
+                .unwrap_or_default()
+                .to_string();
+            Ok(Some(
+                msg.into_reply(Payload::from(requested)),
+            ))
+        }))
+        .dead_letter(dead_letters.clone())
+        .build();
+
+    let msg = Envelope::new(client_addr, missing_addr, Payload::new());
+    let reply = router.route(msg)?.unwrap();
+    assert_eq!(
+        reply.payload(),
+        &Payload::from("http://missing/users/42")
+    );
+    assert!(dead_letters.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn router_registry_changes_while_serving() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let srv_addr = Address::parse("http://versioned")?;
+
+    let version = |version: &'static str| {
+        move |msg: Envelope| Ok(Some(msg.into_reply(Payload::from(version))))
+    };
+
+    // The first version blocks until released, to keep a request in flight.
+    let started = Arc::new(Barrier::new(2));
+    let release = Arc::new(Barrier::new(2));
+    let slow_v1 = {
+        let started = started.clone();
+        let release = release.clone();
+        move |msg: Envelope| {
+            started.wait();
+            release.wait();
+            Ok(Some(msg.into_reply(Payload::from("v1"))))
+        }
+    };
+
+    let router = Arc::new(Router::builder().build());
+    let registry = router.registry();
+    assert!(registry.list().is_empty());
+
+    registry.register(service_fn(srv_addr.clone(), slow_v1));
+    let message = || {
+        Envelope::new(
+            client_addr.clone(),
+            srv_addr.clone(),
+            Payload::new(),
+        )
+    };
+
+    let in_flight = {
+        let router = Arc::clone(&router);
+        let msg = message();
+        thread::spawn(move || router.route(msg))
+    };
+    started.wait();
+
+    // Replace the service while its only instance is processing a request.
+    assert_eq!(
+        registry.replace(service_fn(srv_addr.clone(), version("v2"))),
+        1
+    );
+    let reply = router
+        .route(message())?
+        .unwrap();
+    assert_eq!(reply.payload(), &Payload::from("v2"));
+
+    // The removed instance still completes the request it was given.
+    release.wait();
+    let reply = in_flight
+        .join()
+        .unwrap()?
+        .unwrap();
+    assert_eq!(reply.payload(), &Payload::from("v1"));
+
+    // Scale out, then remove the service altogether.
+    registry.add_instance(&srv_addr)?;
+    assert_eq!(
+        registry
+            .get(&srv_addr)
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(registry.deregister(&srv_addr)?, 2);
+    assert!(matches!(
+        router.route(message()),
+        Err(Error::ServiceNotFound)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn router_correlates_replies_through_a_queue() -> Result<()> {
+    const CALLERS: usize = 4;
+
+    let srv_addr = Address::parse("http://async-echo")?;
+    let outbox = Arc::new(Queue::default());
+
+    // Answers later, by pushing its replies to a queue.
+    let pending = outbox.clone();
+    let echo = service_fn(srv_addr.clone(), move |msg| {
+        let payload = msg.payload().clone();
+        pending.enqueue(msg.into_reply(payload))?;
+        Ok(None)
+    });
+    let router = Arc::new(
+        Router::builder()
+            .registry(
+                Registry::builder()
+                    .register(echo)
+                    .build(),
+            )
+            .build(),
+    );
+
+    // Each caller waits for its own reply.
+    let callers: Vec<_> = (0..CALLERS)
+        .map(|c| {
+            let router = Arc::clone(&router);
+            let srv_addr = srv_addr.clone();
+            thread::spawn(move || -> Result<()> {
+                let client_addr = Address::parse(format!("http://client-{c}"))?;
+                let payload = Payload::from(format!("caller {c}"));
+                let msg = Envelope::new(
+                    client_addr.clone(),
+                    srv_addr,
+                    payload.clone(),
+                );
+                let reply = router.send_and_wait(msg, Duration::from_secs(5))?;
+                assert_eq!(reply.payload(), &payload);
+                assert_eq!(reply.destination(), &client_addr);
+                Ok(())
+            })
+        })
+        .collect();
+
+    // Route the queued replies back once every request is waiting.
+    while router.pending_replies() < CALLERS {
+        thread::sleep(Duration::from_millis(1));
+    }
+    while let Some(reply) = outbox.dequeue()? {
+        assert!(router.route(reply)?.is_none());
+    }
+
+    for caller in callers {
+        caller.join().unwrap()?;
+    }
+    assert_eq!(router.pending_replies(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn router_retries_on_other_instances() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let flaky_addr = Address::parse("http://flaky")?;
+    let down_addr = Address::parse("http://down")?;
+    let broken_addr = Address::parse("http://broken")?;
+
+    // Two flaky instances out of three are restarting.
+    let calls = Arc::new(AtomicUsize::new(0));
+    let flaky = |healthy: bool| {
+        let calls = calls.clone();
+        move |msg: Envelope| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            match healthy {
+                true => {
+                    let attempt = msg
+                        .get_header(ATTEMPT_HEADER)
+                        .unwrap_or_default()
+                        .to_string();
+                    Ok(Some(msg.into_reply(Payload::from(attempt))))
+                }
+                false => Err(Error::Unavailable("restarting".to_string())),
+            }
+        }
+    };
+    let registry = Registry::builder()
+        .register(service_fn(flaky_addr.clone(), flaky(false)))
+        .register(service_fn(flaky_addr.clone(), flaky(false)))
+        .register(service_fn(flaky_addr.clone(), flaky(true)))
+        .register(service_fn(down_addr.clone(), |_| {
+            Err(Error::Unavailable("down".to_string()))
+        }))
+        .register(service_fn(broken_addr.clone(), flaky(false)))
+        .register(service_fn(broken_addr.clone(), |_| {
+            Err(Error::ProcessorNotFound("/".to_string()))
+        }))
+        .build();
+
+    let dead_letters = Arc::new(Queue::default());
+    let policy = RetryPolicy::new(3).backoff(
+        Duration::from_millis(1),
+        Duration::from_millis(10),
+    );
+    let router = Router::builder()
+        .registry(registry)
+        .retry("http://flaky", policy.clone())
+        .retry("http://down", policy)
+        .retry("*", RetryPolicy::new(1))
+        .dead_letter(dead_letters.clone())
+        .build();
+    let message = |dst: &Address| {
+        Envelope::new(
+            client_addr.clone(),
+            dst.clone(),
+            Payload::new(),
+        )
+    };
+
+    // Each attempt goes to another instance, until the healthy one.
+    let reply = router
+        .route(message(&flaky_addr))?
+        .unwrap();
+    let attempts = calls.load(Ordering::Relaxed);
+    assert!(attempts > 1);
+    assert_eq!(
+        reply.payload(),
+        &Payload::from(attempts.to_string())
+    );
+    assert!(dead_letters.is_empty());
+
+    // Dead-lettered once every attempt failed.
+    assert!(matches!(
+        router.route(message(&down_addr)),
+        Err(Error::Unavailable(_))
+    ));
+    let dead = dead_letters
+        .dequeue()?
+        .unwrap();
+    assert_eq!(dead.get_header(ATTEMPT_HEADER), Some("3"));
+    assert_eq!(
+        DeadLetterReason::of(&dead),
+        Some(DeadLetterReason::Failed)
+    );
+
+    // A single attempt is allowed by the catch-all route.
+    calls.store(0, Ordering::Relaxed);
+    assert!(
+        router
+            .route(message(&broken_addr))
+            .is_err()
+    );
+    assert_eq!(
+        dead_letters
+            .dequeue()?
+            .unwrap()
+            .get_header(ATTEMPT_HEADER),
+        Some("1")
+    );
+    assert!(dead_letters.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn router_skips_instances_with_open_circuits() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let srv_addr = Address::parse("http://inventory")?;
+    let solo_addr = Address::parse("http://solo")?;
+
+    let healthy = Arc::new(AtomicUsize::new(0));
+    let failing = Arc::new(AtomicUsize::new(0));
+    let counted = |calls: &Arc<AtomicUsize>, ok: bool| {
+        let calls = calls.clone();
+        move |msg: Envelope| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            match ok {
+                true => Ok(Some(msg.into_reply(Payload::new()))),
+                false => Err(Error::Unavailable("overloaded".to_string())),
+            }
+        }
+    };
+    let registry = Registry::builder()
+        .register(service_fn(
+            srv_addr.clone(),
+            counted(&failing, false),
+        ))
+        .register(service_fn(
+            srv_addr.clone(),
+            counted(&healthy, true),
+        ))
+        .register(service_fn(solo_addr.clone(), |_| {
+            Err(Error::Unavailable("down".to_string()))
+        }))
+        .build();
+
+    let cool_down = Duration::from_millis(50);
+    let router = Router::builder()
+        .registry(registry)
+        .circuit_breaker(
+            BreakerPolicy::default()
+                .consecutive_failures(2)
+                .cool_down(cool_down),
+        )
+        .build();
+    let message = |dst: &Address| {
+        Envelope::new(
+            client_addr.clone(),
+            dst.clone(),
+            Payload::new(),
+        )
+    };
+
+    // The failing instance is skipped once its circuit opened.
+    let failures = (0..10)
+        .filter(|_| {
+            router
+                .route(message(&srv_addr))
+                .is_err()
+        })
+        .count();
+    assert_eq!(failures, 2);
+    assert_eq!(failing.load(Ordering::Relaxed), 2);
+    assert_eq!(healthy.load(Ordering::Relaxed), 8);
+    assert_eq!(
+        router.circuit_states(&srv_addr),
+        Some(vec![
+            CircuitState::Open,
+            CircuitState::Closed
+        ])
+    );
+
+    // After the cool-down, a single probe gets through and fails.
+    thread::sleep(cool_down);
+    for _ in 0..4 {
+        let _ = router.route(message(&srv_addr));
+    }
+    assert_eq!(failing.load(Ordering::Relaxed), 3);
+    assert_eq!(
+        router
+            .circuit_states(&srv_addr)
+            .unwrap()[0],
+        CircuitState::Open
+    );
+
+    // Nothing left to call once every circuit is open.
+    for _ in 0..2 {
+        assert!(
+            router
+                .route(message(&solo_addr))
+                .is_err()
+        );
+    }
+    assert!(matches!(
+        router.route(message(&solo_addr)),
+        Err(Error::CircuitOpen)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn router_times_out_slow_services() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let slow_addr = Address::parse("http://slow")?;
+    let fast_addr = Address::parse("http://fast")?;
+    let fallback_addr = Address::parse("http://fallback")?;
+
+    // The slow service blocks until released.
+    let (release, released) = mpsc::channel::<()>();
+    let released = Arc::new(Mutex::new(released));
+    let registry = Registry::builder()
+        .register(service_fn(slow_addr.clone(), move |msg| {
+            let _ = released
+                .lock()
+                .unwrap()
+                .recv();
+            Ok(Some(msg.into_reply(Payload::from("late"))))
+        }))
+        .register(service_fn(fast_addr.clone(), |msg| {
+            Ok(Some(msg.into_reply(Payload::from("fast"))))
+        }))
+        .build();
+    let registry = LiveRegistry::new(registry);
+
+    let dead_letters = Arc::new(Queue::default());
+    let router = Router::builder()
+        .registry(registry.clone())
+        .timeout("http://slow", Duration::from_millis(50))
+        .retry("*", RetryPolicy::new(1))
+        .dead_letter(dead_letters.clone())
+        .build();
+    let message = |dst: &Address| {
+        Envelope::new(
+            client_addr.clone(),
+            dst.clone(),
+            Payload::new(),
+        )
+    };
+
+    // The router stops waiting, the worker keeps running.
+    let started = Instant::now();
+    assert!(matches!(
+        router.route(message(&slow_addr)),
+        Err(Error::TimedOut(_))
+    ));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(router.overdue_workers(), 1);
+
+    let dead = dead_letters
+        .dequeue()?
+        .unwrap();
+    assert_eq!(
+        DeadLetterReason::of(&dead),
+        Some(DeadLetterReason::TimedOut)
+    );
+    assert_eq!(
+        dead.get_header(DEAD_LETTER_SERVICE_HEADER),
+        Some("http://slow")
+    );
+
+    release.send(()).unwrap();
+    let started = Instant::now();
+    while router.overdue_workers() != 0 {
+        assert!(started.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    // Routes without a timeout are processed inline, unless the message
+    // carries a deadline.
+    let reply = router
+        .route(message(&fast_addr))?
+        .unwrap();
+    assert_eq!(reply.payload(), &Payload::from("fast"));
+
+    let expired = message(&fast_addr).with_deadline(SystemTime::UNIX_EPOCH);
+    assert!(matches!(
+        router.route(expired),
+        Err(Error::TimedOut(_))
+    ));
+    assert_eq!(
+        DeadLetterReason::of(
+            &dead_letters
+                .dequeue()?
+                .unwrap()
+        ),
+        Some(DeadLetterReason::TimedOut)
+    );
+
+    // With a fallback, timed out messages are handed over to it.
+    let router = Router::builder()
+        .registry(registry)
+        .timeout("http://slow", Duration::from_millis(50))
+        .fallback(service_fn(fallback_addr, |msg| {
+            Ok(Some(
+                msg.into_reply(Payload::from("fallback")),
+            ))
+        }))
+        .dead_letter(dead_letters.clone())
+        .build();
+
+    let reply = router
+        .route(message(&slow_addr))?
+        .unwrap();
+    assert_eq!(reply.payload(), &Payload::from("fallback"));
+    assert!(dead_letters.is_empty());
+    release.send(()).unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn router_splits_and_mirrors_traffic() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let v1_addr = Address::parse("http://orders-v1")?;
+    let v2_addr = Address::parse("http://orders-v2")?;
+
+    let mirrored = Arc::new(AtomicUsize::new(0));
+    let counter = mirrored.clone();
+    let registry = Registry::builder()
+        .register(service_fn(v1_addr.clone(), |msg| {
+            Ok(Some(msg.into_reply(Payload::from("v1"))))
+        }))
+        .register(service_fn(v2_addr.clone(), move |msg| {
+            if msg.get_header(MIRROR_HEADER) == Some("http://orders-v1") {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(Some(msg.into_reply(Payload::from("v2"))))
+        }))
+        .build();
+    let registry = LiveRegistry::new(registry);
+    let message = || {
+        Envelope::new(
+            client_addr.clone(),
+            v1_addr.clone(),
+            Payload::new(),
+        )
+    };
+    let replied_by = |router: &Router, msg: Envelope| -> Result<Payload> {
+        Ok(router
+            .route(msg)?
+            .unwrap()
+            .payload()
+            .clone())
+    };
+
+    // Canary requested by header, no traffic otherwise.
+    let router = Router::builder()
+        .registry(registry.clone())
+        .split(
+            "http://orders-v1",
+            Split::to(v2_addr.clone()).when_header("x-canary", "true"),
+        )
+        .build();
+    assert_eq!(
+        replied_by(&router, message())?,
+        Payload::from("v1")
+    );
+    assert_eq!(
+        replied_by(&router, message().header("x-canary", "true"))?,
+        Payload::from("v2")
+    );
+
+    // All the traffic moved over.
+    let router = Router::builder()
+        .registry(registry.clone())
+        .split(
+            "http://orders-v1",
+            Split::to(v2_addr.clone()).percent(100.0),
+        )
+        .build();
+    for _ in 0..5 {
+        assert_eq!(
+            replied_by(&router, message())?,
+            Payload::from("v2")
+        );
+    }
+
+    // Shadow traffic: v1 replies, v2 replies are discarded and recorded.
+    let router = Router::builder()
+        .registry(registry)
+        .mirror("http://orders-v1", v2_addr.clone())
+        .build();
+    for _ in 0..5 {
+        assert_eq!(
+            replied_by(&router, message())?,
+            Payload::from("v1")
+        );
+    }
+
+    let started = Instant::now();
+    while router
+        .mirror_stats(&v2_addr)
+        .is_none_or(|stats| stats.mirrored() < 5)
+    {
+        assert!(started.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+    let stats = router
+        .mirror_stats(&v2_addr)
+        .unwrap();
+    assert_eq!(stats.replied(), 5);
+    assert_eq!(stats.failed(), 0);
+    assert_eq!(stats.diverged(), 5);
+    assert_eq!(mirrored.load(Ordering::Relaxed), 5);
+    assert!(
+        router
+            .mirror_stats(&v1_addr)
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn router_balances_each_service_with_its_own_strategy() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let sessions_addr = Address::parse("http://sessions")?;
+    let stateless_addr = Address::parse("http://stateless")?;
+
+    // Each instance replies with its number.
+    let instance = |addr: &Address, n: usize| {
+        service_fn(addr.clone(), move |msg: Envelope| {
+            Ok(Some(
+                msg.into_reply(Payload::from(n.to_string())),
+            ))
+        })
+    };
+    let mut registry = Registry::builder();
+    for n in 0..3 {
+        registry = registry
+            .register(instance(&sessions_addr, n))
+            .register(instance(&stateless_addr, n));
+    }
+
+    let router = Router::builder()
+        .registry(registry.build())
+        .service_balancer(
+            "http://sessions",
+            Balancer::consistent_hash("x-user-id"),
+        )
+        .build();
+    assert_eq!(router.balancing_strategy(), "round_robin");
+    assert_eq!(
+        router.balancing_strategy_of(&sessions_addr),
+        "consistent_hash"
+    );
+    assert_eq!(
+        router.balancing_strategy_of(&stateless_addr),
+        "round_robin"
+    );
+
+    let replied_by = |dst: &Address, user: &str| -> Result<Payload> {
+        let msg = Envelope::new(
+            client_addr.clone(),
+            dst.clone(),
+            Payload::new(),
+        )
+        .header("x-user-id", user);
+        Ok(router
+            .route(msg)?
+            .unwrap()
+            .payload()
+            .clone())
+    };
+
+    // A session sticks to its instance, whoever else is served.
+    for user in ["alice", "bob", "carol"] {
+        let first = replied_by(&sessions_addr, user)?;
+        for _ in 0..3 {
+            replied_by(&sessions_addr, "someone-else")?;
+            assert_eq!(replied_by(&sessions_addr, user)?, first);
+        }
+    }
+
+    // The stateless service keeps cycling through its instances.
+    let cycle: Vec<Payload> = (0..3)
+        .map(|_| replied_by(&stateless_addr, "alice"))
+        .collect::<Result<_>>()?;
+    assert_eq!(cycle, ["0", "1", "2"].map(Payload::from));
+
+    Ok(())
+}
+
+#[test]
+fn router_applies_routing_rules() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let partner_addr = Address::parse("tcp://partner.com")?;
+    let orders_addr = Address::parse("http://orders")?;
+    let vip_addr = Address::parse("http://orders-vip")?;
+    let legacy_addr = Address::parse("tcp://orders-legacy")?;
+
+    let replying = |addr: &Address, name: &'static str| {
+        service_fn(addr.clone(), move |msg: Envelope| {
+            Ok(Some(msg.into_reply(Payload::from(name))))
+        })
+    };
+    let registry = Registry::builder()
+        .register(replying(&orders_addr, "orders"))
+        .register(replying(&vip_addr, "vip"))
+        .register(replying(&legacy_addr, "legacy"))
+        .build();
+
+    let router = Router::builder()
+        .registry(registry)
+        .rule(
+            Rule::redirect("vip", vip_addr.clone())
+                .when(Condition::destination("http://orders"))
+                .when(Condition::payload(
+                    "urgent order",
+                    |payload| payload.starts_with(b"urgent"),
+                )),
+        )
+        .rule(
+            Rule::redirect("partners", legacy_addr.clone())
+                .when(Condition::source("tcp://*"))
+                .priority(10),
+        )
+        .rule(
+            Rule::redirect("pinned", orders_addr.clone())
+                .when(Condition::header("x-pin", "orders"))
+                .priority(20),
+        )
+        .build();
+    let message = |src: &Address, payload: &'static str| {
+        Envelope::new(
+            src.clone(),
+            orders_addr.clone(),
+            Payload::from(payload),
+        )
+    };
+    let replied_by = |msg: Envelope| -> Result<Payload> {
+        Ok(router
+            .route(msg)?
+            .unwrap()
+            .payload()
+            .clone())
+    };
+
+    // No rule matches: exact destination.
+    assert_eq!(
+        replied_by(message(&client_addr, "order"))?,
+        Payload::from("orders")
+    );
+    // Payload predicate.
+    assert_eq!(
+        replied_by(message(&client_addr, "urgent order"))?,
+        Payload::from("vip")
+    );
+    // Source predicate, ahead of the payload one.
+    assert_eq!(
+        replied_by(message(&partner_addr, "urgent order"))?,
+        Payload::from("legacy")
+    );
+    // Header predicate, ahead of everything.
+    let pinned = message(&partner_addr, "urgent order").header("x-pin", "orders");
+    assert_eq!(replied_by(pinned)?, Payload::from("orders"));
+
+    // Dry run.
+    let explanation = router.explain(&message(&partner_addr, "order"));
+    assert_eq!(explanation.rule(), Some("partners"));
+    assert_eq!(explanation.destination(), &legacy_addr);
+    assert_eq!(explanation.checks().len(), 2);
+    assert_eq!(
+        explanation.checks()[0].unmet(),
+        Some("header x-pin = orders")
+    );
+
+    let explanation = router.explain(&message(&client_addr, "order"));
+    assert_eq!(explanation.rule(), None);
+    assert_eq!(explanation.destination(), &orders_addr);
+    assert_eq!(
+        explanation.checks()[2].unmet(),
+        Some("urgent order")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn router_runs_middleware_chains() -> Result<()> {
+    /// Rejects the messages without credentials.
+    struct Auth;
+
+    impl Middleware for Auth {
+        fn intercept(&self, msg: Envelope) -> Result<Envelope> {
+            match msg.get_header("authorization") {
+                Some(_) => Ok(msg),
+                None => Err(Error::ExtractionFailed(
+                    "missing credentials".to_string(),
+                )),
+            }
+        }
+    }
+
+    /// Logs the destination of each message and the outcome of its
+    /// delivery.
+    struct Log(Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Log {
+        fn around(&self, msg: Envelope, next: Next<'_>) -> Result<Reply> {
+            let destination = msg.destination().to_string();
+            let reply = next.run(msg);
+            let outcome = match &reply {
+                Ok(_) => "ok",
+                Err(_) => "failed",
+            };
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{destination} {outcome}"));
+            reply
+        }
+    }
+
+    /// Answers from a fixed cache.
+    struct Cached;
+
+    impl Middleware for Cached {
+        fn around(&self, msg: Envelope, next: Next<'_>) -> Result<Reply> {
+            match msg.payload().as_ref() {
+                b"cached" => Ok(Some(
+                    msg.into_reply(Payload::from("from cache")),
+                )),
+                _ => next.run(msg),
+            }
+        }
+    }
+
+    let client_addr = Address::parse("http://client-service.com")?;
+    let echo_addr = Address::parse("http://echo")?;
+    let echo = service_fn(echo_addr.clone(), |msg| {
+        let payload = msg.payload().clone();
+        Ok(Some(msg.into_reply(payload)))
+    });
+    let registry = Registry::builder()
+        .register(Layered::new(echo).layer(Cached))
+        .build();
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let router = Router::builder()
+        .registry(registry)
+        .middleware(Log(log.clone()))
+        .middleware(Auth)
+        .build();
+    let message = |payload: &'static str| {
+        Envelope::new(
+            client_addr.clone(),
+            echo_addr.clone(),
+            Payload::from(payload),
+        )
+    };
+
+    // Stopped by the router chain.
+    assert!(matches!(
+        router.route(message("hello")),
+        Err(Error::ExtractionFailed(_))
+    ));
+
+    // Through both chains.
+    let reply = router
+        .route(message("hello").header("authorization", "token"))?
+        .unwrap();
+    assert_eq!(reply.payload(), &Payload::from("hello"));
+
+    // Answered by the service chain.
+    let reply = router
+        .route(message("cached").header("authorization", "token"))?
+        .unwrap();
+    assert_eq!(reply.payload(), &Payload::from("from cache"));
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["http://echo failed", "http://echo ok", "http://echo ok"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn router_runs_and_resumes_sagas() -> Result<()> {
+    /// Saves to a [`FileSagaStore`] until its budget is spent, like a
+    /// process crashing mid-run.
+    struct Crashing(FileSagaStore, AtomicUsize);
+
+    impl SagaStore for Crashing {
+        fn save(&self, state: &SagaState) -> Result<()> {
+            match self
+                .1
+                .fetch_sub(1, Ordering::SeqCst)
+            {
+                0 => Err(Error::PersistenceFailed(
+                    "crashed".to_string(),
+                )),
+                _ => self.0.save(state),
+            }
+        }
+
+        fn load(&self, id: &str) -> Result<Option<SagaState>> { self.0.load(id) }
+
+        fn list(&self) -> Result<Vec<SagaState>> { self.0.list() }
+    }
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut registry = Registry::builder();
+    for name in ["reserve", "release", "charge", "ship"] {
+        let log = log.clone();
+        registry = registry.register(service_fn(
+            Address::parse(format!("http://{name}"))?,
+            move |msg| {
+                if name == "charge" && msg.payload().as_ref() == b"declined" {
+                    return Err(Error::Unavailable(
+                        "card declined".to_string(),
+                    ));
+                }
+                let run = msg
+                    .get_header(SAGA_ID_HEADER)
+                    .unwrap_or("none");
+                log.lock()
+                    .unwrap()
+                    .push(format!("{name} {run}"));
+                Ok(None)
+            },
+        ));
+    }
+    let router = Router::builder()
+        .registry(registry.build())
+        .build();
+
+    let checkout = |store: FileSagaStore, budget: usize| -> Result<Saga> {
+        Ok(Saga::builder("checkout")
+            .step(
+                Step::new("reserve", Address::parse("http://reserve")?)
+                    .compensate_with(Address::parse("http://release")?),
+            )
+            .step(Step::new(
+                "charge",
+                Address::parse("http://charge")?,
+            ))
+            .step(Step::new(
+                "ship",
+                Address::parse("http://ship")?,
+            ))
+            .store(Crashing(store, AtomicUsize::new(budget)))
+            .build())
+    };
+    let order = |payload: &'static str| -> Result<Envelope> {
+        Ok(Envelope::new(
+            Address::parse("http://shop.com")?,
+            Address::parse("http://orders.com")?,
+            Payload::from(payload),
+        ))
+    };
+    let dir = std::env::temp_dir().join(format!(
+        "bakbon-checkout-{}",
+        std::process::id()
+    ));
+    let drain = || std::mem::take(&mut *log.lock().unwrap());
+
+    // Every step succeeds.
+    let saga = checkout(FileSagaStore::new(&dir)?, usize::MAX)?;
+    let state = router.run_saga(&saga, order("ok")?)?;
+    assert_eq!(state.status(), SagaStatus::Completed);
+    let id = state.id().to_string();
+    assert_eq!(
+        drain(),
+        [
+            format!("reserve {id}"),
+            format!("charge {id}"),
+            format!("ship {id}")
+        ]
+    );
+
+    // The charge fails: the reservation is released.
+    let state = router.run_saga(&saga, order("declined")?)?;
+    assert_eq!(state.status(), SagaStatus::Compensated);
+    assert_eq!(
+        state
+            .step("reserve")
+            .unwrap()
+            .status(),
+        StepStatus::Compensated
+    );
+    assert_eq!(
+        state
+            .step("ship")
+            .unwrap()
+            .status(),
+        StepStatus::Pending
+    );
+    assert!(
+        state
+            .error()
+            .unwrap()
+            .contains("card declined")
+    );
+    let id = state.id().to_string();
+    assert_eq!(
+        drain(),
+        [format!("reserve {id}"), format!("release {id}")]
+    );
+
+    // The process crashes once the charge went through, before it is
+    // saved: the run resumes from the charge, past the deadline of the
+    // original request.
+    let saga = checkout(FileSagaStore::new(&dir)?, 2)?;
+    let request = order("ok")?.with_timeout(Duration::from_millis(500));
+    assert!(matches!(
+        router.run_saga(&saga, request),
+        Err(Error::PersistenceFailed(_))
+    ));
+    thread::sleep(Duration::from_millis(600));
+    let saga = checkout(FileSagaStore::new(&dir)?, usize::MAX)?;
+    let unfinished = saga.unfinished()?;
+    assert_eq!(unfinished.len(), 1);
+    let id = unfinished[0].id().to_string();
+    assert!(
+        unfinished[0]
+            .request()
+            .deadline()
+            .is_none()
+    );
+    assert_eq!(
+        unfinished[0]
+            .step("charge")
+            .unwrap()
+            .status(),
+        StepStatus::Pending
+    );
+
+    let state = router.resume_saga(&saga, &id)?;
+    assert_eq!(state.status(), SagaStatus::Completed);
+    assert!(saga.unfinished()?.is_empty());
+    assert_eq!(
+        drain(),
+        [
+            format!("reserve {id}"),
+            format!("charge {id}"),
+            format!("charge {id}"),
+            format!("ship {id}")
+        ]
+    );
+    assert!(matches!(
+        router.resume_saga(&saga, "unknown"),
+        Err(Error::SagaNotFound(_))
+    ));
+
+    std::fs::remove_dir_all(dir).map_err(|e| Error::PersistenceFailed(e.to_string()))?;
+    Ok(())
+}
+
+#[test]
+fn router_resolves_service_versions() -> Result<()> {
+    let client_addr = Address::parse("http://client-service.com")?;
+    let orders_addr = Address::parse("http://orders")?;
+    let orders = |name: &'static str, version: &str| -> Result<_> {
+        let service = service_fn(orders_addr.clone(), move |msg| {
+            Ok(Some(msg.into_reply(Payload::from(name))))
+        });
+        Ok(Versioned::new(
+            service,
+            SemVer::parse(version)?,
+        ))
+    };
+
+    let registry = Registry::builder()
+        .register(orders("v1", "1.4.0")?)
+        .register(orders("v2.1", "2.1.0")?)
+        .register(Layered::new(orders("v2.3-a", "2.3.2")?))
+        .register(orders("v2.3-b", "2.3.2+build.7")?)
+        .register(orders("v3-beta", "3.0.0-beta.1")?)
+        .build();
+    let router = Router::builder()
+        .registry(registry)
+        .build();
+    let route = |range: Option<&str>| -> Result<String> {
+        let mut msg = Envelope::new(
+            client_addr.clone(),
+            orders_addr.clone(),
+            Payload::new(),
+        );
+        if let Some(range) = range {
+            msg.add_header(ACCEPT_VERSION_HEADER, range);
+        }
+        let reply = router.route(msg)?.unwrap();
+        Ok(String::from_utf8_lossy(reply.payload()).into_owned())
+    };
+
+    // The highest compatible version, balanced between its instances
+    // whatever their build.
+    let mut replies: Vec<String> = (0..4)
+        .map(|_| route(Some("^2.1")))
+        .collect::<Result<_>>()?;
+    replies.sort();
+    replies.dedup();
+    assert_eq!(replies, ["v2.3-a", "v2.3-b"]);
+
+    assert_eq!(route(Some("~2.1"))?, "v2.1");
+    assert_eq!(route(Some("<2"))?, "v1");
+    assert_eq!(route(Some(">=3.0.0-alpha"))?, "v3-beta");
+    assert!(route(Some("*"))?.starts_with("v2.3"));
+
+    // Without a range, any instance.
+    let replies: Vec<String> = (0..5)
+        .map(|_| route(None))
+        .collect::<Result<_>>()?;
+    assert!(replies.contains(&"v3-beta".to_string()));
+
+    match route(Some("^4")) {
+        Err(error @ Error::NoMatchingVersion(..)) => assert_eq!(
+            error.to_string(),
+            "No version matches ^4: registered 1.4.0, 2.1.0, 2.3.2, 3.0.0-beta.1."
+        ),
+        other => panic!("unexpected outcome: {other:?}"),
+    }
+    assert!(matches!(
+        route(Some("two")),
+        Err(Error::InvalidVersion(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn router_forwards_to_peer_routers() -> Result<()> {
+    struct DownLink;
+
+    impl PeerLink for DownLink {
+        fn forward(&self, _msg: Envelope) -> Result<Reply> {
+            Err(Error::Unavailable(
+                "connection refused".to_string(),
+            ))
+        }
+
+        fn advertisement(&self) -> Result<Advertisement> {
+            Err(Error::Unavailable(
+                "connection refused".to_string(),
+            ))
+        }
+    }
+
+    let client_addr = Address::parse("http://client-service.com")?;
+    let stock_addr = Address::parse("http://stock")?;
+    let users_addr = Address::parse("http://users")?;
+    let tracing = |addr: &Address, name: &'static str| {
+        service_fn(addr.clone(), move |msg| {
+            let trail = format!(
+                "{name}: {} hops via {}",
+                msg.get_header(HOPS_HEADER)
+                    .unwrap_or("0"),
+                msg.get_header(FORWARDED_BY_HEADER)
+                    .unwrap_or("-"),
+            );
+            Ok(Some(msg.into_reply(Payload::from(trail))))
+        })
+    };
+
+    // a <-> b <-> c, each router only knowing its neighbours.
+    let c = Router::builder()
+        .name("c")
+        .registry(
+            Registry::builder()
+                .register(tracing(&stock_addr, "c"))
+                .build(),
+        )
+        .build();
+    let b = Router::builder()
+        .name("b")
+        .registry(
+            Registry::builder()
+                .register(tracing(&users_addr, "b"))
+                .build(),
+        )
+        .link_router("c", &c)
+        .build();
+    let a = Router::builder()
+        .name("a")
+        .link_router("b", &b)
+        .build();
+    b.link_local("a", &a);
+    c.link_local("b", &b);
+    assert_eq!(a.name(), "a");
+    assert_eq!(b.peers(), ["c", "a"]);
+
+    // Routes spread one hop per refresh.
+    for router in [&c, &b, &a] {
+        router.refresh_peers();
+    }
+    let advertisement = a.advertise();
+    assert_eq!(advertisement.origin(), "a");
+    let stock = advertisement
+        .routes()
+        .iter()
+        .find(|route| route.key() == "http://stock")
+        .unwrap();
+    assert_eq!(stock.path(), ["b", "c"]);
+    assert_eq!(
+        a.peer_health("b")
+            .unwrap()
+            .routes(),
+        2
+    );
+
+    let route = |to: &Address, hops: Option<&str>| -> Result<String> {
+        let mut msg = Envelope::new(
+            client_addr.clone(),
+            to.clone(),
+            Payload::new(),
+        );
+        if let Some(hops) = hops {
+            msg.add_header(HOPS_HEADER, hops);
+        }
+        let reply = a.route(msg)?.unwrap();
+        Ok(String::from_utf8_lossy(reply.payload()).into_owned())
+    };
+    assert_eq!(route(&users_addr, None)?, "b: 1 hops via a");
+    assert_eq!(
+        route(&stock_addr, None)?,
+        "c: 2 hops via a,b"
+    );
+    assert_eq!(
+        a.peer_health("b")
+            .unwrap()
+            .forwarded(),
+        2
+    );
+
+    // Unknown destinations do not bounce between the linked routers.
+    let nowhere = Address::parse("http://nowhere")?;
+    assert!(matches!(
+        route(&nowhere, None),
+        Err(Error::ServiceNotFound)
+    ));
+    assert!(matches!(
+        route(&stock_addr, Some("8")),
+        Err(Error::HopLimitExceeded(8))
+    ));
+
+    // A peer which cannot be reached keeps failing its health checks.
+    a.link("down", DownLink);
+    a.refresh_peers();
+    let health = a.peer_health("down").unwrap();
+    assert_eq!(health.failed(), 1);
+    assert_eq!(health.routes(), 0);
+    assert_eq!(
+        health.last_error(),
+        Some("Service unavailable: connection refused")
+    );
+    assert_eq!(
+        route(&stock_addr, None)?,
+        "c: 2 hops via a,b"
+    );
+
+    for (router, peer) in [(&a, "b"), (&b, "a"), (&b, "c"), (&c, "b")] {
+        assert!(router.unlink(peer));
+    }
+    assert!(route(&stock_addr, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn router_explains_routing_decisions() -> Result<()> {
+    /// Lets every message through, under a name.
+    struct Audit;
+
+    impl Middleware for Audit {
+        fn name(&self) -> &str { "audit" }
+    }
+
+    let client_addr = Address::parse("http://client-service.com")?;
+    let orders_addr = Address::parse("http://orders")?;
+    let payments_addr = Address::parse("http://payments")?;
+    let stock_addr = Address::parse("http://stock")?;
+    let orders = |version: &str| -> Result<_> {
+        let service = service_fn(orders_addr.clone(), |msg| {
+            Ok(Some(msg.into_reply(Payload::from("order"))))
+        });
+        Ok(Versioned::new(
+            service,
+            SemVer::parse(version)?,
+        ))
+    };
+
+    let registry = Registry::builder()
+        .register(orders("1.4.0")?)
+        .register(orders("2.3.0")?)
+        .register(orders("2.3.0")?)
+        .register(service_fn(payments_addr.clone(), |_| {
+            Err(Error::Unavailable("restarting".to_string()))
+        }))
+        .register(service_fn(payments_addr.clone(), |_| {
+            Ok(None)
+        }))
+        .build();
+    let eu = Router::builder()
+        .name("eu")
+        .registry(
+            Registry::builder()
+                .register(service_fn(stock_addr.clone(), |_| Ok(None)))
+                .build(),
+        )
+        .build();
+    let router = Router::builder()
+        .name("us")
+        .registry(registry)
+        .rule(
+            Rule::redirect("legacy-shop", orders_addr.clone())
+                .when(Condition::header("x-legacy", "true")),
+        )
+        .service_balancer(
+            "http://payments",
+            Balancer::new("round_robin"),
+        )
+        .circuit_breaker(BreakerPolicy::default().consecutive_failures(1))
+        .timeout("http://orders", Duration::from_secs(1))
+        .fallback(service_fn(client_addr.clone(), |_| Ok(None)))
+        .link_router("eu", &eu)
+        .middleware(Audit)
+        .split(
+            "http://nowhere",
+            Split::to(Address::parse("http://nowhere-v2")?).percent(30.0),
+        )
+        .split(
+            "http://nowhere",
+            Split::to(Address::parse("http://nowhere-v3")?).percent(90.0),
+        )
+        .build();
+    router.refresh_peers();
+    let msg = |to: &Address| {
+        Envelope::new(
+            client_addr.clone(),
+            to.clone(),
+            Payload::new(),
+        )
+    };
+
+    // Rules, then versions, then the balancing strategy.
+    let shop = msg(&Address::parse("http://shop/cart")?)
+        .header("x-legacy", "true")
+        .header(ACCEPT_VERSION_HEADER, "^2");
+    let explanation = router.explain(&shop);
+    assert_eq!(explanation.rule(), Some("legacy-shop"));
+    assert_eq!(explanation.key(), "http://orders");
+    assert_eq!(explanation.accepted_version(), Some("^2"));
+    assert_eq!(explanation.decision(), &Decision::Deliver);
+    assert_eq!(
+        explanation.reason(),
+        "round_robin turn 0 over 2 available instances"
+    );
+    let candidates = explanation.candidates();
+    assert_eq!(
+        candidates[0].skipped(),
+        Some("version 1.4.0 is not in ^2")
+    );
+    assert!(candidates[1].selected());
+    assert!(
+        !candidates[2].selected()
+            && candidates[2]
+                .skipped()
+                .is_none()
+    );
+
+    // Explaining changes nothing; routing takes a turn.
+    assert_eq!(router.explain(&shop), explanation);
+    router.route(shop.clone())?;
+    assert!(
+        router
+            .explain(&shop)
+            .candidates()[2]
+            .selected()
+    );
+
+    // Instances with an open circuit are ruled out.
+    assert!(
+        router
+            .route(msg(&payments_addr))
+            .is_err()
+    );
+    let explanation = router.explain(&msg(&payments_addr));
+    assert_eq!(
+        explanation.candidates()[0].skipped(),
+        Some("circuit open")
+    );
+    assert_eq!(
+        explanation.candidates()[0]
+            .instance()
+            .circuit(),
+        CircuitState::Open
+    );
+    assert!(explanation.candidates()[1].selected());
+
+    // Messages with no local service go to a peer, or to the fallback.
+    let explanation = router.explain(&msg(&stock_addr));
+    assert_eq!(
+        explanation.decision(),
+        &Decision::Forward("eu".to_string())
+    );
+    assert!(
+        explanation
+            .candidates()
+            .is_empty()
+    );
+    let explanation = router.explain(&msg(&Address::parse("http://nowhere")?));
+    assert_eq!(explanation.decision(), &Decision::Fallback);
+    assert_eq!(
+        explanation.reason(),
+        "no service registered for http://nowhere"
+    );
+
+    // The middleware chain is listed, and the splits rolled per message
+    // are reported with their weight.
+    assert_eq!(explanation.middleware(), ["audit"]);
+    assert_eq!(
+        explanation.weighted_splits(),
+        [
+            "may be split to http://nowhere-v2 with weight 30",
+            "may be split to http://nowhere-v3 with weight 70"
+        ]
+    );
+
+    // The routing table.
+    let routes = router.routes();
+    let keys: Vec<&str> = routes
+        .iter()
+        .map(RouteInfo::key)
+        .collect();
+    assert_eq!(keys, ["http://orders", "http://payments"]);
+    assert_eq!(routes[0].strategy(), "round_robin");
+    assert_eq!(routes[0].turn(), Some(1));
+    assert_eq!(
+        routes[0].timeout(),
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(routes[0].instances().len(), 3);
+    let circuits: Vec<CircuitState> = routes[1]
+        .instances()
+        .iter()
+        .map(|instance| instance.circuit())
+        .collect();
+    assert_eq!(
+        circuits,
+        [CircuitState::Open, CircuitState::Closed]
+    );
+
+    #[cfg(feature = "json")]
+    {
+        let json = router
+            .explain(&shop)
+            .to_json();
+        assert_eq!(json["rule"], "legacy-shop");
+        assert_eq!(json["decision"], "deliver");
+        assert_eq!(json["checks"][0]["matched"], true);
+        assert_eq!(json["candidates"][0]["version"], "1.4.0");
+        assert_eq!(json["candidates"][2]["selected"], true);
+
+        let json = router
+            .explain(&msg(&stock_addr))
+            .to_json();
+        assert_eq!(json["peer"], "eu");
+        assert_eq!(json["middleware"][0], "audit");
+        assert_eq!(
+            routes[1].to_json()["instances"][0]["circuit"],
+            "open"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn router_throttles_routes() -> Result<()> {
+    let alice = Address::parse("http://alice")?;
+    let bob = Address::parse("http://bob")?;
+    let orders_addr = Address::parse("http://orders")?;
+    let users_addr = Address::parse("http://users")?;
+    let ticks_addr = Address::parse("http://ticks")?;
+    let slow_addr = Address::parse("http://slow")?;
+    let queued_addr = Address::parse("http://queued")?;
+    let late_addr = Address::parse("http://late")?;
+
+    // The slow services hold their message until told to reply.
+    let (started_tx, started) = mpsc::channel::<()>();
+    let (release, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+    let started_tx = Mutex::new(started_tx);
+    let hold = move |msg: Envelope| {
+        started_tx
+            .lock()?
+            .send(())
+            .ok();
+        release_rx.lock()?.recv().ok();
+        Ok(Some(msg.into_reply(Payload::from("done"))))
+    };
+    let hold = Arc::new(hold);
+    let held = |addr: &Address| {
+        let hold = hold.clone();
+        service_fn(addr.clone(), move |msg| hold(msg))
+    };
+
+    let registry = Registry::builder()
+        .register(service_fn(orders_addr.clone(), |_| Ok(None)))
+        .register(service_fn(users_addr.clone(), |_| Ok(None)))
+        .register(service_fn(ticks_addr.clone(), |_| Ok(None)))
+        .register(held(&slow_addr))
+        .register(held(&queued_addr))
+        .register(held(&late_addr))
+        .build();
+    let dead_letters = Arc::new(Queue::default());
+    let router = Router::builder()
+        .registry(registry)
+        .dead_letter(dead_letters.clone())
+        .rate_limit(
+            "http://orders",
+            RateLimit::new(2, Duration::from_secs(60)),
+        )
+        .source_rate_limit(
+            "http://users",
+            RateLimit::new(1, Duration::from_secs(60)),
+        )
+        .rate_limit(
+            "http://ticks",
+            RateLimit::new(20, Duration::from_secs(1))
+                .burst(1)
+                .wait(Duration::from_millis(500)),
+        )
+        .concurrency_limit(
+            "http://slow",
+            ConcurrencyLimit::new(1).retry_after(Duration::from_millis(20)),
+        )
+        .concurrency_limit(
+            "http://queued",
+            ConcurrencyLimit::new(1).wait(Duration::from_secs(5)),
+        )
+        .concurrency_limit("http://late", ConcurrencyLimit::new(1))
+        .timeout("http://late", Duration::from_millis(20))
+        .build();
+    let msg =
+        |from: &Address, to: &Address| Envelope::new(from.clone(), to.clone(), Payload::new());
+    let route = |from: &Address, to: &Address| router.route(msg(from, to));
+    let decision = |from: &Address, to: &Address| {
+        router
+            .explain(&msg(from, to))
+            .decision()
+            .clone()
+    };
+
+    // Per route, whoever the source.
+    route(&alice, &orders_addr)?;
+    route(&bob, &orders_addr)?;
+    assert_eq!(
+        decision(&bob, &orders_addr),
+        Decision::Throttle
+    );
+    match route(&alice, &orders_addr) {
+        Err(error @ Error::Throttled(_)) => {
+            assert!(error.retry_after() > Some(Duration::from_secs(29)));
+        }
+        other => panic!("unexpected outcome: {other:?}"),
+    }
+
+    // Per source.
+    route(&alice, &users_addr)?;
+    assert_eq!(
+        decision(&alice, &users_addr),
+        Decision::Throttle
+    );
+    assert_eq!(
+        decision(&bob, &users_addr),
+        Decision::Deliver
+    );
+    assert!(matches!(
+        route(&alice, &users_addr),
+        Err(Error::Throttled(_))
+    ));
+    route(&bob, &users_addr)?;
+
+    // Waiting for the next token rather than failing.
+    let start = Instant::now();
+    route(&alice, &ticks_addr)?;
+    route(&alice, &ticks_addr)?;
+    assert!(start.elapsed() >= Duration::from_millis(40));
+
+    // Throttled messages are not dead-lettered.
+    assert!(dead_letters.is_empty());
+
+    // One message in flight at a time.
+    thread::scope(|scope| -> Result<()> {
+        let first = scope.spawn(|| route(&alice, &slow_addr));
+        started.recv().unwrap();
+        assert_eq!(
+            decision(&bob, &slow_addr),
+            Decision::Throttle
+        );
+        assert!(matches!(
+            route(&bob, &slow_addr),
+            Err(Error::Throttled(retry_after)) if retry_after == Duration::from_millis(20)
+        ));
+        release.send(()).unwrap();
+        assert!(
+            first
+                .join()
+                .unwrap()?
+                .is_some()
+        );
+
+        // Queued until the message in flight completes.
+        let first = scope.spawn(|| route(&alice, &queued_addr));
+        started.recv().unwrap();
+        let second = scope.spawn(|| route(&bob, &queued_addr));
+        thread::sleep(Duration::from_millis(50));
+        release.send(()).unwrap();
+        started.recv().unwrap();
+        release.send(()).unwrap();
+        assert!(
+            first
+                .join()
+                .unwrap()?
+                .is_some()
+        );
+        assert!(
+            second
+                .join()
+                .unwrap()?
+                .is_some()
+        );
+        Ok(())
+    })?;
+
+    // A message keeps its slot until its service returns, even once the
+    // router stopped waiting for it.
+    assert!(matches!(
+        route(&alice, &late_addr),
+        Err(Error::TimedOut(_))
+    ));
+    started.recv().unwrap();
+    assert!(matches!(
+        route(&bob, &late_addr),
+        Err(Error::Throttled(_))
+    ));
+    release.send(()).unwrap();
+    let start = Instant::now();
+    while decision(&bob, &late_addr) == Decision::Throttle {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    Ok(())
+}